@@ -0,0 +1,329 @@
+//! Images inside zip and tar archives (and comic book archives, which are the same), listed and
+//! read without unpacking the archive. An entry is addressed by the archive path joined with
+//! its name, `comic.cbz/001.jpg`, which the [`Archive`](crate::decoder::Archive) decoder
+//! reads back.
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Extensions of archives, including those that can't be read.
+pub const EXTENSIONS: [&str; 8] = ["zip", "cbz", "rar", "cbr", "7z", "cb7", "tar", "tgz"];
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+/// The end of central directory record with the longest comment allowed
+const MAX_END_RECORD: u64 = 22 + u16::MAX as u64;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const TAR_BLOCK: u64 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+}
+
+/// Whether `path` has an archive extension, readable or not.
+pub fn is_archive(path: &Path) -> bool {
+    extension(path).is_some_and(|e| EXTENSIONS.contains(&e.as_str()))
+}
+
+fn kind(archive: &Path) -> io::Result<Kind> {
+    match extension(archive).as_deref() {
+        Some("zip" | "cbz") => Ok(Kind::Zip),
+        Some("tar") => Ok(Kind::Tar),
+        Some("tgz") => Ok(Kind::TarGz),
+        Some(ext @ ("rar" | "cbr" | "7z" | "cb7")) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(".{ext} archives can't be read, only zip, cbz, tar and tgz"),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not an archive", archive.display()),
+        )),
+    }
+}
+
+/// The archive file `path` is inside of and the name of its entry, `None` for paths that aren't
+/// in an archive.
+pub fn split(path: &Path) -> Option<(&Path, String)> {
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|a| is_archive(a) && a.is_file())?;
+    let entry = path.strip_prefix(archive).ok()?;
+    let names: Vec<_> = entry.iter().map(|c| c.to_string_lossy()).collect();
+    Some((archive, names.join("/")))
+}
+
+/// The path an entry of `archive` is addressed by, see [`split`].
+pub fn entry_path(archive: &Path, entry: &str) -> PathBuf {
+    entry
+        .split('/')
+        .fold(archive.to_path_buf(), |p, c| p.join(c))
+}
+
+/// Names of the files in `archive`, in the order they are stored, without folders.
+pub fn entries(archive: &Path) -> io::Result<Vec<String>> {
+    match kind(archive)? {
+        Kind::Zip => Ok(zip_entries(&mut File::open(archive)?)?
+            .into_iter()
+            .map(|e| e.name)
+            .filter(|n| !n.ends_with('/'))
+            .collect()),
+        Kind::Tar | Kind::TarGz => {
+            let mut names = Vec::new();
+            tar_walk(tar_reader(archive)?, |name, _, _| {
+                names.push(name.to_string());
+                Ok(false)
+            })?;
+            Ok(names)
+        }
+    }
+}
+
+/// Contents of the file `entry` of `archive`.
+pub fn read(archive: &Path, entry: &str) -> io::Result<Vec<u8>> {
+    match kind(archive)? {
+        Kind::Zip => {
+            let mut file = File::open(archive)?;
+            let found = zip_entries(&mut file)?
+                .into_iter()
+                .find(|e| e.name == entry)
+                .ok_or_else(|| not_found(entry))?;
+            zip_read(&mut file, &found)
+        }
+        Kind::Tar | Kind::TarGz => {
+            let mut data = None;
+            tar_walk(tar_reader(archive)?, |name, size, reader| {
+                if name != entry {
+                    return Ok(false);
+                }
+                let mut buf = Vec::with_capacity(size as usize);
+                reader.take(size).read_to_end(&mut buf)?;
+                data = Some(buf);
+                Ok(true)
+            })?;
+            data.ok_or_else(|| not_found(entry))
+        }
+    }
+}
+
+fn not_found(entry: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no {entry} in the archive"),
+    )
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+struct ZipEntry {
+    name: String,
+    method: u16,
+    compressed_size: u64,
+    offset: u64,
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+/// The entries of the central directory, found through the end of central directory record.
+fn zip_entries(file: &mut File) -> io::Result<Vec<ZipEntry>> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_start = len.saturating_sub(MAX_END_RECORD);
+    file.seek(SeekFrom::Start(tail_start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(&tail, i) == END_OF_CENTRAL_DIR)
+        .ok_or_else(|| invalid("not a zip archive"))?;
+    let count = u16_at(&tail, end + 10) as usize;
+    let dir_size = u32_at(&tail, end + 12) as usize;
+    let dir_offset = u32_at(&tail, end + 16);
+    if dir_offset == u32::MAX {
+        return Err(invalid("zip64 archives are not supported"));
+    }
+
+    let mut dir = vec![0; dir_size];
+    file.seek(SeekFrom::Start(dir_offset as u64))?;
+    file.read_exact(&mut dir)?;
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 0;
+    for _ in 0..count {
+        if at + 46 > dir.len() || u32_at(&dir, at) != CENTRAL_HEADER {
+            return Err(invalid("broken zip central directory"));
+        }
+        let name_len = u16_at(&dir, at + 28) as usize;
+        let extra_len = u16_at(&dir, at + 30) as usize;
+        let comment_len = u16_at(&dir, at + 32) as usize;
+        let name = dir
+            .get(at + 46..at + 46 + name_len)
+            .ok_or_else(|| invalid("broken zip central directory"))?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(&dir, at + 10),
+            compressed_size: u32_at(&dir, at + 20) as u64,
+            offset: u32_at(&dir, at + 42) as u64,
+        });
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+fn zip_read(file: &mut File, entry: &ZipEntry) -> io::Result<Vec<u8>> {
+    let mut header = [0; 30];
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(&mut header)?;
+    if u32_at(&header, 0) != LOCAL_HEADER {
+        return Err(invalid("broken zip entry"));
+    }
+    let skip = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+    file.seek(SeekFrom::Current(skip))?;
+    let mut data = BufReader::new(file).take(entry.compressed_size);
+    let mut out = Vec::new();
+    match entry.method {
+        STORED => data.read_to_end(&mut out)?,
+        DEFLATED => DeflateDecoder::new(data).read_to_end(&mut out)?,
+        method => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("zip compression method {method} is not supported"),
+            ));
+        }
+    };
+    Ok(out)
+}
+
+fn tar_reader(archive: &Path) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(archive)?);
+    Ok(match kind(archive)? {
+        Kind::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+/// Text of a NUL padded header field.
+fn tar_text(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn tar_size(field: &[u8]) -> io::Result<u64> {
+    let text = tar_text(field);
+    u64::from_str_radix(text.trim(), 8).map_err(|_| invalid("broken tar header"))
+}
+
+/// Call `visit` with the name, size and contents of each file of a tar stream, until it returns
+/// true. Long names from GNU and pax headers are followed.
+fn tar_walk(
+    mut reader: Box<dyn Read>,
+    mut visit: impl FnMut(&str, u64, &mut dyn Read) -> io::Result<bool>,
+) -> io::Result<()> {
+    let mut header = [0; TAR_BLOCK as usize];
+    let mut long_name: Option<String> = None;
+    loop {
+        match reader.read_exact(&mut header) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            result => result?,
+        }
+        // Two zero blocks end the archive, one is enough to stop
+        if header.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        let size = tar_size(&header[124..136])?;
+        let padded = size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+        let mut name = tar_text(&header[0..100]);
+        if &header[257..262] == b"ustar" {
+            let prefix = tar_text(&header[345..500]);
+            if !prefix.is_empty() {
+                name = format!("{prefix}/{name}");
+            }
+        }
+        let mut body = (&mut reader).take(padded);
+        match header[156] {
+            b'0' | 0 => {
+                let name = long_name.take().unwrap_or(name);
+                if visit(&name, size, &mut body)? {
+                    return Ok(());
+                }
+            }
+            // GNU long name of the next entry
+            b'L' => {
+                let mut buf = Vec::new();
+                (&mut body).take(size).read_to_end(&mut buf)?;
+                long_name = Some(tar_text(&buf));
+            }
+            // pax extended header, its path replaces the next entry's name
+            b'x' => {
+                let mut buf = Vec::new();
+                (&mut body).take(size).read_to_end(&mut buf)?;
+                let records = String::from_utf8_lossy(&buf);
+                long_name = records
+                    .lines()
+                    .find_map(|r| r.split_once(" path=").map(|(_, p)| p.to_string()));
+            }
+            _ => long_name = None,
+        }
+        io::copy(&mut body, &mut io::sink())?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tar_entry(out: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", data.len());
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = b'0';
+        header[257..262].copy_from_slice(b"ustar");
+        out.extend_from_slice(&header);
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(512) * 512, 0);
+    }
+
+    #[test]
+    fn tar_entries_are_listed_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("pages.tar");
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "ch1/001.png", b"first");
+        tar_entry(&mut tar, "ch1/002.png", &[7; 600]);
+        tar.extend_from_slice(&[0; 1024]);
+        std::fs::write(&archive, tar).unwrap();
+
+        assert_eq!(entries(&archive).unwrap(), ["ch1/001.png", "ch1/002.png"]);
+        assert_eq!(read(&archive, "ch1/001.png").unwrap(), b"first");
+        assert_eq!(read(&archive, "ch1/002.png").unwrap(), [7; 600]);
+        assert!(read(&archive, "ch1/003.png").is_err());
+
+        let page = entry_path(&archive, "ch1/002.png");
+        assert_eq!(
+            split(&page),
+            Some((archive.as_path(), "ch1/002.png".into()))
+        );
+        assert_eq!(split(&archive), None);
+        assert!(entries(&dir.path().join("pages.cbr")).is_err());
+    }
+}
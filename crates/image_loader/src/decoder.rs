@@ -0,0 +1,270 @@
+//! Backends that turn a path into an image. [`ImageLoader`](crate::ImageLoader) goes through
+//! its decoders in order and uses the first one that takes the source, so image-rs, plugins,
+//! RAW and HEIF files, or a source that fetches or unpacks the file first, all look the same to
+//! the caches and jobs.
+
+use image::{DynamicImage, ImageFormat};
+use log::{error, trace};
+use luminous_plugins::PluginManager;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::{archive, parallel_decode, remote};
+
+/// Decoders in the order they are asked.
+pub type Decoders = Arc<[Arc<dyn Decoder>]>;
+
+/// What decoders choose by: the path and the format its first bytes were recognized as.
+pub struct Source<'a> {
+    pub path: &'a Path,
+    /// `None` when image-rs doesn't know the format or the path isn't a readable file
+    pub format: Option<ImageFormat>,
+}
+
+impl<'a> Source<'a> {
+    pub fn probe(path: &'a Path) -> Self {
+        let t = Instant::now();
+        let mut buf = [0; 256];
+        let format = std::fs::File::open(path)
+            .and_then(|mut f| std::io::Read::read(&mut f, &mut buf))
+            .ok()
+            .and_then(|_| image::guess_format(&buf).ok());
+        trace!(
+            "Detected format for {:?} in {:.3}ms: {:?}",
+            path,
+            t.elapsed().as_secs_f64() * 1000.0,
+            format,
+        );
+        Self { path, format }
+    }
+}
+
+pub trait Decoder: Send + Sync {
+    /// Shown in logs.
+    fn name(&self) -> &str;
+
+    /// Whether this decoder handles `source`. The first decoder that takes it is the only one
+    /// tried, so a decoder shouldn't take files it has no chance with.
+    fn accepts(&self, source: &Source) -> bool;
+
+    /// Decode `source`, `thumb` when a thumbnail is enough (an embedded preview or a smaller
+    /// level). Failures are logged here, `None` leaves the placeholder.
+    fn decode(&self, source: &Source, thumb: bool) -> Option<DynamicImage>;
+
+    /// Whether this decoder reads `path` from somewhere other than a file of that name, so it
+    /// isn't missing for not being on disk.
+    fn serves(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// The decoders of a stock build: archive entries and URLs, RAW and HEIF files by extension
+/// when those features are on, then whatever image-rs recognizes, then the decoder plugins.
+pub fn builtin(plugin_manager: Arc<PluginManager>) -> Decoders {
+    let decoders: Vec<Arc<dyn Decoder>> = vec![
+        Arc::new(Archive),
+        Arc::new(Remote::default()),
+        #[cfg(feature = "raw")]
+        Arc::new(Raw),
+        #[cfg(feature = "heif")]
+        Arc::new(Heif),
+        Arc::new(ImageRs),
+        Arc::new(Plugins(plugin_manager)),
+    ];
+    decoders.into()
+}
+
+/// Decode `path` with the first of `decoders` that takes it.
+pub fn decode(decoders: &[Arc<dyn Decoder>], path: &Path, thumb: bool) -> Option<DynamicImage> {
+    let source = Source::probe(path);
+    let Some(decoder) = decoders.iter().find(|d| d.accepts(&source)) else {
+        error!("Image load failed {path:?}: No decoder for it");
+        return None;
+    };
+    trace!("Decoding {:?} with {}", path, decoder.name());
+    decoder.decode(&source, thumb)
+}
+
+/// Decode `bytes` read from `path` by some other means than opening it.
+fn decode_bytes(path: &Path, bytes: io::Result<impl AsRef<[u8]>>) -> Option<DynamicImage> {
+    bytes
+        .map_err(image::ImageError::IoError)
+        .and_then(|bytes| image::load_from_memory(bytes.as_ref()))
+        .map_err(|e| error!("Image load failed {path:?}: {e}"))
+        .ok()
+}
+
+/// Entries of zip and tar archives, see [`archive`].
+pub struct Archive;
+
+impl Decoder for Archive {
+    fn name(&self) -> &str {
+        "archive"
+    }
+
+    fn accepts(&self, source: &Source) -> bool {
+        source.format.is_none() && self.serves(source.path)
+    }
+
+    fn decode(&self, source: &Source, _thumb: bool) -> Option<DynamicImage> {
+        let (archive, entry) = archive::split(source.path)?;
+        decode_bytes(source.path, archive::read(archive, &entry))
+    }
+
+    fn serves(&self, path: &Path) -> bool {
+        archive::split(path).is_some()
+    }
+}
+
+/// Images at URLs, see [`remote`]. The last download is kept for the full image that follows
+/// its thumbnail.
+#[derive(Default)]
+pub struct Remote {
+    last: Mutex<Option<(PathBuf, Arc<[u8]>)>>,
+}
+
+impl Decoder for Remote {
+    fn name(&self) -> &str {
+        "remote"
+    }
+
+    fn accepts(&self, source: &Source) -> bool {
+        self.serves(source.path)
+    }
+
+    fn decode(&self, source: &Source, _thumb: bool) -> Option<DynamicImage> {
+        let path = source.path;
+        let url = remote::url(path)?;
+        let cached = self.last.lock().unwrap().clone();
+        let bytes = match cached {
+            Some((last, bytes)) if last == path => bytes,
+            _ => {
+                let t = Instant::now();
+                let bytes: Arc<[u8]> = match remote::fetch(url) {
+                    Ok(bytes) => bytes.into(),
+                    Err(e) => {
+                        error!("Image load failed {path:?}: {e}");
+                        return None;
+                    }
+                };
+                trace!(
+                    "Fetched {url} ({} bytes) in {:.1}ms",
+                    bytes.len(),
+                    t.elapsed().as_secs_f64() * 1000.0
+                );
+                *self.last.lock().unwrap() = Some((path.to_path_buf(), bytes.clone()));
+                bytes
+            }
+        };
+        decode_bytes(path, Ok(bytes))
+    }
+
+    fn serves(&self, path: &Path) -> bool {
+        remote::url(path).is_some()
+    }
+}
+
+/// Every format image-rs detects from the file header. Large full TIFFs are split over the
+/// thread pool.
+pub struct ImageRs;
+
+impl Decoder for ImageRs {
+    fn name(&self) -> &str {
+        "image-rs"
+    }
+
+    fn accepts(&self, source: &Source) -> bool {
+        source.format.is_some()
+    }
+
+    fn decode(&self, source: &Source, thumb: bool) -> Option<DynamicImage> {
+        let (path, fmt) = (source.path, source.format?);
+        if !thumb
+            && let Some(img) =
+                parallel_decode::decode(path, fmt, parallel_decode::PARALLEL_MIN_PIXELS)
+        {
+            return Some(img);
+        }
+        std::fs::File::open(path)
+            .map_err(image::ImageError::IoError)
+            .and_then(|f| image::load(std::io::BufReader::new(f), fmt))
+            .map_err(|e| error!("Image load failed {path:?}: {e}"))
+            .ok()
+    }
+}
+
+/// The decoder plugins, last in line as they are tried on anything.
+pub struct Plugins(pub Arc<PluginManager>);
+
+impl Decoder for Plugins {
+    fn name(&self) -> &str {
+        "plugins"
+    }
+
+    fn accepts(&self, _source: &Source) -> bool {
+        true
+    }
+
+    fn decode(&self, source: &Source, _thumb: bool) -> Option<DynamicImage> {
+        let img = self.0.decode_dynamic(source.path);
+        if img.is_none() {
+            error!("Image load failed {:?}: Unknown format", source.path);
+        }
+        img
+    }
+}
+
+/// RAW thumbnails prefer the embedded preview and full view the demosaiced sensor data, each
+/// falling back to the other.
+#[cfg(feature = "raw")]
+pub struct Raw;
+
+#[cfg(feature = "raw")]
+impl Decoder for Raw {
+    fn name(&self) -> &str {
+        "raw"
+    }
+
+    fn accepts(&self, source: &Source) -> bool {
+        crate::raw::is_raw(source.path)
+    }
+
+    fn decode(&self, source: &Source, thumb: bool) -> Option<DynamicImage> {
+        use crate::raw;
+        let path = source.path;
+        let img = if thumb {
+            raw::embedded_preview(path).or_else(|| raw::decode(path))
+        } else {
+            raw::decode(path).or_else(|| raw::embedded_preview(path))
+        };
+        if img.is_none() {
+            error!("Image load failed {path:?}: Undecodable file");
+        }
+        img
+    }
+}
+
+/// HEIF thumbnails use the file's own thumbnail item when it has one.
+#[cfg(feature = "heif")]
+pub struct Heif;
+
+#[cfg(feature = "heif")]
+impl Decoder for Heif {
+    fn name(&self) -> &str {
+        "heif"
+    }
+
+    fn accepts(&self, source: &Source) -> bool {
+        crate::heif::is_heif(source.path)
+    }
+
+    fn decode(&self, source: &Source, thumb: bool) -> Option<DynamicImage> {
+        let img = crate::heif::decode(source.path, thumb);
+        if img.is_none() {
+            error!("Image load failed {:?}: Undecodable file", source.path);
+        }
+        img
+    }
+}
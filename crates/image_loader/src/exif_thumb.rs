@@ -0,0 +1,78 @@
+use image::{DynamicImage, imageops::FilterType};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Long edge the blurred stand-in is upscaled to, the view scales it the rest of the way.
+const PREVIEW_EDGE: u32 = 640;
+const PREVIEW_BLUR_SIGMA: f32 = 3.0;
+
+/// Thumbnail the camera stored in the EXIF block (IFD1), `None` when there's none.
+/// For JPEGs only the APP1 segment is read.
+pub fn read(path: &Path) -> Option<DynamicImage> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let offset = exif
+        .get_field(exif::Tag::JPEGInterchangeFormat, exif::In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let len = exif
+        .get_field(exif::Tag::JPEGInterchangeFormatLength, exif::In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let jpeg = exif.buf().get(offset..offset.checked_add(len)?)?;
+    image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg).ok()
+}
+
+/// Stand-in for the full image while it decodes: upscaled so it fills the view
+/// like the real image would, blurred so the JPEG blocks don't pass as detail.
+pub fn blurred_preview(thumb: &DynamicImage) -> DynamicImage {
+    let scale = PREVIEW_EDGE as f32 / thumb.width().max(thumb.height()).max(1) as f32;
+    let w = (thumb.width() as f32 * scale).round().max(1.0) as u32;
+    let h = (thumb.height() as f32 * scale).round().max(1.0) as u32;
+    thumb
+        .resize_exact(w, h, FilterType::Triangle)
+        .fast_blur(PREVIEW_BLUR_SIGMA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn reads_ifd1_jpeg_thumbnail() {
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(40, 30))
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let width = exif::Field {
+            tag: exif::Tag::ImageWidth,
+            ifd_num: exif::In::PRIMARY,
+            value: exif::Value::Long(vec![4000]),
+        };
+        let mut writer = exif::experimental::Writer::new();
+        writer.push_field(&width);
+        writer.set_jpeg(&jpeg, exif::In::THUMBNAIL);
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("camera.tif");
+        std::fs::write(&path, tiff.into_inner()).unwrap();
+
+        let thumb = read(&path).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (40, 30));
+        let preview = blurred_preview(&thumb);
+        assert_eq!((preview.width(), preview.height()), (PREVIEW_EDGE, 480));
+
+        let plain = dir.path().join("plain.tif");
+        std::fs::write(&plain, b"II*\0").unwrap();
+        assert!(read(&plain).is_none());
+    }
+}
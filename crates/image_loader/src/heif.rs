@@ -0,0 +1,69 @@
+use image::{DynamicImage, RgbaImage};
+use libheif_rs::{ColorSpace, HeifContext, ImageHandle, ItemId, LibHeif, RgbChroma};
+use log::error;
+use std::path::Path;
+
+pub const HEIF_EXTS: [&str; 4] = ["heic", "heif", "hif", "avci"];
+
+pub fn is_heif(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| HEIF_EXTS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Decode the primary image, or its first embedded thumbnail when `thumb` is set
+/// and the file has one.
+pub fn decode(path: &Path, thumb: bool) -> Option<DynamicImage> {
+    let ctx = HeifContext::read_from_file(path.to_str()?)
+        .map_err(|e| error!("HEIF open failed {path:?}: {e}"))
+        .ok()?;
+    let primary = ctx
+        .primary_image_handle()
+        .map_err(|e| error!("HEIF has no primary image {path:?}: {e}"))
+        .ok()?;
+    let handle = if thumb {
+        let mut ids: [ItemId; 1] = [0];
+        match primary.thumbnail_ids(&mut ids) {
+            0 => primary,
+            _ => primary.thumbnail(ids[0]).unwrap_or(primary),
+        }
+    } else {
+        primary
+    };
+    to_rgba8(&handle)
+        .map_err(|e| error!("HEIF decode failed {path:?}: {e}"))
+        .ok()
+        .map(DynamicImage::ImageRgba8)
+}
+
+fn to_rgba8(handle: &ImageHandle) -> libheif_rs::Result<RgbaImage> {
+    // 10/12-bit HEICs (most recent phones) come out as 16-bit little endian samples
+    let bits = handle.luma_bits_per_pixel();
+    let chroma = if bits > 8 {
+        RgbChroma::HdrRgbaLe
+    } else {
+        RgbChroma::Rgba
+    };
+    let image = LibHeif::new().decode(handle, ColorSpace::Rgb(chroma), None)?;
+    let (w, h) = (image.width(), image.height());
+    let planes = image.planes();
+    let Some(plane) = planes.interleaved else {
+        return Ok(RgbaImage::new(w, h));
+    };
+
+    let mut out = RgbaImage::new(w, h);
+    let row_len = w as usize * 4;
+    for (y, dst) in out.chunks_exact_mut(row_len).enumerate() {
+        let src = &plane.data[y * plane.stride..];
+        if bits > 8 {
+            let shift = plane.bits_per_pixel.saturating_sub(8);
+            for (d, s) in dst.iter_mut().zip(src.chunks_exact(2)) {
+                *d = (u16::from_le_bytes([s[0], s[1]]) >> shift).min(255) as u8;
+            }
+        } else {
+            dst.copy_from_slice(&src[..row_len]);
+        }
+    }
+    Ok(out)
+}
@@ -8,52 +8,203 @@ use slint::{Image, Rgba8Pixel, SharedPixelBuffer};
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use luminous_plugins::PluginManager;
 
+pub mod archive;
+pub mod decoder;
+mod exif_thumb;
+#[cfg(feature = "heif")]
+pub mod heif;
+mod parallel_decode;
+#[cfg(feature = "raw")]
+pub mod raw;
+mod readahead;
+pub mod remote;
+pub mod tone_map;
+
+use decoder::{Decoder, Decoders};
+use readahead::Readahead;
+use tone_map::ToneMap;
+
 const THUMB_FILTER: FilterType = FilterType::Triangle;
+const DEFAULT_THUMB_RES: u32 = 256;
+/// Threads for the EXIF thumbnail pass, kept apart so it isn't queued behind full decodes.
+const EXIF_WORKERS: usize = 2;
 
 pub type ImageReadyFn = Arc<dyn Fn(usize, SharedPixelBuffer<Rgba8Pixel>) + Send + Sync>;
 pub type ImageReadyHook = Option<ImageReadyFn>;
+/// Produces images for paths that don't exist on disk (e.g. demo mode), `None` falls back to decoding.
+pub type GenerateFn = Arc<dyn Fn(&Path) -> Option<image::DynamicImage> + Send + Sync>;
+pub type GenerateHook = Option<GenerateFn>;
+/// Gets the path of a file that was deleted after it was listed.
+pub type MissingFn = Arc<dyn Fn(PathBuf) + Send + Sync>;
+pub type MissingHook = Option<MissingFn>;
+/// Gets the path of an image no decoder could read.
+pub type FailedFn = Arc<dyn Fn(PathBuf) + Send + Sync>;
+pub type FailedHook = Option<FailedFn>;
+/// Applies edits kept outside the file to a decoded full image.
+pub type EditFn = Arc<
+    dyn Fn(&Path, SharedPixelBuffer<Rgba8Pixel>) -> SharedPixelBuffer<Rgba8Pixel> + Send + Sync,
+>;
+pub type EditHook = Option<EditFn>;
+/// Looks at each generated thumbnail along with the path of its image.
+pub type AnalyzeFn = Arc<dyn Fn(&Path, &SharedPixelBuffer<Rgba8Pixel>) + Send + Sync>;
+pub type AnalyzeHook = Option<AnalyzeFn>;
+
+/// Snapshot of the loader's queues and caches for diagnostics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoaderStats {
+    /// Decode jobs waiting for or running on the pool
+    pub queued: usize,
+    pub thumbs: usize,
+    pub thumb_bytes: usize,
+    pub full: usize,
+    pub full_bytes: usize,
+    /// Grid thumbnails found in memory and ones that had to be queued
+    pub thumb_hits: usize,
+    pub thumb_misses: usize,
+    /// Full view images found in memory and ones that had to be decoded
+    pub full_hits: usize,
+    pub full_misses: usize,
+    /// How long the last full view decode took, edits included
+    pub last_decode: Option<Duration>,
+}
+
+/// Lookups and timings behind the counters of [`LoaderStats`].
+#[derive(Default)]
+struct Counters {
+    thumb_hits: AtomicUsize,
+    thumb_misses: AtomicUsize,
+    full_hits: AtomicUsize,
+    full_misses: AtomicUsize,
+    /// Microseconds, 0 before the first decode
+    last_decode_us: AtomicU64,
+}
 
 fn placeholder() -> SharedPixelBuffer<Rgba8Pixel> {
     SharedPixelBuffer::<Rgba8Pixel>::new(1, 1)
 }
 
+/// Modification time and size of a file, cached images are only used while both still match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    mtime: SystemTime,
+    size: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        Some(Self {
+            mtime: meta.modified().ok()?,
+            size: meta.len(),
+        })
+    }
+}
+
+/// Decoded image with the stamp its file had when decoding started.
+#[derive(Clone)]
+struct Cached {
+    buffer: SharedPixelBuffer<Rgba8Pixel>,
+    stamp: Option<FileStamp>,
+}
+
+type Cache = DashMap<usize, Cached>;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Thumbnail and prefetch jobs held back while the app is in the background, so they don't take
+/// pool threads from loads of the shown image.
+#[derive(Default)]
+struct Pause {
+    /// `None` while not paused
+    held: Mutex<Option<Vec<Job>>>,
+}
+
+impl Pause {
+    /// The jobs held since pausing, to be spawned when resuming.
+    fn set(&self, paused: bool) -> Vec<Job> {
+        let mut held = self.held.lock().unwrap();
+        if paused {
+            held.get_or_insert_with(Vec::new);
+            Vec::new()
+        } else {
+            held.take().unwrap_or_default()
+        }
+    }
+
+    /// Keep `job` until resumed, or hand it back when not paused.
+    fn hold(&self, job: Job) -> Option<Job> {
+        match self.held.lock().unwrap().as_mut() {
+            Some(held) => {
+                held.push(job);
+                None
+            }
+            None => Some(job),
+        }
+    }
+}
+
 pub fn to_pixel_buffer(img: image::DynamicImage) -> SharedPixelBuffer<Rgba8Pixel> {
     let rgba = img.into_rgba8();
     SharedPixelBuffer::clone_from_slice(rgba.as_raw(), rgba.width(), rgba.height())
 }
 
+/// Full decode of `path` the way the viewer does it, without caching, for headless use.
+/// `None` when no decoder could read it.
+pub fn decode_file(
+    path: &Path,
+    plugin_manager: &Arc<PluginManager>,
+) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
+    let decoders = decoder::builtin(plugin_manager.clone());
+    ImageLoader::try_decode_full(path, &decoders, &None, ToneMap::default())
+}
+
 pub fn to_slint_image(buf: SharedPixelBuffer<Rgba8Pixel>) -> Image {
     Image::from_rgba8(buf)
 }
 
 // TODO: Save thumb_cache to db
 pub struct ImageLoader {
-    thumb_cache: Arc<DashMap<usize, SharedPixelBuffer<Rgba8Pixel>>>,
-    full_cache: Arc<DashMap<usize, SharedPixelBuffer<Rgba8Pixel>>>,
+    thumb_cache: Arc<Cache>,
+    full_cache: Arc<Cache>,
+    /// Check cache hits against their files, see [`Self::set_validate_cache`]
+    validate: bool,
+    /// Reads window files ahead of their decode jobs, see [`Self::set_readahead`]
+    readahead: Option<Readahead>,
 
     pub paths: RwLock<Vec<PathBuf>>,
     pub pool: Arc<ThreadPool>,
+    exif_pool: ThreadPool,
+    queued: Arc<AtomicUsize>,
+    counters: Arc<Counters>,
     pub active_idx: Arc<AtomicUsize>,
-    pub window_size: usize,
+    window_size: AtomicUsize,
     pub plugin_manager: Arc<PluginManager>,
+    decoders: Decoders,
 
     // TODO: load from the closest requested token for better results
     active_window: Arc<Mutex<HashSet<usize>>>,
     thumb_epoch: Arc<AtomicUsize>,
     next_full_token: Arc<AtomicUsize>,
     window_epoch: Arc<AtomicUsize>,
+    pause: Pause,
 
     cache_dir: Option<PathBuf>,
     bucket_resolution: AtomicU32,
 
     on_thumb_ready: ImageReadyHook,
     on_full_ready: ImageReadyHook,
+    on_missing: MissingHook,
+    on_failed: FailedHook,
+    generator: GenerateHook,
+    edits: EditHook,
+    analyzer: AnalyzeHook,
+    tone_map: RwLock<ToneMap>,
 }
 
 impl ImageLoader {
@@ -75,23 +226,40 @@ impl ImageLoader {
             .num_threads(workers)
             .build()
             .expect("Failed to build rayon thread pool");
+        let exif_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(EXIF_WORKERS)
+            .build()
+            .expect("Failed to build rayon thread pool");
 
         Self {
             thumb_cache: Arc::new(DashMap::new()),
             full_cache: Arc::new(DashMap::new()),
+            validate: true,
+            readahead: None,
             paths: RwLock::new(paths),
             pool: Arc::new(pool),
+            exif_pool,
+            queued: Arc::new(AtomicUsize::new(0)),
+            counters: Arc::default(),
             active_idx: Arc::new(AtomicUsize::new(0)),
             active_window: Arc::new(Mutex::new(HashSet::new())),
             thumb_epoch: Arc::new(AtomicUsize::new(0)),
             next_full_token: Arc::new(AtomicUsize::new(0)),
             window_epoch: Arc::new(AtomicUsize::new(0)),
-            window_size,
+            pause: Pause::default(),
+            window_size: AtomicUsize::new(window_size),
             cache_dir,
             bucket_resolution: AtomicU32::new(0),
+            decoders: decoder::builtin(plugin_manager.clone()),
             plugin_manager: plugin_manager,
             on_thumb_ready: None,
             on_full_ready: None,
+            on_missing: None,
+            on_failed: None,
+            generator: None,
+            edits: None,
+            analyzer: None,
+            tone_map: RwLock::new(ToneMap::default()),
         }
     }
 
@@ -109,6 +277,171 @@ impl ImageLoader {
         self.on_full_ready = Some(Arc::new(f));
     }
 
+    /// Called from the decode threads for each image whose file is gone, possibly more than
+    /// once for the same file.
+    pub fn on_missing<F>(&mut self, f: F)
+    where
+        F: Fn(PathBuf) + Send + Sync + 'static,
+    {
+        self.on_missing = Some(Arc::new(f));
+    }
+
+    /// Called from the decode threads when the image shown in full view can't be decoded.
+    /// Thumbnails and prefetched neighbours only get a placeholder.
+    pub fn on_failed<F>(&mut self, f: F)
+    where
+        F: Fn(PathBuf) + Send + Sync + 'static,
+    {
+        self.on_failed = Some(Arc::new(f));
+    }
+
+    /// Ask `decoder` before the built-in ones, e.g. for files fetched from elsewhere or read
+    /// out of an archive.
+    pub fn add_decoder<D>(&mut self, decoder: D)
+    where
+        D: Decoder + 'static,
+    {
+        let decoder: Arc<dyn Decoder> = Arc::new(decoder);
+        self.decoders = std::iter::once(decoder)
+            .chain(self.decoders.iter().cloned())
+            .collect();
+    }
+
+    pub fn set_generator<F>(&mut self, f: F)
+    where
+        F: Fn(&Path) -> Option<image::DynamicImage> + Send + Sync + 'static,
+    {
+        self.generator = Some(Arc::new(f));
+    }
+
+    /// Run on every full image after decoding, thumbnails are left alone.
+    pub fn set_edits<F>(&mut self, f: F)
+    where
+        F: Fn(&Path, SharedPixelBuffer<Rgba8Pixel>) -> SharedPixelBuffer<Rgba8Pixel>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.edits = Some(Arc::new(f));
+    }
+
+    /// Run on the decode threads for every thumbnail generated in the background, before it is
+    /// handed to `on_thumb_ready`. EXIF previews and blocking loads are left out.
+    pub fn set_thumb_analyzer<F>(&mut self, f: F)
+    where
+        F: Fn(&Path, &SharedPixelBuffer<Rgba8Pixel>) + Send + Sync + 'static,
+    {
+        self.analyzer = Some(Arc::new(f));
+    }
+
+    /// Whether cached images are checked against the modification time and size of their file
+    /// on every hit, on by default. Each check reads the file metadata, which is slow on some
+    /// network mounts.
+    pub fn set_validate_cache(&mut self, validate: bool) {
+        self.validate = validate;
+    }
+
+    /// Whether the files of the sliding window are read into the page cache ahead of their
+    /// decode jobs, off until enabled. Worth it on spinning disks and network mounts, where the
+    /// small reads of the decoders are slow. Only on Linux, where the kernel does the reading
+    /// in the background, elsewhere it would compete with the decoders for the disk.
+    pub fn set_readahead(&mut self, enabled: bool) {
+        self.readahead = (enabled && cfg!(target_os = "linux")).then(Readahead::new);
+    }
+
+    /// Hold back thumbnail generation and prefetching until resumed. Their jobs are kept out of
+    /// the pool meanwhile, loads of the shown image still go through right away.
+    pub fn set_paused(&self, paused: bool) {
+        debug!(
+            "Background loading {}",
+            if paused { "paused" } else { "resumed" }
+        );
+        for job in self.pause.set(paused) {
+            self.pool.spawn(job);
+        }
+    }
+
+    /// Applies to images decoded from now on, cached ones keep their old mapping.
+    pub fn set_tone_map(&self, tone_map: ToneMap) {
+        *self.tone_map.write().unwrap() = tone_map;
+    }
+
+    pub fn tone_map(&self) -> ToneMap {
+        *self.tone_map.read().unwrap()
+    }
+
+    /// Thumbnails ignore exposure adjustments so their disk cache stays valid.
+    fn thumb_tone_map(&self) -> ToneMap {
+        ToneMap {
+            exposure: 0.0,
+            ..self.tone_map()
+        }
+    }
+
+    /// Run `job` on the decode pool, counted in `stats().queued` until it returns.
+    fn spawn<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let queued = self.queued.clone();
+        queued.fetch_add(1, Ordering::Relaxed);
+        self.pool.spawn(move || {
+            job();
+            queued.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+
+    /// Like [`Self::spawn`] for thumbnail and prefetch jobs, which wait outside the pool while
+    /// paused, see [`Self::set_paused`].
+    fn spawn_background<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let queued = self.queued.clone();
+        queued.fetch_add(1, Ordering::Relaxed);
+        let job: Job = Box::new(move || {
+            job();
+            queued.fetch_sub(1, Ordering::Relaxed);
+        });
+        if let Some(job) = self.pause.hold(job) {
+            self.pool.spawn(job);
+        }
+    }
+
+    pub fn stats(&self) -> LoaderStats {
+        let bytes = |cache: &Cache| -> usize {
+            cache
+                .iter()
+                .map(|e| e.value().buffer.as_bytes().len())
+                .sum()
+        };
+        LoaderStats {
+            queued: self.queued.load(Ordering::Relaxed),
+            thumbs: self.thumb_cache.len(),
+            thumb_bytes: bytes(&self.thumb_cache),
+            full: self.full_cache.len(),
+            full_bytes: bytes(&self.full_cache),
+            thumb_hits: self.counters.thumb_hits.load(Ordering::Relaxed),
+            thumb_misses: self.counters.thumb_misses.load(Ordering::Relaxed),
+            full_hits: self.counters.full_hits.load(Ordering::Relaxed),
+            full_misses: self.counters.full_misses.load(Ordering::Relaxed),
+            last_decode: match self.counters.last_decode_us.load(Ordering::Relaxed) {
+                0 => None,
+                us => Some(Duration::from_micros(us)),
+            },
+        }
+    }
+
+    /// Images kept decoded on each side of the shown one.
+    pub fn window_size(&self) -> usize {
+        self.window_size.load(Ordering::Relaxed)
+    }
+
+    /// Applies from the next move of the sliding window.
+    pub fn set_window_size(&self, window_size: usize) {
+        self.window_size.store(window_size, Ordering::Relaxed);
+    }
+
     pub fn set_bucket_resolution(&self, resolution: u32) {
         self.bucket_resolution.store(resolution, Ordering::Relaxed);
         self.thumb_epoch.fetch_add(1, Ordering::Relaxed);
@@ -147,8 +480,52 @@ impl ImageLoader {
     }
 
     pub fn cache_buffer(&self, idx: usize, buf: SharedPixelBuffer<Rgba8Pixel>) {
-        self.full_cache.insert(idx, buf.clone());
-        self.thumb_cache.insert(idx, buf);
+        let cached = Cached {
+            buffer: buf,
+            stamp: self.get_path(idx).as_deref().and_then(FileStamp::of),
+        };
+        self.full_cache.insert(idx, cached.clone());
+        self.thumb_cache.insert(idx, cached);
+    }
+
+    /// The image of `idx` in `cache`, unless its file changed since it was decoded. Stale
+    /// entries are dropped.
+    fn cached(&self, cache: &Cache, idx: usize) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
+        let cached = cache.get(&idx)?.clone();
+        if self.validate {
+            let stamp = self.get_path(idx).as_deref().and_then(FileStamp::of);
+            if stamp != cached.stamp {
+                debug!("Dropped stale cache entry {idx}, its file changed");
+                cache.remove(&idx);
+                return None;
+            }
+        }
+        Some(cached.buffer)
+    }
+
+    /// Forget the decoded images of `idx` after its file changed on disk, the next request
+    /// decodes it again. Disk thumbnails are keyed by modification time and size, the ones
+    /// matching the file now only go stale on filesystems with coarse timestamps.
+    pub fn invalidate(&self, idx: usize) {
+        self.full_cache.remove(&idx);
+        self.thumb_cache.remove(&idx);
+        let Some(path) = self.get_path(idx) else {
+            return;
+        };
+        let bucket = self.bucket_resolution.load(Ordering::Relaxed);
+        for res in [DEFAULT_THUMB_RES, bucket] {
+            let Some(cache_path) = Self::disk_cache_path(self.cache_dir.as_ref(), &path, res)
+            else {
+                continue;
+            };
+            match fs::remove_file(&cache_path) {
+                Ok(()) => trace!("Removed stale disk thumb {cache_path:?}"),
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    error!("Failed to remove disk thumb {cache_path:?}: {e}")
+                }
+                Err(_) => {}
+            }
+        }
     }
 
     pub fn full_cache_contains(&self, idx: usize) -> bool {
@@ -157,10 +534,13 @@ impl ImageLoader {
 
     pub fn get_curr_active_buffer(&self) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
         let idx = self.active_idx.load(Ordering::Relaxed);
-        self.full_cache.get(&idx).map(|r| r.clone()).or_else(|| {
-            error!("Active image not in cache (index: {idx})");
-            None
-        })
+        self.full_cache
+            .get(&idx)
+            .map(|r| r.buffer.clone())
+            .or_else(|| {
+                error!("Active image not in cache (index: {idx})");
+                None
+            })
     }
 
     pub fn get_file_name(&self, idx: usize) -> Option<String> {
@@ -227,6 +607,35 @@ impl ImageLoader {
             .unwrap_or(0)
     }
 
+    pub fn get_image_disk_cache_bytes(&self) -> u64 {
+        let Some(ref dir) = self.cache_dir else {
+            return 0;
+        };
+
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok()?.metadata().ok())
+                    .filter(|m| m.is_file())
+                    .map(|m| m.len())
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Drop every decoded image held in memory, views reload what they need on demand.
+    pub fn purge_memory_caches(&self) {
+        let freed = self.stats();
+        self.clear_thumbs();
+        self.evict_all();
+        debug!(
+            "Purged {} thumbs and {} full images ({} bytes)",
+            freed.thumbs,
+            freed.full,
+            freed.thumb_bytes + freed.full_bytes
+        );
+    }
+
     pub fn clear_disk_cache(&self) -> bool {
         let Some(ref dir) = self.cache_dir else {
             return false;
@@ -251,6 +660,64 @@ impl ImageLoader {
         true
     }
 
+    /// Thumbnail for `path`, decoded on the calling thread when it isn't cached yet. Looks
+    /// `path` up among the loaded paths, [`Self::load_thumb_blocking_at`] skips that for callers
+    /// going through many.
+    pub fn load_thumb_blocking(&self, path: &Path) -> SharedPixelBuffer<Rgba8Pixel> {
+        let idx = self
+            .paths
+            .read()
+            .ok()
+            .and_then(|paths| paths.iter().position(|p| p == path));
+        self.thumb_blocking(idx, path)
+    }
+
+    /// [`Self::load_thumb_blocking`] for `path` at `idx` of the loaded paths. The cache is only
+    /// used while `idx` still holds `path`.
+    pub fn load_thumb_blocking_at(&self, idx: usize, path: &Path) -> SharedPixelBuffer<Rgba8Pixel> {
+        let loaded = self
+            .paths
+            .read()
+            .is_ok_and(|paths| paths.get(idx).is_some_and(|p| p == path));
+        self.thumb_blocking(loaded.then_some(idx), path)
+    }
+
+    fn thumb_blocking(&self, idx: Option<usize>, path: &Path) -> SharedPixelBuffer<Rgba8Pixel> {
+        if let Some(buf) = idx.and_then(|i| self.cached(&self.thumb_cache, i)) {
+            return buf;
+        }
+        let res = match self.bucket_resolution.load(Ordering::Relaxed) {
+            0 => DEFAULT_THUMB_RES,
+            res => res,
+        };
+        let cache_path = Self::disk_cache_path(self.cache_dir.as_ref(), path, res);
+        Self::decode_thumb(
+            path,
+            &self.decoders,
+            &self.generator,
+            self.thumb_tone_map(),
+            &cache_path,
+            res,
+        )
+    }
+
+    /// Full image at `index`, decoded on the calling thread when it isn't cached.
+    /// Unlike `load_full_progressive` it leaves the active image alone.
+    pub fn load_full_blocking(&self, index: usize) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
+        if let Some(buf) = self.cached(&self.full_cache, index) {
+            return Some(buf);
+        }
+        let path = self.get_path(index)?;
+        let buffer = Self::decode_full(&path, &self.decoders, &self.generator, self.tone_map());
+        Some(Self::edited(&self.edits, &path, buffer))
+    }
+
+    /// Full decode of a file outside the collection, such as a reference to compare against,
+    /// with the decoders and tone mapping of the collection. Nothing is cached.
+    pub fn load_file_blocking(&self, path: &Path) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
+        Self::try_decode_full(path, &self.decoders, &None, self.tone_map())
+    }
+
     // source: https://github.com/slint-ui/slint/discussions/5140
     pub fn load_grid_thumb(&self, index: usize) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
         let res = self.bucket_resolution.load(Ordering::Relaxed);
@@ -258,27 +725,60 @@ impl ImageLoader {
             return Some(placeholder());
         }
 
-        if let Some(buf) = self.thumb_cache.get(&index) {
-            return Some(buf.clone());
+        if let Some(buf) = self.cached(&self.thumb_cache, index) {
+            self.counters.thumb_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(buf);
         }
 
         let path = self.paths.read().ok()?.get(index)?.clone();
+        self.counters.thumb_misses.fetch_add(1, Ordering::Relaxed);
         let cache_clone = self.thumb_cache.clone();
         let cache_path = Self::disk_cache_path(self.cache_dir.as_ref(), &path, res);
-        let plugin_manager = self.plugin_manager.clone();
+        let decoders = self.decoders.clone();
+        let generator = self.generator.clone();
         let on_ready = self.on_thumb_ready.clone();
+        let on_missing = self.on_missing.clone();
+        let analyzer = self.analyzer.clone();
 
+        let tone_map = self.thumb_tone_map();
         let my_epoch = self.thumb_epoch.load(Ordering::Relaxed);
         let epoch_counter = self.thumb_epoch.clone();
 
-        self.pool.spawn(move || {
+        // First pass on cold caches: the camera's EXIF thumbnail, replaced once
+        // the proper thumbnail is generated
+        let cold = self.generator.is_none() && !cache_path.as_ref().is_some_and(|p| p.exists());
+        if cold && let Some(h) = on_ready.clone() {
+            let path = path.clone();
+            let cache = self.thumb_cache.clone();
+            let epoch_counter = epoch_counter.clone();
+            self.exif_pool.spawn(move || {
+                if epoch_counter.load(Ordering::Relaxed) != my_epoch {
+                    return;
+                }
+                let Some(thumb) = exif_thumb::read(&path) else {
+                    return;
+                };
+                // Holding the entry keeps the generated thumbnail from landing in between
+                if let dashmap::Entry::Vacant(_) = cache.entry(index) {
+                    trace!("EXIF thumb pass index={index}");
+                    h(index, to_pixel_buffer(thumb));
+                }
+            });
+        }
+
+        self.spawn_background(move || {
             if epoch_counter.load(Ordering::Relaxed) != my_epoch {
                 trace!("Thumb job cancelled (epoch mismatch) index={index}");
                 return;
             }
 
+            if Self::vanished(&path, &decoders, &generator, &on_missing) {
+                return;
+            }
             let t = Instant::now();
-            let buffer = Self::decode_thumb(&path, &plugin_manager, &cache_path, res);
+            let stamp = FileStamp::of(&path);
+            let buffer =
+                Self::decode_thumb(&path, &decoders, &generator, tone_map, &cache_path, res);
 
             if epoch_counter.load(Ordering::Relaxed) != my_epoch {
                 trace!("Thumb job discarded after decode (epoch mismatch) index={index}");
@@ -291,7 +791,16 @@ impl ImageLoader {
                 t.elapsed().as_secs_f64() * 1000.0
             );
 
-            cache_clone.insert(index, buffer.clone());
+            cache_clone.insert(
+                index,
+                Cached {
+                    buffer: buffer.clone(),
+                    stamp,
+                },
+            );
+            if let Some(analyze) = &analyzer {
+                analyze(&path, &buffer);
+            }
             if let Some(h) = &on_ready {
                 h(index, buffer);
             }
@@ -305,32 +814,50 @@ impl ImageLoader {
         self.active_idx.store(index, Ordering::Relaxed);
 
         if !force_disk_reload {
-            if let Some(buf) = self.full_cache.get(&index) {
+            if let Some(buf) = self.cached(&self.full_cache, index) {
                 trace!("Full cache hit: {index}");
-                return Image::from_rgba8(buf.clone());
+                self.counters.full_hits.fetch_add(1, Ordering::Relaxed);
+                return Image::from_rgba8(buf);
             }
         } else {
             trace!("Forcing disk reload for index: {index}");
             self.full_cache.remove(&index);
         }
 
-        let backup = self
-            .thumb_cache
-            .get(&index)
-            .map(|buf| Image::from_rgba8(buf.clone()))
-            .unwrap_or_default();
+        let thumb = self.cached(&self.thumb_cache, index);
 
         let path = match self.paths.read().unwrap().get(index) {
             Some(p) => p.clone(),
-            None => return backup,
+            None => return thumb.map(Image::from_rgba8).unwrap_or_default(),
         };
 
+        // Progressive steps on a cold cache, pushed from the decode job: the disk thumbnail cache
+        // or else the blurred EXIF thumbnail, then the full decode
+        let disk_thumb = match thumb {
+            Some(_) => None,
+            None => {
+                let res = match self.bucket_resolution.load(Ordering::Relaxed) {
+                    0 => DEFAULT_THUMB_RES,
+                    res => res,
+                };
+                Self::disk_cache_path(self.cache_dir.as_ref(), &path, res)
+            }
+        };
+        let backup = thumb.map(Image::from_rgba8).unwrap_or_default();
+
+        self.counters.full_misses.fetch_add(1, Ordering::Relaxed);
+        let counters = self.counters.clone();
         let cache_clone = self.full_cache.clone();
         let token_counter = self.next_full_token.clone();
-        let plugin_manager = self.plugin_manager.clone();
+        let decoders = self.decoders.clone();
+        let generator = self.generator.clone();
+        let edits = self.edits.clone();
+        let tone_map = self.tone_map();
         let on_ready = self.on_full_ready.clone();
+        let on_missing = self.on_missing.clone();
+        let on_failed = self.on_failed.clone();
 
-        self.pool.spawn(move || {
+        self.spawn(move || {
             let latest = token_counter.load(Ordering::Relaxed);
             if my_token + 1 < latest {
                 trace!(
@@ -338,17 +865,52 @@ impl ImageLoader {
                 );
                 return;
             }
+            if Self::vanished(&path, &decoders, &generator, &on_missing) {
+                return;
+            }
+
+            if let Some(cache_path) = disk_thumb
+                && let Some(h) = &on_ready
+            {
+                if cache_path.exists()
+                    && let Ok(img) = image::open(&cache_path)
+                {
+                    trace!("Full view thumbnail step index={index}");
+                    h(index, to_pixel_buffer(img));
+                } else if let Some(t) = exif_thumb::read(&path) {
+                    trace!("Full view blurred EXIF step index={index}");
+                    h(index, to_pixel_buffer(exif_thumb::blurred_preview(&t)));
+                }
+            }
 
             let t = Instant::now();
-            let buffer = Self::decode_full(&path, &plugin_manager);
+            let stamp = FileStamp::of(&path);
+            let buffer = Self::try_decode_full(&path, &decoders, &generator, tone_map)
+                .unwrap_or_else(|| {
+                    if let Some(f) = &on_failed {
+                        f(path.clone());
+                    }
+                    placeholder()
+                });
+            let buffer = Self::edited(&edits, &path, buffer);
 
+            let elapsed = t.elapsed();
+            counters
+                .last_decode_us
+                .store((elapsed.as_micros() as u64).max(1), Ordering::Relaxed);
             trace!(
                 "Full {:?} {:.1}ms",
                 path.file_name().unwrap_or_default(),
-                t.elapsed().as_secs_f64() * 1000.0
+                elapsed.as_secs_f64() * 1000.0
             );
 
-            cache_clone.insert(index, buffer.clone());
+            cache_clone.insert(
+                index,
+                Cached {
+                    buffer: buffer.clone(),
+                    stamp,
+                },
+            );
 
             let latest = token_counter.load(Ordering::Relaxed);
             if my_token + 1 < latest {
@@ -378,6 +940,16 @@ impl ImageLoader {
             active.extend(&window_indices);
         }
 
+        if let Some(readahead) = &self.readahead {
+            let paths = self.paths.read().unwrap();
+            readahead.request(
+                window_indices
+                    .iter()
+                    .filter(|idx| !self.full_cache.contains_key(idx))
+                    .filter_map(|&idx| paths.get(idx).cloned())
+                    .collect(),
+            );
+        }
         for &idx in &window_indices {
             self.preload_background(idx);
         }
@@ -393,7 +965,7 @@ impl ImageLoader {
     }
 
     fn preload_background(&self, index: usize) {
-        if self.full_cache.contains_key(&index) {
+        if self.cached(&self.full_cache, index).is_some() {
             return;
         }
         let path = match self.paths.read().unwrap().get(index) {
@@ -402,12 +974,16 @@ impl ImageLoader {
         };
         let cache_clone = self.full_cache.clone();
         let active_window = self.active_window.clone();
-        let plugin_manager = self.plugin_manager.clone();
+        let decoders = self.decoders.clone();
+        let generator = self.generator.clone();
+        let edits = self.edits.clone();
+        let tone_map = self.tone_map();
+        let on_missing = self.on_missing.clone();
 
         let my_epoch = self.window_epoch.load(Ordering::Relaxed);
         let window_epoch = self.window_epoch.clone();
 
-        self.pool.spawn(move || {
+        self.spawn_background(move || {
             if window_epoch.load(Ordering::Relaxed) != my_epoch {
                 return;
             }
@@ -417,22 +993,59 @@ impl ImageLoader {
             if cache_clone.contains_key(&index) {
                 return;
             }
-            cache_clone.insert(index, Self::decode_full(&path, &plugin_manager));
+            if Self::vanished(&path, &decoders, &generator, &on_missing) {
+                return;
+            }
+            let stamp = FileStamp::of(&path);
+            let buffer = Self::decode_full(&path, &decoders, &generator, tone_map);
+            let buffer = Self::edited(&edits, &path, buffer);
+            cache_clone.insert(index, Cached { buffer, stamp });
         });
     }
 
+    fn edited(
+        edits: &EditHook,
+        path: &Path,
+        buffer: SharedPixelBuffer<Rgba8Pixel>,
+    ) -> SharedPixelBuffer<Rgba8Pixel> {
+        match edits {
+            Some(f) => f(path, buffer),
+            None => buffer,
+        }
+    }
+
+    /// Whether the file of `path` was deleted, which is reported to the missing hook. Generated
+    /// images, archive entries and URLs have no file.
+    fn vanished(
+        path: &Path,
+        decoders: &Decoders,
+        generator: &GenerateHook,
+        on_missing: &MissingHook,
+    ) -> bool {
+        if generator.is_some() || path.exists() || decoders.iter().any(|d| d.serves(path)) {
+            return false;
+        }
+        debug!("{path:?} is gone from disk");
+        if let Some(h) = on_missing {
+            h(path.to_path_buf());
+        }
+        true
+    }
+
+    /// Keyed by path, modification time and size, so an edited file never hits the thumbnail of
+    /// its previous contents.
     fn disk_cache_path(cache_dir: Option<&PathBuf>, path: &Path, res: u32) -> Option<PathBuf> {
-        let meta = fs::metadata(path).ok()?;
-        let mtime = meta
-            .modified()
-            .ok()?
+        let stamp = FileStamp::of(path)?;
+        let mtime = stamp
+            .mtime
             .duration_since(std::time::UNIX_EPOCH)
             .ok()?
-            .as_secs();
+            .as_nanos();
 
         let mut h = Sha256::new();
         h.update(path.to_string_lossy().as_bytes());
         h.update(mtime.to_be_bytes());
+        h.update(stamp.size.to_be_bytes());
 
         Some(cache_dir?.join(format!("{}_{res}.webp", hex::encode(h.finalize()))))
     }
@@ -441,46 +1054,26 @@ impl ImageLoader {
     /// This needs to handled by the caller.
     fn decode_thumb(
         path: &Path,
-        plugin_manager: &PluginManager,
+        decoders: &[Arc<dyn Decoder>],
+        generator: &GenerateHook,
+        tone_map: ToneMap,
         cache_path: &Option<PathBuf>,
         res: u32,
     ) -> SharedPixelBuffer<Rgba8Pixel> {
-        if let Some(cp) = cache_path.as_ref().filter(|p| p.exists()) {
+        let generated = generator.as_ref().and_then(|g| g(path));
+        if let Some(cp) = cache_path
+            .as_ref()
+            .filter(|p| generated.is_none() && p.exists())
+        {
             match image::open(cp) {
                 Ok(img) => return to_pixel_buffer(img),
                 Err(_) => error!("Corrupt disk cache {cp:?}, regenerating"),
             }
         }
 
-        let t = Instant::now();
-        let mut buf = [0; 256];
-        let known_format = std::fs::File::open(path)
-            .and_then(|mut f| std::io::Read::read(&mut f, &mut buf))
-            .ok()
-            .and_then(|_| image::guess_format(&buf).ok());
-
-        trace!(
-            "Detected format for {:?} in {:.3}ms: {:?}",
-            path,
-            t.elapsed().as_secs_f64() * 1000.0,
-            known_format,
-        );
-
-        let dynamic = if let Some(fmt) = known_format {
-            match std::fs::File::open(path) {
-                Ok(f) => image::load(std::io::BufReader::new(f), fmt)
-                    .map_err(|e| error!("Load failed {path:?}: {e}"))
-                    .ok(),
-                Err(e) => {
-                    error!("Load failed {path:?}: {e}");
-                    None
-                }
-            }
-        } else {
-            plugin_manager.decode_dynamic(path)
-        };
+        let dynamic = generated.or_else(|| decoder::decode(decoders, path, true));
 
-        let Some(img) = dynamic else {
+        let Some(img) = dynamic.map(|img| tone_map::apply(img, tone_map)) else {
             return placeholder();
         };
 
@@ -511,41 +1104,26 @@ impl ImageLoader {
     }
 
     // TODO: encode_full for all formats in context menu
-    fn decode_full(path: &Path, plugin_manager: &PluginManager) -> SharedPixelBuffer<Rgba8Pixel> {
-        let t = Instant::now();
-        let mut buf = [0; 256];
-        let known_format = std::fs::File::open(path)
-            .and_then(|mut f| std::io::Read::read(&mut f, &mut buf))
-            .ok()
-            .and_then(|_| image::guess_format(&buf).ok());
-
-        trace!(
-            "Detected format for {:?} in {:.3}ms: {:?}",
-            path,
-            t.elapsed().as_secs_f64() * 1000.0,
-            known_format
-        );
+    fn decode_full(
+        path: &Path,
+        decoders: &[Arc<dyn Decoder>],
+        generator: &GenerateHook,
+        tone_map: ToneMap,
+    ) -> SharedPixelBuffer<Rgba8Pixel> {
+        Self::try_decode_full(path, decoders, generator, tone_map).unwrap_or_else(placeholder)
+    }
 
-        if let Some(fmt) = known_format {
-            match std::fs::File::open(path) {
-                Ok(f) => match image::load(std::io::BufReader::new(f), fmt) {
-                    Ok(img) => to_pixel_buffer(img),
-                    Err(e) => {
-                        error!("Image load failed {path:?}: {e}");
-                        placeholder()
-                    }
-                },
-                Err(e) => {
-                    error!("Image load failed {path:?}: {e}");
-                    placeholder()
-                }
-            }
-        } else if let Some(buf) = plugin_manager.decode(path) {
-            buf
-        } else {
-            error!("Image load failed {path:?}: Unknown format");
-            placeholder()
+    fn try_decode_full(
+        path: &Path,
+        decoders: &[Arc<dyn Decoder>],
+        generator: &GenerateHook,
+        tone_map: ToneMap,
+    ) -> Option<SharedPixelBuffer<Rgba8Pixel>> {
+        if let Some(img) = generator.as_ref().and_then(|g| g(path)) {
+            return Some(to_pixel_buffer(img));
         }
+        decoder::decode(decoders, path, false)
+            .map(|img| to_pixel_buffer(tone_map::apply(img, tone_map)))
     }
 }
 
@@ -588,13 +1166,15 @@ mod tests {
     #[test]
     fn test_decode_thumb_various_resolutions() {
         let (_dir, path) = make_test_image(1920, 1080, ImageFormat::Jpeg);
-        let plugin_manager = Arc::new(PluginManager::new());
+        let decoders = decoder::builtin(Arc::new(PluginManager::new()));
 
-        let buf_256 = ImageLoader::decode_thumb(&path, &plugin_manager, &None, 256);
+        let buf_256 =
+            ImageLoader::decode_thumb(&path, &decoders, &None, ToneMap::default(), &None, 256);
         assert!(buf_256.width() <= 256);
         assert!(buf_256.height() <= 256);
 
-        let buf_512 = ImageLoader::decode_thumb(&path, &plugin_manager, &None, 512);
+        let buf_512 =
+            ImageLoader::decode_thumb(&path, &decoders, &None, ToneMap::default(), &None, 512);
         assert!(buf_512.width() <= 512);
         assert!(buf_512.height() <= 512);
     }
@@ -602,9 +1182,9 @@ mod tests {
     #[test]
     fn test_decode_jpeg() {
         let (_dir, path) = make_test_image(800, 600, ImageFormat::Jpeg);
-        let plugin_manager = Arc::new(PluginManager::new());
+        let decoders = decoder::builtin(Arc::new(PluginManager::new()));
 
-        let buf = ImageLoader::decode_full(&path, &plugin_manager);
+        let buf = ImageLoader::decode_full(&path, &decoders, &None, ToneMap::default());
         assert_eq!(buf.width(), 800);
         assert_eq!(buf.height(), 600);
     }
@@ -612,13 +1192,41 @@ mod tests {
     #[test]
     fn test_decode_png() {
         let (_dir, path) = make_test_image(800, 600, ImageFormat::Png);
-        let plugin_manager = Arc::new(PluginManager::new());
+        let decoders = decoder::builtin(Arc::new(PluginManager::new()));
 
-        let buf = ImageLoader::decode_full(&path, &plugin_manager);
+        let buf = ImageLoader::decode_full(&path, &decoders, &None, ToneMap::default());
         assert_eq!(buf.width(), 800);
         assert_eq!(buf.height(), 600);
     }
 
+    #[test]
+    fn test_added_decoders_come_first() {
+        struct Solid;
+        impl Decoder for Solid {
+            fn name(&self) -> &str {
+                "solid"
+            }
+            fn accepts(&self, source: &decoder::Source) -> bool {
+                source.path.to_string_lossy().starts_with("archive:")
+            }
+            fn decode(&self, _source: &decoder::Source, thumb: bool) -> Option<DynamicImage> {
+                let size = if thumb { 8 } else { 32 };
+                Some(DynamicImage::ImageRgb8(RgbImage::new(size, size)))
+            }
+        }
+
+        let (_dir, path) = make_test_image(40, 20, ImageFormat::Png);
+        let paths = vec![PathBuf::from("archive:photos.zip/a.png"), path];
+        let mut loader = ImageLoader::new(paths, 1, 8, Arc::new(PluginManager::new()));
+        loader.add_decoder(Solid);
+
+        let buf = loader.load_full_blocking(0).unwrap();
+        assert_eq!((buf.width(), buf.height()), (32, 32));
+        // Everything else still goes to the built-in decoders
+        let buf = loader.load_full_blocking(1).unwrap();
+        assert_eq!((buf.width(), buf.height()), (40, 20));
+    }
+
     #[test]
     fn test_disk_cache_path_deterministic() {
         let (dir, path) = make_test_image(100, 100, ImageFormat::Jpeg);
@@ -653,4 +1261,118 @@ mod tests {
             "Full cache should be empty after evict"
         );
     }
+
+    #[test]
+    fn test_stats_count_cache_lookups_and_decode_time() {
+        let (_dir, path) = make_test_image(64, 64, ImageFormat::Png);
+        let loader = ImageLoader::new(vec![path], 1, 1, Arc::new(PluginManager::new()));
+        assert!(loader.stats().last_decode.is_none());
+
+        loader.load_full_progressive(0, false);
+        let start = Instant::now();
+        while loader.stats().last_decode.is_none() || !loader.full_cache_contains(0) {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "decode never finished"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        loader.load_full_progressive(0, false);
+
+        let stats = loader.stats();
+        assert_eq!((stats.full_hits, stats.full_misses), (1, 1));
+        assert_eq!((stats.thumb_hits, stats.thumb_misses), (0, 0));
+    }
+
+    #[test]
+    fn test_invalidate_drops_memory_and_disk_entries() {
+        let (dir, path) = make_test_image(100, 100, ImageFormat::Png);
+        let mut loader = ImageLoader::new(vec![path.clone()], 1, 8, Arc::new(PluginManager::new()));
+        loader.cache_dir = Some(dir.path().to_path_buf());
+        loader.cache_buffer(0, placeholder());
+        let cache_path =
+            ImageLoader::disk_cache_path(loader.cache_dir.as_ref(), &path, DEFAULT_THUMB_RES)
+                .unwrap();
+        fs::write(&cache_path, b"stale").unwrap();
+
+        loader.invalidate(0);
+        assert!(!loader.full_cache_contains(0));
+        assert!(loader.thumb_cache.is_empty());
+        assert!(!cache_path.exists());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_cache_hits_are_validated_against_the_file() {
+        let (_dir, path) = make_test_image(100, 100, ImageFormat::Png);
+        let mut loader = ImageLoader::new(vec![path.clone()], 1, 8, Arc::new(PluginManager::new()));
+        let rewrite = |size| {
+            DynamicImage::ImageRgb8(RgbImage::new(size, size))
+                .save_with_format(&path, ImageFormat::Png)
+                .unwrap()
+        };
+
+        loader.cache_buffer(0, placeholder());
+        assert_eq!(loader.load_full_blocking(0).unwrap().width(), 1);
+        rewrite(50);
+        assert_eq!(loader.load_full_blocking(0).unwrap().width(), 50);
+        assert!(!loader.full_cache_contains(0), "stale entry is dropped");
+
+        loader.set_validate_cache(false);
+        loader.cache_buffer(0, placeholder());
+        rewrite(60);
+        assert_eq!(loader.load_full_blocking(0).unwrap().width(), 1);
+    }
+
+    #[test]
+    fn test_deleted_files_are_reported() {
+        let (_dir, path) = make_test_image(100, 100, ImageFormat::Png);
+        let mut loader = ImageLoader::new(vec![path.clone()], 1, 8, Arc::new(PluginManager::new()));
+        let (tx, rx) = std::sync::mpsc::channel();
+        loader.on_missing(move |p| {
+            let _ = tx.send(p);
+        });
+        fs::remove_file(&path).unwrap();
+
+        loader.set_bucket_resolution(64);
+        assert!(loader.load_grid_thumb(0).is_none());
+        let reported = rx.recv_timeout(std::time::Duration::from_secs(5));
+        assert_eq!(reported.unwrap(), path);
+        assert!(loader.thumb_cache.is_empty());
+    }
+
+    #[test]
+    fn test_paused_thumbs_wait_for_resume() {
+        let (_dir, path) = make_test_image(100, 100, ImageFormat::Png);
+        let mut loader = ImageLoader::new(vec![path], 1, 8, Arc::new(PluginManager::new()));
+        let (tx, rx) = std::sync::mpsc::channel();
+        loader.on_thumb_ready(move |idx, _| {
+            let _ = tx.send(idx);
+        });
+
+        loader.set_bucket_resolution(64);
+        loader.set_paused(true);
+        assert!(loader.load_grid_thumb(0).is_none());
+        let wait = std::time::Duration::from_millis(300);
+        assert!(rx.recv_timeout(wait).is_err());
+        assert_eq!(loader.stats().queued, 1);
+        // The held job doesn't take the only pool thread
+        assert!(loader.load_full_blocking(0).is_some());
+
+        loader.set_paused(false);
+        let ready = rx.recv_timeout(std::time::Duration::from_secs(5));
+        assert_eq!(ready.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_disk_cache_path_follows_file_contents() {
+        let (dir, path) = make_test_image(100, 100, ImageFormat::Png);
+        let cache_dir = Some(dir.path().to_path_buf());
+        let before = ImageLoader::disk_cache_path(cache_dir.as_ref(), &path, 256);
+        DynamicImage::ImageRgb8(RgbImage::new(10, 10))
+            .save_with_format(&path, ImageFormat::Png)
+            .unwrap();
+        let after = ImageLoader::disk_cache_path(cache_dir.as_ref(), &path, 256);
+        assert_ne!(before, after);
+    }
 }
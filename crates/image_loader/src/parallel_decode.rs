@@ -0,0 +1,127 @@
+use image::{DynamicImage, ImageFormat};
+use log::{debug, trace};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use tiff::ColorType;
+use tiff::decoder::{ChunkType, Decoder, DecodingResult};
+
+/// Below this many pixels a single threaded decode finishes before the split pays off.
+pub const PARALLEL_MIN_PIXELS: u64 = 16_000_000;
+
+/// Decode a large image on the rayon pool, `None` when the format or layout isn't
+/// splittable (or the image is small) and the regular decoder should be used.
+///
+/// Only TIFF qualifies for now: its strips and tiles are compressed independently.
+/// JPEG restart intervals and PNG would need a custom entropy decoder.
+pub fn decode(path: &Path, format: ImageFormat, min_pixels: u64) -> Option<DynamicImage> {
+    match format {
+        ImageFormat::Tiff => decode_tiff(path, min_pixels),
+        _ => None,
+    }
+}
+
+fn open(path: &Path) -> Option<Decoder<BufReader<File>>> {
+    Decoder::new(BufReader::new(File::open(path).ok()?)).ok()
+}
+
+fn decode_tiff(path: &Path, min_pixels: u64) -> Option<DynamicImage> {
+    let mut decoder = open(path)?;
+    let (width, height) = decoder.dimensions().ok()?;
+    if (width as u64) * (height as u64) < min_pixels {
+        return None;
+    }
+    let samples = match decoder.colortype().ok()? {
+        ColorType::Gray(8) => 1,
+        ColorType::GrayA(8) => 2,
+        ColorType::RGB(8) => 3,
+        ColorType::RGBA(8) => 4,
+        other => {
+            trace!("No parallel decode for {other:?} TIFF {path:?}");
+            return None;
+        }
+    };
+    let chunk_type = decoder.get_chunk_type();
+    let chunks = match chunk_type {
+        ChunkType::Strip => decoder.strip_count().ok()?,
+        ChunkType::Tile => decoder.tile_count().ok()?,
+    };
+    let (chunk_w, chunk_h) = decoder.chunk_dimensions();
+    let chunks_across = match chunk_type {
+        ChunkType::Strip => 1,
+        ChunkType::Tile => width.div_ceil(chunk_w),
+    };
+    // Planar images store one chunk per sample plane
+    if chunks_across * height.div_ceil(chunk_h) != chunks || chunks < 2 {
+        return None;
+    }
+
+    let t = std::time::Instant::now();
+    // Each worker opens its own decoder and takes a contiguous run of chunks
+    let per_worker = chunks.div_ceil(rayon::current_num_threads() as u32).max(1);
+    let decoded: Vec<(u32, Vec<u8>)> = (0..chunks)
+        .step_by(per_worker as usize)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|start| -> Option<Vec<(u32, Vec<u8>)>> {
+            let mut decoder = open(path)?;
+            (start..(start + per_worker).min(chunks))
+                .map(|i| match decoder.read_chunk(i).ok()? {
+                    DecodingResult::U8(data) => Some((i, data)),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let stride = width as usize * samples;
+    let mut pixels = vec![0u8; stride * height as usize];
+    for (i, data) in decoded {
+        let (data_w, data_h) = decoder.chunk_data_dimensions(i);
+        let x = (i % chunks_across * chunk_w) as usize * samples;
+        let y = (i / chunks_across * chunk_h) as usize;
+        let row_len = data_w as usize * samples;
+        for (row, src) in data.chunks_exact(row_len).take(data_h as usize).enumerate() {
+            let dst = (y + row) * stride + x;
+            pixels[dst..dst + row_len].copy_from_slice(src);
+        }
+    }
+
+    debug!(
+        "Parallel decoded {path:?} ({width}x{height}, {chunks} chunks) in {:.3}ms",
+        t.elapsed().as_secs_f64() * 1000.0
+    );
+    match samples {
+        1 => image::GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        2 => image::GrayAlphaImage::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+        3 => image::RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+        _ => image::RgbaImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn striped_tiff_matches_single_threaded_decode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.tif");
+        let img = image::RgbImage::from_fn(1000, 1000, |x, y| {
+            image::Rgb([x as u8, y as u8, (x ^ y) as u8])
+        });
+        // The encoder splits into ~1MiB strips
+        tiff::encoder::TiffEncoder::new(File::create(&path).unwrap())
+            .unwrap()
+            .write_image::<tiff::encoder::colortype::RGB8>(1000, 1000, &img)
+            .unwrap();
+
+        assert!(decode(&path, ImageFormat::Tiff, u64::MAX).is_none());
+        let parallel = decode(&path, ImageFormat::Tiff, 0).unwrap();
+        assert_eq!(parallel, DynamicImage::ImageRgb8(img));
+    }
+}
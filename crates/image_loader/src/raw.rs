@@ -0,0 +1,179 @@
+use image::{DynamicImage, RgbImage};
+use log::{debug, error};
+use rayon::prelude::*;
+use std::path::Path;
+
+/// Camera RAW extensions, most of them TIFF containers that `image` would
+/// otherwise misdetect as a plain (and undecodable) TIFF.
+pub const RAW_EXTS: [&str; 8] = ["cr2", "nef", "arw", "dng", "orf", "rw2", "raf", "pef"];
+
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| RAW_EXTS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Largest JPEG preview the camera embedded in the file, far cheaper than a demosaic.
+pub fn embedded_preview(path: &Path) -> Option<DynamicImage> {
+    let data = std::fs::read(path)
+        .map_err(|e| error!("Failed to read RAW {path:?}: {e}"))
+        .ok()?;
+    let largest = jpeg_spans(&data)
+        .into_iter()
+        .filter_map(|r| {
+            let (w, h) = image::ImageReader::with_format(
+                std::io::Cursor::new(&data[r.clone()]),
+                image::ImageFormat::Jpeg,
+            )
+            .into_dimensions()
+            .ok()?;
+            Some((w as u64 * h as u64, r))
+        })
+        .max_by_key(|(pixels, _)| *pixels)?;
+    image::load_from_memory_with_format(&data[largest.1], image::ImageFormat::Jpeg)
+        .map_err(|e| error!("Embedded preview decode failed {path:?}: {e}"))
+        .ok()
+}
+
+/// Byte ranges from each JPEG start marker to the last end marker in the file.
+/// False positives inside compressed data are rejected by the header parse.
+fn jpeg_spans(data: &[u8]) -> Vec<std::ops::Range<usize>> {
+    let Some(last_eoi) = data.windows(2).rposition(|w| w == [0xFF, 0xD9]) else {
+        return Vec::new();
+    };
+    data.windows(3)
+        .enumerate()
+        .filter(|(i, w)| *i < last_eoi && *w == [0xFF, 0xD8, 0xFF])
+        .map(|(i, _)| i..last_eoi + 2)
+        .collect()
+}
+
+/// Full resolution decode: black/white level scaling, camera white balance,
+/// bilinear demosaic and an sRGB-ish gamma. No color matrix is applied.
+pub fn decode(path: &Path) -> Option<DynamicImage> {
+    let raw = rawloader::decode_file(path)
+        .map_err(|e| error!("RAW decode failed {path:?}: {e}"))
+        .ok()?;
+    let t = std::time::Instant::now();
+
+    let [top, right, bottom, left] = raw.crops;
+    let (w, h) = (raw.width, raw.height);
+    let (out_w, out_h) = (w.checked_sub(left + right)?, h.checked_sub(top + bottom)?);
+
+    // Relative to green, unknown coefficients come through as NaN
+    let green = raw.wb_coeffs[1];
+    let wb = raw.wb_coeffs.map(|c| {
+        let c = c / green;
+        if c.is_finite() && c > 0.0 { c } else { 1.0 }
+    });
+    let linear: Vec<f32> = match &raw.data {
+        rawloader::RawImageData::Integer(v) => v
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                let c = if raw.cpp == 1 {
+                    raw.cfa.color_at(i / w, i % w)
+                } else {
+                    i % raw.cpp
+                };
+                let black = raw.blacklevels[c] as f32;
+                let white = raw.whitelevels[c] as f32;
+                ((x as f32 - black) / (white - black).max(1.0)).clamp(0.0, 1.0)
+            })
+            .collect(),
+        rawloader::RawImageData::Float(v) => v.clone(),
+    };
+
+    let gamma = |v: f32| (v.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0) as u8;
+    let mut out = vec![0u8; out_w * out_h * 3];
+    out.par_chunks_mut(out_w * 3)
+        .enumerate()
+        .for_each(|(oy, row)| {
+            let y = oy + top;
+            for ox in 0..out_w {
+                let x = ox + left;
+                let rgb = if raw.cpp == 3 {
+                    let i = (y * w + x) * 3;
+                    [linear[i], linear[i + 1], linear[i + 2]]
+                } else {
+                    bilinear(&linear, w, h, x, y, |r, c| raw.cfa.color_at(r, c), &wb)
+                };
+                for (c, v) in rgb.into_iter().enumerate() {
+                    row[ox * 3 + c] = gamma(v);
+                }
+            }
+        });
+
+    debug!(
+        "Demosaiced {path:?} ({out_w}x{out_h}) in {:.3}ms",
+        t.elapsed().as_secs_f64() * 1000.0
+    );
+    RgbImage::from_raw(out_w as u32, out_h as u32, out).map(DynamicImage::ImageRgb8)
+}
+
+/// Average each channel over the 3x3 neighbourhood, white balanced per photosite.
+/// The 4th CFA color (a second green or emerald) is folded into green.
+fn bilinear(
+    data: &[f32],
+    w: usize,
+    h: usize,
+    x: usize,
+    y: usize,
+    color_at: impl Fn(usize, usize) -> usize,
+    wb: &[f32; 4],
+) -> [f32; 3] {
+    let mut sum = [0.0f32; 3];
+    let mut n = [0u32; 3];
+    for ny in y.saturating_sub(1)..(y + 2).min(h) {
+        for nx in x.saturating_sub(1)..(x + 2).min(w) {
+            let c = color_at(ny, nx);
+            let ch = if c == 3 { 1 } else { c };
+            sum[ch] += data[ny * w + nx] * wb[c];
+            n[ch] += 1;
+        }
+    }
+    [0, 1, 2].map(|c| if n[c] > 0 { sum[c] / n[c] as f32 } else { 0.0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_embedded_jpeg_previews() {
+        let jpeg = |w, h| {
+            let mut out = Vec::new();
+            DynamicImage::ImageRgb8(RgbImage::new(w, h))
+                .write_to(
+                    &mut std::io::Cursor::new(&mut out),
+                    image::ImageFormat::Jpeg,
+                )
+                .unwrap();
+            out
+        };
+        // Small thumbnail first, the larger preview after it
+        let mut file = b"II*\0fake raw header".to_vec();
+        file.extend_from_slice(&jpeg(4, 4));
+        file.extend_from_slice(&[0x12; 64]);
+        file.extend_from_slice(&jpeg(16, 8));
+        file.extend_from_slice(&[0x12; 64]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("IMG_0001.CR2");
+        std::fs::write(&path, &file).unwrap();
+
+        assert!(is_raw(&path));
+        let preview = embedded_preview(&path).unwrap();
+        assert_eq!((preview.width(), preview.height()), (16, 8));
+    }
+
+    #[test]
+    fn bilinear_fills_missing_channels() {
+        // RGGB, every photosite at full value
+        let rggb = |r: usize, c: usize| [[0, 1], [3, 2]][r % 2][c % 2];
+        let data = [1.0f32; 16];
+        let rgb = bilinear(&data, 4, 4, 1, 1, rggb, &[0.5, 1.0, 1.0, 1.0]);
+        assert_eq!(rgb, [0.5, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,106 @@
+//! Reads the files of the sliding window ahead of their decode jobs. Decoders read in small
+//! pieces as they go, which spinning disks and network mounts serve slowly. One thread going
+//! through the window nearest first, a whole file at a time, has the kernel get the bytes into
+//! the page cache while the workers are still busy with the shown image. Linux only.
+
+use log::trace;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+pub struct Readahead {
+    tx: Sender<Vec<PathBuf>>,
+}
+
+impl Readahead {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("readahead".into())
+            .spawn(move || run(rx, read_ahead))
+            .expect("Failed to spawn readahead thread");
+        Self { tx }
+    }
+
+    /// Read `paths` ahead in this order, instead of what is left of the previous request.
+    pub fn request(&self, paths: Vec<PathBuf>) {
+        let _ = self.tx.send(paths);
+    }
+}
+
+fn run(rx: Receiver<Vec<PathBuf>>, read: impl Fn(&Path) -> io::Result<()>) {
+    let mut queue = VecDeque::new();
+    loop {
+        // The window moved on, the rest of the old one can wait for its turn to come again
+        if let Ok(paths) = rx.try_recv() {
+            queue = paths.into();
+        }
+        match queue.pop_front() {
+            Some(path) => {
+                if let Err(e) = read(&path) {
+                    trace!("No readahead for {:?}: {e}", path);
+                }
+            }
+            None => match rx.recv() {
+                Ok(paths) => queue = paths.into(),
+                Err(_) => return,
+            },
+        }
+    }
+}
+
+/// Have the kernel read the file into the page cache in the background.
+#[cfg(target_os = "linux")]
+fn read_ahead(path: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let file = std::fs::File::open(path)?;
+    // SAFETY: the descriptor is open for the duration of the call, the advice only affects
+    // caching
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_WILLNEED) };
+    match ret {
+        0 => Ok(()),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+/// Reading the files through at normal priority would hold up the decoders,
+/// [`ImageLoader::set_readahead`](crate::ImageLoader::set_readahead) leaves readahead off.
+#[cfg(not(target_os = "linux"))]
+fn read_ahead(_path: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn newer_requests_replace_older_ones() {
+        let (tx, rx) = mpsc::channel();
+        let names = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        tx.send(names(&["a0", "a1", "a2"])).unwrap();
+        tx.send(names(&["b0", "b1"])).unwrap();
+        drop(tx);
+
+        let read = RefCell::new(Vec::new());
+        // Ends once the requests are done and the sender is gone
+        run(rx, |path: &Path| {
+            read.borrow_mut().push(path.to_path_buf());
+            Ok(())
+        });
+        assert_eq!(read.into_inner(), names(&["a0", "b0", "b1"]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn missing_files_get_no_readahead() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.jpg");
+        std::fs::write(&path, [0u8; 64]).unwrap();
+        assert!(read_ahead(&path).is_ok());
+        assert!(read_ahead(&dir.path().join("missing.jpg")).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Images at http and https URLs, downloaded with `curl` into memory. The URL stands in for a
+//! path, the [`Remote`](crate::decoder::Remote) decoder fetches it.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Larger downloads are refused.
+const MAX_SIZE: &str = "512M";
+/// Seconds a download may take.
+const TIMEOUT: &str = "120";
+
+/// The URL `path` stands for, `None` for paths on disk.
+pub fn url(path: &Path) -> Option<&str> {
+    path.to_str()
+        .filter(|p| p.starts_with("http://") || p.starts_with("https://"))
+}
+
+/// Contents of `url`, following redirects.
+pub fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--max-filesize",
+            MAX_SIZE,
+            "--max-time",
+            TIMEOUT,
+            "--",
+            url,
+        ])
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                io::Error::new(e.kind(), "opening URLs needs curl installed")
+            }
+            _ => e,
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_stand_in_for_paths() {
+        let path = Path::new("https://example.com/photos/a.jpg");
+        assert_eq!(url(path), Some("https://example.com/photos/a.jpg"));
+        assert_eq!(path.file_name().unwrap(), "a.jpg");
+        assert_eq!(url(Path::new("/photos/a.jpg")), None);
+        assert_eq!(url(Path::new("ftp://example.com/a.jpg")), None);
+    }
+}
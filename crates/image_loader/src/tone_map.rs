@@ -0,0 +1,140 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::path::Path;
+
+/// Extensions whose decoded data is linear float and needs tone mapping.
+pub const HDR_EXTS: [&str; 2] = ["hdr", "exr"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// `x / (1 + x)`, gentle but flattens highlights
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve, more contrast
+    #[default]
+    Aces,
+}
+
+impl ToneMapOperator {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "reinhard" => Some(ToneMapOperator::Reinhard),
+            "aces" => Some(ToneMapOperator::Aces),
+            _ => None,
+        }
+    }
+
+    fn map(self, x: f32) -> f32 {
+        match self {
+            ToneMapOperator::Reinhard => x / (1.0 + x),
+            ToneMapOperator::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                (x * (a * x + b)) / (x * (c * x + d) + e)
+            }
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ToneMap {
+    pub operator: ToneMapOperator,
+    /// Exposure adjustment in stops (EV) applied before the curve
+    pub exposure: f32,
+}
+
+pub fn is_hdr_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| HDR_EXTS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn is_hdr(img: &DynamicImage) -> bool {
+    matches!(
+        img,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    )
+}
+
+fn srgb_encode(v: f32) -> u8 {
+    let v = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (v * 255.0).round() as u8
+}
+
+/// Tone map linear float images to 8-bit sRGB, other images are returned as is.
+pub fn apply(img: DynamicImage, tone_map: ToneMap) -> DynamicImage {
+    if !is_hdr(&img) {
+        return img;
+    }
+    let scale = 2f32.powf(tone_map.exposure);
+    let linear = img.into_rgba32f();
+    let out = RgbaImage::from_fn(linear.width(), linear.height(), |x, y| {
+        let [r, g, b, a] = linear.get_pixel(x, y).0;
+        let map = |c: f32| srgb_encode(tone_map.operator.map(c.max(0.0) * scale));
+        Rgba([
+            map(r),
+            map(g),
+            map(b),
+            (a.clamp(0.0, 1.0) * 255.0).round() as u8,
+        ])
+    });
+    DynamicImage::ImageRgba8(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, Rgb32FImage};
+
+    fn hdr(values: &[f32]) -> DynamicImage {
+        DynamicImage::ImageRgb32F(Rgb32FImage::from_fn(values.len() as u32, 1, |x, _| {
+            let v = values[x as usize];
+            Rgb([v, v, v])
+        }))
+    }
+
+    #[test]
+    fn highlights_compress_instead_of_clipping() {
+        for operator in [ToneMapOperator::Reinhard, ToneMapOperator::Aces] {
+            let out = apply(
+                hdr(&[0.0, 1.0, 4.0, 16.0]),
+                ToneMap {
+                    operator,
+                    exposure: 0.0,
+                },
+            )
+            .into_rgba8();
+            let v: Vec<u8> = out.pixels().map(|p| p[0]).collect();
+            assert_eq!(v[0], 0, "{operator:?}");
+            assert!(v[1] < v[2] && v[2] < v[3], "{operator:?} {v:?}");
+            assert_eq!(out.get_pixel(0, 0)[3], 255);
+        }
+    }
+
+    #[test]
+    fn exposure_brightens_and_ldr_passes_through() {
+        let at = |exposure| {
+            apply(
+                hdr(&[0.25]),
+                ToneMap {
+                    exposure,
+                    ..Default::default()
+                },
+            )
+            .into_rgba8()
+            .get_pixel(0, 0)[0]
+        };
+        assert!(at(1.0) > at(0.0));
+        assert!(at(-1.0) < at(0.0));
+
+        let ldr = DynamicImage::ImageRgb8(image::RgbImage::new(2, 2));
+        assert_eq!(apply(ldr.clone(), ToneMap::default()), ldr);
+        assert_eq!(
+            ToneMapOperator::from_name("ACES"),
+            Some(ToneMapOperator::Aces)
+        );
+    }
+}
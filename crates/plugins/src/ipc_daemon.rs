@@ -1,9 +1,10 @@
-use crate::{Backend, manifest::PluginManifest};
+use crate::{Backend, BatchProgressFn, ErrorHook, Tag, manifest::PluginManifest};
 use log::{debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
 use shared_memory::{Shmem, ShmemConf};
 use slint::{Rgba8Pixel, SharedPixelBuffer};
-use std::io::{Read, Write};
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::net::{SocketAddr, TcpStream};
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
@@ -17,6 +18,64 @@ use std::sync::{
 // TODO: use SHM only on local linux combination
 const USE_SHM_TRANSFER: bool = false;
 
+/// Failures talking to a plugin daemon, by where they happened.
+#[derive(Debug)]
+pub enum IpcError {
+    /// The daemon couldn't be started or reached, or the connection broke
+    Transport(io::Error),
+    /// A message that doesn't parse, or a reply that doesn't fit the request
+    Protocol(String),
+    /// Shared memory for the image and mask couldn't be set up
+    Shm(shared_memory::ShmemError),
+    /// The daemon was busy or turned the request down
+    Daemon(String),
+}
+
+impl fmt::Display for IpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "{e}"),
+            Self::Protocol(message) => write!(f, "bad message: {message}"),
+            Self::Shm(e) => write!(f, "shared memory: {e}"),
+            Self::Daemon(message) => write!(f, "daemon: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for IpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e),
+            Self::Shm(e) => Some(e),
+            Self::Protocol(_) | Self::Daemon(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for IpcError {
+    fn from(e: io::Error) -> Self {
+        Self::Transport(e)
+    }
+}
+
+impl From<serde_json::Error> for IpcError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Protocol(e.to_string())
+    }
+}
+
+impl From<base64::DecodeError> for IpcError {
+    fn from(e: base64::DecodeError) -> Self {
+        Self::Protocol(e.to_string())
+    }
+}
+
+impl From<shared_memory::ShmemError> for IpcError {
+    fn from(e: shared_memory::ShmemError) -> Self {
+        Self::Shm(e)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum PluginControl {
     Enable, // Can be enabled
@@ -74,6 +133,33 @@ pub(crate) enum IpcCmd {
         paths: Vec<PathBuf>,
         query: String,
     },
+    Similar {
+        paths: Vec<PathBuf>,
+        query_path: PathBuf,
+    },
+    ProcessBatch {
+        paths: Vec<PathBuf>,
+        dst_dir: PathBuf,
+        args: serde_json::Value,
+    },
+    Upscale {
+        path: PathBuf,
+        dst_path: PathBuf,
+        scale: u32,
+    },
+    Tag {
+        path: PathBuf,
+        pixels: Vec<u8>,
+        width: u32,
+        height: u32,
+    },
+    Capture {
+        dst_dir: PathBuf,
+    },
+    SortKeys {
+        sorter: String,
+        paths: Vec<PathBuf>,
+    },
     Shutdown,
 }
 
@@ -90,6 +176,37 @@ pub(crate) enum IpcSearchResponse {
     SearchResult { paths: Vec<PathBuf> },
 }
 
+/// The daemon streams any number of `progress` messages before the final `done` or `error`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub(crate) enum IpcBatchResponse {
+    Progress { done: usize, total: usize },
+    Done { paths: Vec<PathBuf> },
+    Error { message: String },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub(crate) enum IpcTagResponse {
+    Ok { tags: Vec<Tag> },
+    Error { message: String },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub(crate) enum IpcCaptureResponse {
+    Ok { paths: Vec<PathBuf> },
+    Busy,
+    Error { message: String },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub(crate) enum IpcSortKeysResponse {
+    Ok { keys: Vec<Option<f64>> },
+    Error { message: String },
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum IpcStatus {
     NotRunning,
@@ -156,9 +273,49 @@ enum WorkerRequest {
         query: String,
         tx: mpsc::SyncSender<Option<Vec<PathBuf>>>,
     },
+    Similar {
+        paths: Vec<PathBuf>,
+        query_path: PathBuf,
+        tx: mpsc::SyncSender<Option<Vec<PathBuf>>>,
+    },
+    ProcessBatch {
+        paths: Vec<PathBuf>,
+        dst_dir: PathBuf,
+        args: serde_json::Value,
+        on_progress: ProgressCallback,
+        tx: mpsc::SyncSender<Option<Vec<PathBuf>>>,
+    },
+    Upscale {
+        path: PathBuf,
+        dst_path: PathBuf,
+        scale: u32,
+        tx: mpsc::SyncSender<Option<PathBuf>>,
+    },
+    Tag {
+        path: PathBuf,
+        thumb: SharedPixelBuffer<Rgba8Pixel>,
+        tx: mpsc::SyncSender<Option<Vec<Tag>>>,
+    },
+    Capture {
+        dst_dir: PathBuf,
+        tx: mpsc::SyncSender<Option<Vec<PathBuf>>>,
+    },
+    SortKeys {
+        sorter: String,
+        paths: Vec<PathBuf>,
+        tx: mpsc::SyncSender<Option<Vec<Option<f64>>>>,
+    },
     Shutdown,
 }
 
+struct ProgressCallback(BatchProgressFn);
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
 pub struct DaemonBackend {
     id: String,
     manifest: PluginManifest,
@@ -172,11 +329,17 @@ pub struct DaemonBackend {
     on_status_change: Arc<Mutex<Option<Box<dyn Fn(IpcStatus) + Send + Sync>>>>,
     state: Arc<RwLock<PluginControl>>,
     on_state_change: Arc<Mutex<Option<Box<dyn Fn(PluginControl) + Send + Sync>>>>,
+    on_error: ErrorHook,
     running: AtomicBool,
 }
 
 impl DaemonBackend {
-    pub fn new(id: String, manifest: &PluginManifest, dir: &Path) -> Arc<Self> {
+    pub fn new(
+        id: String,
+        manifest: &PluginManifest,
+        dir: &Path,
+        on_error: ErrorHook,
+    ) -> Arc<Self> {
         let (tx, rx) = mpsc::sync_channel::<WorkerRequest>(1);
 
         let status = Arc::new(RwLock::new(IpcStatus::NotRunning));
@@ -199,6 +362,7 @@ impl DaemonBackend {
             on_status_change: on_status_change.clone(),
             state: state.clone(),
             on_state_change: on_state_change.clone(),
+            on_error,
             running: AtomicBool::new(false),
         });
         daemon
@@ -267,6 +431,7 @@ impl Backend for DaemonBackend {
         let local = self.manifest.daemon_ip.is_none();
 
         self.set_state(PluginControl::Starting);
+        let mut start_error = None;
         let process = self.manifest.interpreter.as_ref().and_then(|interp| {
             if local {
                 let parts: Vec<&str> = interp.split_whitespace().collect();
@@ -287,6 +452,7 @@ impl Backend for DaemonBackend {
                     .stdout(Stdio::inherit())
                     .stderr(Stdio::inherit())
                     .spawn()
+                    .map_err(|e| start_error = Some(e))
                     .ok()
             } else {
                 info!("Remote plugin, only trying to connect");
@@ -295,7 +461,14 @@ impl Backend for DaemonBackend {
         });
         if process.is_none() && local {
             self.set_state(PluginControl::Enable);
-            error!("Failed to start daemon: {}", self.manifest.name)
+            error!("Failed to start daemon: {}", self.manifest.name);
+            let e = start_error.unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no interpreter to start it with",
+                )
+            });
+            crate::report(&self.on_error, &self.manifest.name, IpcError::Transport(e));
         }
         *self.process.lock().unwrap() = process;
         self.running.store(true, Ordering::SeqCst);
@@ -314,6 +487,8 @@ impl Backend for DaemonBackend {
         let on_state_w = self.on_state_change.clone();
         let status_w = self.status.clone();
         let on_status_w = self.on_status_change.clone();
+        let on_error = self.on_error.clone();
+        let name = self.manifest.name.clone();
         let pending_image = self.pending_image.clone();
         let image_token = self.image_token.clone();
 
@@ -328,6 +503,10 @@ impl Backend for DaemonBackend {
                         cb(s);
                     }
                 };
+                let fail = |e: IpcError| {
+                    crate::report(&on_error, &name, e);
+                    set_status(IpcStatus::Error);
+                };
                 let set_state = |s: PluginControl| {
                     debug!("State changing to: {:?}", s);
                     *state_w.write().unwrap() = s.clone();
@@ -337,10 +516,10 @@ impl Backend for DaemonBackend {
                 };
 
                 let mut stream = match connect_with_retry(ip, port, 30, 500) {
-                    Some(s) => s,
-                    None => {
+                    Ok(s) => s,
+                    Err(e) => {
                         error!("Failed to connect to daemon on port {port} after retries");
-                        set_status(IpcStatus::Error);
+                        fail(e.into());
                         *rx_mutex.lock().unwrap() = Some(rx);
                         set_state(PluginControl::Enable);
                         return;
@@ -382,7 +561,7 @@ impl Backend for DaemonBackend {
                                 }
                                 Err(e) => {
                                     error!("send_image failed: {e}");
-                                    set_status(IpcStatus::Error);
+                                    fail(e);
                                     break;
                                 }
                             }
@@ -454,6 +633,111 @@ impl Backend for DaemonBackend {
                                 }
                             }
                         }
+                        WorkerRequest::Similar {
+                            paths,
+                            query_path,
+                            tx,
+                        } => {
+                            debug!("similar ({:?})", query_path);
+                            match ipc_similar(&mut stream, paths, query_path) {
+                                Ok(result) => {
+                                    let _ = tx.send(result);
+                                }
+                                Err(e) => {
+                                    error!("similarity search failed: {e}");
+                                    let _ = tx.send(None);
+                                }
+                            }
+                        }
+                        WorkerRequest::ProcessBatch {
+                            paths,
+                            dst_dir,
+                            args,
+                            on_progress,
+                            tx,
+                        } => {
+                            debug!("process_batch ({} paths) -> {:?}", paths.len(), dst_dir);
+                            set_status(IpcStatus::Busy);
+                            match ipc_process_batch(
+                                &mut stream,
+                                paths,
+                                dst_dir,
+                                args,
+                                &on_progress.0,
+                            ) {
+                                Ok(result) => {
+                                    let _ = tx.send(result);
+                                    set_status(IpcStatus::Ready);
+                                }
+                                Err(e) => {
+                                    error!("process_batch failed: {e}");
+                                    let _ = tx.send(None);
+                                    fail(e);
+                                }
+                            }
+                        }
+                        WorkerRequest::Upscale {
+                            path,
+                            dst_path,
+                            scale,
+                            tx,
+                        } => {
+                            debug!("upscale x{scale} {:?} -> {:?}", path, dst_path);
+                            set_status(IpcStatus::Busy);
+                            match ipc_upscale(&mut stream, path, dst_path, scale) {
+                                Ok(result) => {
+                                    let _ = tx.send(result);
+                                    set_status(IpcStatus::Ready);
+                                }
+                                Err(e) => {
+                                    error!("upscale failed: {e}");
+                                    let _ = tx.send(None);
+                                    fail(e);
+                                }
+                            }
+                        }
+                        WorkerRequest::Tag { path, thumb, tx } => {
+                            trace!("tag {:?}", path);
+                            match ipc_tag(&mut stream, path, &thumb) {
+                                Ok(result) => {
+                                    let _ = tx.send(result);
+                                }
+                                Err(e) => {
+                                    error!("tag failed: {e}");
+                                    let _ = tx.send(None);
+                                }
+                            }
+                        }
+                        WorkerRequest::Capture { dst_dir, tx } => {
+                            debug!("capture -> {:?}", dst_dir);
+                            set_status(IpcStatus::Busy);
+                            match ipc_capture(&mut stream, dst_dir) {
+                                Ok(result) => {
+                                    let _ = tx.send(result);
+                                    set_status(IpcStatus::Ready);
+                                }
+                                Err(e) => {
+                                    error!("capture failed: {e}");
+                                    let _ = tx.send(None);
+                                    fail(e);
+                                }
+                            }
+                        }
+                        WorkerRequest::SortKeys { sorter, paths, tx } => {
+                            debug!("sort_keys {sorter} ({} paths)", paths.len());
+                            set_status(IpcStatus::Busy);
+                            match ipc_sort_keys(&mut stream, sorter, paths) {
+                                Ok(result) => {
+                                    let _ = tx.send(result);
+                                    set_status(IpcStatus::Ready);
+                                }
+                                Err(e) => {
+                                    error!("sort_keys failed: {e}");
+                                    let _ = tx.send(None);
+                                    fail(e);
+                                }
+                            }
+                        }
                         WorkerRequest::Shutdown => break,
                     }
                 }
@@ -634,6 +918,117 @@ impl Backend for DaemonBackend {
         result_rx.recv().ok().flatten()
     }
 
+    fn find_similar(&self, paths: &Vec<PathBuf>, query: &Path) -> Option<Vec<PathBuf>> {
+        if self.status() == IpcStatus::Busy {
+            warn!("Similarity search ignored: daemon is busy");
+            return None;
+        }
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        self.tx
+            .try_send(WorkerRequest::Similar {
+                paths: paths.clone(),
+                query_path: query.to_path_buf(),
+                tx: result_tx,
+            })
+            .map_err(|e| warn!("find_similar enqueue failed: {e}"))
+            .ok()?;
+        result_rx.recv().ok().flatten()
+    }
+
+    fn process_batch(
+        &self,
+        paths: &[PathBuf],
+        dst_dir: &Path,
+        args: serde_json::Value,
+        on_progress: BatchProgressFn,
+    ) -> Option<Vec<PathBuf>> {
+        if self.status() == IpcStatus::Busy {
+            warn!("Batch processing ignored: daemon is busy");
+            return None;
+        }
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        self.tx
+            .try_send(WorkerRequest::ProcessBatch {
+                paths: paths.to_vec(),
+                dst_dir: dst_dir.to_path_buf(),
+                args,
+                on_progress: ProgressCallback(on_progress),
+                tx: result_tx,
+            })
+            .map_err(|e| warn!("process_batch enqueue failed: {e}"))
+            .ok()?;
+        result_rx.recv().ok().flatten()
+    }
+
+    fn upscale(&self, path: &Path, dst_path: &Path, scale: u32) -> Option<PathBuf> {
+        if self.status() == IpcStatus::Busy {
+            warn!("Upscale ignored: daemon is busy");
+            return None;
+        }
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        self.tx
+            .try_send(WorkerRequest::Upscale {
+                path: path.to_path_buf(),
+                dst_path: dst_path.to_path_buf(),
+                scale,
+                tx: result_tx,
+            })
+            .map_err(|e| warn!("upscale enqueue failed: {e}"))
+            .ok()?;
+        result_rx.recv().ok().flatten()
+    }
+
+    fn tag(&self, path: &Path, thumb: &SharedPixelBuffer<Rgba8Pixel>) -> Option<Vec<Tag>> {
+        if self.status() == IpcStatus::Busy {
+            warn!("Tagging ignored: daemon is busy");
+            return None;
+        }
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        // Blocking send, tagging runs over many images back to back
+        self.tx
+            .send(WorkerRequest::Tag {
+                path: path.to_path_buf(),
+                thumb: thumb.clone(),
+                tx: result_tx,
+            })
+            .map_err(|e| warn!("tag enqueue failed: {e}"))
+            .ok()?;
+        result_rx.recv().ok().flatten()
+    }
+
+    fn capture(&self, dst_dir: &Path) -> Option<Vec<PathBuf>> {
+        if self.status() == IpcStatus::Busy {
+            warn!("Capture ignored: daemon is busy");
+            return None;
+        }
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        self.tx
+            .try_send(WorkerRequest::Capture {
+                dst_dir: dst_dir.to_path_buf(),
+                tx: result_tx,
+            })
+            .map_err(|e| warn!("capture enqueue failed: {e}"))
+            .ok()?;
+        result_rx.recv().ok().flatten()
+    }
+
+    fn sort_keys(&self, sorter: &str, paths: &[PathBuf]) -> Option<Vec<Option<f64>>> {
+        if self.status() == IpcStatus::Busy {
+            warn!("Sort ignored: daemon is busy");
+            return None;
+        }
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        self.tx
+            .try_send(WorkerRequest::SortKeys {
+                sorter: sorter.to_string(),
+                paths: paths.to_vec(),
+                tx: result_tx,
+            })
+            .map_err(|e| warn!("sort_keys enqueue failed: {e}"))
+            .ok()?;
+        result_rx.recv().ok().flatten()
+    }
+
     fn on_status_change(&self, cb: Box<dyn Fn(IpcStatus) + Send + Sync>) {
         *self.on_status_change.lock().unwrap() = Some(cb);
     }
@@ -653,30 +1048,33 @@ impl Drop for DaemonBackend {
     }
 }
 
+/// The error of the last attempt when none got through.
 fn connect_with_retry(
     ip: Option<String>,
     port: u16,
     attempts: u32,
     delay_ms: u64,
-) -> Option<TcpStream> {
+) -> io::Result<TcpStream> {
     let ip_str = ip.unwrap_or_else(|| "127.0.0.1".to_string());
     let addr = match std::net::IpAddr::from_str(&ip_str) {
         Ok(ip_addr) => SocketAddr::new(ip_addr, port),
         Err(e) => {
             error!("Invalid IP address '{}': {}", ip_str, e);
-            return None;
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
         }
     };
     debug!("Trying to connect to {addr}");
 
+    let mut last_error = io::Error::from(io::ErrorKind::NotConnected);
     for attempt in 0..attempts {
         match TcpStream::connect(addr) {
             Ok(s) => {
                 info!("Connected to daemon on port {port} (attempt {attempt})");
-                return Some(s);
+                return Ok(s);
             }
             Err(e) => {
                 trace!("Connection attempt {} failed: {}", attempt, e);
+                last_error = e;
                 if attempt < attempts {
                     std::thread::sleep(std::time::Duration::from_millis(delay_ms));
                 }
@@ -684,20 +1082,17 @@ fn connect_with_retry(
         }
     }
     error!("Failed to connect to daemon after {} attempts", attempts);
-    None
+    Err(last_error)
 }
 
-pub(crate) fn send_msg(
-    stream: &mut TcpStream,
-    cmd: &IpcCmd,
-) -> Result<(), Box<dyn std::error::Error>> {
+pub(crate) fn send_msg(stream: &mut TcpStream, cmd: &IpcCmd) -> Result<(), IpcError> {
     let payload = serde_json::to_vec(cmd)?;
     stream.write_all(&(payload.len() as u32).to_be_bytes())?;
     stream.write_all(&payload)?;
     Ok(())
 }
 
-pub(crate) fn recv_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub(crate) fn recv_msg(stream: &mut TcpStream) -> Result<Vec<u8>, IpcError> {
     let mut len_buf = [0u8; 4];
     stream.read_exact(&mut len_buf)?;
     let mut payload = vec![0u8; u32::from_be_bytes(len_buf) as usize];
@@ -709,7 +1104,7 @@ fn ipc_send_image(
     stream: &mut TcpStream,
     buf: &SharedPixelBuffer<Rgba8Pixel>,
     path: PathBuf,
-) -> Result<Option<ActiveShmem>, Box<dyn std::error::Error>> {
+) -> Result<Option<ActiveShmem>, IpcError> {
     let (w, h) = (buf.width(), buf.height());
 
     if USE_SHM_TRANSFER {
@@ -742,8 +1137,8 @@ fn ipc_send_image(
                 height: h,
                 path,
             })),
-            IpcResponse::Busy => Err("daemon busy".into()),
-            IpcResponse::Error { message } => Err(message.into()),
+            IpcResponse::Busy => Err(IpcError::Daemon("busy".into())),
+            IpcResponse::Error { message } => Err(IpcError::Daemon(message)),
         }
     } else {
         let raw_pixels = unsafe {
@@ -769,8 +1164,8 @@ fn ipc_send_image(
                 height: h,
                 path,
             })),
-            IpcResponse::Busy => Err("daemon busy".into()),
-            IpcResponse::Error { message } => Err(message.into()),
+            IpcResponse::Busy => Err(IpcError::Daemon("busy".into())),
+            IpcResponse::Error { message } => Err(IpcError::Daemon(message)),
         }
     }
 }
@@ -780,7 +1175,7 @@ fn ipc_click(
     shm: &ActiveShmem,
     x: u32,
     y: u32,
-) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, Box<dyn std::error::Error>> {
+) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, IpcError> {
     send_msg(
         stream,
         &IpcCmd::Click {
@@ -804,7 +1199,7 @@ fn ipc_rect_select(
     y1: u32,
     x2: u32,
     y2: u32,
-) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, Box<dyn std::error::Error>> {
+) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, IpcError> {
     send_msg(
         stream,
         &IpcCmd::RectSelect {
@@ -827,7 +1222,7 @@ fn ipc_text_to_mask(
     stream: &mut TcpStream,
     shm: &ActiveShmem,
     text: String,
-) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, Box<dyn std::error::Error>> {
+) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, IpcError> {
     send_msg(
         stream,
         &IpcCmd::TextToMask {
@@ -847,7 +1242,7 @@ fn ipc_search(
     stream: &mut TcpStream,
     paths: Vec<PathBuf>,
     query: String,
-) -> Result<Option<Vec<PathBuf>>, Box<dyn std::error::Error>> {
+) -> Result<Option<Vec<PathBuf>>, IpcError> {
     send_msg(stream, &IpcCmd::Search { paths, query })?;
     let response = serde_json::from_slice::<IpcSearchResponse>(&recv_msg(stream)?)?;
     match response {
@@ -855,10 +1250,125 @@ fn ipc_search(
     }
 }
 
+fn ipc_similar(
+    stream: &mut TcpStream,
+    paths: Vec<PathBuf>,
+    query_path: PathBuf,
+) -> Result<Option<Vec<PathBuf>>, IpcError> {
+    send_msg(stream, &IpcCmd::Similar { paths, query_path })?;
+    let response = serde_json::from_slice::<IpcSearchResponse>(&recv_msg(stream)?)?;
+    match response {
+        IpcSearchResponse::SearchResult { paths } => Ok(Some(paths)),
+    }
+}
+
+fn ipc_process_batch(
+    stream: &mut TcpStream,
+    paths: Vec<PathBuf>,
+    dst_dir: PathBuf,
+    args: serde_json::Value,
+    on_progress: &BatchProgressFn,
+) -> Result<Option<Vec<PathBuf>>, IpcError> {
+    send_msg(
+        stream,
+        &IpcCmd::ProcessBatch {
+            paths,
+            dst_dir,
+            args,
+        },
+    )?;
+    loop {
+        match serde_json::from_slice::<IpcBatchResponse>(&recv_msg(stream)?)? {
+            IpcBatchResponse::Progress { done, total } => {
+                trace!("process_batch progress {done}/{total}");
+                on_progress(done, total);
+            }
+            IpcBatchResponse::Done { paths } => return Ok(Some(paths)),
+            IpcBatchResponse::Error { message } => return Err(IpcError::Daemon(message)),
+        }
+    }
+}
+
+fn ipc_tag(
+    stream: &mut TcpStream,
+    path: PathBuf,
+    thumb: &SharedPixelBuffer<Rgba8Pixel>,
+) -> Result<Option<Vec<Tag>>, IpcError> {
+    send_msg(
+        stream,
+        &IpcCmd::Tag {
+            path,
+            pixels: thumb.as_bytes().to_vec(),
+            width: thumb.width(),
+            height: thumb.height(),
+        },
+    )?;
+    match serde_json::from_slice::<IpcTagResponse>(&recv_msg(stream)?)? {
+        IpcTagResponse::Ok { tags } => Ok(Some(tags)),
+        IpcTagResponse::Error { message } => Err(IpcError::Daemon(message)),
+    }
+}
+
+/// The daemon triggers the camera and downloads every resulting file (e.g. RAW + JPEG) into `dst_dir`.
+fn ipc_capture(stream: &mut TcpStream, dst_dir: PathBuf) -> Result<Option<Vec<PathBuf>>, IpcError> {
+    send_msg(stream, &IpcCmd::Capture { dst_dir })?;
+    match serde_json::from_slice::<IpcCaptureResponse>(&recv_msg(stream)?)? {
+        IpcCaptureResponse::Ok { paths } => Ok(Some(paths)),
+        IpcCaptureResponse::Busy => {
+            warn!("Daemon busy during capture");
+            Ok(None)
+        }
+        IpcCaptureResponse::Error { message } => Err(IpcError::Daemon(message)),
+    }
+}
+
+/// The daemon replies with one key per path, in the same order.
+fn ipc_sort_keys(
+    stream: &mut TcpStream,
+    sorter: String,
+    paths: Vec<PathBuf>,
+) -> Result<Option<Vec<Option<f64>>>, IpcError> {
+    let expected = paths.len();
+    send_msg(stream, &IpcCmd::SortKeys { sorter, paths })?;
+    match serde_json::from_slice::<IpcSortKeysResponse>(&recv_msg(stream)?)? {
+        IpcSortKeysResponse::Ok { keys } if keys.len() == expected => Ok(Some(keys)),
+        IpcSortKeysResponse::Ok { keys } => Err(IpcError::Protocol(format!(
+            "expected {expected} sort keys, got {}",
+            keys.len()
+        ))),
+        IpcSortKeysResponse::Error { message } => Err(IpcError::Daemon(message)),
+    }
+}
+
+/// The daemon writes the upscaled image to `dst_path` and replies with a plain `ok`.
+fn ipc_upscale(
+    stream: &mut TcpStream,
+    path: PathBuf,
+    dst_path: PathBuf,
+    scale: u32,
+) -> Result<Option<PathBuf>, IpcError> {
+    send_msg(
+        stream,
+        &IpcCmd::Upscale {
+            path,
+            dst_path: dst_path.clone(),
+            scale,
+        },
+    )?;
+    match serde_json::from_slice::<IpcResponse>(&recv_msg(stream)?)? {
+        IpcResponse::Ok { .. } => Ok(Some(dst_path)),
+        IpcResponse::Busy => {
+            warn!("Daemon busy during upscale");
+            Ok(None)
+        }
+        IpcResponse::Error { message } => Err(IpcError::Daemon(message)),
+    }
+}
+
 fn read_mask_response(
     stream: &mut TcpStream,
     shm: &ActiveShmem,
-) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, Box<dyn std::error::Error>> {
+) -> Result<Option<SharedPixelBuffer<Rgba8Pixel>>, IpcError> {
     match serde_json::from_slice::<IpcResponse>(&recv_msg(stream)?)? {
         IpcResponse::Ok { mask_data } => {
             let (w, h) = (shm.width, shm.height);
@@ -867,7 +1377,10 @@ fn read_mask_response(
                     base64::Engine::decode(&base64::engine::general_purpose::STANDARD, b64)?;
                 mask_to_rgba_overlay(&bytes)
             } else {
-                let mask_shm = shm.mask.as_ref().ok_or("no mask shm in shm mode")?;
+                let mask_shm = shm
+                    .mask
+                    .as_ref()
+                    .ok_or_else(|| IpcError::Protocol("mask reply without shared memory".into()))?;
                 let mask =
                     unsafe { std::slice::from_raw_parts(mask_shm.0.as_ptr(), (w * h) as usize) };
                 mask_to_rgba_overlay(mask)
@@ -878,7 +1391,7 @@ fn read_mask_response(
             warn!("Daemon busy during prediction");
             Ok(None)
         }
-        IpcResponse::Error { message } => Err(message.into()),
+        IpcResponse::Error { message } => Err(IpcError::Daemon(message)),
     }
 }
 
@@ -3,16 +3,36 @@ pub mod manifest;
 pub mod shared_lib;
 
 use image::DynamicImage;
-pub use ipc_daemon::{IpcStatus, PluginControl};
+pub use ipc_daemon::{IpcError, IpcStatus, PluginControl};
 pub use manifest::{BackendKind, PluginCapability, PluginManifest, load_manifest};
 
 use ipc_daemon::DaemonBackend;
 use log::{debug, error, info};
+use serde::Deserialize;
 use shared_lib::SharedLibBackend;
 use slint::{Rgba8Pixel, SharedPixelBuffer};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Progress callback for batch operations, called with `(done, total)`.
+pub type BatchProgressFn = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Gets failures of running plugins the user should hear about, see [`PluginManager::on_error`].
+pub(crate) type ErrorHook = Arc<Mutex<Option<Box<dyn Fn(&str, IpcError) + Send + Sync>>>>;
+
+pub(crate) fn report(hook: &ErrorHook, plugin: &str, error: IpcError) {
+    if let Some(f) = hook.lock().unwrap().as_ref() {
+        f(plugin, error);
+    }
+}
+
+/// A label returned by a tagger plugin.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub label: String,
+    pub confidence: f32,
+}
 
 // WARN: Duplicate from crate::fs_scan::ImageFormat;
 // use crate::fs_scan::ImageFormat;
@@ -35,6 +55,11 @@ pub trait Backend: Send + Sync {
     fn encode(&self, _path: &Path, _buf: &DynamicImage) -> bool {
         false
     }
+    /// Low and high end of the window the decoded image is best viewed with, in 0..1 of its
+    /// pixel values, e.g. from the Window Center/Width of a DICOM file.
+    fn default_window(&self, _path: &Path) -> Option<(f32, f32)> {
+        None
+    }
     fn set_image(&self, _buf: &SharedPixelBuffer<Rgba8Pixel>, _path: &PathBuf) -> bool {
         false
     }
@@ -56,6 +81,38 @@ pub trait Backend: Send + Sync {
     fn semantic_image_search(&self, _paths: &Vec<PathBuf>, _query: &str) -> Option<Vec<PathBuf>> {
         None
     }
+    /// Rank `paths` by similarity to the image at `query`, most similar first.
+    fn find_similar(&self, _paths: &Vec<PathBuf>, _query: &Path) -> Option<Vec<PathBuf>> {
+        None
+    }
+    /// Process a list of images in one call, writing the results into `dst_dir`.
+    /// Returns the output paths in the same order as `paths`.
+    fn process_batch(
+        &self,
+        _paths: &[PathBuf],
+        _dst_dir: &Path,
+        _args: serde_json::Value,
+        _on_progress: BatchProgressFn,
+    ) -> Option<Vec<PathBuf>> {
+        None
+    }
+    /// Upscale a single image by `scale`, writing the result to `dst_path`.
+    fn upscale(&self, _path: &Path, _dst_path: &Path, _scale: u32) -> Option<PathBuf> {
+        None
+    }
+    /// Classify a thumbnail of the image at `path`.
+    fn tag(&self, _path: &Path, _thumb: &SharedPixelBuffer<Rgba8Pixel>) -> Option<Vec<Tag>> {
+        None
+    }
+    /// Trigger the camera and download the captured frames into `dst_dir`.
+    fn capture(&self, _dst_dir: &Path) -> Option<Vec<PathBuf>> {
+        None
+    }
+    /// Key of each of `paths` for the named `sorter`, in the same order, `None` where it
+    /// couldn't be computed. Lower sorts first.
+    fn sort_keys(&self, _sorter: &str, _paths: &[PathBuf]) -> Option<Vec<Option<f64>>> {
+        None
+    }
     /// Callback invoked whenever the backend status changes
     fn on_status_change(&self, _cb: Box<dyn Fn(IpcStatus) + Send + Sync>) {}
     fn get_state(&self) -> PluginControl {
@@ -79,10 +136,11 @@ impl Plugin {
         dir: PathBuf,
         auto_start: bool,
         image_format_support: ImageFormat,
+        on_error: ErrorHook,
     ) -> Option<Self> {
         let backend: Box<dyn Backend> = match manifest.backend {
             BackendKind::Daemon => {
-                Box::new(DaemonBackend::new(id.clone(), &manifest, &dir) as Arc<_>)
+                Box::new(DaemonBackend::new(id.clone(), &manifest, &dir, on_error) as Arc<_>)
             }
             BackendKind::SharedLib => Box::new(SharedLibBackend::new(&manifest, &dir)?),
         };
@@ -141,6 +199,13 @@ impl Plugin {
         self.backend.encode(path, buf)
     }
 
+    pub fn default_window(&self, path: &Path) -> Option<(f32, f32)> {
+        if !self.manifest.has_capability(&PluginCapability::Decoder) {
+            return None;
+        }
+        self.backend.default_window(path)
+    }
+
     // -- interactive (daemon) --
     pub fn set_interactive_image(
         &self,
@@ -172,6 +237,81 @@ impl Plugin {
         self.backend.semantic_image_search(paths, query)
     }
 
+    pub fn find_similar(&self, paths: &Vec<PathBuf>, query: &Path) -> Option<Vec<PathBuf>> {
+        self.backend.find_similar(paths, query)
+    }
+
+    // -- batch (daemon) --
+    pub fn process_batch<F>(
+        &self,
+        paths: &[PathBuf],
+        dst_dir: &Path,
+        args: serde_json::Value,
+        on_progress: F,
+    ) -> Option<Vec<PathBuf>>
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        if !self.manifest.has_capability(&PluginCapability::Batch) {
+            error!(
+                "Plugin '{}' does not support batch processing",
+                self.manifest.name
+            );
+            return None;
+        }
+        self.backend
+            .process_batch(paths, dst_dir, args, Box::new(on_progress))
+    }
+
+    // -- upscaler (daemon) --
+    pub fn upscale(&self, path: &Path, dst_path: &Path, scale: u32) -> Option<PathBuf> {
+        if !self.manifest.has_capability(&PluginCapability::Upscaler) {
+            error!("Plugin '{}' does not support upscaling", self.manifest.name);
+            return None;
+        }
+        self.backend.upscale(path, dst_path, scale)
+    }
+
+    // -- tagger (daemon) --
+    pub fn tag(&self, path: &Path, thumb: &SharedPixelBuffer<Rgba8Pixel>) -> Option<Vec<Tag>> {
+        if !self.manifest.has_capability(&PluginCapability::Tagger) {
+            error!("Plugin '{}' does not support tagging", self.manifest.name);
+            return None;
+        }
+        self.backend.tag(path, thumb)
+    }
+
+    // -- capture (daemon) --
+    pub fn capture(&self, dst_dir: &Path) -> Option<Vec<PathBuf>> {
+        if !self.manifest.has_capability(&PluginCapability::Capture) {
+            error!("Plugin '{}' does not support capture", self.manifest.name);
+            return None;
+        }
+        self.backend.capture(dst_dir)
+    }
+
+    // -- sorter (daemon) --
+    /// Sort keys the plugin registers for the grid.
+    pub fn sorters(&self) -> Vec<String> {
+        self.manifest
+            .capabilities
+            .iter()
+            .filter_map(|cap| match cap {
+                PluginCapability::Sorter(names) => Some(names.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    pub fn sort_keys(&self, sorter: &str, paths: &[PathBuf]) -> Option<Vec<Option<f64>>> {
+        if !self.sorters().iter().any(|s| s == sorter) {
+            error!("Plugin '{}' has no sorter '{}'", self.manifest.name, sorter);
+            return None;
+        }
+        self.backend.sort_keys(sorter, paths)
+    }
+
     pub fn on_status_change<F>(&self, cb: F)
     where
         F: Fn(IpcStatus) + Send + Sync + 'static,
@@ -222,6 +362,30 @@ impl Backend for Arc<DaemonBackend> {
     fn semantic_image_search(&self, paths: &Vec<PathBuf>, query: &str) -> Option<Vec<PathBuf>> {
         DaemonBackend::semantic_image_search(self, paths, query)
     }
+    fn find_similar(&self, paths: &Vec<PathBuf>, query: &Path) -> Option<Vec<PathBuf>> {
+        DaemonBackend::find_similar(self, paths, query)
+    }
+    fn process_batch(
+        &self,
+        paths: &[PathBuf],
+        dst_dir: &Path,
+        args: serde_json::Value,
+        on_progress: BatchProgressFn,
+    ) -> Option<Vec<PathBuf>> {
+        DaemonBackend::process_batch(self, paths, dst_dir, args, on_progress)
+    }
+    fn upscale(&self, path: &Path, dst_path: &Path, scale: u32) -> Option<PathBuf> {
+        DaemonBackend::upscale(self, path, dst_path, scale)
+    }
+    fn tag(&self, path: &Path, thumb: &SharedPixelBuffer<Rgba8Pixel>) -> Option<Vec<Tag>> {
+        DaemonBackend::tag(self, path, thumb)
+    }
+    fn capture(&self, dst_dir: &Path) -> Option<Vec<PathBuf>> {
+        DaemonBackend::capture(self, dst_dir)
+    }
+    fn sort_keys(&self, sorter: &str, paths: &[PathBuf]) -> Option<Vec<Option<f64>>> {
+        DaemonBackend::sort_keys(self, sorter, paths)
+    }
     fn on_status_change(&self, cb: Box<dyn Fn(IpcStatus) + Send + Sync>) {
         DaemonBackend::on_status_change(self, move |s| cb(s));
     }
@@ -235,6 +399,7 @@ impl Backend for Arc<DaemonBackend> {
 
 pub struct PluginManager {
     plugins: Vec<Arc<Plugin>>,
+    on_error: ErrorHook,
 }
 
 impl Default for PluginManager {
@@ -247,9 +412,19 @@ impl PluginManager {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            on_error: Arc::default(),
         }
     }
 
+    /// Called from the plugin threads with the plugin's name when it fails while running, e.g.
+    /// its daemon can't be reached or a request to it fails. Details go to the log as before.
+    pub fn on_error<F>(&self, f: F)
+    where
+        F: Fn(&str, IpcError) + Send + Sync + 'static,
+    {
+        *self.on_error.lock().unwrap() = Some(Box::new(f));
+    }
+
     /// Scan a directory for plugin subdirectories containing a `plugin.json`.
     pub fn discover(&mut self, auto_start_ids: &[String]) -> Vec<String> {
         let plugins_dir = directories::ProjectDirs::from("", "", "luminous").and_then(|proj| {
@@ -323,6 +498,20 @@ impl PluginManager {
             .filter(|p| p.manifest.capabilities.contains(&PluginCapability::Search))
     }
 
+    pub fn get_batch_plugins(&self) -> impl Iterator<Item = &Arc<Plugin>> {
+        self.plugins
+            .iter()
+            .filter(|p| p.manifest.capabilities.contains(&PluginCapability::Batch))
+    }
+
+    pub fn get_upscaler_plugins(&self) -> impl Iterator<Item = &Arc<Plugin>> {
+        self.plugins.iter().filter(|p| {
+            p.manifest
+                .capabilities
+                .contains(&PluginCapability::Upscaler)
+        })
+    }
+
     // WARN: tmp, returns the first plugin
     // TODO: return by some kind of UUID?
     pub fn get_interactive_plugin(&self) -> Option<Arc<Plugin>> {
@@ -333,6 +522,31 @@ impl PluginManager {
         self.get_search_plugins().next().cloned()
     }
 
+    pub fn get_upscaler_plugin(&self) -> Option<Arc<Plugin>> {
+        self.get_upscaler_plugins().next().cloned()
+    }
+
+    pub fn get_tagger_plugins(&self) -> impl Iterator<Item = &Arc<Plugin>> {
+        self.plugins
+            .iter()
+            .filter(|p| p.manifest.capabilities.contains(&PluginCapability::Tagger))
+    }
+
+    pub fn get_capture_plugins(&self) -> impl Iterator<Item = &Arc<Plugin>> {
+        self.plugins
+            .iter()
+            .filter(|p| p.manifest.capabilities.contains(&PluginCapability::Capture))
+    }
+
+    pub fn get_sorter_plugins(&self) -> impl Iterator<Item = &Arc<Plugin>> {
+        self.plugins.iter().filter(|p| {
+            p.manifest
+                .capabilities
+                .iter()
+                .any(|cap| matches!(cap, PluginCapability::Sorter(_)))
+        })
+    }
+
     pub fn get_supported_extensions(&self) -> Vec<ImageFormat> {
         self.plugins
             .iter()
@@ -432,6 +646,19 @@ impl PluginManager {
         plugin.decode_dynamic(path)
     }
 
+    /// Display window suggested by the plugin decoding `path`, see [`Backend::default_window`].
+    pub fn default_window(&self, path: &Path) -> Option<(f32, f32)> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        let plugin = self.plugins.iter().find(|p| {
+            if let Ok(support) = p.image_format_support.read() {
+                support.decoding_support && support.exts.contains(&ext)
+            } else {
+                false
+            }
+        })?;
+        plugin.default_window(path)
+    }
+
     fn register(&mut self, id: String, dir: PathBuf, manifest: PluginManifest, auto_start: bool) {
         let plugin = match Plugin::new(
             id,
@@ -443,6 +670,7 @@ impl PluginManager {
                 decoding_support: false,
                 encoding_support: false,
             },
+            self.on_error.clone(),
         ) {
             Some(p) => Arc::new(p),
             None => {
@@ -478,6 +706,21 @@ impl PluginManager {
                 PluginCapability::Search => {
                     debug!("Search plugin '{}'", manifest.name);
                 }
+                PluginCapability::Batch => {
+                    debug!("Batch plugin '{}'", manifest.name);
+                }
+                PluginCapability::Upscaler => {
+                    debug!("Upscaler plugin '{}'", manifest.name);
+                }
+                PluginCapability::Tagger => {
+                    debug!("Tagger plugin '{}'", manifest.name);
+                }
+                PluginCapability::Capture => {
+                    debug!("Capture plugin '{}'", manifest.name);
+                }
+                PluginCapability::Sorter(sorters) => {
+                    debug!("Sorter plugin '{}': {:?}", manifest.name, sorters);
+                }
                 PluginCapability::Unknown => {
                     error!(
                         "Unknown capability in plugin '{}', not registering",
@@ -30,6 +30,12 @@ pub enum PluginCapability {
     Encoder,
     Interactive(Vec<InteractiveCapability>),
     Search,
+    Batch,
+    Upscaler,
+    Tagger,
+    Capture,
+    /// Named sort keys for the grid, e.g. `{"sorter": ["aspect_ratio", "dominant_hue"]}`
+    Sorter(Vec<String>),
     #[serde(other)]
     Unknown,
 }
@@ -282,4 +288,27 @@ mod tests {
         fs::write(&manifest_path, json).unwrap();
         assert!(load_manifest(&manifest_path).is_none());
     }
+
+    #[test]
+    fn load_manifest_batch_daemon() {
+        let temp_dir = TempDir::new().unwrap();
+        let json = r#"{"name":"upscaler","version":"1.0.0","backend":"daemon","extensions":[],"capabilities":["batch"],"daemon_port":8080,"interpreter":"python","entry":"main.py"}"#;
+        let manifest_path = temp_dir.path().join("manifest.json");
+        fs::write(&manifest_path, json).unwrap();
+        let manifest = load_manifest(&manifest_path).unwrap();
+        assert!(manifest.has_capability(&PluginCapability::Batch));
+    }
+
+    #[test]
+    fn load_manifest_sorter_daemon() {
+        let temp_dir = TempDir::new().unwrap();
+        let json = r#"{"name":"sorter","version":"1.0.0","backend":"daemon","extensions":[],"capabilities":[{"sorter":["aspect_ratio","dominant_hue"]}],"daemon_port":8080,"interpreter":"python","entry":"main.py"}"#;
+        let manifest_path = temp_dir.path().join("manifest.json");
+        fs::write(&manifest_path, json).unwrap();
+        let manifest = load_manifest(&manifest_path).unwrap();
+        assert!(manifest.has_capability(&PluginCapability::Sorter(vec![
+            "aspect_ratio".to_string(),
+            "dominant_hue".to_string()
+        ])));
+    }
 }
@@ -23,6 +23,10 @@ pub struct ImagePluginApi {
     save_image: unsafe extern "C" fn(path: *const i8, img: ImageBuffer) -> bool,
     free_image: unsafe extern "C" fn(img: ImageBuffer),
     get_plugin_info: unsafe extern "C" fn(name: *mut i8, n_max: i32, exts: *mut i8, e_max: i32),
+    /// Optional, default display window of a decoded image in 0..1 units, see
+    /// [`Backend::default_window`]
+    get_window:
+        Option<unsafe extern "C" fn(path: *const i8, low: *mut f32, high: *mut f32) -> bool>,
 }
 
 pub struct SharedLibBackend {
@@ -125,6 +129,15 @@ impl Backend for SharedLibBackend {
         debug!("Plugin FFI res={res}");
         res
     }
+
+    fn default_window(&self, path: &Path) -> Option<(f32, f32)> {
+        let get_window = self.container.get_window?;
+        let c_path = CString::new(path.to_str()?).ok()?;
+        let (mut low, mut high) = (0.0, 1.0);
+        let found = unsafe { get_window(c_path.as_ptr(), &mut low, &mut high) };
+        debug!("Plugin window of {:?}: {found} {low}..{high}", path);
+        found.then_some((low, high))
+    }
 }
 
 fn decode_ffi_buffer(ffi_buf: &ImageBuffer) -> Option<image::DynamicImage> {
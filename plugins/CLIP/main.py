@@ -164,6 +164,16 @@ class Worker:
         )
         return [r for r in results if r["_distance"] <= DIM_THRESHOLD]
 
+    def similar(self, query_path: str, limit: int = 50) -> list[dict]:
+        img = self.preprocess(Image.open(query_path)).unsqueeze(0).to(self.device)
+        with torch.no_grad():
+            vec = self.model.encode_image(img).float()
+            vec /= vec.norm(dim=-1, keepdim=True)
+
+        return (
+            self._db_table.search(vec.squeeze().cpu().numpy()).limit(limit).to_list()
+        )
+
 
 def handle_connection(conn: socket.socket, addr: tuple, worker: Worker) -> None:
     log.info(f"Host connected from {addr}")
@@ -201,6 +211,18 @@ def handle_connection(conn: socket.socket, addr: tuple, worker: Worker) -> None:
                     if "paths" in cmd:
                         worker.enqueue_index(cmd["paths"])
 
+                elif action == "similar":
+                    worker.ensure_table()
+                    paths = []
+                    if worker.is_ready():
+                        results = worker.similar(cmd["query_path"], cmd.get("limit", 50))
+                        paths = [r["path"] for r in results]
+
+                    send_resp(conn, {"SearchResult": {"paths": paths}})
+
+                    if "paths" in cmd:
+                        worker.enqueue_index(cmd["paths"])
+
                 elif action == "shutdown":
                     send_resp(conn, {"status": "ok"})
                     return
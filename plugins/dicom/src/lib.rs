@@ -0,0 +1,199 @@
+use dicom_dictionary_std::tags;
+use dicom_object::{DefaultDicomObject, open_file};
+use dicom_pixeldata::{
+    ConvertOptions, ModalityLutOption, PhotometricInterpretation, PixelDecoder, VoiLutOption,
+};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ImageBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+    pub width: u32,
+    pub height: u32,
+    pub channels: u32,
+}
+
+impl ImageBuffer {
+    fn null() -> Self {
+        Self {
+            data: std::ptr::null_mut(),
+            len: 0,
+            width: 0,
+            height: 0,
+            channels: 0,
+        }
+    }
+
+    fn from_vec(mut data: Vec<u8>, width: u32, height: u32, channels: u32) -> Self {
+        data.shrink_to_fit();
+        let len = data.len();
+        let ptr = data.as_mut_ptr();
+        std::mem::forget(data);
+        Self {
+            data: ptr,
+            len,
+            width,
+            height,
+            channels,
+        }
+    }
+}
+
+/// First frame of a grayscale image in modality units (e.g. Hounsfield for CT), with its range.
+struct Frame {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+    min: f32,
+    max: f32,
+    /// MONOCHROME1 images show their lowest values as white
+    inverted: bool,
+}
+
+fn path_arg<'a>(path: *const c_char) -> Option<&'a str> {
+    if path.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(path) }.to_str().ok()
+}
+
+fn open(path: *const c_char) -> Option<DefaultDicomObject> {
+    let path = path_arg(path)?;
+    open_file(path)
+        .map_err(|e| eprintln!("DICOM Error: Failed to open {}: {}", path, e))
+        .ok()
+}
+
+fn grayscale_frame(obj: &DefaultDicomObject) -> Option<Frame> {
+    let pixels = obj
+        .decode_pixel_data()
+        .map_err(|e| eprintln!("DICOM Error: Failed to decode pixel data: {}", e))
+        .ok()?;
+    if pixels.samples_per_pixel() != 1 {
+        return None;
+    }
+    // Only the modality LUT, windowing is left to the viewer
+    let options = ConvertOptions::new()
+        .with_modality_lut(ModalityLutOption::Default)
+        .with_voi_lut(VoiLutOption::Identity);
+    let values = pixels
+        .to_vec_frame_with_options::<f32>(0, &options)
+        .map_err(|e| eprintln!("DICOM Error: Failed to convert pixel data: {}", e))
+        .ok()?;
+    let (min, max) = values
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    Some(Frame {
+        width: pixels.columns(),
+        height: pixels.rows(),
+        values,
+        min,
+        max,
+        inverted: pixels.photometric_interpretation() == &PhotometricInterpretation::Monochrome1,
+    })
+}
+
+/// Position of `value` within the range of `frame`, which maps to 0..1 in the decoded buffer.
+fn normalize(frame: &Frame, value: f32) -> f32 {
+    let t = if frame.max > frame.min {
+        (value - frame.min) / (frame.max - frame.min)
+    } else {
+        0.0
+    };
+    if frame.inverted { 1.0 - t } else { t }
+}
+
+/// Decode the first frame. Grayscale images come out as one 8-bit channel spanning their whole
+/// value range, so the host's windowing can narrow it down, color images as RGB.
+#[unsafe(no_mangle)]
+pub extern "C" fn load_image(path: *const c_char) -> ImageBuffer {
+    let Some(obj) = open(path) else {
+        return ImageBuffer::null();
+    };
+
+    if let Some(frame) = grayscale_frame(&obj) {
+        let gray = frame
+            .values
+            .iter()
+            .map(|&v| (normalize(&frame, v) * 255.0).round().clamp(0.0, 255.0) as u8)
+            .collect();
+        return ImageBuffer::from_vec(gray, frame.width, frame.height, 1);
+    }
+
+    let rgb = match obj.decode_pixel_data().and_then(|p| p.to_dynamic_image(0)) {
+        Ok(img) => img.to_rgb8(),
+        Err(e) => {
+            eprintln!("DICOM Error: Failed to decode color image: {}", e);
+            return ImageBuffer::null();
+        }
+    };
+    let (w, h) = rgb.dimensions();
+    ImageBuffer::from_vec(rgb.into_raw(), w, h, 3)
+}
+
+/// Default VOI window of a grayscale image from its Window Center/Width tags, as the low and
+/// high end in the 0..1 units of the buffer returned by `load_image`.
+#[unsafe(no_mangle)]
+pub extern "C" fn get_window(path: *const c_char, low: *mut f32, high: *mut f32) -> bool {
+    if low.is_null() || high.is_null() {
+        return false;
+    }
+    let Some(obj) = open(path) else {
+        return false;
+    };
+    let first = |tag| {
+        obj.element(tag)
+            .ok()?
+            .to_multi_float32()
+            .ok()?
+            .first()
+            .copied()
+    };
+    let (Some(center), Some(width)) = (first(tags::WINDOW_CENTER), first(tags::WINDOW_WIDTH))
+    else {
+        return false;
+    };
+    let Some(frame) = grayscale_frame(&obj) else {
+        return false;
+    };
+
+    let (a, b) = (
+        normalize(&frame, center - width / 2.0),
+        normalize(&frame, center + width / 2.0),
+    );
+    unsafe {
+        *low = a.min(b).clamp(0.0, 1.0);
+        *high = a.max(b).clamp(0.0, 1.0);
+    }
+    true
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn save_image(_path: *const i8, _img: ImageBuffer) -> bool {
+    eprintln!("Error: image encoding is not supported");
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn free_image(img: ImageBuffer) {
+    if !img.data.is_null() {
+        unsafe {
+            let _ = Vec::from_raw_parts(img.data, img.len, img.len);
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_plugin_info(name: *mut i8, n_max: i32, exts: *mut i8, e_max: i32) {
+    let n = "DICOM Image Plugin\0";
+    let e = "dcm,dicom\0";
+    unsafe {
+        std::ptr::copy_nonoverlapping(n.as_ptr() as *const i8, name, n.len().min(n_max as usize));
+        std::ptr::copy_nonoverlapping(e.as_ptr() as *const i8, exts, e.len().min(e_max as usize));
+    }
+}
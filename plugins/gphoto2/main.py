@@ -0,0 +1,129 @@
+import json
+import logging as log
+import shutil
+import socket
+import struct
+import subprocess
+from datetime import datetime
+from pathlib import Path
+
+HOST = "127.0.0.1"
+PORT = 50024
+
+CAPTURE_TIMEOUT_S = 60
+
+
+def recv_msg(conn: socket.socket) -> dict | None:
+    try:
+        header = conn.recv(4)
+        if not header:
+            return None
+        msg_len = struct.unpack(">I", header)[0]
+        chunks = []
+        received = 0
+        while received < msg_len:
+            chunk = conn.recv(min(msg_len - received, 4096))
+            if not chunk:
+                raise RuntimeError("Connection broken")
+            chunks.append(chunk)
+            received += len(chunk)
+        return json.loads(b"".join(chunks))
+    except Exception:
+        return None
+
+
+def send_resp(conn: socket.socket, data: dict) -> None:
+    payload = json.dumps(data).encode()
+    conn.sendall(struct.pack(">I", len(payload)) + payload)
+
+
+def capture(dst_dir: str) -> list[str]:
+    """Trigger the camera and download all resulting files into `dst_dir`."""
+    dst = Path(dst_dir)
+    dst.mkdir(parents=True, exist_ok=True)
+    stem = datetime.now().strftime("capture_%Y%m%d_%H%M%S")
+    before = set(dst.glob(f"{stem}*"))
+
+    # %C is the camera's own file extension, so RAW + JPEG pairs keep both
+    result = subprocess.run(
+        [
+            "gphoto2",
+            "--capture-image-and-download",
+            "--force-overwrite",
+            "--filename",
+            str(dst / f"{stem}_%n.%C"),
+        ],
+        capture_output=True,
+        text=True,
+        timeout=CAPTURE_TIMEOUT_S,
+    )
+    if result.returncode != 0:
+        raise RuntimeError(result.stderr.strip() or "gphoto2 capture failed")
+
+    paths = sorted(set(dst.glob(f"{stem}*")) - before)
+    log.info(f"Captured {[p.name for p in paths]}")
+    return [str(p) for p in paths]
+
+
+def handle_connection(conn: socket.socket, addr: tuple) -> None:
+    log.info(f"Host connected from {addr}")
+    conn.setsockopt(socket.IPPROTO_TCP, socket.TCP_NODELAY, 1)
+
+    with conn:
+        while True:
+            cmd = recv_msg(conn)
+            if cmd is None:
+                log.info("Host disconnected.")
+                break
+
+            try:
+                action = cmd.get("action")
+                log.debug(f"{action=}")
+
+                if action == "ping":
+                    send_resp(conn, {"status": "ok"})
+
+                elif action == "capture":
+                    send_resp(conn, {"status": "ok", "paths": capture(cmd["dst_dir"])})
+
+                elif action == "shutdown":
+                    send_resp(conn, {"status": "ok"})
+                    return
+
+                else:
+                    log.error(f"Unknown action: {action}")
+                    send_resp(
+                        conn,
+                        {"status": "error", "message": f"Unknown action: {action}"},
+                    )
+
+            except Exception as e:
+                log.error(f"Capture error: {e}")
+                send_resp(conn, {"status": "error", "message": str(e)})
+
+
+def main() -> None:
+    log.basicConfig(
+        format="[gphoto2]:%(asctime)s:%(levelname)s:%(message)s", level=log.DEBUG
+    )
+
+    if shutil.which("gphoto2") is None:
+        log.error("gphoto2 not found on PATH")
+        return
+
+    try:
+        with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as srv:
+            srv.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+            srv.bind((HOST, PORT))
+            srv.listen(1)
+            log.info(f"gphoto2 daemon listening on {HOST}:{PORT}")
+            conn, addr = srv.accept()
+            handle_connection(conn, addr)
+    except (OSError, KeyboardInterrupt) as e:
+        log.error(f"Server error: {e}")
+    finally:
+        log.info("gphoto2 daemon exiting...")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,136 @@
+import colorsys
+import json
+import logging as log
+import socket
+import struct
+
+from PIL import Image
+
+HOST = "127.0.0.1"
+PORT = 50025
+
+# Hue key of images without a dominant color, sorts them after every hue
+NO_HUE = 2.0
+
+
+def recv_msg(conn: socket.socket) -> dict | None:
+    try:
+        header = conn.recv(4)
+        if not header:
+            return None
+        msg_len = struct.unpack(">I", header)[0]
+        chunks = []
+        received = 0
+        while received < msg_len:
+            chunk = conn.recv(min(msg_len - received, 4096))
+            if not chunk:
+                raise RuntimeError("Connection broken")
+            chunks.append(chunk)
+            received += len(chunk)
+        return json.loads(b"".join(chunks))
+    except Exception:
+        return None
+
+
+def send_resp(conn: socket.socket, data: dict) -> None:
+    payload = json.dumps(data).encode()
+    conn.sendall(struct.pack(">I", len(payload)) + payload)
+
+
+def aspect_ratio(path: str) -> float:
+    with Image.open(path) as img:
+        w, h = img.size
+    return w / h if h else 0.0
+
+
+def dominant_hue(path: str) -> float:
+    """Hue in 0..1 of the most common saturated color of a small thumbnail."""
+    with Image.open(path) as img:
+        img.draft("RGB", (64, 64))
+        thumb = img.convert("RGB").resize((32, 32))
+    colors = thumb.quantize(colors=8).convert("RGB").getcolors(32 * 32) or []
+    for _, (r, g, b) in sorted(colors, reverse=True):
+        h, s, v = colorsys.rgb_to_hsv(r / 255, g / 255, b / 255)
+        if s > 0.2 and v > 0.15:
+            return h
+    return NO_HUE
+
+
+SORTERS = {
+    "aspect_ratio": aspect_ratio,
+    "dominant_hue": dominant_hue,
+}
+
+
+def sort_keys(sorter: str, paths: list[str]) -> list[float | None]:
+    key = SORTERS[sorter]
+    keys = []
+    for path in paths:
+        try:
+            keys.append(key(path))
+        except Exception as e:
+            # Sorted after every valid key and asked for again next time
+            log.warning(f"{sorter} failed for {path}: {e}")
+            keys.append(None)
+    return keys
+
+
+def handle_connection(conn: socket.socket, addr: tuple) -> None:
+    log.info(f"Host connected from {addr}")
+    conn.setsockopt(socket.IPPROTO_TCP, socket.TCP_NODELAY, 1)
+
+    with conn:
+        while True:
+            cmd = recv_msg(conn)
+            if cmd is None:
+                log.info("Host disconnected.")
+                break
+
+            try:
+                action = cmd.get("action")
+                log.debug(f"{action=}")
+
+                if action == "ping":
+                    send_resp(conn, {"status": "ok"})
+
+                elif action == "sort_keys":
+                    keys = sort_keys(cmd["sorter"], cmd["paths"])
+                    send_resp(conn, {"status": "ok", "keys": keys})
+
+                elif action == "shutdown":
+                    send_resp(conn, {"status": "ok"})
+                    return
+
+                else:
+                    log.error(f"Unknown action: {action}")
+                    send_resp(
+                        conn,
+                        {"status": "error", "message": f"Unknown action: {action}"},
+                    )
+
+            except Exception as e:
+                log.error(f"Sort error: {e}")
+                send_resp(conn, {"status": "error", "message": str(e)})
+
+
+def main() -> None:
+    log.basicConfig(
+        format="[sort]:%(asctime)s:%(levelname)s:%(message)s", level=log.DEBUG
+    )
+
+    try:
+        with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as srv:
+            srv.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+            srv.bind((HOST, PORT))
+            srv.listen(1)
+            log.info(f"Sort daemon listening on {HOST}:{PORT}")
+            conn, addr = srv.accept()
+            handle_connection(conn, addr)
+    except (OSError, KeyboardInterrupt) as e:
+        log.error(f"Server error: {e}")
+    finally:
+        log.info("Sort daemon exiting...")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,108 @@
+//! Accessibility settings of the desktop, the defaults of the matching config options.
+
+use log::debug;
+use std::process::Command;
+
+/// Bit of the Windows `HighContrast` flags set while a contrast theme is on
+const HCF_HIGHCONTRASTON: u32 = 1;
+
+/// Whether the desktop asks applications to keep animations to a minimum: GNOME's
+/// `enable-animations`, KDE's animation speed, the macOS "Reduce motion" setting and the
+/// Windows "Animate controls and elements" one. False where none of them can be read.
+pub fn prefers_reduced_motion() -> bool {
+    let reduced = if cfg!(target_os = "macos") {
+        read(
+            "defaults",
+            &["read", "com.apple.universalaccess", "reduceMotion"],
+        )
+        .is_some_and(|v| v == "1")
+    } else if cfg!(windows) {
+        read(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Control Panel\Desktop\WindowMetrics",
+                "/v",
+                "MinAnimate",
+            ],
+        )
+        .is_some_and(|v| reg_value(&v) == Some("0"))
+    } else {
+        std::env::var("GTK_ENABLE_ANIMATIONS").is_ok_and(|v| v == "0")
+            || read(
+                "gsettings",
+                &["get", "org.gnome.desktop.interface", "enable-animations"],
+            )
+            .is_some_and(|v| v == "false")
+            || read(
+                "kreadconfig6",
+                &["--group", "KDE", "--key", "AnimationDurationFactor"],
+            )
+            .and_then(|v| v.parse::<f32>().ok())
+            .is_some_and(|factor| factor == 0.0)
+    };
+    debug!("Desktop prefers reduced motion: {reduced}");
+    reduced
+}
+
+/// Whether the desktop uses a high contrast theme: GNOME's `high-contrast`, the macOS "Increase
+/// contrast" setting and the Windows contrast themes.
+pub fn prefers_high_contrast() -> bool {
+    let high = if cfg!(target_os = "macos") {
+        read(
+            "defaults",
+            &["read", "com.apple.universalaccess", "increaseContrast"],
+        )
+        .is_some_and(|v| v == "1")
+    } else if cfg!(windows) {
+        read(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Control Panel\Accessibility\HighContrast",
+                "/v",
+                "Flags",
+            ],
+        )
+        .and_then(|v| reg_value(&v).and_then(|flags| flags.parse::<u32>().ok()))
+        .is_some_and(|flags| flags & HCF_HIGHCONTRASTON != 0)
+    } else {
+        read(
+            "gsettings",
+            &["get", "org.gnome.desktop.a11y.interface", "high-contrast"],
+        )
+        .is_some_and(|v| v == "true")
+    };
+    debug!("Desktop prefers high contrast: {high}");
+    high
+}
+
+/// Trimmed stdout of a command that succeeded.
+fn read(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The data of the value `reg query` printed, as in `MinAnimate    REG_SZ    0`.
+fn reg_value(output: &str) -> Option<&str> {
+    output
+        .lines()
+        .find_map(|line| line.split_once("REG_"))
+        .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reg_query_output() {
+        let output = "\r\nHKEY_CURRENT_USER\\Control Panel\\Desktop\\WindowMetrics\r\n    \
+                      MinAnimate    REG_SZ    0\r\n\r\n";
+        assert_eq!(reg_value(output), Some("0"));
+        assert_eq!(reg_value("ERROR: not found"), None);
+    }
+}
@@ -0,0 +1,455 @@
+//! Everything the viewer can be asked to do from outside its views: key bindings, the command
+//! palette, the control socket, D-Bus and scripts all name an [`Action`] and hand it to the
+//! one dispatcher, so a new action is added here and in the dispatcher only. [`Macro`]s from
+//! the config chain actions under a name of their own, and session scripts record and replay
+//! them, see [`crate::recording`].
+
+use crate::library::{ColorLabel, Flag};
+use crate::remote::Request;
+use crate::test_patterns::TestPattern;
+use crate::{EditOp, EditOpKind, ViewMode};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const RECENT_PREFIX: &str = "recent:";
+const PATTERN_PREFIX: &str = "pattern:";
+const MACRO_PREFIX: &str = "macro:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Pick a folder in a dialog
+    OpenFolder,
+    /// Pick an image in a dialog, shown with the rest of its folder
+    OpenFile,
+    /// Show this file with its folder, or this folder, and raise the window
+    Open(PathBuf),
+    /// Show a recently opened location again
+    OpenRecent(PathBuf),
+    CopyImage,
+    PasteImage,
+    ToggleFullscreen,
+    SwitchView,
+    /// Grid or full view
+    View(ViewMode),
+    /// Two pages side by side in the full view, or one
+    ToggleSpread,
+    /// Right to left reading, for the arrow keys and spreads
+    ToggleReadingDirection,
+    ShowFavorites,
+    LastBookmark,
+    ExportZip,
+    Rename,
+    ShowSettings,
+    CommandPalette,
+    TogglePerfOverlay,
+    /// Start the slideshow in the full view, or stop it
+    ToggleSlideshow,
+    /// Drop the memory caches, and the disk ones too with `disk`
+    PurgeCaches {
+        disk: bool,
+    },
+    TestPattern(TestPattern),
+    Next,
+    Prev,
+    /// Show the nth of the shown images, counted from 1
+    Goto(usize),
+    /// Show the image with this file name in the open folder
+    Show(String),
+    /// Position, count and path of the current image, see [`Request::Current`]
+    Current,
+    /// Raise the window
+    Focus,
+    Quit,
+    /// Rate the image in the full view, 0 clears the rating
+    Rate(u8),
+    Flag(Flag),
+    Label(ColorLabel),
+    ToggleFavorite,
+    /// Filter the grid, an empty query shows everything
+    Search(String),
+    Edit(EditOp),
+    /// Upscale the image in the full view with the upscaler plugin
+    Upscale(u32),
+    /// Run a batch plugin over the images with these file names
+    Batch {
+        plugin: String,
+        names: Vec<String>,
+    },
+    /// Run the steps of the macro with this name
+    Macro(String),
+}
+
+/// Offered in the command palette, in this order, before recent locations and test patterns.
+const PALETTE: [Action; 18] = [
+    Action::OpenFolder,
+    Action::OpenFile,
+    Action::CopyImage,
+    Action::PasteImage,
+    Action::ToggleFullscreen,
+    Action::SwitchView,
+    Action::ToggleSpread,
+    Action::ToggleReadingDirection,
+    Action::ToggleSlideshow,
+    Action::TogglePerfOverlay,
+    Action::ShowFavorites,
+    Action::LastBookmark,
+    Action::ExportZip,
+    Action::Rename,
+    Action::ShowSettings,
+    Action::PurgeCaches { disk: false },
+    Action::PurgeCaches { disk: true },
+    Action::Quit,
+];
+
+impl Action {
+    /// Name in the command palette and the key handler, `None` for actions that need more
+    /// than a name.
+    pub fn id(&self) -> Option<String> {
+        let id = match self {
+            Self::OpenFolder => "open",
+            Self::OpenFile => "open-file",
+            Self::CopyImage => "copy-image",
+            Self::PasteImage => "paste-image",
+            Self::ToggleFullscreen => "toggle-fullscreen",
+            Self::SwitchView => "switch-view",
+            Self::ToggleSpread => "toggle-spread",
+            Self::ToggleReadingDirection => "toggle-rtl",
+            Self::ToggleSlideshow => "toggle-slideshow",
+            Self::TogglePerfOverlay => "perf-overlay",
+            Self::CommandPalette => "command-palette",
+            Self::ShowFavorites => "favorites",
+            Self::LastBookmark => "last-bookmark",
+            Self::ExportZip => "export-zip",
+            Self::Rename => "rename",
+            Self::ShowSettings => "cache-usage",
+            Self::PurgeCaches { disk: false } => "purge-caches",
+            Self::PurgeCaches { disk: true } => "purge-caches-disk",
+            Self::Next => "next",
+            Self::Prev => "prev",
+            Self::Current => "current",
+            Self::Focus => "focus",
+            Self::Quit => "quit",
+            Self::ToggleFavorite => "toggle-favorite",
+            Self::OpenRecent(path) => return Some(format!("{RECENT_PREFIX}{}", path.display())),
+            Self::TestPattern(p) => return Some(format!("{PATTERN_PREFIX}{}", p.id())),
+            Self::Macro(name) => return Some(format!("{MACRO_PREFIX}{name}")),
+            Self::Open(_)
+            | Self::Goto(_)
+            | Self::Show(_)
+            | Self::View(_)
+            | Self::Rate(_)
+            | Self::Flag(_)
+            | Self::Label(_)
+            | Self::Search(_)
+            | Self::Edit(_)
+            | Self::Upscale(_)
+            | Self::Batch { .. } => return None,
+        };
+        Some(id.to_string())
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        if let Some(path) = id.strip_prefix(RECENT_PREFIX) {
+            return Some(Self::OpenRecent(PathBuf::from(path)));
+        }
+        if let Some(pattern) = id.strip_prefix(PATTERN_PREFIX) {
+            return TestPattern::from_id(pattern).map(Self::TestPattern);
+        }
+        if let Some(name) = id.strip_prefix(MACRO_PREFIX) {
+            return Some(Self::Macro(name.to_string()));
+        }
+        let action = match id {
+            "next" => Self::Next,
+            "prev" => Self::Prev,
+            "current" => Self::Current,
+            "focus" => Self::Focus,
+            "toggle-favorite" => Self::ToggleFavorite,
+            "command-palette" => Self::CommandPalette,
+            _ => PALETTE
+                .into_iter()
+                .find(|a| a.id().is_some_and(|a| a == id))?,
+        };
+        Some(action)
+    }
+
+    /// An action as written in a macro step or session script: its id, followed by the
+    /// argument for `set-rating 5`, `set-flag pick`, `set-label red`, `goto 12`, `open PATH`,
+    /// `show NAME`, `view grid`, `search QUERY`, `edit KIND [INT FLOAT TEXT]`, `upscale 2` and
+    /// `batch PLUGIN<tab>NAME...`. Underscores may stand in for dashes, `next-image` and
+    /// `prev-image` for `next` and `prev`.
+    pub fn parse(step: &str) -> Result<Self, String> {
+        let step = step.trim();
+        if let Some(action) = Self::from_id(step) {
+            return Ok(action);
+        }
+        let (name, arg) = step
+            .split_once(char::is_whitespace)
+            .map_or((step, ""), |(name, arg)| (name, arg.trim()));
+        let name = name.replace('_', "-");
+        let action = match (name.as_str(), arg) {
+            ("set-rating", n) => n
+                .parse()
+                .ok()
+                .filter(|&n| n <= 5)
+                .map(Self::Rate)
+                .ok_or_else(|| format!("invalid rating '{n}', expected 0 to 5"))?,
+            ("set-flag", f) => Flag::from_name(f)
+                .map(Self::Flag)
+                .ok_or_else(|| format!("invalid flag '{f}', expected pick, reject or none"))?,
+            ("set-label", l) => ColorLabel::from_name(l).map(Self::Label).ok_or_else(|| {
+                format!("invalid label '{l}', expected red, yellow, green, blue or none")
+            })?,
+            ("goto", n) => n
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .map(Self::Goto)
+                .ok_or_else(|| format!("invalid image number '{n}'"))?,
+            ("open", path) if !path.is_empty() => Self::Open(PathBuf::from(path)),
+            ("show", name) if !name.is_empty() => Self::Show(name.to_string()),
+            ("view", "grid") => Self::View(ViewMode::Grid),
+            ("view", "full") => Self::View(ViewMode::Full),
+            ("view", v) => return Err(format!("invalid view '{v}', expected grid or full")),
+            ("search", query) => Self::Search(query.to_string()),
+            ("edit", args) if !args.is_empty() => Self::Edit(parse_edit(args)?),
+            ("upscale", n) => Self::Upscale(number(n)?),
+            ("batch", args) if !args.is_empty() => {
+                let mut fields = args.split('\t').map(str::trim);
+                Self::Batch {
+                    plugin: fields.next().unwrap_or_default().to_string(),
+                    names: fields.map(String::from).collect(),
+                }
+            }
+            ("next-image", "") => Self::Next,
+            ("prev-image", "") => Self::Prev,
+            (id, "") => Self::from_id(id).ok_or_else(|| format!("unknown action '{id}'"))?,
+            (id, _) => return Err(format!("'{id}' takes no argument")),
+        };
+        Ok(action)
+    }
+
+    /// The action as a macro step, read back by [`Self::parse`]. Tabs and line breaks in names
+    /// and queries become spaces.
+    pub fn step(&self) -> String {
+        match self {
+            Self::Open(path) => format!("open {}", field(&path.to_string_lossy())),
+            Self::Goto(n) => format!("goto {n}"),
+            Self::Show(name) => format!("show {}", field(name)),
+            Self::View(ViewMode::Grid) => "view grid".to_string(),
+            Self::View(ViewMode::Full) => "view full".to_string(),
+            Self::Rate(n) => format!("set-rating {n}"),
+            Self::Flag(flag) => format!("set-flag {}", flag.name()),
+            Self::Label(label) => format!("set-label {}", label.name()),
+            Self::Search(query) => format!("search {}", field(query)),
+            Self::Edit(op) => {
+                let kind = EDIT_KINDS
+                    .iter()
+                    .find(|(k, _)| *k == op.kind)
+                    .map_or("reset", |(_, name)| name);
+                format!(
+                    "edit {kind} {} {} {}",
+                    op.int_val,
+                    op.float_val,
+                    field(&op.string_val)
+                )
+            }
+            Self::Upscale(scale) => format!("upscale {scale}"),
+            Self::Batch { plugin, names } => names
+                .iter()
+                .fold(format!("batch\t{}", field(plugin)), |step, name| {
+                    step + "\t" + &field(name)
+                }),
+            _ => self.id().unwrap_or_default(),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let title = match self {
+            Self::OpenFolder => "Open folder",
+            Self::OpenFile => "Open image",
+            Self::CopyImage => "Copy image to clipboard",
+            Self::PasteImage => "Paste image from clipboard",
+            Self::ToggleFullscreen => "Toggle fullscreen",
+            Self::SwitchView => "Switch grid/full view",
+            Self::View(ViewMode::Grid) => "Grid view",
+            Self::View(ViewMode::Full) => "Full view",
+            Self::ToggleSpread => "Toggle two-page spread",
+            Self::ToggleReadingDirection => "Toggle right-to-left reading",
+            Self::ToggleSlideshow => "Toggle slideshow",
+            Self::TogglePerfOverlay => "Toggle performance overlay",
+            Self::CommandPalette => "Command palette",
+            Self::ShowFavorites => "Show favorites",
+            Self::LastBookmark => "Jump to last favorite",
+            Self::ExportZip => "Export images as zip",
+            Self::Rename => "Rename images",
+            Self::ShowSettings => "Show cache usage",
+            Self::PurgeCaches { disk: false } => "Purge memory caches",
+            Self::PurgeCaches { disk: true } => "Purge memory and disk caches",
+            Self::Next => "Next image",
+            Self::Prev => "Previous image",
+            Self::Current => "Current image",
+            Self::Focus => "Raise the window",
+            Self::Quit => "Quit",
+            Self::ToggleFavorite => "Toggle favorite",
+            Self::Open(path) => return format!("Open {}", path.display()),
+            Self::OpenRecent(path) => return format!("Open recent: {}", path.display()),
+            Self::TestPattern(p) => return format!("Test pattern: {}", p.title()),
+            Self::Goto(n) => return format!("Go to image {n}"),
+            Self::Show(name) => return format!("Show {name}"),
+            Self::Search(query) => return format!("Search for '{query}'"),
+            Self::Edit(op) => return format!("Edit: {:?}", op.kind),
+            Self::Upscale(scale) => return format!("Upscale {scale}x"),
+            Self::Batch { plugin, names } => {
+                return format!("Run {plugin} on {} images", names.len());
+            }
+            Self::Rate(0) => "Clear rating",
+            Self::Rate(n) => return format!("Rate {n} stars"),
+            Self::Flag(Flag::None) => "Clear flag",
+            Self::Flag(flag) => return format!("Flag as {}", flag.name()),
+            Self::Label(ColorLabel::None) => "Clear label",
+            Self::Label(label) => return format!("Label {}", label.name()),
+            Self::Macro(name) => return format!("Macro: {name}"),
+        };
+        title.to_string()
+    }
+
+    /// Everything the command palette offers, with `macros` and `recent` locations.
+    pub fn palette(macros: &[Macro], recent: &[PathBuf]) -> Vec<Self> {
+        PALETTE
+            .into_iter()
+            .chain(macros.iter().map(|m| Self::Macro(m.name.clone())))
+            .chain(recent.iter().cloned().map(Self::OpenRecent))
+            .chain(TestPattern::ALL.into_iter().map(Self::TestPattern))
+            .collect()
+    }
+}
+
+const EDIT_KINDS: [(EditOpKind, &str); 12] = [
+    (EditOpKind::RotateCW, "rotate-cw"),
+    (EditOpKind::RotateCCW, "rotate-ccw"),
+    (EditOpKind::FlipH, "flip-h"),
+    (EditOpKind::FlipV, "flip-v"),
+    (EditOpKind::Brighten, "brighten"),
+    (EditOpKind::Contrast, "contrast"),
+    (EditOpKind::Crop, "crop"),
+    (EditOpKind::ColorSpace, "color-space"),
+    (EditOpKind::Reset, "reset"),
+    (EditOpKind::Copy, "copy"),
+    (EditOpKind::Delete, "delete"),
+    (EditOpKind::Save, "save"),
+];
+
+/// `KIND [INT FLOAT TEXT]` of an `edit` step, the values left out are zero and empty.
+fn parse_edit(args: &str) -> Result<EditOp, String> {
+    let mut fields = args.splitn(4, char::is_whitespace);
+    let name = fields.next().unwrap_or_default();
+    let kind = EDIT_KINDS
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(kind, _)| *kind)
+        .ok_or_else(|| format!("unknown edit '{name}'"))?;
+    let int_val = fields.next().map_or(Ok(0), number)?;
+    let float_val = fields.next().map_or(Ok(0.0), number)?;
+    Ok(EditOp {
+        kind,
+        int_val,
+        float_val,
+        string_val: fields.next().unwrap_or_default().into(),
+    })
+}
+
+fn number<T: FromStr>(s: &str) -> Result<T, String> {
+    s.parse()
+        .map_err(|_| format!("expected a number, got '{s}'"))
+}
+
+/// Steps are one line, with tabs between batch names.
+fn field(s: &str) -> String {
+    s.replace(['\t', '\n', '\r'], " ")
+}
+
+impl From<Request> for Action {
+    fn from(request: Request) -> Self {
+        match request {
+            Request::Open(path) => Self::Open(path),
+            Request::Focus => Self::Focus,
+            Request::Next => Self::Next,
+            Request::Prev => Self::Prev,
+            Request::Goto(n) => Self::Goto(n),
+            Request::Current => Self::Current,
+            Request::Quit => Self::Quit,
+        }
+    }
+}
+
+/// Actions run one after the other under one name, from `[macros]` in the config. Keys bound to
+/// the name, the built-in bindings like `rate_5` included, run the macro instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<Action>,
+}
+
+impl Macro {
+    /// The macro `name` with all of `steps`, or what is wrong with the first bad one.
+    pub fn parse(name: &str, steps: &[String]) -> Result<Self, String> {
+        if steps.is_empty() {
+            return Err("no steps".to_string());
+        }
+        let steps = steps
+            .iter()
+            .map(|step| match Action::parse(step)? {
+                Action::Macro(_) => Err("macros can't run other macros".to_string()),
+                action => Ok(action),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: name.to_string(),
+            steps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_round_trip() {
+        let cull = Macro::parse("cull", &["next".to_string()]).unwrap();
+        for action in Action::palette(&[cull], &[PathBuf::from("/photos/2024")]) {
+            let id = action.id().unwrap();
+            assert_eq!(Action::from_id(&id), Some(action), "{id}");
+        }
+        assert_eq!(Action::from_id("next"), Some(Action::Next));
+        assert_eq!(Action::from_id("pattern:nope"), None);
+        assert_eq!(Action::from_id("launch"), None);
+        assert_eq!(Action::Goto(3).id(), None);
+    }
+
+    #[test]
+    fn macro_steps() {
+        let steps = ["set_rating 5", "next_image", "set-flag pick", "goto 3"].map(String::from);
+        let rate_5 = Macro::parse("rate_5", &steps).unwrap();
+        assert_eq!(
+            rate_5.steps,
+            [
+                Action::Rate(5),
+                Action::Next,
+                Action::Flag(Flag::Pick),
+                Action::Goto(3)
+            ]
+        );
+        assert_eq!(
+            Action::parse("recent:/photos/my trip"),
+            Ok(Action::OpenRecent("/photos/my trip".into()))
+        );
+
+        let bad = |step: &str| Macro::parse("m", &[step.to_string()]).unwrap_err();
+        assert_eq!(bad("set-rating 6"), "invalid rating '6', expected 0 to 5");
+        assert_eq!(bad("next 2"), "'next' takes no argument");
+        assert_eq!(bad("launch"), "unknown action 'launch'");
+        assert_eq!(bad("macro:m"), "macros can't run other macros");
+        assert!(Macro::parse("m", &[]).is_err());
+    }
+}
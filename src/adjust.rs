@@ -0,0 +1,136 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+
+/// Tonal and color adjustments, shown as a view transform until baked into an export.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjustments {
+    /// Offset added to every channel, -1 (black) to 1 (white)
+    pub brightness: f32,
+    /// -1 flattens to mid gray, 1 is the steepest curve
+    pub contrast: f32,
+    /// -1 is grayscale, 1 doubles the saturation
+    pub saturation: f32,
+    /// Above 1 lifts the midtones, below 1 darkens them
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl Adjustments {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Gamma, contrast and brightness folded into one table, they act on each channel alone.
+    fn tone_lut(&self) -> [u8; 256] {
+        let gamma = 1.0 / self.gamma.max(0.01);
+        let c = self.contrast.clamp(-1.0, 1.0);
+        let slope = (1.0 + c) / (1.0 - c).max(0.01);
+        std::array::from_fn(|i| {
+            let v = (i as f32 / 255.0).powf(gamma);
+            let v = (v - 0.5) * slope + 0.5 + self.brightness;
+            (v.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+    }
+}
+
+/// An adjusted copy of `src`, alpha is kept.
+pub fn apply(
+    src: &SharedPixelBuffer<Rgba8Pixel>,
+    adj: Adjustments,
+) -> SharedPixelBuffer<Rgba8Pixel> {
+    let mut out = src.clone();
+    let w = out.width() as usize;
+    if w == 0 || adj.is_identity() {
+        return out;
+    }
+    let lut = adj.tone_lut();
+    let sat = 1.0 + adj.saturation.clamp(-1.0, 1.0);
+    out.make_mut_slice().par_chunks_mut(w).for_each(|row| {
+        for px in row {
+            let [r, g, b] = [px.r, px.g, px.b].map(|c| lut[c as usize] as f32);
+            // Rec. 709 luma, saturation moves the channels away from or towards it
+            let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            let mix = |c: f32| (y + (c - y) * sat).clamp(0.0, 255.0).round() as u8;
+            px.r = mix(r);
+            px.g = mix(g);
+            px.b = mix(b);
+        }
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(p: Rgba8Pixel, adj: Adjustments) -> Rgba8Pixel {
+        let mut buf = SharedPixelBuffer::new(1, 1);
+        buf.make_mut_slice()[0] = p;
+        apply(&buf, adj).as_slice()[0]
+    }
+
+    fn px(r: u8, g: u8, b: u8, a: u8) -> Rgba8Pixel {
+        Rgba8Pixel { r, g, b, a }
+    }
+
+    #[test]
+    fn neutral_settings_change_nothing() {
+        let adj = Adjustments::default();
+        assert!(adj.is_identity());
+        for i in 0..=255 {
+            assert_eq!(adj.tone_lut()[i], i as u8);
+        }
+        assert_eq!(single(px(10, 200, 90, 40), adj), px(10, 200, 90, 40));
+    }
+
+    #[test]
+    fn tone_controls() {
+        let mid = px(128, 128, 128, 255);
+        let brighter = Adjustments {
+            brightness: 0.25,
+            ..Default::default()
+        };
+        assert!(single(mid, brighter).r > 180);
+        let flat = Adjustments {
+            contrast: -1.0,
+            ..Default::default()
+        };
+        assert_eq!(single(px(0, 255, 30, 255), flat), px(128, 128, 128, 255));
+        let lifted = Adjustments {
+            gamma: 2.0,
+            ..Default::default()
+        };
+        let out = single(mid, lifted);
+        assert!(out.r > 170, "{out:?}");
+        assert_eq!(single(px(0, 0, 0, 9), lifted), px(0, 0, 0, 9));
+    }
+
+    #[test]
+    fn saturation_moves_towards_gray() {
+        let gray = Adjustments {
+            saturation: -1.0,
+            ..Default::default()
+        };
+        let out = single(px(200, 40, 40, 128), gray);
+        assert!(out.r == out.g && out.g == out.b, "{out:?}");
+        assert_eq!(out.a, 128);
+        let vivid = Adjustments {
+            saturation: 1.0,
+            ..Default::default()
+        };
+        let out = single(px(150, 100, 100, 255), vivid);
+        assert!(out.r > 150 && out.g < 100, "{out:?}");
+    }
+}
@@ -3,6 +3,7 @@ use directories::ProjectDirs;
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
 use slint::ComponentHandle;
+use std::path::PathBuf;
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct AppState {
@@ -13,7 +14,30 @@ pub struct AppState {
     pub height: u32,
     pub full_view_footer_visible: bool,
     pub full_view_side_panel_visible: bool,
+    #[serde(default)]
+    pub full_view_filmstrip_enabled: bool,
     pub grid_view_side_panel_visible: bool,
+    #[serde(default)]
+    pub session: Option<Session>,
+}
+
+/// Where the last run left off, reopened when started without a path. Sort order and fit mode
+/// come back with the folder's view profile.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub dir: PathBuf,
+    /// Image shown in the full view, found again by name if files were added or removed
+    pub image: Option<PathBuf>,
+    pub index: usize,
+    pub full_view: bool,
+    pub zoom: f32,
+}
+
+/// Position and size of the window outside of fullscreen.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowGeometry {
+    pub position: slint::PhysicalPosition,
+    pub size: slint::PhysicalSize,
 }
 
 pub fn load_app_state() -> AppState {
@@ -31,7 +55,13 @@ pub fn load_app_state() -> AppState {
         })
 }
 
-pub fn save_app_state(window: &MainWindow) {
+/// `windowed_geometry` is saved instead of the current one while in fullscreen,
+/// so the next start restores the window to its pre-fullscreen size.
+pub fn save_app_state(
+    window: &MainWindow,
+    windowed_geometry: Option<WindowGeometry>,
+    session: Option<Session>,
+) {
     if let Some(dirs) = ProjectDirs::from("", "", "luminous") {
         let cache_dir = dirs.cache_dir();
         if let Err(e) = std::fs::create_dir_all(cache_dir) {
@@ -43,15 +73,25 @@ pub fn save_app_state(window: &MainWindow) {
         let fv = window.global::<FullViewState>();
         let gv = window.global::<GridViewState>();
 
+        let fullscreen = win.is_fullscreen();
+        let geometry = windowed_geometry
+            .filter(|_| fullscreen)
+            .unwrap_or(WindowGeometry {
+                position: win.position(),
+                size: win.size(),
+            });
+
         let state = AppState {
-            fullscreen: win.is_fullscreen(),
-            x: win.position().x,
-            y: win.position().y,
-            width: win.size().width,
-            height: win.size().height,
+            fullscreen,
+            x: geometry.position.x,
+            y: geometry.position.y,
+            width: geometry.size.width,
+            height: geometry.size.height,
             full_view_footer_visible: fv.get_footer_visible(),
             full_view_side_panel_visible: fv.get_side_panel_visible(),
+            full_view_filmstrip_enabled: fv.get_filmstrip_enabled(),
             grid_view_side_panel_visible: gv.get_side_panel_visible(),
+            session,
         };
 
         match toml::to_string(&state) {
@@ -67,3 +107,28 @@ pub fn save_app_state(window: &MainWindow) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_roundtrips_and_is_optional() {
+        let old = "fullscreen = false\nx = 0\ny = 0\nwidth = 800\nheight = 600\n\
+            full_view_footer_visible = true\nfull_view_side_panel_visible = false\n\
+            grid_view_side_panel_visible = false\n";
+        let mut state: AppState = toml::from_str(old).unwrap();
+        assert_eq!(state.session, None);
+
+        let session = Session {
+            dir: PathBuf::from("/photos/trip"),
+            image: Some(PathBuf::from("/photos/trip/b.jpg")),
+            index: 1,
+            full_view: true,
+            zoom: 2.5,
+        };
+        state.session = Some(session.clone());
+        let saved: AppState = toml::from_str(&toml::to_string(&state).unwrap()).unwrap();
+        assert_eq!(saved.session, Some(session));
+    }
+}
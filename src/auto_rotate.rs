@@ -0,0 +1,106 @@
+use image::{DynamicImage, imageops::FilterType};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// How much brighter (0-255) a side band must be than the top and bottom bands.
+const BAND_MARGIN: f32 = 15.0;
+/// Images with less contrast than this between bands carry no usable signal.
+const MIN_CONTRAST: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Rotation {
+    pub fn apply(self, img: &DynamicImage) -> DynamicImage {
+        match self {
+            Rotation::Clockwise => img.rotate90(),
+            Rotation::CounterClockwise => img.rotate270(),
+        }
+    }
+}
+
+/// Guess whether `img` is lying on its side, assuming the sky (the brightest
+/// region of most photos) belongs at the top.
+pub fn suggest(img: &DynamicImage) -> Option<Rotation> {
+    let small = img.resize_exact(64, 64, FilterType::Triangle).to_luma8();
+    let band = 16;
+    let mean = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| -> f32 {
+        let n = (xs.len() * ys.len()) as f32;
+        let sum: u32 = ys
+            .flat_map(|y| xs.clone().map(move |x| (x, y)))
+            .map(|(x, y)| small.get_pixel(x, y)[0] as u32)
+            .sum();
+        sum as f32 / n
+    };
+
+    let top = mean(0..64, 0..band);
+    let bottom = mean(0..64, 64 - band..64);
+    let left = mean(0..band, 0..64);
+    let right = mean(64 - band..64, 0..64);
+
+    let bands = [top, bottom, left, right];
+    let max = bands.iter().copied().fold(f32::MIN, f32::max);
+    let min = bands.iter().copied().fold(f32::MAX, f32::min);
+    if max - min < MIN_CONTRAST {
+        return None;
+    }
+
+    let beats_vertical = |side: f32| side >= top + BAND_MARGIN && side >= bottom + BAND_MARGIN;
+    if left == max && beats_vertical(left) {
+        Some(Rotation::Clockwise)
+    } else if right == max && beats_vertical(right) {
+        Some(Rotation::CounterClockwise)
+    } else {
+        None
+    }
+}
+
+/// Files that already declare an orientation are trusted and never suggested.
+pub fn has_exif_orientation(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .map(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .is_some()
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    // Bright "sky" on the top half, dark ground below
+    fn landscape() -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(120, 80, |_, y| {
+            Luma([if y < 40 { 220 } else { 40 }])
+        }))
+    }
+
+    #[test]
+    fn upright_and_flat_images_need_no_rotation() {
+        assert_eq!(suggest(&landscape()), None);
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(50, 50, Luma([128])));
+        assert_eq!(suggest(&flat), None);
+    }
+
+    #[test]
+    fn sideways_images_rotate_back_upright() {
+        let upright = landscape();
+        for (sideways, expected) in [
+            (upright.rotate270(), Rotation::Clockwise),
+            (upright.rotate90(), Rotation::CounterClockwise),
+        ] {
+            assert_eq!(suggest(&sideways), Some(expected));
+            assert_eq!(suggest(&expected.apply(&sideways)), None);
+        }
+    }
+}
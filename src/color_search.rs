@@ -0,0 +1,260 @@
+//! Find images by color. Thumbnails are reduced to a few dominant colors, cached in the library,
+//! and a picked color ranks images by how much of it they show.
+
+use crate::library::Library;
+use palette::{IntoColor, Lab, Srgb};
+use rayon::prelude::*;
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Colors kept per image
+const PALETTE_SIZE: usize = 5;
+/// Bits of each channel that pick a pixel's bin
+const BIN_BITS: u32 = 3;
+/// Pixels sampled along the longer side of a thumbnail
+const SAMPLES: u32 = 64;
+/// Lab distance at which a color no longer counts as the picked one
+const MAX_DISTANCE: f32 = 40.0;
+/// Score below which an image is left out of the results
+pub const MIN_SCORE: f32 = 0.1;
+
+/// One of the dominant colors of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Swatch {
+    pub rgb: [u8; 3],
+    /// Part of the image it covers, 0-1
+    pub share: f32,
+}
+
+/// The most common colors of `buffer`, most common first. Empty for the 1x1 placeholders of
+/// images that couldn't be read.
+pub fn dominant(buffer: &SharedPixelBuffer<Rgba8Pixel>) -> Vec<Swatch> {
+    let (w, h) = (buffer.width(), buffer.height());
+    if w < 2 || h < 2 {
+        return Vec::new();
+    }
+    let step = (w.max(h) / SAMPLES).max(1) as usize;
+    let pixels = buffer.as_slice();
+    let shift = 8 - BIN_BITS;
+    let mut bins: HashMap<u32, ([u32; 3], u32)> = HashMap::new();
+    let mut total = 0;
+    for y in (0..h).step_by(step) {
+        for x in (0..w).step_by(step) {
+            let px = pixels[(y * w + x) as usize];
+            if px.a < 128 {
+                continue;
+            }
+            let key = (px.r as u32 >> shift) << (2 * BIN_BITS)
+                | (px.g as u32 >> shift) << BIN_BITS
+                | px.b as u32 >> shift;
+            let (sum, count) = bins.entry(key).or_default();
+            sum[0] += px.r as u32;
+            sum[1] += px.g as u32;
+            sum[2] += px.b as u32;
+            *count += 1;
+            total += 1;
+        }
+    }
+    let mut bins: Vec<_> = bins.into_iter().collect();
+    bins.sort_by(|(ka, (_, a)), (kb, (_, b))| b.cmp(a).then(ka.cmp(kb)));
+    bins.into_iter()
+        .take(PALETTE_SIZE)
+        .map(|(_, (sum, count))| Swatch {
+            rgb: sum.map(|c| (c / count) as u8),
+            share: count as f32 / total as f32,
+        })
+        .collect()
+}
+
+/// `rrggbb:share` pairs separated by spaces, as kept in the library.
+pub fn to_text(swatches: &[Swatch]) -> String {
+    swatches
+        .iter()
+        .map(|s| {
+            let [r, g, b] = s.rgb;
+            format!("{r:02x}{g:02x}{b:02x}:{:.3}", s.share)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reverse of `to_text`, entries that don't parse are skipped.
+pub fn from_text(text: &str) -> Vec<Swatch> {
+    text.split_whitespace()
+        .filter_map(|entry| {
+            let (hex, share) = entry.split_once(':')?;
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)?;
+            Some(Swatch {
+                rgb: [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8],
+                share: share.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+fn lab([r, g, b]: [u8; 3]) -> Lab {
+    Srgb::new(r, g, b).into_format::<f32>().into_color()
+}
+
+/// How much of `swatches` is close to `query`, 0-1. Nearby colors count in part.
+pub fn score(query: [u8; 3], swatches: &[Swatch]) -> f32 {
+    let q = lab(query);
+    swatches
+        .iter()
+        .map(|s| {
+            let c = lab(s.rgb);
+            let d = ((q.l - c.l).powi(2) + (q.a - c.a).powi(2) + (q.b - c.b).powi(2)).sqrt();
+            s.share * (1.0 - d / MAX_DISTANCE).max(0.0)
+        })
+        .sum()
+}
+
+/// Keys of `palettes` scoring at least `MIN_SCORE` for `query`, most of it first.
+pub fn rank<K: Copy>(query: [u8; 3], palettes: &[(K, Vec<Swatch>)]) -> Vec<K> {
+    let mut ranked: Vec<(K, f32)> = palettes
+        .iter()
+        .map(|(key, swatches)| (*key, score(query, swatches)))
+        .filter(|&(_, s)| s >= MIN_SCORE)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.into_iter().map(|(key, _)| key).collect()
+}
+
+/// 0 for files that can't be read.
+fn mtime(path: &Path) -> i64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Dominant colors kept in the library, filled in as thumbnails are generated.
+pub struct ColorIndex {
+    library: Arc<Library>,
+    /// Files already looked up this run
+    seen: Mutex<HashSet<PathBuf>>,
+}
+
+impl ColorIndex {
+    pub fn new(library: Arc<Library>) -> Self {
+        Self {
+            library,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Store the colors of a freshly generated thumbnail unless the library has them already.
+    pub fn note_thumb(&self, path: &Path, buffer: &SharedPixelBuffer<Rgba8Pixel>) {
+        let new = self
+            .seen
+            .lock()
+            .is_ok_and(|mut seen| seen.insert(path.to_path_buf()));
+        if !new {
+            return;
+        }
+        let file = (path.to_path_buf(), mtime(path));
+        if self
+            .library
+            .palettes(std::slice::from_ref(&file))
+            .is_empty()
+        {
+            let swatches = dominant(buffer);
+            if !swatches.is_empty() && file.1 != 0 {
+                self.library.set_palette(path, file.1, &to_text(&swatches));
+            }
+        }
+    }
+
+    /// Colors of each of `paths`, those not cached yet are computed from `thumb` of their
+    /// index and path and stored.
+    pub fn palettes(
+        &self,
+        paths: &[PathBuf],
+        thumb: impl Fn(usize, &Path) -> SharedPixelBuffer<Rgba8Pixel> + Sync,
+    ) -> Vec<Vec<Swatch>> {
+        let files: Vec<(PathBuf, i64)> = paths.iter().map(|p| (p.clone(), mtime(p))).collect();
+        let cached = self.library.palettes(&files);
+        files
+            .par_iter()
+            .enumerate()
+            .map(|(i, (path, mtime))| match cached.get(path) {
+                Some(text) => from_text(text),
+                None => {
+                    let swatches = dominant(&thumb(i, path));
+                    // Generated images have no file to cache them for
+                    if *mtime != 0 {
+                        self.library.set_palette(path, *mtime, &to_text(&swatches));
+                    }
+                    if let Ok(mut seen) = self.seen.lock() {
+                        seen.insert(path.clone());
+                    }
+                    swatches
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(
+        w: u32,
+        h: u32,
+        color: impl Fn(u32, u32) -> [u8; 3],
+    ) -> SharedPixelBuffer<Rgba8Pixel> {
+        let mut buf = SharedPixelBuffer::<Rgba8Pixel>::new(w, h);
+        for (i, px) in buf.make_mut_slice().iter_mut().enumerate() {
+            let [r, g, b] = color(i as u32 % w, i as u32 / w);
+            *px = Rgba8Pixel { r, g, b, a: 255 };
+        }
+        buf
+    }
+
+    #[test]
+    fn dominant_colors_by_area() {
+        // Left three quarters red, the rest blue
+        let img = buffer(
+            40,
+            10,
+            |x, _| {
+                if x < 30 { [250, 10, 10] } else { [10, 10, 250] }
+            },
+        );
+        let swatches = dominant(&img);
+        assert_eq!(swatches.len(), 2);
+        assert_eq!(swatches[0].rgb, [250, 10, 10]);
+        assert!((swatches[0].share - 0.75).abs() < 1e-6);
+        assert_eq!(from_text(&to_text(&swatches))[1].rgb, [10, 10, 250]);
+        assert!(dominant(&buffer(1, 1, |_, _| [0; 3])).is_empty());
+    }
+
+    #[test]
+    fn rank_by_the_picked_color() {
+        let red = Swatch {
+            rgb: [230, 20, 30],
+            share: 1.0,
+        };
+        let blue = Swatch {
+            rgb: [20, 40, 220],
+            share: 1.0,
+        };
+        let palettes = [
+            (0, vec![blue]),
+            (
+                1,
+                vec![Swatch { share: 0.3, ..red }, Swatch { share: 0.7, ..blue }],
+            ),
+            (2, vec![red]),
+        ];
+        assert_eq!(rank([255, 0, 0], &palettes), vec![2, 1]);
+        assert_eq!(rank([0, 0, 255], &palettes)[0], 0);
+        assert!(rank([0, 255, 0], &palettes).is_empty());
+    }
+}
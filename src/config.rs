@@ -1,25 +1,346 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use directories::ProjectDirs;
 use serde::Deserialize;
 use slint::Color;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::accessibility;
+use crate::actions::Macro;
+use crate::config_check;
+use crate::error::{self, LuminousError};
+use crate::fs_scan::{ExtensionOverrides, NameFilter};
+use crate::keymap;
+use crate::metadata::{TagEdit, TextTag};
+use crate::naming::Template;
+use crate::power;
+use crate::watermark::{Anchor, Watermark};
+use crate::{FitMode, ViewMode};
+use luminous_image_loader::tone_map::ToneMapOperator;
+
+/// Written on the first run, every option with its default and what it does.
+pub(crate) const DEFAULT_CONFIG: &str = include_str!("../examples/luminous.toml");
+/// Sequence playback frame rate cap on battery power
+const BATTERY_PLAYBACK_FPS: u32 = 12;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub path: String,
+    /// False when `path` is only the `.` default
+    pub path_given: bool,
     pub log: String,
     pub threads: usize,
     pub window_size: usize,
+    pub prefetch_rows: usize,
     pub background: Color,
+    pub fit_mode: FitMode,
+    pub tone_map: ToneMapOperator,
+    pub playback_fps: u32,
+    /// JPEG quality (1-100) images are saved with
+    pub jpeg_quality: u8,
+    /// View opened when started with a directory
+    pub directory_view: ViewMode,
+    /// View opened when started with a single image, or a URL
+    pub file_view: ViewMode,
+    /// View opened when started with a zip or tar archive
+    pub archive_view: ViewMode,
+    /// Overrides the startup views above when set
+    pub mode: Option<StartMode>,
+    /// Reopen the last session instead of `.` when no path was given
+    pub restore_session: bool,
+    /// Seconds each image stays on screen in a slideshow
+    pub slideshow_interval: f32,
+    /// Draw the slideshow progress bar and image count
+    pub slideshow_progress: bool,
+    /// File names under the grid thumbnails
+    pub grid_captions: bool,
+    /// Check cached images against the modification time and size of their file
+    pub validate_cache: bool,
+    /// Read the files around the shown image ahead of decoding them, for slow disks and mounts
+    pub readahead: bool,
+    /// When thumbnail generation and prefetching stop while the window is in the background
+    pub pause_in_background: BackgroundPause,
+    /// Hand paths to an already running viewer instead of opening another window
+    pub single_instance: bool,
+    /// No animated transitions, from the desktop setting unless configured
+    pub reduced_motion: bool,
+    /// Opaque overlays, stronger badge colors and wider outlines, from the desktop unless
+    /// configured
+    pub high_contrast: bool,
+    /// Switch to the battery profile while on battery power, see [`Config::save_battery`]
+    pub battery_saver: bool,
+    /// On battery power with `battery_saver` on, kept current while running by the config watcher
+    pub on_battery: bool,
+    /// Only files with matching names are scanned
+    pub filter: Option<NameFilter>,
+    /// `extra_extensions` and `exclude_extensions`, applied with
+    /// [`crate::fs_scan::set_extension_overrides`]
+    pub extensions: ExtensionOverrides,
     pub bindings: HashMap<String, String>,
+    pub targets: Vec<FileTarget>,
+    /// rhai scripts run on their keys, see [`crate::scripting`]
+    pub scripts: Vec<Script>,
+    /// Chained actions by name, see [`crate::actions::Macro`]
+    pub macros: Vec<Macro>,
+    pub naming: Naming,
+    /// Stamped on exported and converted images, `None` when neither text nor image is set
+    pub watermark: Option<Watermark>,
+    pub hot_folder: bool,
+    pub demo: Option<usize>,
     pub safe_mode: bool,
+    /// Session script user actions are written to, see [`crate::recording`]
+    pub record: Option<PathBuf>,
+    /// Session script played back after startup
+    pub replay: Option<PathBuf>,
+    /// Socket scripts control the viewer through, see [`crate::remote`]
+    pub control_socket: Option<PathBuf>,
+    /// Subcommand to run instead of the viewer
+    pub command: Option<Command>,
+    /// Name of the profile loaded over the config file
+    pub profile: Option<String>,
+    /// No config file existed yet, the onboarding overlay is shown
+    pub first_run: bool,
+    /// Config file in use, or written with the defaults on the first run
+    pub config_path: Option<PathBuf>,
+    /// Kept over the config files when they are reloaded
+    overrides: Overrides,
+}
+
+/// Settings given on the command line that [`Config::reload`] can change.
+#[derive(Debug, Clone, Default)]
+struct Overrides {
+    window_size: Option<usize>,
+    prefetch_rows: Option<usize>,
+    playback_fps: Option<u32>,
+    background: Option<String>,
+    slideshow_interval: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartMode {
+    Grid,
+    Full,
+    Slideshow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundPause {
+    Never,
+    Minimized,
+    Unfocused,
+}
+
+impl BackgroundPause {
+    /// Whether background loading should wait in this window state.
+    pub fn applies(self, focused: bool, visible: bool) -> bool {
+        match self {
+            BackgroundPause::Never => false,
+            BackgroundPause::Minimized => !visible,
+            BackgroundPause::Unfocused => !visible || !focused,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMode {
+    Copy,
+    Move,
+}
+
+/// Destination directory the current image is copied or moved to on `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTarget {
+    pub key: slint::SharedString,
+    pub dir: PathBuf,
+    pub mode: TransferMode,
+}
+
+/// Script run on `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    /// Keys as written in the config, like `Ctrl+e` or `g e`
+    pub binding: String,
+    /// Canonical text of `binding`, see [`keymap::parse`]
+    pub key: slint::SharedString,
+    pub path: PathBuf,
+}
+
+/// `[naming]`, file name templates like `{exif.date}_{seq:3}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    /// Prefilled in the batch rename dialog
+    pub rename: String,
+    /// Entry names in zip exports, the original names when unset
+    pub export: Option<Template>,
+    /// Names of images copied or moved to targets, the original names when unset
+    pub ingest: Option<Template>,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Self {
+            rename: "{exif.date}_{seq:3}".to_string(),
+            export: None,
+            ingest: None,
+        }
+    }
+}
+
+/// Run instead of opening the viewer.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Register luminous with the desktop (a .desktop file and icon on Linux, registry entries
+    /// on Windows) so it can be picked as the image viewer
+    InstallIntegration {
+        /// Also make luminous the default application for images (Linux)
+        #[arg(long)]
+        default: bool,
+    },
+    /// Remove what install-integration added
+    UninstallIntegration,
+    /// Edit the artist, copyright and description EXIF tags of JPEG and PNG files in place
+    Exif {
+        #[command(subcommand)]
+        action: ExifAction,
+    },
+    /// Keep switching the desktop wallpaper to the next image of a folder, until stopped
+    Wallpaper {
+        /// Folder of images, or "favorites:" for the favorites
+        /// Defaults to the configured path
+        path: Option<String>,
+        /// Seconds between switches
+        #[arg(long, default_value_t = 900, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Random order, reshuffled each time all images were shown
+        #[arg(long)]
+        shuffle: bool,
+    },
+    /// Print the size, format, dimensions, color type and EXIF of images, without opening the
+    /// viewer
+    Info {
+        /// One JSON array of objects instead of text
+        #[arg(long)]
+        json: bool,
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Print the keybindings in use, and the ones that conflict
+    Bindings,
+    /// Write the default config file, or print where it is looked up
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Convert and resize the images of a folder into another one, without opening the viewer
+    Convert {
+        /// Output format, by extension (png, jpg, webp, or one a plugin encodes)
+        #[arg(long)]
+        to: String,
+        /// Scale images down so that their longest side is at most this many pixels
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        max_size: Option<u32>,
+        /// JPEG quality (1-100), defaults to jpeg_quality from the config
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+        quality: Option<u8>,
+        /// Replace files that already exist in the destination
+        #[arg(long)]
+        overwrite: bool,
+        /// Leave out the [watermark] of the config
+        #[arg(long)]
+        no_watermark: bool,
+        /// Image or folder of images to convert
+        src: PathBuf,
+        /// Folder the converted images are written to, created if missing
+        dst: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigAction {
+    /// Write luminous.toml with every setting and the default bindings, commented
+    Init {
+        /// Replace the config file if there is one
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print where luminous.toml is looked up
+    Path,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ExifAction {
+    /// Set tags, the ones not given are kept
+    Set {
+        #[arg(long)]
+        artist: Option<String>,
+        #[arg(long)]
+        copyright: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Remove tags
+    Del {
+        #[arg(long)]
+        artist: bool,
+        #[arg(long)]
+        copyright: bool,
+        #[arg(long)]
+        description: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+impl ExifAction {
+    /// The tag edits and the files they apply to.
+    pub fn edits(&self) -> (Vec<TagEdit>, &[PathBuf]) {
+        match self {
+            ExifAction::Set {
+                artist,
+                copyright,
+                description,
+                files,
+            } => {
+                let values = [artist, copyright, description];
+                let edits = TextTag::ALL
+                    .into_iter()
+                    .zip(values)
+                    .filter_map(|(tag, value)| {
+                        Some(TagEdit {
+                            tag,
+                            value: Some(value.clone()?),
+                        })
+                    })
+                    .collect();
+                (edits, files)
+            }
+            ExifAction::Del {
+                artist,
+                copyright,
+                description,
+                files,
+            } => {
+                let flags = [artist, copyright, description];
+                let edits = TextTag::ALL
+                    .into_iter()
+                    .zip(flags)
+                    .filter(|(_, flag)| **flag)
+                    .map(|(tag, _)| TagEdit { tag, value: None })
+                    .collect();
+                (edits, files)
+            }
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Luminous - Image viewer and editor.", long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// The path to the image or directory to open
     path: Option<String>,
     /// Logging level (error, warn, info, debug, trace)
@@ -33,15 +354,64 @@ struct Cli {
     /// Custom path to a config file
     #[arg(long)]
     config_file: Option<PathBuf>,
+    /// Named config from profiles/<NAME>.toml in the config directory, its settings replace
+    /// those of the main config file
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+    /// Check the config file and profile, print every problem found and exit
+    #[arg(long)]
+    check_config: bool,
     /// Cache size in full view
     #[arg(long)]
     window_size: Option<usize>,
+    /// Grid rows decoded ahead of and behind the viewport, reduced automatically during fast scrolling
+    /// Defaults to 5
+    #[arg(long)]
+    prefetch_rows: Option<usize>,
     /// Background window color (RGB hexadecimal (with and without `#` prefix) format)
     #[arg(long)]
     background: Option<String>,
+    /// Default full view fit mode (fit, fill, 1:1, fit-width)
+    #[arg(long)]
+    fit_mode: Option<String>,
+    /// Tone mapping curve for HDR/EXR images (aces, reinhard)
+    /// Defaults to aces
+    #[arg(long)]
+    tone_map: Option<String>,
+    /// Frame rate for playing back numbered image sequences
+    /// Defaults to 24
+    #[arg(long)]
+    playback_fps: Option<u32>,
+    /// View to start in (grid, full, slideshow), regardless of the path type
+    #[arg(long)]
+    mode: Option<String>,
+    /// Seconds each image is shown in a slideshow
+    /// Defaults to 5
+    #[arg(long)]
+    slideshow_interval: Option<f32>,
+    /// Only show files whose names match, a glob like "*.png" or a regex prefixed with "re:"
+    #[arg(long)]
+    filter: Option<String>,
+    /// Watch the directory and jump to new images as they arrive (tethered shooting)
+    #[arg(long)]
+    hot_folder: bool,
+    /// Show generated images instead of files on disk, optionally how many
+    /// Defaults to 200
+    #[arg(long, num_args = 0..=1, default_missing_value = "200")]
+    demo: Option<usize>,
     /// Start without plugins
     #[arg(long)]
     safe_mode: bool,
+    /// Write user actions with timestamps to a script that can be replayed, for bug reports
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// Play back a script written by --record, against the folder given as the path
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    /// Listen on this Unix socket for commands, one per line: next, prev, goto <n>,
+    /// open <path>, focus, quit
+    #[arg(long, value_name = "PATH")]
+    control_socket: Option<PathBuf>,
 }
 
 #[derive(Deserialize, Default)]
@@ -50,24 +420,94 @@ struct TomlConfig {
     log: Option<String>,
     threads: Option<usize>,
     window_size: Option<usize>,
+    prefetch_rows: Option<usize>,
     background: Option<String>,
+    fit_mode: Option<String>,
+    tone_map: Option<String>,
+    playback_fps: Option<u32>,
+    jpeg_quality: Option<u8>,
+    mode: Option<String>,
+    slideshow_interval: Option<f32>,
+    slideshow_progress: Option<bool>,
+    grid_captions: Option<bool>,
+    validate_cache: Option<bool>,
+    readahead: Option<bool>,
+    pause_in_background: Option<String>,
+    single_instance: Option<bool>,
+    reduced_motion: Option<bool>,
+    high_contrast: Option<bool>,
+    battery_saver: Option<bool>,
+    filter: Option<String>,
+    extra_extensions: Option<Vec<String>>,
+    exclude_extensions: Option<Vec<String>>,
     bindings: Option<HashMap<String, String>>,
-    #[serde(flatten)]
-    unknown: HashMap<String, toml::Value>,
+    targets: Option<HashMap<String, String>>,
+    scripts: Option<HashMap<String, String>>,
+    macros: Option<HashMap<String, Vec<String>>>,
+    naming: Option<NamingToml>,
+    startup: Option<StartupToml>,
+    watermark: Option<WatermarkToml>,
+}
+
+/// `[startup]`, the view to open for each kind of startup path.
+#[derive(Deserialize, Default)]
+struct StartupToml {
+    directory: Option<String>,
+    file: Option<String>,
+    archive: Option<String>,
+    session: Option<bool>,
+}
+
+/// `[watermark]`, see [`Watermark`].
+#[derive(Deserialize, Default)]
+struct WatermarkToml {
+    image: Option<PathBuf>,
+    text: Option<String>,
+    position: Option<String>,
+    opacity: Option<f32>,
+    scale: Option<f32>,
+    color: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct NamingToml {
+    rename: Option<String>,
+    export: Option<String>,
+    ingest: Option<String>,
 }
 
 impl Config {
     pub fn load() -> Self {
         let cli = Cli::parse();
+        let overrides = Overrides {
+            window_size: cli.window_size,
+            prefetch_rows: cli.prefetch_rows,
+            playback_fps: cli.playback_fps,
+            background: cli.background.clone(),
+            slideshow_interval: cli.slideshow_interval,
+        };
 
-        let toml_config = Self::find_config_path(&cli.config_file)
-            .map(|p| Self::load_toml(&p))
-            .unwrap_or_default();
-
-        if !toml_config.unknown.is_empty() {
-            eprintln!("Unknown config keys: {:?}", toml_config.unknown.keys());
+        let mut config_path = Self::find_config_path(&cli.config_file);
+        let first_run = config_path.is_none() && cli.command.is_none() && !cli.check_config;
+        if first_run {
+            config_path = Self::write_default_config();
+        }
+        let profile_path = cli.profile.as_deref().and_then(Self::find_profile_path);
+        let files: Vec<&PathBuf> = [config_path.as_ref(), profile_path.as_ref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        let (toml_config, reports) = Self::load_toml(&files);
+        if cli.check_config {
+            std::process::exit(Self::print_check_report(&reports));
+        }
+        for (path, issues) in &reports {
+            for issue in issues {
+                eprintln!("Warning: {:?}: {}", path, issue);
+            }
         }
 
+        let path_given = cli.path.is_some() || toml_config.path.is_some();
         let path = Self::resolve(cli.path, toml_config.path, ".".to_string());
         let log = Self::resolve(cli.log, toml_config.log, "warn".to_string());
         let threads = cli
@@ -76,29 +516,286 @@ impl Config {
             .filter(|&t| t > 0)
             .unwrap_or_else(num_cpus::get);
         let window_size = Self::resolve(cli.window_size, toml_config.window_size, 3);
+        let prefetch_rows = Self::resolve(cli.prefetch_rows, toml_config.prefetch_rows, 5);
         let background_str = Self::resolve(
             cli.background,
             toml_config.background,
             "#000000".to_string(),
         );
         let background = Self::parse_color(&background_str);
+        let fit_mode_str = Self::resolve(cli.fit_mode, toml_config.fit_mode, "fit".to_string());
+        let fit_mode = Self::parse_fit_mode(&fit_mode_str);
+        let tone_map_str = Self::resolve(cli.tone_map, toml_config.tone_map, "aces".to_string());
+        let tone_map = ToneMapOperator::from_name(&tone_map_str).unwrap_or_else(|| {
+            eprintln!(
+                "Warning: Invalid tone map '{}', defaulting to aces",
+                tone_map_str
+            );
+            ToneMapOperator::Aces
+        });
+        let playback_fps =
+            Self::resolve(cli.playback_fps, toml_config.playback_fps, 24).clamp(1, 120);
+        let jpeg_quality = toml_config.jpeg_quality.unwrap_or(90).clamp(1, 100);
 
-        let mut bindings = Self::default_bindings();
-        if let Some(user_bindings) = toml_config.bindings {
-            bindings.extend(user_bindings);
+        let startup = toml_config.startup.unwrap_or_default();
+        let directory_view =
+            Self::parse_view_mode(&startup.directory.unwrap_or_else(|| "grid".to_string()));
+        let file_view = Self::parse_view_mode(&startup.file.unwrap_or_else(|| "full".to_string()));
+        let archive_view =
+            Self::parse_view_mode(&startup.archive.unwrap_or_else(|| "grid".to_string()));
+        let restore_session = startup.session.unwrap_or(true);
+
+        let mode = cli
+            .mode
+            .or(toml_config.mode)
+            .and_then(|m| Self::parse_start_mode(&m));
+        let slideshow_interval =
+            Self::resolve(cli.slideshow_interval, toml_config.slideshow_interval, 5.0)
+                .clamp(0.5, 3600.0);
+        let slideshow_progress = toml_config.slideshow_progress.unwrap_or(true);
+        let grid_captions = toml_config.grid_captions.unwrap_or(true);
+        let validate_cache = toml_config.validate_cache.unwrap_or(true);
+        let readahead = toml_config.readahead.unwrap_or(true);
+        let pause_in_background = toml_config
+            .pause_in_background
+            .map_or(BackgroundPause::Minimized, |p| {
+                Self::parse_background_pause(&p)
+            });
+        let single_instance = toml_config.single_instance.unwrap_or(false);
+        let reduced_motion = toml_config
+            .reduced_motion
+            .unwrap_or_else(accessibility::prefers_reduced_motion);
+        let high_contrast = toml_config
+            .high_contrast
+            .unwrap_or_else(accessibility::prefers_high_contrast);
+        let battery_saver = toml_config.battery_saver.unwrap_or(true);
+        let on_battery = battery_saver && power::on_battery();
+        let filter = cli
+            .filter
+            .or(toml_config.filter)
+            .filter(|f| !f.is_empty())
+            .and_then(|f| match NameFilter::parse(&f) {
+                Ok(filter) => Some(filter),
+                Err(e) => {
+                    eprintln!("Warning: Invalid filter '{}': {}", f, e);
+                    None
+                }
+            });
+
+        let extension_list = |exts: Option<Vec<String>>| -> Vec<String> {
+            exts.unwrap_or_default()
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect()
+        };
+        let extensions = ExtensionOverrides {
+            extra: extension_list(toml_config.extra_extensions),
+            excluded: extension_list(toml_config.exclude_extensions),
+        };
+
+        let bindings = Self::merge_bindings(toml_config.bindings);
+        let scripts = Self::parse_scripts(toml_config.scripts.unwrap_or_default());
+        for conflict in keymap::conflicts(&Self::with_scripts(&bindings, &scripts)) {
+            eprintln!("Warning: {}", conflict);
         }
 
+        let targets = Self::parse_targets(toml_config.targets.unwrap_or_default());
+        let macros = Self::parse_macros(toml_config.macros.unwrap_or_default());
+        let naming = Self::parse_naming(toml_config.naming.unwrap_or_default());
+        let watermark = toml_config.watermark.and_then(Self::parse_watermark);
+
+        let hot_folder = cli.hot_folder;
+        let demo = cli.demo;
         let safe_mode = cli.safe_mode;
+        let record = cli.record;
+        let replay = cli.replay;
+        let control_socket = cli.control_socket;
 
-        Config {
+        let mut config = Config {
             path,
+            path_given,
             log,
             threads,
             window_size,
+            prefetch_rows,
             background,
+            fit_mode,
+            tone_map,
+            playback_fps,
+            jpeg_quality,
+            directory_view,
+            file_view,
+            archive_view,
+            mode,
+            restore_session,
+            slideshow_interval,
+            slideshow_progress,
+            grid_captions,
+            validate_cache,
+            readahead,
+            pause_in_background,
+            single_instance,
+            reduced_motion,
+            high_contrast,
+            battery_saver,
+            on_battery,
+            filter,
+            extensions,
             bindings,
+            targets,
+            scripts,
+            macros,
+            naming,
+            watermark,
+            hot_folder,
+            demo,
             safe_mode,
+            record,
+            replay,
+            control_socket,
+            command: cli.command,
+            profile: profile_path.and(cli.profile),
+            first_run,
+            config_path,
+            overrides,
+        };
+        if on_battery {
+            config.save_battery();
         }
+        config
+    }
+
+    /// Fewer worker threads, then the limits of [`Self::limit_for_battery`], to go easy on the
+    /// battery.
+    pub fn save_battery(&mut self) {
+        self.threads = (self.threads / 2).max(1);
+        self.limit_for_battery();
+    }
+
+    /// No prefetching beyond the neighbours of the shown image, a lower sequence frame rate and
+    /// no animations. Unlike the thread count these follow the power supply while running.
+    fn limit_for_battery(&mut self) {
+        self.window_size = 1;
+        self.prefetch_rows = 0;
+        self.playback_fps = self.playback_fps.min(BATTERY_PLAYBACK_FPS);
+        self.reduced_motion = true;
+    }
+
+    /// Take the battery profile on or off, reading the config files again for the settings it
+    /// replaced. Fails like [`Self::reload`], the worker thread count stays as started.
+    pub fn set_on_battery(&mut self, on_battery: bool) -> Result<Vec<String>, String> {
+        self.on_battery = on_battery;
+        self.reload()
+    }
+
+    /// Read the config files again and take over the bindings, macros, scripts, background,
+    /// slideshow interval, playback rate, reduced motion, battery saver and cache sizes they set,
+    /// the command line still going first. Returns the problems found, or why a file couldn't be
+    /// read, which leaves everything as it was.
+    pub fn reload(&mut self) -> Result<Vec<String>, String> {
+        let profile_path = self.profile.as_deref().and_then(Self::find_profile_path);
+        let files: Vec<&PathBuf> = [self.config_path.as_ref(), profile_path.as_ref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        let file_name = |path: &Path| {
+            path.file_name().map_or_else(
+                || path.display().to_string(),
+                |n| n.to_string_lossy().into(),
+            )
+        };
+        // Half-written edits would reset everything to the defaults otherwise
+        for path in &files {
+            Self::read_table(path).map_err(|e| format!("{}: {}", file_name(path), e))?;
+        }
+        let (toml_config, reports) = Self::load_toml(&files);
+
+        let overrides = self.overrides.clone();
+        self.bindings = Self::merge_bindings(toml_config.bindings);
+        self.macros = Self::parse_macros(toml_config.macros.unwrap_or_default());
+        self.scripts = Self::parse_scripts(toml_config.scripts.unwrap_or_default());
+        self.background = Self::parse_color(&Self::resolve(
+            overrides.background,
+            toml_config.background,
+            "#000000".to_string(),
+        ));
+        self.slideshow_interval = Self::resolve(
+            overrides.slideshow_interval,
+            toml_config.slideshow_interval,
+            5.0,
+        )
+        .clamp(0.5, 3600.0);
+        self.window_size = Self::resolve(overrides.window_size, toml_config.window_size, 3);
+        self.prefetch_rows = Self::resolve(overrides.prefetch_rows, toml_config.prefetch_rows, 5);
+        self.playback_fps =
+            Self::resolve(overrides.playback_fps, toml_config.playback_fps, 24).clamp(1, 120);
+        self.reduced_motion = toml_config
+            .reduced_motion
+            .unwrap_or_else(accessibility::prefers_reduced_motion);
+        self.battery_saver = toml_config.battery_saver.unwrap_or(true);
+        self.on_battery &= self.battery_saver;
+        if self.on_battery {
+            self.limit_for_battery();
+        }
+        Ok(reports
+            .into_iter()
+            .flat_map(|(path, issues)| {
+                let name = file_name(&path);
+                issues
+                    .into_iter()
+                    .map(move |issue| format!("{name}: {issue}"))
+            })
+            .chain(keymap::conflicts(&self.key_bindings()))
+            .collect())
+    }
+
+    /// Scripts by their keys, those with keys that don't parse are left out.
+    fn parse_scripts(scripts: HashMap<String, String>) -> Vec<Script> {
+        let mut scripts: Vec<Script> = scripts
+            .into_iter()
+            .filter_map(|(binding, path)| {
+                let key = keymap::parse(&binding).ok()?;
+                Some(Script {
+                    binding,
+                    key: key.into(),
+                    path: PathBuf::from(path),
+                })
+            })
+            .collect();
+        scripts.sort_by(|a, b| a.key.cmp(&b.key));
+        scripts
+    }
+
+    /// The `[bindings]` and the keys of the `[scripts]`, each script named after its file.
+    pub fn key_bindings(&self) -> HashMap<String, String> {
+        Self::with_scripts(&self.bindings, &self.scripts)
+    }
+
+    fn with_scripts(
+        bindings: &HashMap<String, String>,
+        scripts: &[Script],
+    ) -> HashMap<String, String> {
+        let mut bindings = bindings.clone();
+        for script in scripts {
+            bindings.insert(
+                format!("script {}", script.path.display()),
+                script.binding.clone(),
+            );
+        }
+        bindings
+    }
+
+    /// The default bindings with those of the config files over them.
+    fn merge_bindings(user_bindings: Option<HashMap<String, String>>) -> HashMap<String, String> {
+        let mut bindings = Self::default_bindings();
+        if let Some(mut user_bindings) = user_bindings {
+            // Older configs still use the name copy_image had before
+            if let Some(key) = user_bindings.remove("copy_to_clipboard") {
+                user_bindings.entry("copy_image".into()).or_insert(key);
+            }
+            bindings.extend(user_bindings);
+        }
+        bindings
     }
 
     fn resolve<T>(cli: Option<T>, toml: Option<T>, default: T) -> T {
@@ -109,34 +806,138 @@ impl Config {
         if let Some(p) = cli_path {
             return Some(p.clone());
         }
-        if let Some(proj_dirs) = ProjectDirs::from("", "", "luminous") {
-            let config_dir = proj_dirs.config_dir();
-            let default_loc = config_dir.join("luminous.toml");
-            if default_loc.exists() {
-                return Some(default_loc);
+        Self::default_path().filter(|p| p.exists())
+    }
+
+    /// Where the config file is looked up without `--config-file`.
+    pub fn default_path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "luminous").map(|d| d.config_dir().join("luminous.toml"))
+    }
+
+    /// Write the commented example config to `path` and the folders it is in. A file already
+    /// there is only replaced with `force`.
+    pub fn write_default(path: &Path, force: bool) -> error::Result<()> {
+        if !force && path.exists() {
+            return Err(LuminousError::Config(format!(
+                "{} already exists",
+                path.display()
+            )));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, DEFAULT_CONFIG)?;
+        Ok(())
+    }
+
+    /// Scaffold the commented example config at the standard location, returns where.
+    fn write_default_config() -> Option<PathBuf> {
+        let path = Self::default_path()?;
+        match Self::write_default(&path, false) {
+            Ok(()) => {
+                eprintln!("Wrote a default config to {:?}", path);
+                Some(path)
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to write a default config to {:?}: {}",
+                    path, e
+                );
+                None
             }
         }
+    }
+
+    fn profiles_dir() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "luminous").map(|d| d.config_dir().join("profiles"))
+    }
+
+    /// Names of the profiles in `dir`, sorted.
+    fn list_profiles(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub(crate) fn find_profile_path(name: &str) -> Option<PathBuf> {
+        let dir = Self::profiles_dir()?;
+        let path = dir.join(format!("{name}.toml"));
+        if path.exists() {
+            return Some(path);
+        }
+        let available = Self::list_profiles(&dir);
+        eprintln!(
+            "Warning: No profile '{}' in {:?}, available: {}",
+            name,
+            dir,
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        );
         None
     }
 
-    fn load_toml(path: &PathBuf) -> TomlConfig {
+    /// The config files merged in order, tables key by key, without the entries that fail
+    /// [`config_check::check`]. Also returns the problems found in each file.
+    fn load_toml(files: &[&PathBuf]) -> (TomlConfig, Vec<(PathBuf, Vec<String>)>) {
+        let mut merged = toml::Table::new();
+        let mut reports = Vec::new();
+        for &path in files {
+            let issues = match Self::read_table(path) {
+                Ok(mut table) => {
+                    let issues = config_check::check(&mut table);
+                    merge_tables(&mut merged, table);
+                    issues
+                }
+                Err(e) => vec![e],
+            };
+            reports.push((path.clone(), issues));
+        }
+        let config = merged.try_into().unwrap_or_else(|e| {
+            eprintln!("Warning: Invalid config: {}", e);
+            TomlConfig::default()
+        });
+        (config, reports)
+    }
+
+    fn read_table(path: &PathBuf) -> Result<toml::Table, String> {
         if !path.exists() {
-            return TomlConfig::default();
+            return Ok(toml::Table::new());
         }
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("failed to read the file: {}", e))?;
+        toml::from_str(&content).map_err(|e| format!("failed to parse the file: {}", e))
+    }
 
-        match fs::read_to_string(path) {
-            Ok(content) => match toml::from_str(&content) {
-                Ok(cfg) => cfg,
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse config file {:?}: {}", path, e);
-                    TomlConfig::default()
-                }
-            },
-            Err(e) => {
-                eprintln!("Warning: Failed to read config file {:?}: {}", path, e);
-                TomlConfig::default()
+    /// Print the problems of each file for `--check-config`, returns the exit code.
+    fn print_check_report(reports: &[(PathBuf, Vec<String>)]) -> i32 {
+        if reports.is_empty() {
+            println!("No config file found, the defaults are used");
+            return 0;
+        }
+        let mut failed = false;
+        for (path, issues) in reports {
+            if issues.is_empty() {
+                println!("{}: OK", path.display());
+                continue;
+            }
+            failed = true;
+            let plural = if issues.len() == 1 { "" } else { "s" };
+            println!("{}: {} problem{plural}", path.display(), issues.len());
+            for issue in issues {
+                println!("  {issue}");
             }
         }
+        i32::from(failed)
     }
 
     fn parse_color(color_str: &str) -> slint::Color {
@@ -158,7 +959,179 @@ impl Config {
             })
     }
 
-    fn default_bindings() -> HashMap<String, String> {
+    pub(crate) fn fit_mode_name(mode: FitMode) -> &'static str {
+        match mode {
+            FitMode::Fit => "fit",
+            FitMode::Fill => "fill",
+            FitMode::Actual => "actual",
+            FitMode::FitWidth => "fit-width",
+        }
+    }
+
+    /// The fit mode written as `mode_str`, in any case, `1:1` and `fit_width` included.
+    pub(crate) fn fit_mode_from_name(mode_str: &str) -> Option<FitMode> {
+        match mode_str.to_lowercase().as_str() {
+            "fit" => Some(FitMode::Fit),
+            "fill" => Some(FitMode::Fill),
+            "1:1" | "actual" => Some(FitMode::Actual),
+            "fit-width" | "fit_width" => Some(FitMode::FitWidth),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn parse_fit_mode(mode_str: &str) -> FitMode {
+        Self::fit_mode_from_name(mode_str).unwrap_or_else(|| {
+            eprintln!(
+                "Warning: Invalid fit mode '{}', defaulting to fit",
+                mode_str
+            );
+            FitMode::Fit
+        })
+    }
+
+    fn parse_view_mode(mode_str: &str) -> ViewMode {
+        match mode_str.to_lowercase().as_str() {
+            "grid" => ViewMode::Grid,
+            "full" => ViewMode::Full,
+            _ => {
+                eprintln!(
+                    "Warning: Invalid startup view '{}', defaulting to grid",
+                    mode_str
+                );
+                ViewMode::Grid
+            }
+        }
+    }
+
+    fn parse_background_pause(pause_str: &str) -> BackgroundPause {
+        match pause_str.to_lowercase().as_str() {
+            "never" => BackgroundPause::Never,
+            "minimized" => BackgroundPause::Minimized,
+            "unfocused" => BackgroundPause::Unfocused,
+            _ => {
+                eprintln!(
+                    "Warning: Invalid pause_in_background '{}', defaulting to minimized",
+                    pause_str
+                );
+                BackgroundPause::Minimized
+            }
+        }
+    }
+
+    fn parse_start_mode(mode_str: &str) -> Option<StartMode> {
+        match mode_str.to_lowercase().as_str() {
+            "grid" => Some(StartMode::Grid),
+            "full" => Some(StartMode::Full),
+            "slideshow" => Some(StartMode::Slideshow),
+            _ => {
+                eprintln!(
+                    "Warning: Invalid mode '{}', expected grid, full or slideshow",
+                    mode_str
+                );
+                None
+            }
+        }
+    }
+
+    /// Entries are named `c<key>` to copy or `m<key>` to move, e.g. `m1 = "/photos/keep"`.
+    fn parse_targets(entries: HashMap<String, String>) -> Vec<FileTarget> {
+        let mut targets: Vec<FileTarget> = entries
+            .into_iter()
+            .filter_map(|(name, dir)| {
+                let mode = match name.chars().next() {
+                    Some('c') => TransferMode::Copy,
+                    Some('m') => TransferMode::Move,
+                    _ => {
+                        eprintln!(
+                            "Warning: Invalid target '{}', expected c<key> or m<key>",
+                            name
+                        );
+                        return None;
+                    }
+                };
+                let key = &name[1..];
+                if key.is_empty() {
+                    eprintln!("Warning: Target '{}' has no key", name);
+                    return None;
+                }
+                Some(FileTarget {
+                    key: Self::get_slint_key_string(key),
+                    dir: PathBuf::from(dir),
+                    mode,
+                })
+            })
+            .collect();
+        targets.sort_by(|a, b| a.key.cmp(&b.key));
+        targets
+    }
+
+    /// Macros with a step that doesn't parse are dropped with a warning.
+    fn parse_macros(entries: HashMap<String, Vec<String>>) -> Vec<Macro> {
+        let mut macros: Vec<Macro> = entries
+            .into_iter()
+            .filter_map(|(name, steps)| match Macro::parse(&name, &steps) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    eprintln!("Warning: Invalid macro '{}': {}", name, e);
+                    None
+                }
+            })
+            .collect();
+        macros.sort_by(|a, b| a.name.cmp(&b.name));
+        macros
+    }
+
+    /// Templates that don't parse are dropped with a warning.
+    fn parse_naming(toml: NamingToml) -> Naming {
+        let parse = |key: &str, text: String| match Template::parse(&text) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                eprintln!("Warning: Invalid naming.{} '{}': {}", key, text, e);
+                None
+            }
+        };
+        let defaults = Naming::default();
+        Naming {
+            rename: toml
+                .rename
+                .and_then(|t| parse("rename", t))
+                .map_or(defaults.rename, |t| t.as_str().to_string()),
+            export: toml.export.and_then(|t| parse("export", t)),
+            ingest: toml.ingest.and_then(|t| parse("ingest", t)),
+        }
+    }
+
+    /// Out of range values are clamped and unknown positions fall back to bottom-right.
+    fn parse_watermark(toml: WatermarkToml) -> Option<Watermark> {
+        let text = toml.text.filter(|t| !t.trim().is_empty());
+        if toml.image.is_none() && text.is_none() {
+            return None;
+        }
+        let defaults = Watermark::default();
+        let anchor = toml.position.map_or(defaults.anchor, |p| {
+            Anchor::from_name(&p).unwrap_or_else(|| {
+                eprintln!(
+                    "Warning: Invalid watermark.position '{}', defaulting to bottom-right",
+                    p
+                );
+                defaults.anchor
+            })
+        });
+        let color = toml.color.map_or(defaults.color, |c| {
+            let c = Self::parse_color(&c);
+            image::Rgba([c.red(), c.green(), c.blue(), c.alpha()])
+        });
+        Some(Watermark {
+            image: toml.image,
+            text,
+            anchor,
+            opacity: toml.opacity.unwrap_or(defaults.opacity).clamp(0.0, 1.0),
+            scale: toml.scale.unwrap_or(defaults.scale).clamp(0.01, 1.0),
+            color,
+        })
+    }
+
+    pub(crate) fn default_bindings() -> HashMap<String, String> {
         let mut map = HashMap::new();
         map.insert("quit".into(), "q".into());
         map.insert("toggle_fullscreen".into(), "f".into());
@@ -166,10 +1139,41 @@ impl Config {
         map.insert("grid_page_down".into(), "PageDown".into());
         map.insert("grid_page_up".into(), "PageUp".into());
         map.insert("reset_zoom".into(), "z".into());
+        map.insert("cycle_fit_mode".into(), "w".into());
         map.insert("toggle_side_panel".into(), "s".into());
-        map.insert("copy_to_clipboard".into(), "y".into());
+        map.insert("copy_image".into(), "y".into());
+        map.insert("paste_image".into(), "V".into());
+        map.insert("crop".into(), "C".into());
+        map.insert("markup".into(), "M".into());
         map.insert("delete".into(), "Delete".into());
         map.insert("show_settings".into(), "F1".into());
+        map.insert("hot_folder_pause".into(), "p".into());
+        map.insert("capture".into(), "c".into());
+        map.insert("command_palette".into(), ":".into());
+        map.insert("perf_overlay".into(), "F3".into());
+        map.insert("exposure_up".into(), "]".into());
+        map.insert("exposure_down".into(), "[".into());
+        map.insert("play_sequence".into(), "Space".into());
+        map.insert("loop_in".into(), "i".into());
+        map.insert("loop_out".into(), "o".into());
+        map.insert("pixel_inspector".into(), "e".into());
+        map.insert("copy_pixel_hex".into(), "x".into());
+        map.insert("slideshow".into(), "F5".into());
+        map.insert("info_overlay".into(), "n".into());
+        map.insert("caption".into(), "t".into());
+        map.insert("channel_view".into(), "v".into());
+        for stars in 0..=5 {
+            map.insert(format!("rate_{stars}"), stars.to_string());
+        }
+        map.insert("flag_pick".into(), "P".into());
+        map.insert("flag_reject".into(), "X".into());
+        map.insert("flag_clear".into(), "U".into());
+        map.insert("label_red".into(), "6".into());
+        map.insert("label_yellow".into(), "7".into());
+        map.insert("label_green".into(), "8".into());
+        map.insert("label_blue".into(), "9".into());
+        map.insert("toggle_favorite".into(), "b".into());
+        map.insert("last_bookmark".into(), "'".into());
         map
     }
 
@@ -184,6 +1188,7 @@ impl Config {
             "Escape" | "Esc" => Key::Escape.into(),
             "Return" | "Enter" => Key::Return.into(),
             "Tab" => Key::Tab.into(),
+            "Space" => Key::Space.into(),
             "Backspace" => Key::Backspace.into(),
             "PageUp" => Key::PageUp.into(),
             "PageDown" => Key::PageDown.into(),
@@ -191,8 +1196,289 @@ impl Config {
             "End" => Key::End.into(),
             "Delete" => Key::Delete.into(),
             "F1" => Key::F1.into(),
+            "F2" => Key::F2.into(),
+            "F3" => Key::F3.into(),
+            "F4" => Key::F4.into(),
+            "F5" => Key::F5.into(),
             // For single characters, return as is
             other => slint::SharedString::from(other),
         }
     }
 }
+
+/// Merge `overlay` into `base`: nested tables are merged, any other value is replaced.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_targets_reads_mode_and_key() {
+        let entries = HashMap::from([
+            ("m1".to_string(), "/photos/keep".to_string()),
+            ("c2".to_string(), "/photos/share".to_string()),
+            ("x3".to_string(), "/photos/ignored".to_string()),
+            ("m".to_string(), "/photos/no-key".to_string()),
+        ]);
+        assert_eq!(
+            Config::parse_targets(entries),
+            vec![
+                FileTarget {
+                    key: "1".into(),
+                    dir: PathBuf::from("/photos/keep"),
+                    mode: TransferMode::Move,
+                },
+                FileTarget {
+                    key: "2".into(),
+                    dir: PathBuf::from("/photos/share"),
+                    mode: TransferMode::Copy,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fit_mode_names_read_back() {
+        for mode in [
+            FitMode::Fit,
+            FitMode::Fill,
+            FitMode::Actual,
+            FitMode::FitWidth,
+        ] {
+            assert_eq!(
+                Config::fit_mode_from_name(Config::fit_mode_name(mode)),
+                Some(mode)
+            );
+        }
+        assert_eq!(Config::fit_mode_from_name("1:1"), Some(FitMode::Actual));
+        assert_eq!(
+            Config::fit_mode_from_name("Fit_Width"),
+            Some(FitMode::FitWidth)
+        );
+        assert_eq!(Config::fit_mode_from_name("zoom"), None);
+    }
+
+    #[test]
+    fn script_keys_conflict_with_bindings() {
+        let scripts = Config::parse_scripts(HashMap::from([
+            ("Ctrl+e".to_string(), "/scripts/publish.rhai".to_string()),
+            ("Hyper+x".to_string(), "/scripts/ignored.rhai".to_string()),
+        ]));
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(Ok(scripts[0].key.to_string()), keymap::parse("ctrl+E"));
+
+        let bindings = HashMap::from([("quit".to_string(), "Ctrl+e e".to_string())]);
+        assert_eq!(
+            keymap::conflicts(&Config::with_scripts(&bindings, &scripts)),
+            ["quit on 'Ctrl+e e' hides script /scripts/publish.rhai on 'Ctrl+e'"]
+        );
+    }
+
+    #[test]
+    fn parse_naming_drops_invalid_templates() {
+        let naming = Config::parse_naming(NamingToml {
+            rename: Some("{bogus}".to_string()),
+            export: Some("{parent}_{name}".to_string()),
+            ingest: Some("{seq".to_string()),
+        });
+        assert_eq!(naming.rename, Naming::default().rename);
+        assert_eq!(
+            naming.export.map(|t| t.as_str().to_string()).as_deref(),
+            Some("{parent}_{name}")
+        );
+        assert_eq!(naming.ingest, None);
+    }
+
+    #[test]
+    fn profiles_override_the_config_file() {
+        let mut base: toml::Table = toml::from_str(
+            "window_size = 3\nbackground = \"#000000\"\n\
+             [bindings]\nquit = \"q\"\nslideshow = \"F5\"\n",
+        )
+        .unwrap();
+        let profile: toml::Table = toml::from_str(
+            "background = \"#202020\"\nwindow_size = 9\n[bindings]\nslideshow = \"Space\"\n",
+        )
+        .unwrap();
+        merge_tables(&mut base, profile);
+        let config: TomlConfig = base.try_into().unwrap();
+        assert_eq!(config.window_size, Some(9));
+        assert_eq!(config.background.as_deref(), Some("#202020"));
+        let bindings = config.bindings.unwrap();
+        assert_eq!(bindings["quit"], "q");
+        assert_eq!(bindings["slideshow"], "Space");
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["presentation.toml", "culling.toml", "notes.txt"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert_eq!(
+            Config::list_profiles(dir.path()),
+            ["culling", "presentation"]
+        );
+    }
+
+    #[test]
+    fn subcommands_and_paths_parse() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["luminous", "install-integration", "--default"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::InstallIntegration { default: true })
+        );
+        let cli = Cli::try_parse_from(["luminous", "uninstall-integration"]).unwrap();
+        assert_eq!(cli.command, Some(Command::UninstallIntegration));
+        let cli = Cli::try_parse_from([
+            "luminous", "exif", "set", "--artist", "Ann", "a.jpg", "b.png",
+        ])
+        .unwrap();
+        let Some(Command::Exif { action }) = cli.command else {
+            panic!("not an exif command: {:?}", cli.command);
+        };
+        let (edits, files) = action.edits();
+        assert_eq!(
+            edits,
+            [TagEdit {
+                tag: TextTag::Artist,
+                value: Some("Ann".to_string())
+            }]
+        );
+        assert_eq!(files.len(), 2);
+        let cli = Cli::try_parse_from(["luminous", "exif", "del", "--copyright", "a.jpg"]).unwrap();
+        let Some(Command::Exif { action }) = cli.command else {
+            panic!("not an exif command: {:?}", cli.command);
+        };
+        assert_eq!(action.edits().0[0].value, None);
+        let cli =
+            Cli::try_parse_from(["luminous", "wallpaper", "favorites:", "--shuffle"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Wallpaper {
+                path: Some("favorites:".into()),
+                interval: 900,
+                shuffle: true,
+            })
+        );
+        assert!(Cli::try_parse_from(["luminous", "wallpaper", "--interval", "0"]).is_err());
+        let cli = Cli::try_parse_from([
+            "luminous",
+            "convert",
+            "--to",
+            "webp",
+            "--max-size",
+            "2048",
+            "src/",
+            "dst/",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Convert {
+                to: "webp".into(),
+                max_size: Some(2048),
+                quality: None,
+                overwrite: false,
+                no_watermark: false,
+                src: "src/".into(),
+                dst: "dst/".into(),
+            })
+        );
+        assert!(Cli::try_parse_from(["luminous", "exif", "set", "--artist", "Ann"]).is_err());
+        let cli = Cli::try_parse_from(["luminous", "config", "init", "--force"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Config {
+                action: ConfigAction::Init { force: true }
+            })
+        );
+        let cli = Cli::try_parse_from(["luminous", "--mode", "grid", "photos"]).unwrap();
+        assert_eq!((cli.command, cli.path.as_deref()), (None, Some("photos")));
+    }
+
+    #[test]
+    fn default_config_is_written_once_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("luminous").join("luminous.toml");
+        Config::write_default(&path, false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+
+        fs::write(&path, "window_size = 9\n").unwrap();
+        assert!(matches!(
+            Config::write_default(&path, false),
+            Err(LuminousError::Config(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "window_size = 9\n");
+        Config::write_default(&path, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+    }
+
+    #[test]
+    fn parse_watermark_needs_text_or_image() {
+        assert_eq!(Config::parse_watermark(WatermarkToml::default()), None);
+        let watermark = Config::parse_watermark(WatermarkToml {
+            text: Some("© Ann".into()),
+            position: Some("Top-Left".into()),
+            opacity: Some(3.0),
+            color: Some("#ff0000".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(watermark.anchor, Anchor::TopLeft);
+        assert_eq!(watermark.opacity, 1.0);
+        assert_eq!(watermark.color, image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn default_config_passes_the_check() {
+        let mut table: toml::Table = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(config_check::check(&mut table), Vec::<String>::new());
+        let config: TomlConfig = table.try_into().unwrap();
+        assert!(
+            config.path.is_none(),
+            "a path would disable session restore"
+        );
+        assert_eq!(
+            config.bindings.unwrap_or_default(),
+            Config::default_bindings()
+        );
+        assert_eq!(
+            keymap::conflicts(&Config::default_bindings()),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn parse_start_mode_is_case_insensitive() {
+        assert_eq!(
+            Config::parse_start_mode("Slideshow"),
+            Some(StartMode::Slideshow)
+        );
+        assert_eq!(Config::parse_start_mode("grid"), Some(StartMode::Grid));
+        assert_eq!(Config::parse_start_mode("thumbnails"), None);
+    }
+
+    #[test]
+    fn background_pause_levels() {
+        let pause = Config::parse_background_pause("Unfocused");
+        assert!(pause.applies(false, true));
+        assert!(pause.applies(true, false));
+        let pause = Config::parse_background_pause("minimized");
+        assert!(!pause.applies(false, true));
+        assert!(pause.applies(false, false));
+        assert!(!BackgroundPause::Never.applies(false, false));
+    }
+}
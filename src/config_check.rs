@@ -0,0 +1,448 @@
+use crate::actions::Action;
+use crate::config::Config;
+use crate::fs_scan::NameFilter;
+use crate::keymap;
+use crate::naming::Template;
+use crate::watermark::Anchor;
+use luminous_image_loader::tone_map::ToneMapOperator;
+use toml::Value;
+
+/// What a config value must look like.
+enum Kind {
+    Bool,
+    Int {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    /// Any string
+    Str,
+    /// One of these, ignoring case
+    OneOf(&'static [&'static str]),
+    /// A string accepted by the function, which explains what is wrong otherwise
+    Parsed(fn(&str) -> Result<(), String>),
+    Table(&'static [Field]),
+    /// Table with free-form keys checked by the function, each value a string checked like the
+    /// given kind
+    Map(fn(&str) -> Result<(), String>, &'static Kind),
+    /// Array of values checked like the given kind, dropped whole when one is wrong
+    List(&'static Kind),
+}
+
+struct Field {
+    key: &'static str,
+    kind: Kind,
+}
+
+const fn field(key: &'static str, kind: Kind) -> Field {
+    Field { key, kind }
+}
+
+const VIEWS: &[&str] = &["grid", "full"];
+
+const FIT_MODES: &[&str] = &["fit", "fill", "1:1", "actual", "fit-width", "fit_width"];
+
+const SLIDESHOW_INTERVAL: Kind = Kind::Float {
+    min: 0.5,
+    max: 3600.0,
+};
+
+const SCHEMA: &[Field] = &[
+    field("path", Kind::Str),
+    field("log", Kind::Parsed(log_filter)),
+    // 0 picks the number of CPUs
+    field("threads", Kind::Int { min: 0, max: 1024 }),
+    field("window_size", Kind::Int { min: 1, max: 100 }),
+    field("prefetch_rows", Kind::Int { min: 0, max: 100 }),
+    field("background", Kind::Parsed(color)),
+    field("fit_mode", Kind::OneOf(FIT_MODES)),
+    field("tone_map", Kind::Parsed(tone_map)),
+    field("playback_fps", Kind::Int { min: 1, max: 120 }),
+    field("jpeg_quality", Kind::Int { min: 1, max: 100 }),
+    field("mode", Kind::OneOf(&["grid", "full", "slideshow"])),
+    field("slideshow_interval", SLIDESHOW_INTERVAL),
+    field("slideshow_progress", Kind::Bool),
+    field("grid_captions", Kind::Bool),
+    field("validate_cache", Kind::Bool),
+    field("readahead", Kind::Bool),
+    field("battery_saver", Kind::Bool),
+    field("single_instance", Kind::Bool),
+    field("reduced_motion", Kind::Bool),
+    field("high_contrast", Kind::Bool),
+    field(
+        "pause_in_background",
+        Kind::OneOf(&["never", "minimized", "unfocused"]),
+    ),
+    field("filter", Kind::Parsed(name_filter)),
+    field("extra_extensions", Kind::List(&Kind::Parsed(extension))),
+    field("exclude_extensions", Kind::List(&Kind::Parsed(extension))),
+    field(
+        "bindings",
+        Kind::Map(binding_action, &Kind::Parsed(binding_keys)),
+    ),
+    field("targets", Kind::Map(target_name, &Kind::Str)),
+    field("scripts", Kind::Map(binding_keys, &Kind::Str)),
+    field(
+        "macros",
+        Kind::Map(macro_name, &Kind::List(&Kind::Parsed(macro_step))),
+    ),
+    field(
+        "naming",
+        Kind::Table(&[
+            field("rename", Kind::Parsed(template)),
+            field("export", Kind::Parsed(template)),
+            field("ingest", Kind::Parsed(template)),
+        ]),
+    ),
+    field(
+        "watermark",
+        Kind::Table(&[
+            field("image", Kind::Str),
+            field("text", Kind::Str),
+            field("position", Kind::OneOf(&Anchor::NAMES)),
+            field("opacity", Kind::Float { min: 0.0, max: 1.0 }),
+            field(
+                "scale",
+                Kind::Float {
+                    min: 0.01,
+                    max: 1.0,
+                },
+            ),
+            field("color", Kind::Parsed(color)),
+        ]),
+    ),
+    field(
+        "startup",
+        Kind::Table(&[
+            field("directory", Kind::OneOf(VIEWS)),
+            field("file", Kind::OneOf(VIEWS)),
+            field("archive", Kind::OneOf(VIEWS)),
+            field("session", Kind::Bool),
+        ]),
+    ),
+];
+
+/// Keys of a folder's `.luminous.toml`, see [`crate::folder_config`].
+const FOLDER_SCHEMA: &[Field] = &[
+    field("sort", Kind::OneOf(&["name", "name-desc", "custom"])),
+    field("filter", Kind::Parsed(name_filter)),
+    field("exclude_extensions", Kind::List(&Kind::Parsed(extension))),
+    field("fit_mode", Kind::OneOf(FIT_MODES)),
+    field("grid_columns", Kind::Int { min: 2, max: 20 }),
+    field("slideshow_interval", SLIDESHOW_INTERVAL),
+    field("slideshow_progress", Kind::Bool),
+    field("reading_direction", Kind::OneOf(&["ltr", "rtl"])),
+    field("spread", Kind::Bool),
+];
+
+fn log_filter(s: &str) -> Result<(), String> {
+    const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+    // env_logger directives: a level, or module=level, comma separated
+    for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let level = directive.rsplit_once('=').map_or(directive, |(_, l)| l);
+        if !LEVELS.contains(&level.to_lowercase().as_str()) {
+            return Err(format!(
+                "unknown log level '{level}', expected one of {}",
+                LEVELS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn color(s: &str) -> Result<(), String> {
+    csscolorparser::parse(s)
+        .map(|_| ())
+        .map_err(|_| format!("'{s}' is not a color, e.g. \"#202020\""))
+}
+
+fn tone_map(s: &str) -> Result<(), String> {
+    ToneMapOperator::from_name(s)
+        .map(|_| ())
+        .ok_or_else(|| format!("unknown tone map '{s}', expected aces or reinhard"))
+}
+
+fn name_filter(s: &str) -> Result<(), String> {
+    NameFilter::parse(s)
+        .map(|_| ())
+        .map_err(|e| format!("invalid filter {s:?}: {e}"))
+}
+
+fn extension(s: &str) -> Result<(), String> {
+    let ext = s.strip_prefix('.').unwrap_or(s);
+    if ext.is_empty() || !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("'{s}' is not a file extension, e.g. \"jfif\""));
+    }
+    Ok(())
+}
+
+fn template(s: &str) -> Result<(), String> {
+    Template::parse(s).map(|_| ())
+}
+
+fn binding_action(action: &str) -> Result<(), String> {
+    let actions = Config::default_bindings();
+    // Old name of copy_image, still read
+    if actions.contains_key(action) || action == "copy_to_clipboard" {
+        return Ok(());
+    }
+    let mut names: Vec<&str> = actions.keys().map(String::as_str).collect();
+    names.sort();
+    Err(unknown("action", action, &names))
+}
+
+/// Keys with modifiers, or a sequence of them, see [`crate::keymap`].
+fn binding_keys(keys: &str) -> Result<(), String> {
+    keymap::parse(keys).map(|_| ())
+}
+
+/// A single character or a key name like "PageDown".
+fn key_name(key: &str) -> Result<(), String> {
+    if key.chars().count() == 1 || Config::get_slint_key_string(key) != key {
+        Ok(())
+    } else {
+        Err(format!("unknown key '{key}'"))
+    }
+}
+
+fn macro_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        Err("macros need a name".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn macro_step(step: &str) -> Result<(), String> {
+    match Action::parse(step)? {
+        Action::Macro(_) => Err("macros can't run other macros".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn target_name(name: &str) -> Result<(), String> {
+    match name.split_at_checked(1) {
+        Some(("c" | "m", key)) if !key.is_empty() => key_name(key),
+        _ => Err(format!(
+            "invalid target '{name}', expected c<key> to copy or m<key> to move"
+        )),
+    }
+}
+
+/// Number of single character insertions, deletions and substitutions turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn unknown(what: &str, name: &str, known: &[&str]) -> String {
+    let closest = known
+        .iter()
+        .map(|k| (edit_distance(name, k), *k))
+        .filter(|&(distance, k)| distance <= 2.max(k.len() / 4))
+        .min();
+    match closest {
+        Some((_, k)) => format!("unknown {what} '{name}', did you mean '{k}'?"),
+        None => format!("unknown {what} '{name}'"),
+    }
+}
+
+/// Whether `value` fits `kind`, otherwise what is wrong goes to `issues`. Nested tables are
+/// checked field by field and keep their valid entries.
+fn check_value(kind: &Kind, path: &str, value: &mut Value, issues: &mut Vec<String>) -> bool {
+    let found = value.type_str();
+    let expected = |what: &str| format!("{path}: expected {what}, found {found}");
+    let out_of_range = |v: String, min: String, max: String| {
+        format!("{path}: {v} is out of range, expected {min} to {max}")
+    };
+    let problem = match (kind, value) {
+        (Kind::Bool, Value::Boolean(_)) | (Kind::Str, Value::String(_)) => None,
+        (Kind::Int { min, max }, Value::Integer(i)) => (*i < *min || *i > *max)
+            .then(|| out_of_range(i.to_string(), min.to_string(), max.to_string())),
+        (Kind::Float { min, max }, Value::Float(f)) => (*f < *min || *f > *max)
+            .then(|| out_of_range(f.to_string(), min.to_string(), max.to_string())),
+        (Kind::Float { min, max }, Value::Integer(i)) => ((*i as f64) < *min || (*i as f64) > *max)
+            .then(|| out_of_range(i.to_string(), min.to_string(), max.to_string())),
+        (Kind::OneOf(choices), Value::String(s)) => {
+            (!choices.iter().any(|c| c.eq_ignore_ascii_case(s))).then(|| {
+                format!(
+                    "{path}: invalid value '{s}', expected one of {}",
+                    choices.join(", ")
+                )
+            })
+        }
+        (Kind::Parsed(parse), Value::String(s)) => parse(s).err().map(|e| format!("{path}: {e}")),
+        (Kind::Table(fields), Value::Table(table)) => {
+            check_table(fields, path, table, issues);
+            None
+        }
+        (Kind::Map(check_key, value_kind), Value::Table(table)) => {
+            table.retain(|key, value| {
+                let key_path = format!("{path}.{key}");
+                if let Err(e) = check_key(key) {
+                    issues.push(format!("{key_path}: {e}"));
+                    return false;
+                }
+                check_value(value_kind, &key_path, value, issues)
+            });
+            None
+        }
+        (Kind::List(item_kind), Value::Array(items)) => {
+            let mut valid = !items.is_empty();
+            if !valid {
+                issues.push(format!("{path}: expected at least one value"));
+            }
+            for (i, item) in items.iter_mut().enumerate() {
+                valid &= check_value(item_kind, &format!("{path}[{i}]"), item, issues);
+            }
+            if !valid {
+                return false;
+            }
+            None
+        }
+        (Kind::Bool, _) => Some(expected("true or false")),
+        (Kind::Int { .. }, _) => Some(expected("an integer")),
+        (Kind::Float { .. }, _) => Some(expected("a number")),
+        (Kind::Str | Kind::OneOf(_) | Kind::Parsed(_), _) => Some(expected("a string")),
+        (Kind::Table(_) | Kind::Map(..), _) => Some(expected("a table")),
+        (Kind::List(_), _) => Some(expected("an array")),
+    };
+    match problem {
+        Some(problem) => {
+            issues.push(problem);
+            false
+        }
+        None => true,
+    }
+}
+
+fn check_table(fields: &[Field], prefix: &str, table: &mut toml::Table, issues: &mut Vec<String>) {
+    let path = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+    let known: Vec<String> = fields.iter().map(|f| path(f.key)).collect();
+    let known: Vec<&str> = known.iter().map(String::as_str).collect();
+    table.retain(|key, value| match fields.iter().find(|f| f.key == key) {
+        Some(field) => check_value(&field.kind, &path(key), value, issues),
+        None => {
+            issues.push(unknown("key", &path(key), &known));
+            false
+        }
+    });
+}
+
+/// Check a config file against the schema, returning what is wrong with it. Invalid entries are
+/// removed so the rest of the file still applies.
+pub fn check(table: &mut toml::Table) -> Vec<String> {
+    let mut issues = Vec::new();
+    check_table(SCHEMA, "", table, &mut issues);
+    issues
+}
+
+/// Like [`check`], for a folder's `.luminous.toml`.
+pub fn check_folder(table: &mut toml::Table) -> Vec<String> {
+    let mut issues = Vec::new();
+    check_table(FOLDER_SCHEMA, "", table, &mut issues);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(config: &str) -> (Vec<String>, toml::Table) {
+        let mut table: toml::Table = toml::from_str(config).unwrap();
+        (check(&mut table), table)
+    }
+
+    #[test]
+    fn valid_config_passes_untouched() {
+        let (problems, table) = issues(
+            "threads = 0\nslideshow_interval = 5\nlog = \"info,luminous=debug\"\n\
+             fit_mode = \"Fit-Width\"\n[bindings]\nquit = \"Escape\"\n[targets]\nm1 = \"/keep\"\n\
+             [startup]\nsession = false\n",
+        );
+        assert_eq!(problems, Vec::<String>::new());
+        assert_eq!(table.len(), 7);
+    }
+
+    #[test]
+    fn problems_are_reported_and_dropped() {
+        let (mut problems, table) = issues(
+            "window_sise = 3\nthreads = \"4\"\nprefetch_rows = 500\nlog = \"loud\"\n\
+             mode = \"cinema\"\nbackground = \"#12\"\n[bindings]\nqiut = \"q\"\nslideshow = \"F12\"\n\
+             [startup]\ndirectroy = \"grid\"\n[macros]\nrate_5 = [\"set_rating 7\", \"next\"]\n",
+        );
+        problems.sort();
+        assert_eq!(
+            problems,
+            [
+                "background: '#12' is not a color, e.g. \"#202020\"",
+                "bindings.qiut: unknown action 'qiut', did you mean 'quit'?",
+                "bindings.slideshow: unknown key 'F12'",
+                "log: unknown log level 'loud', expected one of off, error, warn, info, debug, trace",
+                "macros.rate_5[0]: invalid rating '7', expected 0 to 5",
+                "mode: invalid value 'cinema', expected one of grid, full, slideshow",
+                "prefetch_rows: 500 is out of range, expected 0 to 100",
+                "threads: expected an integer, found string",
+                "unknown key 'startup.directroy', did you mean 'startup.directory'?",
+                "unknown key 'window_sise', did you mean 'window_size'?",
+            ]
+        );
+        // Empty tables stay, everything else was dropped
+        assert_eq!(table.len(), 3);
+    }
+
+    #[test]
+    fn example_config_has_every_key() {
+        let lines: Vec<&str> = crate::config::DEFAULT_CONFIG
+            .lines()
+            .map(|l| l.trim_start_matches('#').trim())
+            .collect();
+        let has_key = |key: &str| lines.iter().any(|l| l.starts_with(&format!("{key} =")));
+        let has_table = |key: &str| lines.contains(&format!("[{key}]").as_str());
+        for field in SCHEMA {
+            match &field.kind {
+                Kind::Table(fields) => {
+                    assert!(has_table(field.key), "[{}] is missing", field.key);
+                    for f in *fields {
+                        assert!(has_key(f.key), "{}.{} is missing", field.key, f.key);
+                    }
+                }
+                Kind::Map(..) => assert!(has_table(field.key), "[{}] is missing", field.key),
+                _ => assert!(has_key(field.key), "{} is missing", field.key),
+            }
+        }
+    }
+
+    #[test]
+    fn suggestions_need_a_close_match() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(
+            unknown("key", "tone_mpa", &["tone_map", "fit_mode"]),
+            "unknown key 'tone_mpa', did you mean 'tone_map'?"
+        );
+        assert_eq!(
+            unknown("key", "colour", &["tone_map"]),
+            "unknown key 'colour'"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! `luminous convert`, batch conversion of images through the decoders and encoders the viewer
+//! uses, plugins included.
+
+use crate::image_processing::{encode, to_dynamic};
+use crate::watermark::Stamp;
+use image::DynamicImage;
+use image::imageops::FilterType;
+use log::{debug, error, warn};
+use luminous_plugins::PluginManager;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// Extension of the output format
+    pub format: String,
+    /// Longest side of the output, smaller images are left as they are
+    pub max_size: Option<u32>,
+    /// JPEG quality, 1-100
+    pub quality: u8,
+    /// Replace files already in the destination instead of skipping them
+    pub overwrite: bool,
+    /// Stamped on every image after resizing
+    pub watermark: Option<Stamp>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub converted: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// `src` renamed to `format` inside `dst_dir`.
+pub fn target(src: &Path, dst_dir: &Path, format: &str) -> PathBuf {
+    let stem = src.file_stem().unwrap_or_default().to_string_lossy();
+    dst_dir.join(format!("{stem}.{format}"))
+}
+
+/// [`target`] of each of `paths`. Sources that would land on the same file, like `a.png` and
+/// `a.jpg`, keep their extension in the name instead: `a.png.webp` and `a.jpg.webp`.
+pub fn targets(paths: &[PathBuf], dst_dir: &Path, format: &str) -> Vec<PathBuf> {
+    let key = |p: &PathBuf| target(p, dst_dir, format).to_string_lossy().to_lowercase();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for path in paths {
+        *counts.entry(key(path)).or_default() += 1;
+    }
+    paths
+        .iter()
+        .map(|src| {
+            let dst = target(src, dst_dir, format);
+            if counts[&key(src)] == 1 {
+                return dst;
+            }
+            let name = src.file_name().unwrap_or_default().to_string_lossy();
+            let renamed = dst_dir.join(format!("{name}.{format}"));
+            warn!(
+                "Convert: {} has the name of another source, writing {:?}",
+                name, renamed
+            );
+            renamed
+        })
+        .collect()
+}
+
+fn fit(img: DynamicImage, max_size: Option<u32>) -> DynamicImage {
+    match max_size {
+        Some(max) if img.width() > max || img.height() > max => {
+            img.resize(max, max, FilterType::Lanczos3)
+        }
+        _ => img,
+    }
+}
+
+/// Convert each of `paths` into `dst_dir` on `threads` workers. Files already there are
+/// skipped unless overwriting, and a file is never written over its own source.
+pub fn convert(
+    paths: &[PathBuf],
+    dst_dir: &Path,
+    options: &ConvertOptions,
+    plugin_manager: &Arc<PluginManager>,
+    threads: usize,
+) -> io::Result<Summary> {
+    std::fs::create_dir_all(dst_dir)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(io::Error::other)?;
+    let (converted, skipped, failed) = (
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+    );
+    let targets = targets(paths, dst_dir, &options.format);
+    pool.install(|| {
+        paths.par_iter().zip(&targets).for_each(|(src, dst)| {
+            let same_file = dst
+                .canonicalize()
+                .is_ok_and(|d| src.canonicalize().is_ok_and(|s| s == d));
+            if same_file || (!options.overwrite && dst.exists()) {
+                debug!("Convert: {:?} exists, skipping", dst);
+                skipped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            let Some(buffer) = luminous_image_loader::decode_file(src, plugin_manager) else {
+                failed.fetch_add(1, Ordering::Relaxed);
+                return;
+            };
+            let mut img = fit(to_dynamic(buffer), options.max_size);
+            if let Some(stamp) = &options.watermark {
+                img = stamp.apply(img);
+            }
+            match encode(&img, dst, &options.format, options.quality, plugin_manager) {
+                Ok(()) => {
+                    debug!("Converted {:?} -> {:?}", src, dst);
+                    converted.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    error!("Failed to write {:?}: {}", dst, e);
+                    let _ = std::fs::remove_file(dst);
+                    failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    });
+    Ok(Summary {
+        converted: converted.into_inner(),
+        skipped: skipped.into_inner(),
+        failed: failed.into_inner(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_resizes_and_skips_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+        std::fs::create_dir_all(&src).unwrap();
+        let wide = src.join("wide.png");
+        let small = src.join("small.png");
+        image::RgbaImage::new(400, 100).save(&wide).unwrap();
+        image::RgbaImage::new(20, 30).save(&small).unwrap();
+        std::fs::write(src.join("broken.png"), b"not an image").unwrap();
+        let paths = [wide, small, src.join("broken.png")];
+
+        let mut options = ConvertOptions {
+            format: "jpg".into(),
+            max_size: Some(200),
+            quality: 90,
+            overwrite: false,
+            watermark: None,
+        };
+        let pm = Arc::new(PluginManager::new());
+        let summary = convert(&paths, &dst, &options, &pm, 2).unwrap();
+        assert_eq!((summary.converted, summary.failed), (2, 1));
+        assert_eq!(
+            image::image_dimensions(dst.join("wide.jpg")).unwrap(),
+            (200, 50)
+        );
+        assert_eq!(
+            image::image_dimensions(dst.join("small.jpg")).unwrap(),
+            (20, 30)
+        );
+
+        assert_eq!(
+            convert(&paths[..2], &dst, &options, &pm, 2)
+                .unwrap()
+                .skipped,
+            2
+        );
+        options.overwrite = true;
+        assert_eq!(
+            convert(&paths[..2], &dst, &options, &pm, 2)
+                .unwrap()
+                .converted,
+            2
+        );
+        // Never over the source, even when overwriting
+        options.format = "png".into();
+        assert_eq!(
+            convert(&paths[..1], &src, &options, &pm, 1)
+                .unwrap()
+                .skipped,
+            1
+        );
+
+        // Same name, different extension
+        let twin = src.join("small.tiff");
+        image::RgbaImage::new(5, 5).save(&twin).unwrap();
+        options.format = "webp".into();
+        let twins = [paths[1].clone(), twin];
+        assert_eq!(
+            targets(&twins, &dst, "webp"),
+            [dst.join("small.png.webp"), dst.join("small.tiff.webp")]
+        );
+        let summary = convert(&twins, &dst, &options, &pm, 2).unwrap();
+        assert_eq!(summary.converted, 2);
+    }
+}
@@ -0,0 +1,88 @@
+//! The `org.luminous.Viewer` session bus interface, so the viewer can be scripted with
+//! `busctl`, `gdbus` and other desktop tools. It takes the same requests as the control socket,
+//! see [`crate::remote`].
+
+use crate::remote::{self, Request};
+use log::info;
+use std::path::PathBuf;
+use zbus::fdo;
+
+pub const BUS_NAME: &str = "org.luminous.Viewer";
+pub const OBJECT_PATH: &str = "/org/luminous/Viewer";
+
+type Handler = Box<dyn Fn(Request) -> Result<String, String> + Send + Sync>;
+
+struct Viewer {
+    handle: Handler,
+}
+
+impl Viewer {
+    fn request(&self, request: Request) -> fdo::Result<String> {
+        (self.handle)(request).map_err(fdo::Error::Failed)
+    }
+}
+
+#[zbus::interface(name = "org.luminous.Viewer")]
+impl Viewer {
+    fn next(&self) -> fdo::Result<()> {
+        self.request(Request::Next).map(drop)
+    }
+
+    fn prev(&self) -> fdo::Result<()> {
+        self.request(Request::Prev).map(drop)
+    }
+
+    /// Show the nth of the shown images, counted from 1.
+    fn goto(&self, position: u32) -> fdo::Result<()> {
+        if position == 0 {
+            return Err(fdo::Error::InvalidArgs("positions count from 1".into()));
+        }
+        self.request(Request::Goto(position as usize)).map(drop)
+    }
+
+    /// Show a file with its folder, or a folder, and raise the window.
+    fn open(&self, path: String) -> fdo::Result<()> {
+        self.request(Request::Open(PathBuf::from(path))).map(drop)
+    }
+
+    fn focus(&self) -> fdo::Result<()> {
+        self.request(Request::Focus).map(drop)
+    }
+
+    /// Position among the shown images counted from 1, their count and the path of the current
+    /// image.
+    fn current(&self) -> fdo::Result<(u32, u32, String)> {
+        let reply = self.request(Request::Current)?;
+        remote::parse_current(&reply)
+            .map(|(position, count, path)| (position as u32, count as u32, path))
+            .ok_or_else(|| fdo::Error::Failed(format!("unexpected reply '{reply}'")))
+    }
+
+    fn quit(&self) -> fdo::Result<()> {
+        self.request(Request::Quit).map(drop)
+    }
+}
+
+/// Serves until dropped, then gives up the bus name.
+pub struct Service {
+    _connection: zbus::blocking::Connection,
+}
+
+/// Take the bus name on the session bus and call `handle` with each method call, on a
+/// background thread. Fails when there is no session bus or another viewer owns the name.
+pub fn serve<F>(handle: F) -> zbus::Result<Service>
+where
+    F: Fn(Request) -> Result<String, String> + Send + Sync + 'static,
+{
+    let viewer = Viewer {
+        handle: Box::new(handle),
+    };
+    let connection = zbus::blocking::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, viewer)?
+        .build()?;
+    info!("Serving {} on the session bus", BUS_NAME);
+    Ok(Service {
+        _connection: connection,
+    })
+}
@@ -0,0 +1,95 @@
+use crate::test_patterns::draw_text;
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Root of the virtual demo paths, never touches the file system.
+pub const DEMO_DIR: &str = "demo:";
+
+/// Sizes cycled through so decoding and scaling see landscape, portrait and large images.
+const SIZES: [(u32, u32); 5] = [
+    (1920, 1080),
+    (1080, 1440),
+    (800, 800),
+    (4000, 3000),
+    (640, 480),
+];
+
+pub fn paths(count: usize) -> Vec<PathBuf> {
+    (0..count)
+        .map(|i| Path::new(DEMO_DIR).join(format!("demo_{i:04}.png")))
+        .collect()
+}
+
+fn index_of(path: &Path) -> Option<usize> {
+    if !path.starts_with(DEMO_DIR) {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("demo_")?
+        .parse()
+        .ok()
+}
+
+/// Deterministic image for a demo path: a gradient or noise, labelled with its index.
+pub fn generate(path: &Path) -> Option<DynamicImage> {
+    let i = index_of(path)?;
+    let (w, h) = SIZES[i % SIZES.len()];
+    let mut img = match i % 2 {
+        0 => gradient(w, h, i),
+        _ => noise(w, h, i as u32),
+    };
+    let scale = h / 40;
+    draw_text(
+        &mut img,
+        &i.to_string(),
+        scale * 2,
+        scale * 2,
+        scale,
+        Rgba([255, 255, 255, 255]),
+    );
+    Some(DynamicImage::ImageRgba8(img))
+}
+
+fn gradient(w: u32, h: u32, i: usize) -> RgbaImage {
+    // Rotate the channels per image so neighbours look different
+    let shift = (i / 2 % 3) as u32;
+    RgbaImage::from_fn(w, h, |x, y| {
+        let c = [(x * 255 / w) as u8, (y * 255 / h) as u8, 128];
+        Rgba([
+            c[(shift % 3) as usize],
+            c[((shift + 1) % 3) as usize],
+            c[((shift + 2) % 3) as usize],
+            255,
+        ])
+    })
+}
+
+fn noise(w: u32, h: u32, seed: u32) -> RgbaImage {
+    let mut state = seed.wrapping_mul(0x9E37_79B9) | 1;
+    RgbaImage::from_fn(w, h, |_, _| {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let [r, g, b, _] = state.to_le_bytes();
+        Rgba([r, g, b, 255])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_only_demo_paths() {
+        let paths = paths(3);
+        assert_eq!(paths[2], PathBuf::from("demo:/demo_0002.png"));
+        assert!(generate(Path::new("/photos/demo_0002.png")).is_none());
+
+        let img = generate(&paths[1]).unwrap();
+        assert_eq!((img.width(), img.height()), SIZES[1]);
+        // Same path, same pixels
+        assert_eq!(img, generate(&paths[1]).unwrap());
+    }
+}
@@ -0,0 +1,299 @@
+use rayon::prelude::*;
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use std::sync::LazyLock;
+
+/// View-only transform applied to the displayed image, never to the cached pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayFilter {
+    #[default]
+    None,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+    /// Relative luminance as gray
+    Luminance,
+    Red,
+    Green,
+    Blue,
+    /// Opacity as gray, fully opaque
+    Alpha,
+}
+
+/// Brettel et al. 1997 dichromacy simulation, expressed in linear RGB.
+///
+/// The LMS confusion line projection is folded into two matrices, one per half-plane; the
+/// separation plane normal picks which one a color falls on.
+struct Brettel {
+    first: [[f32; 3]; 3],
+    second: [[f32; 3]; 3],
+    normal: [f32; 3],
+}
+
+// Matrices from libDaltonLens (Public Domain), for sRGB primaries and D65
+const PROTAN: Brettel = Brettel {
+    first: [
+        [0.14980, 1.19548, -0.34528],
+        [0.10764, 0.84864, 0.04372],
+        [0.00384, -0.00540, 1.00156],
+    ],
+    second: [
+        [0.14570, 1.16172, -0.30742],
+        [0.10816, 0.85291, 0.03892],
+        [0.00386, -0.00524, 1.00139],
+    ],
+    normal: [0.00048, 0.00393, -0.00441],
+};
+
+const DEUTAN: Brettel = Brettel {
+    first: [
+        [0.36477, 0.86381, -0.22858],
+        [0.26294, 0.64245, 0.09462],
+        [-0.02006, 0.02728, 0.99278],
+    ],
+    second: [
+        [0.37298, 0.88166, -0.25464],
+        [0.25954, 0.63506, 0.10540],
+        [-0.01980, 0.02784, 0.99196],
+    ],
+    normal: [-0.00281, -0.00611, 0.00892],
+};
+
+const TRITAN: Brettel = Brettel {
+    first: [
+        [1.01277, 0.13548, -0.14826],
+        [-0.01243, 0.86812, 0.14431],
+        [0.07589, 0.80500, 0.11911],
+    ],
+    second: [
+        [0.93678, 0.18979, -0.12657],
+        [0.06154, 0.81526, 0.12320],
+        [-0.37562, 1.12767, 0.24796],
+    ],
+    normal: [0.03901, -0.02788, -0.01113],
+};
+
+static SRGB_TO_LINEAR: LazyLock<[f32; 256]> = LazyLock::new(|| {
+    std::array::from_fn(|i| {
+        let c = i as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+});
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0).round() as u8
+}
+
+impl DisplayFilter {
+    pub const ALL: [Self; 9] = [
+        Self::None,
+        Self::Protanopia,
+        Self::Deuteranopia,
+        Self::Tritanopia,
+        Self::Luminance,
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Alpha,
+    ];
+
+    /// Order the channel views are stepped through, back to the plain image.
+    const CHANNELS: [Self; 6] = [
+        Self::None,
+        Self::Luminance,
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Alpha,
+    ];
+
+    /// The channel view after this one; color blindness filters restart the cycle.
+    pub fn next_channel(self) -> Self {
+        match Self::CHANNELS.iter().position(|&f| f == self) {
+            Some(i) => Self::CHANNELS[(i + 1) % Self::CHANNELS.len()],
+            None => Self::Luminance,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Protanopia => "Protanopia",
+            Self::Deuteranopia => "Deuteranopia",
+            Self::Tritanopia => "Tritanopia",
+            Self::Luminance => "Luminance",
+            Self::Red => "Red",
+            Self::Green => "Green",
+            Self::Blue => "Blue",
+            Self::Alpha => "Alpha",
+        }
+    }
+
+    fn brettel(self) -> Option<&'static Brettel> {
+        match self {
+            Self::Protanopia => Some(&PROTAN),
+            Self::Deuteranopia => Some(&DEUTAN),
+            Self::Tritanopia => Some(&TRITAN),
+            _ => None,
+        }
+    }
+}
+
+/// A filtered copy of `src`. Alpha is kept as is, except by [`DisplayFilter::Alpha`].
+pub fn apply(
+    src: &SharedPixelBuffer<Rgba8Pixel>,
+    filter: DisplayFilter,
+) -> SharedPixelBuffer<Rgba8Pixel> {
+    let mut out = src.clone();
+    let w = out.width() as usize;
+    if w == 0 || filter == DisplayFilter::None {
+        return out;
+    }
+    let lut = &*SRGB_TO_LINEAR;
+    let Some(params) = filter.brettel() else {
+        out.make_mut_slice().par_chunks_mut(w).for_each(|row| {
+            for px in row {
+                *px = isolate_channel(*px, filter, lut);
+            }
+        });
+        return out;
+    };
+
+    out.make_mut_slice().par_chunks_mut(w).for_each(|row| {
+        for px in row {
+            let rgb = [lut[px.r as usize], lut[px.g as usize], lut[px.b as usize]];
+            let side: f32 = rgb.iter().zip(params.normal).map(|(c, n)| c * n).sum();
+            let m = if side >= 0.0 {
+                &params.first
+            } else {
+                &params.second
+            };
+            let [r, g, b] = m.map(|row| row.iter().zip(rgb).map(|(k, c)| k * c).sum::<f32>());
+            px.r = linear_to_srgb(r);
+            px.g = linear_to_srgb(g);
+            px.b = linear_to_srgb(b);
+        }
+    });
+    out
+}
+
+fn isolate_channel(px: Rgba8Pixel, filter: DisplayFilter, lut: &[f32; 256]) -> Rgba8Pixel {
+    let gray = |v: u8, a: u8| Rgba8Pixel {
+        r: v,
+        g: v,
+        b: v,
+        a,
+    };
+    match filter {
+        DisplayFilter::Luminance => {
+            // Rec. 709 weights, on linear light
+            let y = 0.2126 * lut[px.r as usize]
+                + 0.7152 * lut[px.g as usize]
+                + 0.0722 * lut[px.b as usize];
+            gray(linear_to_srgb(y), px.a)
+        }
+        DisplayFilter::Red => Rgba8Pixel { g: 0, b: 0, ..px },
+        DisplayFilter::Green => Rgba8Pixel { r: 0, b: 0, ..px },
+        DisplayFilter::Blue => Rgba8Pixel { r: 0, g: 0, ..px },
+        DisplayFilter::Alpha => gray(px.a, 255),
+        _ => px,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(p: Rgba8Pixel, filter: DisplayFilter) -> Rgba8Pixel {
+        let mut buf = SharedPixelBuffer::new(1, 1);
+        buf.make_mut_slice()[0] = p;
+        apply(&buf, filter).as_slice()[0]
+    }
+
+    fn px(r: u8, g: u8, b: u8, a: u8) -> Rgba8Pixel {
+        Rgba8Pixel { r, g, b, a }
+    }
+
+    #[test]
+    fn grays_and_alpha_are_preserved() {
+        for filter in &DisplayFilter::ALL[..5] {
+            for v in [0, 64, 128, 255] {
+                let out = single(px(v, v, v, 77), *filter);
+                assert_eq!(out.a, 77);
+                for c in [out.r, out.g, out.b] {
+                    assert!(c.abs_diff(v) <= 2, "{filter:?} turned {v} into {out:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn red_green_confusion() {
+        // Red and green collapse towards the same yellowish hue without L or M cones
+        for filter in [DisplayFilter::Protanopia, DisplayFilter::Deuteranopia] {
+            let red = single(px(255, 0, 0, 255), filter);
+            let green = single(px(0, 255, 0, 255), filter);
+            assert!(red.r.abs_diff(red.g) < 60, "{filter:?} red {red:?}");
+            assert!(green.r.abs_diff(green.g) < 60, "{filter:?} green {green:?}");
+            assert!(red.b < 60 && green.b < 60);
+        }
+        // Tritanopes still tell them apart
+        let red = single(px(255, 0, 0, 255), DisplayFilter::Tritanopia);
+        assert!(red.r > 200 && red.g < 60);
+    }
+
+    #[test]
+    fn filter_names() {
+        assert_eq!(
+            DisplayFilter::from_name("deuteranopia"),
+            Some(DisplayFilter::Deuteranopia)
+        );
+        assert_eq!(DisplayFilter::from_name("None"), Some(DisplayFilter::None));
+        assert_eq!(DisplayFilter::from_name("achromatopsia"), None);
+    }
+
+    #[test]
+    fn channel_views() {
+        let p = px(200, 100, 50, 128);
+        assert_eq!(single(p, DisplayFilter::Red), px(200, 0, 0, 128));
+        assert_eq!(single(p, DisplayFilter::Blue), px(0, 0, 50, 128));
+        assert_eq!(single(p, DisplayFilter::Alpha), px(128, 128, 128, 255));
+
+        let y = single(p, DisplayFilter::Luminance);
+        assert!(y.r == y.g && y.g == y.b && y.a == 128);
+        // Green weighs the most
+        assert!(single(px(0, 255, 0, 255), DisplayFilter::Luminance).r > 200);
+        assert!(single(px(0, 0, 255, 255), DisplayFilter::Luminance).r < 100);
+    }
+
+    #[test]
+    fn channel_cycle_wraps() {
+        let mut filter = DisplayFilter::None;
+        let mut seen = vec![];
+        for _ in 0..6 {
+            filter = filter.next_channel();
+            seen.push(filter);
+        }
+        assert_eq!(seen.first(), Some(&DisplayFilter::Luminance));
+        assert_eq!(seen.last(), Some(&DisplayFilter::None));
+        assert_eq!(
+            DisplayFilter::Tritanopia.next_channel(),
+            DisplayFilter::Luminance
+        );
+    }
+}
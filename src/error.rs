@@ -0,0 +1,110 @@
+//! Failures that leave a function of this crate, by kind, so callers can tell a missing file
+//! from an image without an encoder or a plugin that gave up.
+
+use luminous_plugins::IpcError;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum LuminousError {
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// An image couldn't be decoded or encoded
+    Decode(image::ImageError),
+    /// No plugin could handle the request, or the plugin failed at it
+    Plugin(String),
+    /// The control socket, the session bus or a plugin daemon connection failed
+    Ipc(IpcError),
+    /// A config or settings file couldn't be read or written
+    Config(String),
+    /// The window couldn't be created or run
+    Ui(slint::PlatformError),
+}
+
+pub type Result<T> = std::result::Result<T, LuminousError>;
+
+impl fmt::Display for LuminousError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Decode(e) => write!(f, "{e}"),
+            Self::Plugin(message) => write!(f, "plugin: {message}"),
+            Self::Ipc(e) => write!(f, "ipc: {e}"),
+            Self::Config(message) => write!(f, "config: {message}"),
+            Self::Ui(e) => write!(f, "window: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LuminousError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Decode(e) => Some(e),
+            Self::Ui(e) => Some(e),
+            Self::Ipc(e) => Some(e),
+            Self::Plugin(_) | Self::Config(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for LuminousError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<image::ImageError> for LuminousError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            // Failed writes are file errors whichever format they were in
+            image::ImageError::IoError(e) => Self::Io(e),
+            e => Self::Decode(e),
+        }
+    }
+}
+
+impl From<IpcError> for LuminousError {
+    fn from(e: IpcError) -> Self {
+        Self::Ipc(e)
+    }
+}
+
+impl From<slint::PlatformError> for LuminousError {
+    fn from(e: slint::PlatformError) -> Self {
+        Self::Ui(e)
+    }
+}
+
+impl From<toml::ser::Error> for LuminousError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for LuminousError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_keep_their_kind() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        let e = LuminousError::from(image::ImageError::IoError(missing));
+        assert!(matches!(&e, LuminousError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+
+        let e = LuminousError::from(toml::from_str::<toml::Table>("a = ").unwrap_err());
+        assert!(matches!(e, LuminousError::Config(_)));
+        assert!(e.to_string().starts_with("config: "));
+
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        let e = LuminousError::from(IpcError::Transport(refused));
+        assert!(matches!(e, LuminousError::Ipc(IpcError::Transport(_))));
+        assert!(std::error::Error::source(&e).is_some());
+    }
+}
@@ -0,0 +1,175 @@
+use image::DynamicImage;
+use rayon::prelude::*;
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+
+/// Colormap the windowed samples are drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    Gray,
+    #[default]
+    Viridis,
+    Inferno,
+}
+
+// Evenly spaced stops of the matplotlib colormaps, interpolated linearly in between
+const VIRIDIS: [u32; 10] = [
+    0x440154, 0x482878, 0x3e4a89, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6dcd59, 0xb4de2c,
+    0xfde725,
+];
+const INFERNO: [u32; 10] = [
+    0x000004, 0x1b0c42, 0x4b0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925, 0xfb9a06, 0xf7d03c,
+    0xfcffa4,
+];
+
+impl Palette {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "gray" => Some(Self::Gray),
+            "viridis" => Some(Self::Viridis),
+            "inferno" => Some(Self::Inferno),
+            _ => None,
+        }
+    }
+
+    /// Color for `t` in 0..1.
+    fn color(self, t: f32) -> [u8; 3] {
+        let stops: &[u32] = match self {
+            Self::Gray => &[0x000000, 0xffffff],
+            Self::Viridis => &VIRIDIS,
+            Self::Inferno => &INFERNO,
+        };
+        let pos = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (pos as usize).min(stops.len() - 2);
+        let f = pos - i as f32;
+        let channel = |c: u32, shift: u32| ((c >> shift) & 0xff) as f32;
+        let mix = |shift| {
+            let (a, b) = (channel(stops[i], shift), channel(stops[i + 1], shift));
+            (a + (b - a) * f).round() as u8
+        };
+        [mix(16), mix(8), mix(0)]
+    }
+
+    fn lut(self) -> Vec<[u8; 3]> {
+        (0..256).map(|i| self.color(i as f32 / 255.0)).collect()
+    }
+}
+
+/// One value per pixel, at the bit depth of the source: 16-bit integers map to 0..1, float images
+/// keep their values.
+pub struct Samples {
+    pub width: u32,
+    pub height: u32,
+    values: Vec<f32>,
+    /// Smallest and largest finite value
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Samples {
+    /// Luminance of `img`, a grayscale image is taken as is.
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let luma = img.to_luma32f();
+        Self::new(luma.width(), luma.height(), luma.into_raw())
+    }
+
+    /// Fallback for sources only available as display pixels, e.g. from decoder plugins.
+    pub fn from_buffer(buffer: &SharedPixelBuffer<Rgba8Pixel>) -> Self {
+        let values = buffer
+            .as_slice()
+            .iter()
+            .map(|p| (0.2126 * p.r as f32 + 0.7152 * p.g as f32 + 0.0722 * p.b as f32) / 255.0)
+            .collect();
+        Self::new(buffer.width(), buffer.height(), values)
+    }
+
+    fn new(width: u32, height: u32, values: Vec<f32>) -> Self {
+        let (min, max) = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+        let (min, max) = if min <= max { (min, max) } else { (0.0, 1.0) };
+        Self {
+            width,
+            height,
+            values,
+            min,
+            max,
+        }
+    }
+}
+
+/// Map `samples` through `palette`. Values at or below `low` get the darkest color, at or above
+/// `high` the brightest.
+pub fn apply(
+    samples: &Samples,
+    palette: Palette,
+    low: f32,
+    high: f32,
+) -> SharedPixelBuffer<Rgba8Pixel> {
+    let mut out = SharedPixelBuffer::<Rgba8Pixel>::new(samples.width, samples.height);
+    let w = samples.width as usize;
+    if w == 0 {
+        return out;
+    }
+    let lut = palette.lut();
+    let scale = if high > low {
+        255.0 / (high - low)
+    } else {
+        0.0
+    };
+
+    out.make_mut_slice()
+        .par_chunks_mut(w)
+        .zip(samples.values.par_chunks(w))
+        .for_each(|(row, values)| {
+            for (px, &v) in row.iter_mut().zip(values) {
+                // NaN lands on the darkest color
+                let i = ((v - low) * scale).clamp(0.0, 255.0) as usize;
+                let [r, g, b] = lut[i];
+                *px = Rgba8Pixel { r, g, b, a: 255 };
+            }
+        });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma};
+
+    #[test]
+    fn sixteen_bit_values_keep_their_precision() {
+        let img = DynamicImage::ImageLuma16(ImageBuffer::from_fn(3, 1, |x, _| {
+            Luma([[1000u16, 1001, 1002][x as usize]])
+        }));
+        let samples = Samples::from_image(&img);
+        assert_eq!((samples.min * 65535.0).round(), 1000.0);
+        assert_eq!((samples.max * 65535.0).round(), 1002.0);
+
+        // Windowing over the three levels spreads them over the whole palette
+        let out = apply(&samples, Palette::Gray, samples.min, samples.max);
+        let grays: Vec<u8> = out.as_slice().iter().map(|p| p.r).collect();
+        assert_eq!(grays[0], 0);
+        assert!((120..=135).contains(&grays[1]));
+        assert_eq!(grays[2], 255);
+    }
+
+    #[test]
+    fn palette_ends() {
+        assert_eq!(Palette::Viridis.color(0.0), [0x44, 0x01, 0x54]);
+        assert_eq!(Palette::Viridis.color(1.0), [0xfd, 0xe7, 0x25]);
+        assert_eq!(Palette::Inferno.color(2.0), [0xfc, 0xff, 0xa4]);
+        assert_eq!(Palette::from_name("Inferno"), Some(Palette::Inferno));
+        assert_eq!(Palette::from_name("jet"), None);
+    }
+
+    #[test]
+    fn values_outside_the_window_clamp() {
+        let samples = Samples::new(2, 1, vec![-5.0, 5.0]);
+        let out = apply(&samples, Palette::Gray, 0.0, 1.0);
+        assert_eq!(out.as_slice()[0].r, 0);
+        assert_eq!(out.as_slice()[1].r, 255);
+    }
+}
@@ -0,0 +1,258 @@
+// The desktop entry helpers build everywhere so their tests run on every platform
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::io;
+use std::path::Path;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Image types luminous registers for, with their file extensions.
+const IMAGE_TYPES: [(&str, &[&str]); 9] = [
+    ("image/png", &["png"]),
+    ("image/jpeg", &["jpg", "jpeg"]),
+    ("image/gif", &["gif"]),
+    ("image/webp", &["webp"]),
+    ("image/bmp", &["bmp"]),
+    ("image/tiff", &["tif", "tiff"]),
+    ("image/x-portable-anymap", &["pnm", "pbm", "pgm", "ppm"]),
+    ("image/x-exr", &["exr"]),
+    ("image/vnd.radiance", &["hdr"]),
+];
+
+const DESKTOP_FILE: &str = "luminous.desktop";
+const ICON: &str = include_str!("../ui/icons/luminous.svg");
+#[cfg(target_os = "windows")]
+const PROG_ID: &str = "Luminous.Image";
+
+/// Whether [`install`] can register luminous on this platform. On macOS that comes with the
+/// app bundle.
+pub fn supported() -> bool {
+    cfg!(any(target_os = "linux", target_os = "windows"))
+}
+
+/// Whether [`install`] can also make luminous the default viewer. Windows only lets the user
+/// pick that in "Open with".
+pub fn can_set_default() -> bool {
+    cfg!(target_os = "linux")
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{command:?} exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+fn desktop_entry(exe: &Path) -> String {
+    let mime_types: Vec<&str> = IMAGE_TYPES.iter().map(|(mime, _)| *mime).collect();
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Luminous\n\
+         GenericName=Image Viewer\n\
+         Exec=\"{}\" %f\n\
+         Icon=luminous\n\
+         Terminal=false\n\
+         Categories=Graphics;Viewer;\n\
+         MimeType={};\n",
+        exe.display(),
+        mime_types.join(";")
+    )
+}
+
+/// `mimeapps.list` without luminous as a default or added association.
+fn without_desktop_file(mimeapps: &str) -> String {
+    let mut out = String::new();
+    for line in mimeapps.lines() {
+        match line.split_once('=') {
+            Some((mime, apps)) if !line.starts_with('[') => {
+                let kept: Vec<&str> = apps
+                    .split(';')
+                    .filter(|app| !app.is_empty() && *app != DESKTOP_FILE)
+                    .collect();
+                if !kept.is_empty() {
+                    out.push_str(&format!("{mime}={};\n", kept.join(";")));
+                }
+            }
+            _ => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(target_os = "linux")]
+struct LinuxPaths {
+    desktop_file: PathBuf,
+    icon: PathBuf,
+    mimeapps: PathBuf,
+}
+
+#[cfg(target_os = "linux")]
+fn linux_paths() -> io::Result<LinuxPaths> {
+    let dirs = directories::BaseDirs::new()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    Ok(LinuxPaths {
+        desktop_file: dirs.data_dir().join("applications").join(DESKTOP_FILE),
+        icon: dirs
+            .data_dir()
+            .join("icons/hicolor/scalable/apps/luminous.svg"),
+        mimeapps: dirs.config_dir().join("mimeapps.list"),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)
+}
+
+#[cfg(target_os = "linux")]
+fn remove_file(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Register the running executable for the image types so it shows up in "Open with", and
+/// with `make_default` also make it the default application where [`can_set_default`].
+#[cfg(target_os = "linux")]
+pub fn install(make_default: bool) -> io::Result<()> {
+    let paths = linux_paths()?;
+    let exe = std::env::current_exe()?;
+    write_file(&paths.desktop_file, &desktop_entry(&exe))?;
+    write_file(&paths.icon, ICON)?;
+    if let Some(apps_dir) = paths.desktop_file.parent() {
+        // Only refreshes the "Open with" lists sooner, not every desktop ships it
+        if let Err(e) = run(Command::new("update-desktop-database").arg(apps_dir)) {
+            log::debug!("Skipped update-desktop-database: {e}");
+        }
+    }
+    if make_default {
+        run(Command::new("xdg-mime")
+            .arg("default")
+            .arg(DESKTOP_FILE)
+            .args(IMAGE_TYPES.iter().map(|(mime, _)| mime)))?;
+    }
+    Ok(())
+}
+
+/// Undo [`install`], also where luminous was made the default.
+#[cfg(target_os = "linux")]
+pub fn uninstall() -> io::Result<()> {
+    let paths = linux_paths()?;
+    remove_file(&paths.desktop_file)?;
+    remove_file(&paths.icon)?;
+    match std::fs::read_to_string(&paths.mimeapps) {
+        Ok(mimeapps) => std::fs::write(&paths.mimeapps, without_desktop_file(&mimeapps))?,
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        Err(_) => {}
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn reg_add(key: &str, value: Option<&str>, data: &str) -> io::Result<()> {
+    let mut command = Command::new("reg");
+    command
+        .arg("add")
+        .arg(format!(r"HKCU\Software\Classes\{key}"));
+    match value {
+        Some(value) => command.arg("/v").arg(value),
+        None => command.arg("/ve"),
+    };
+    run(command.arg("/d").arg(data).arg("/f"))
+}
+
+#[cfg(target_os = "windows")]
+pub fn install(_make_default: bool) -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let exe = exe.display();
+    reg_add(PROG_ID, None, "Luminous image")?;
+    reg_add(
+        &format!(r"{PROG_ID}\DefaultIcon"),
+        None,
+        &format!("\"{exe}\",0"),
+    )?;
+    reg_add(
+        &format!(r"{PROG_ID}\shell\open\command"),
+        None,
+        &format!("\"{exe}\" \"%1\""),
+    )?;
+    for ext in IMAGE_TYPES.iter().flat_map(|(_, exts)| exts.iter()) {
+        reg_add(&format!(r".{ext}\OpenWithProgids"), Some(PROG_ID), "")?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn uninstall() -> io::Result<()> {
+    for ext in IMAGE_TYPES.iter().flat_map(|(_, exts)| exts.iter()) {
+        // Missing when the extension was never registered, which is fine
+        let _ = run(Command::new("reg")
+            .arg("delete")
+            .arg(format!(r"HKCU\Software\Classes\.{ext}\OpenWithProgids"))
+            .args(["/v", PROG_ID, "/f"]));
+    }
+    run(Command::new("reg")
+        .arg("delete")
+        .arg(format!(r"HKCU\Software\Classes\{PROG_ID}"))
+        .arg("/f"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn install(_make_default: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "desktop integration is only set up on Linux and Windows",
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn uninstall() -> io::Result<()> {
+    install(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desktop_entry_opens_files_with_the_executable() {
+        let entry = desktop_entry(Path::new("/opt/luminous/luminous"));
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Exec=\"/opt/luminous/luminous\" %f\n"));
+        assert!(entry.contains("Icon=luminous\n"));
+        assert!(entry.contains("MimeType=image/png;image/jpeg;"));
+        assert!(entry.ends_with(";\n"));
+    }
+
+    #[test]
+    fn uninstall_leaves_other_associations() {
+        let mimeapps = "[Default Applications]\n\
+                        image/png=luminous.desktop;\n\
+                        image/jpeg=luminous.desktop;org.gnome.eog.desktop;\n\
+                        text/plain=gedit.desktop\n\
+                        \n\
+                        [Added Associations]\n\
+                        image/png=gimp.desktop;luminous.desktop;\n";
+        assert_eq!(
+            without_desktop_file(mimeapps),
+            "[Default Applications]\n\
+             image/jpeg=org.gnome.eog.desktop;\n\
+             text/plain=gedit.desktop;\n\
+             \n\
+             [Added Associations]\n\
+             image/png=gimp.desktop;\n"
+        );
+    }
+}
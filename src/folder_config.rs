@@ -0,0 +1,154 @@
+//! A `.luminous.toml` in a folder sets how that folder is shown: its sort order, file filter,
+//! fit mode, grid columns, slideshow, reading direction, two-page spreads and extensions left
+//! out. It applies over the config file and over the settings last used in the folder.
+
+use crate::FitMode;
+use crate::config::Config;
+use crate::config_check;
+use crate::fs_scan::NameFilter;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+pub const FILE_NAME: &str = ".luminous.toml";
+
+/// Order of the images of a folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    Name,
+    NameDesc,
+    /// Drag and drop order, or the scan order when the folder was never reordered
+    Custom,
+}
+
+impl Sort {
+    /// Ascending name sort, as the grid keeps it, `None` for the custom order.
+    pub fn ascending(self) -> Option<bool> {
+        match self {
+            Self::Name => Some(true),
+            Self::NameDesc => Some(false),
+            Self::Custom => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FolderToml {
+    sort: Option<String>,
+    filter: Option<String>,
+    exclude_extensions: Option<Vec<String>>,
+    fit_mode: Option<String>,
+    grid_columns: Option<i32>,
+    slideshow_interval: Option<f32>,
+    slideshow_progress: Option<bool>,
+    reading_direction: Option<String>,
+    spread: Option<bool>,
+}
+
+/// Settings of a folder, each `None` when its file leaves it out.
+#[derive(Debug, Default)]
+pub struct FolderConfig {
+    pub sort: Option<Sort>,
+    /// Only files matching it are scanned, on top of the filter picked by the user
+    pub filter: Option<NameFilter>,
+    /// Extensions not scanned in the folder, on top of the config's `exclude_extensions`
+    pub exclude_extensions: Vec<String>,
+    pub fit_mode: Option<FitMode>,
+    pub grid_columns: Option<i32>,
+    /// Seconds per image
+    pub slideshow_interval: Option<f32>,
+    pub slideshow_progress: Option<bool>,
+    /// `reading_direction = "rtl"`, the arrow keys turn pages the other way and a spread puts
+    /// the next page on the left
+    pub right_to_left: Option<bool>,
+    /// Two pages side by side in the full view
+    pub spread: Option<bool>,
+}
+
+impl FolderConfig {
+    /// The settings in the `.luminous.toml` of `dir`. Invalid entries are logged and left out,
+    /// a folder without the file has none.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(FILE_NAME);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        let mut table: toml::Table = match toml::from_str(&content) {
+            Ok(table) => table,
+            Err(e) => {
+                warn!("Ignoring {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        for issue in config_check::check_folder(&mut table) {
+            warn!("{}: {issue}, ignored", path.display());
+        }
+        let folder: FolderToml = table.try_into().unwrap_or_else(|e| {
+            warn!("Ignoring {}: {}", path.display(), e);
+            FolderToml::default()
+        });
+        debug!("Folder settings from {}: {:?}", path.display(), folder);
+        Self {
+            sort: folder.sort.and_then(|s| match s.to_lowercase().as_str() {
+                "name" => Some(Sort::Name),
+                "name-desc" => Some(Sort::NameDesc),
+                "custom" => Some(Sort::Custom),
+                _ => None,
+            }),
+            filter: folder.filter.and_then(|f| NameFilter::parse(&f).ok()),
+            exclude_extensions: folder.exclude_extensions.unwrap_or_default(),
+            fit_mode: folder.fit_mode.as_deref().map(Config::parse_fit_mode),
+            grid_columns: folder.grid_columns,
+            slideshow_interval: folder.slideshow_interval,
+            slideshow_progress: folder.slideshow_progress,
+            right_to_left: folder
+                .reading_direction
+                .map(|d| d.eq_ignore_ascii_case("rtl")),
+            spread: folder.spread,
+        }
+    }
+
+    /// Whether the file changes the slideshow, which then has to be put back outside the
+    /// folder.
+    pub fn has_slideshow(&self) -> bool {
+        self.slideshow_interval.is_some() || self.slideshow_progress.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_settings_skip_invalid_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(FolderConfig::load(dir.path()).sort.is_none());
+
+        fs::write(
+            dir.path().join(FILE_NAME),
+            "sort = \"Name-Desc\"\nfilter = \"*.png\"\nfit_mode = \"1:1\"\n\
+             grid_columns = 50\nslideshow_interval = 2.5\nzoom = 3\n\
+             reading_direction = \"RTL\"\nspread = true\n",
+        )
+        .unwrap();
+        let folder = FolderConfig::load(dir.path());
+        assert_eq!(folder.sort, Some(Sort::NameDesc));
+        assert_eq!(folder.sort.unwrap().ascending(), Some(false));
+        assert!(
+            folder
+                .filter
+                .as_ref()
+                .is_some_and(|f| f.matches("page.png"))
+        );
+        assert_eq!(folder.fit_mode, Some(FitMode::Actual));
+        assert_eq!(folder.grid_columns, None);
+        assert_eq!(folder.slideshow_interval, Some(2.5));
+        assert!(folder.has_slideshow());
+        assert_eq!(folder.right_to_left, Some(true));
+        assert_eq!(folder.spread, Some(true));
+
+        fs::write(dir.path().join(FILE_NAME), "sort = [").unwrap();
+        assert!(FolderConfig::load(dir.path()).sort.is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One visible line of the folder tree, in display order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FolderRow {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: usize,
+    pub expanded: bool,
+    /// The folder shown in the grid
+    pub current: bool,
+}
+
+/// Directories directly inside `dir`, hidden ones left out, sorted case-insensitively.
+pub fn subfolders(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .map(|e| e.path())
+        .collect();
+    dirs.sort_by_key(|p| {
+        p.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase()
+    });
+    dirs
+}
+
+/// Which folders are expanded. The tree starts at the filesystem root and opening a folder
+/// expands everything above it, so its parent and siblings are always a click away.
+#[derive(Debug, Default)]
+pub struct FolderTree {
+    expanded: HashSet<PathBuf>,
+}
+
+impl FolderTree {
+    /// Expand the folders leading to `dir` and `dir` itself.
+    pub fn reveal(&mut self, dir: &Path) {
+        self.expanded.extend(dir.ancestors().map(Path::to_path_buf));
+    }
+
+    pub fn toggle(&mut self, dir: &Path) {
+        if !self.expanded.remove(dir) {
+            self.expanded.insert(dir.to_path_buf());
+        }
+    }
+
+    /// Rows from the root down, children listed under each expanded folder.
+    pub fn rows(&self, current: &Path) -> Vec<FolderRow> {
+        let mut rows = Vec::new();
+        if let Some(root) = current.ancestors().last() {
+            self.push_rows(root, 0, current, &mut rows);
+        }
+        rows
+    }
+
+    fn push_rows(&self, dir: &Path, depth: usize, current: &Path, rows: &mut Vec<FolderRow>) {
+        let expanded = self.expanded.contains(dir);
+        rows.push(FolderRow {
+            path: dir.to_path_buf(),
+            name: dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| dir.display().to_string()),
+            depth,
+            expanded,
+            current: dir == current,
+        });
+        if expanded {
+            for child in subfolders(dir) {
+                self.push_rows(&child, depth + 1, current, rows);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_follow_expanded_folders() {
+        // Not hidden, as the default `.tmp` prefix would be
+        let dir = tempfile::Builder::new()
+            .prefix("luminous")
+            .tempdir()
+            .unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        for sub in ["b/inner", "A", ".hidden", "c"] {
+            fs::create_dir_all(base.join(sub)).unwrap();
+        }
+        fs::write(base.join("image.png"), b"").unwrap();
+
+        let current = base.join("b");
+        let mut tree = FolderTree::default();
+        tree.reveal(&current);
+        let rows = tree.rows(&current);
+        assert_eq!(rows[0].depth, 0);
+        assert!(
+            rows.iter()
+                .all(|r| r.expanded == current.starts_with(&r.path))
+        );
+
+        let depth = rows.iter().find(|r| r.path == base).unwrap().depth;
+        let below: Vec<(&str, usize, bool)> = rows
+            .iter()
+            .skip_while(|r| r.path != base)
+            .skip(1)
+            .take_while(|r| r.depth > depth)
+            .map(|r| (r.name.as_str(), r.depth, r.current))
+            .collect();
+        assert_eq!(
+            below,
+            [
+                ("A", depth + 1, false),
+                ("b", depth + 1, true),
+                ("inner", depth + 2, false),
+                ("c", depth + 1, false),
+            ]
+        );
+
+        tree.toggle(&current);
+        assert!(!tree.rows(&current).iter().any(|r| r.name == "inner"));
+        tree.toggle(&base.join("c"));
+        let rows = tree.rows(&current);
+        assert!(rows.iter().any(|r| r.name == "c" && r.expanded));
+    }
+}
@@ -0,0 +1,151 @@
+//! Finished frames of the full view: the adjusted image, the reference composite and the
+//! display filter result. Toggling an overlay or filter back and forth, or redrawing the same
+//! image, then reuses the pixels instead of compositing them again.
+
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use std::collections::VecDeque;
+
+/// Frames beyond this many bytes push out the least recently used ones. Each frame also keeps
+/// the pixels it was made from, which are mostly held by the image caches anyway.
+pub const DEFAULT_BUDGET: usize = 256 << 20;
+
+type Buffer = SharedPixelBuffer<Rgba8Pixel>;
+
+struct Entry {
+    /// Kept alive so its address can't be reused by another image while the entry exists
+    base: Buffer,
+    settings: String,
+    frame: Buffer,
+}
+
+/// Frames keyed by the pixels they were made from and the settings that made them.
+pub struct FrameCache {
+    /// Least recently used first
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    budget: usize,
+}
+
+impl Default for FrameCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET)
+    }
+}
+
+impl FrameCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            bytes: 0,
+            budget,
+        }
+    }
+
+    /// The frame made from `base` with `settings`, which describe the stage and everything it
+    /// depends on besides the base pixels, e.g. `format!("filter {filter:?}")`.
+    pub fn get(&mut self, base: &Buffer, settings: &str) -> Option<Buffer> {
+        let pos = self
+            .entries
+            .iter()
+            .position(|e| same_pixels(&e.base, base) && e.settings == settings)?;
+        let entry = self.entries.remove(pos)?;
+        let frame = entry.frame.clone();
+        self.entries.push_back(entry);
+        Some(frame)
+    }
+
+    pub fn insert(&mut self, base: Buffer, settings: String, frame: Buffer) {
+        if let Some(pos) = self
+            .entries
+            .iter()
+            .position(|e| same_pixels(&e.base, &base) && e.settings == settings)
+            && let Some(old) = self.entries.remove(pos)
+        {
+            self.bytes -= size(&old.frame);
+        }
+        self.bytes += size(&frame);
+        self.entries.push_back(Entry {
+            base,
+            settings,
+            frame,
+        });
+        // The newest frame stays even when it alone is over the budget
+        while self.bytes > self.budget && self.entries.len() > 1 {
+            if let Some(old) = self.entries.pop_front() {
+                self.bytes -= size(&old.frame);
+            }
+        }
+    }
+
+    /// Drop every frame, for inputs the keys don't cover such as another reference image.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    /// Memory taken by the frames, not counting the pixels they were made from.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Buffers are shared rather than copied, the same pixels have the same address.
+fn same_pixels(a: &Buffer, b: &Buffer) -> bool {
+    a.as_bytes().as_ptr() == b.as_bytes().as_ptr() && a.size() == b.size()
+}
+
+fn size(frame: &Buffer) -> usize {
+    frame.as_bytes().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(side: u32) -> Buffer {
+        SharedPixelBuffer::new(side, side)
+    }
+
+    #[test]
+    fn frames_are_keyed_by_pixels_and_settings() {
+        let mut cache = FrameCache::new(1 << 20);
+        let base = buffer(4);
+        let frame = buffer(4);
+        cache.insert(base.clone(), "filter Protanopia".into(), frame.clone());
+
+        let hit = cache.get(&base, "filter Protanopia").unwrap();
+        assert!(same_pixels(&hit, &frame));
+        assert!(cache.get(&base, "filter Tritanopia").is_none());
+        // Equal pixels elsewhere are another image as far as the cache knows
+        assert!(cache.get(&buffer(4), "filter Protanopia").is_none());
+    }
+
+    #[test]
+    fn least_recently_used_frames_go_first() {
+        // Room for two 16x16 frames
+        let mut cache = FrameCache::new(2 * 16 * 16 * 4);
+        let (a, b, c) = (buffer(1), buffer(1), buffer(1));
+        cache.insert(a.clone(), "x".into(), buffer(16));
+        cache.insert(b.clone(), "x".into(), buffer(16));
+        assert!(cache.get(&a, "x").is_some());
+        cache.insert(c.clone(), "x".into(), buffer(16));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&a, "x").is_some());
+        assert!(cache.get(&b, "x").is_none());
+        assert!(cache.get(&c, "x").is_some());
+
+        cache.insert(c.clone(), "x".into(), buffer(32));
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
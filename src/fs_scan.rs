@@ -1,19 +1,113 @@
-use log::{debug, error, info};
+use crate::folder_config::FolderConfig;
+use log::{debug, error, info, warn};
+use luminous_image_loader::{archive, remote};
 use luminous_plugins::ImageFormat;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use walkdir::WalkDir;
 
+#[derive(Clone)]
 pub struct ScanResult {
     pub paths: Vec<PathBuf>,
+    /// Directory the images were collected from
+    pub dir: PathBuf,
     pub start_index: usize,
     pub is_dir: bool,
     pub image_formats: ImageFormats,
 }
 
+impl ScanResult {
+    /// The scan with `path`, a file that appeared in the scanned folder since, where scanning
+    /// the folder again would list it. `None` when it is somewhere else or wouldn't be listed.
+    pub fn with_arrival(&self, path: &Path, filter: Option<&NameFilter>) -> Option<ScanResult> {
+        if path.parent() != Some(self.dir.as_path())
+            || !path.is_file()
+            || !is_image(path, &self.image_formats.get_all_decoding_exts())
+        {
+            return None;
+        }
+        let name = path.file_name()?;
+        let folder_filter = FolderConfig::load(&self.dir).filter;
+        let name_str = name.to_str()?;
+        if filter
+            .into_iter()
+            .chain(&folder_filter)
+            .any(|f| !f.matches(name_str))
+        {
+            return None;
+        }
+        let index = match self
+            .paths
+            .binary_search_by(|p| p.file_name().cmp(&Some(name)))
+        {
+            Ok(_) => return None,
+            Err(index) => index,
+        };
+        let mut scan = self.clone();
+        scan.paths.insert(index, path.to_path_buf());
+        if !scan.is_dir && index <= scan.start_index && !self.paths.is_empty() {
+            scan.start_index += 1;
+        }
+        Some(scan)
+    }
+}
+
+/// What the path given at startup points to, which decides how it is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchTarget {
+    Directory,
+    File,
+    Archive,
+    Url,
+    Missing,
+}
+
+pub fn classify(path_str: &str) -> LaunchTarget {
+    if let Some((scheme, _)) = path_str.split_once("://")
+        && !scheme.is_empty()
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+')
+    {
+        return LaunchTarget::Url;
+    }
+    let path = Path::new(path_str);
+    match fs::metadata(path) {
+        Ok(m) if m.is_dir() => LaunchTarget::Directory,
+        Ok(_) if archive::is_archive(path) => LaunchTarget::Archive,
+        Ok(_) => LaunchTarget::File,
+        Err(_) => LaunchTarget::Missing,
+    }
+}
+
+/// Extensions the config adds to every scan or leaves out of it, merged with the built-in and
+/// plugin formats.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtensionOverrides {
+    /// Scanned as images even though no format claims them
+    pub extra: Vec<String>,
+    /// Never scanned, whichever format claims them
+    pub excluded: Vec<String>,
+}
+
+static EXTENSION_OVERRIDES: OnceLock<ExtensionOverrides> = OnceLock::new();
+
+/// Apply `overrides` to every [`ImageFormats`] created from now on. Set once at startup, later
+/// calls are ignored.
+pub fn set_extension_overrides(overrides: ExtensionOverrides) {
+    debug!("Extension overrides: {:?}", overrides);
+    if EXTENSION_OVERRIDES.set(overrides).is_err() {
+        warn!("Extension overrides were already set");
+    }
+}
+
+#[derive(Clone)]
 pub struct ImageFormats {
     pub image_formats: HashSet<ImageFormat>,
+    /// Left out of the decoding extensions, see [`ExtensionOverrides::excluded`]
+    excluded: HashSet<String>,
 }
 
 impl ImageFormats {
@@ -40,30 +134,62 @@ impl ImageFormats {
         add_fmt!("ff", ["ff"], true, false);
         add_fmt!("gif", ["gif"], true, false);
         add_fmt!("hdr", ["hdr"], true, false);
+        add_fmt!("heif", ["heic", "heif", "hif", "avci"], true, false);
         add_fmt!("ico", ["ico"], true, true);
         add_fmt!("jpeg", ["jpeg", "jpg"], true, true);
         add_fmt!("exr", ["exr"], true, false);
         add_fmt!("png", ["png"], true, true);
         add_fmt!("pnm", ["pnm", "pbm", "pgm", "ppm", "pam"], true, false);
         add_fmt!("qoi", ["qoi"], true, true);
+        add_fmt!(
+            "raw",
+            ["cr2", "nef", "arw", "dng", "orf", "rw2", "raf", "pef"],
+            true,
+            false
+        );
         add_fmt!("tga", ["tga"], true, false);
         add_fmt!("tiff", ["tiff", "tif"], true, true);
         add_fmt!("webp", ["webp"], true, true);
 
-        ImageFormats {
+        let mut formats = ImageFormats {
             image_formats: formats,
+            excluded: HashSet::new(),
+        };
+        if let Some(overrides) = EXTENSION_OVERRIDES.get() {
+            formats.apply(overrides);
         }
+        formats
+    }
+
+    fn apply(&mut self, overrides: &ExtensionOverrides) {
+        if !overrides.extra.is_empty() {
+            self.add_format(ImageFormat {
+                exts: overrides.extra.clone(),
+                decoding_support: true,
+                encoding_support: false,
+            });
+        }
+        self.exclude(&overrides.excluded);
     }
 
     pub fn add_format(&mut self, image_format: ImageFormat) {
         self.image_formats.insert(image_format);
     }
 
+    /// Stop scanning files with these extensions, formats added later included.
+    pub fn exclude(&mut self, exts: &[String]) {
+        self.excluded.extend(
+            exts.iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase()),
+        );
+    }
+
     pub fn get_all_decoding_exts(&self) -> HashSet<String> {
         self.image_formats
             .iter()
             .filter(|f| f.decoding_support)
             .flat_map(|f| f.exts.iter().cloned())
+            .filter(|e| !self.excluded.contains(e))
             .collect()
     }
 
@@ -94,7 +220,68 @@ fn is_image(path: &Path, extensions: &HashSet<String>) -> bool {
     extensions.contains(&lower)
 }
 
-pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResult {
+/// Restricts which files a scan picks up, by name.
+#[derive(Debug, Clone)]
+pub enum NameFilter {
+    /// Glob with `*` and `?`, or a plain substring, see [`name_matches`]
+    Glob(String),
+    Regex(regex::Regex),
+}
+
+impl NameFilter {
+    /// `re:` prefixes a regular expression, anything else is a glob.
+    pub fn parse(filter: &str) -> Result<Self, regex::Error> {
+        match filter.strip_prefix("re:") {
+            Some(re) => regex::Regex::new(re).map(Self::Regex),
+            None => Ok(Self::Glob(filter.to_string())),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(glob) => name_matches(name, glob),
+            Self::Regex(re) => re.is_match(name),
+        }
+    }
+
+    /// The filter as it would be typed
+    pub fn to_text(&self) -> String {
+        match self {
+            Self::Glob(glob) => glob.clone(),
+            Self::Regex(re) => format!("re:{}", re.as_str()),
+        }
+    }
+}
+
+/// Treat `paths` as the contents of the virtual folder `dir`, e.g. the favorites, shown in the
+/// grid.
+pub fn from_paths(
+    paths: Vec<PathBuf>,
+    dir: &Path,
+    extra_image_formats: &Vec<ImageFormat>,
+) -> ScanResult {
+    let mut image_formats = ImageFormats::new();
+    for image_format in extra_image_formats {
+        image_formats.add_format(image_format.clone());
+    }
+    info!("Showing {} images as {}", paths.len(), dir.display());
+    ScanResult {
+        paths,
+        dir: dir.to_path_buf(),
+        start_index: 0,
+        is_dir: true,
+        image_formats,
+    }
+}
+
+/// Collect the images next to or in `path_str`, or in the archive it is. With a `filter`, only
+/// files whose names match are kept, except the image that was asked for. A URL is opened on its
+/// own.
+pub fn scan(
+    path_str: &str,
+    extra_image_formats: &Vec<ImageFormat>,
+    filter: Option<&NameFilter>,
+) -> ScanResult {
     let mut image_formats = ImageFormats::new();
     debug!(
         "Active decoding extensions: {:?}",
@@ -119,6 +306,19 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
         image_formats.get_all_encoding_exts()
     );
 
+    match classify(path_str) {
+        LaunchTarget::Archive => {
+            return scan_archive(
+                Path::new(path_str),
+                image_formats,
+                &decode_extensions,
+                filter,
+            );
+        }
+        LaunchTarget::Url => return scan_url(path_str, image_formats),
+        _ => {}
+    }
+
     let main_path = Path::new(&path_str);
     let metadata = match fs::metadata(main_path) {
         Ok(m) => m,
@@ -126,6 +326,7 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
             error!("Failed to get metadata for {}: {}", main_path.display(), e);
             return ScanResult {
                 paths: vec![],
+                dir: main_path.to_path_buf(),
                 start_index: 0,
                 is_dir: false,
                 image_formats,
@@ -147,6 +348,7 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
             );
             return ScanResult {
                 paths: vec![],
+                dir: main_path.to_path_buf(),
                 start_index: 0,
                 is_dir: false,
                 image_formats,
@@ -163,12 +365,17 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
         );
         return ScanResult {
             paths: vec![],
+            dir: main_path.to_path_buf(),
             start_index: 0,
             is_dir: false,
             image_formats,
         };
     };
     debug!("Scanning directory: {}", scan_dir.display());
+    let folder = FolderConfig::load(scan_dir);
+    let folder_filter = folder.filter;
+    image_formats.exclude(&folder.exclude_extensions);
+    let decode_extensions = image_formats.get_all_decoding_exts();
 
     for entry in WalkDir::new(scan_dir)
         .max_depth(1)
@@ -177,7 +384,18 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
         .filter_map(|e| e.ok())
     {
         let path = entry.into_path();
-        if path.is_file() && is_image(&path, &decode_extensions) {
+        let filtered_out = filter.into_iter().chain(&folder_filter).any(|f| {
+            start_img_path.as_ref() != Some(&path)
+                && !path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| f.matches(n))
+        });
+        if filtered_out {
+            continue;
+        }
+        let opened = start_img_path.as_ref() == Some(&path);
+        if path.is_file() && (opened || is_image(&path, &decode_extensions)) {
             if let Some(ref curr) = start_img_path {
                 if path == *curr {
                     start_index = paths.len();
@@ -193,6 +411,9 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
         is_dir = true;
     }
 
+    for filter in filter.into_iter().chain(&folder_filter) {
+        debug!("Kept files matching {:?}", filter.to_text());
+    }
     info!(
         "Found {} images. Starting index: {}",
         paths.len(),
@@ -200,8 +421,270 @@ pub fn scan(path_str: &str, extra_image_formats: &Vec<ImageFormat>) -> ScanResul
     );
     ScanResult {
         paths,
+        dir: scan_dir.to_path_buf(),
         start_index,
         is_dir,
         image_formats,
     }
 }
+
+/// The images of the archive at `path`, addressed as described in [`archive`].
+fn scan_archive(
+    path: &Path,
+    image_formats: ImageFormats,
+    decode_extensions: &HashSet<String>,
+    filter: Option<&NameFilter>,
+) -> ScanResult {
+    let entries = archive::entries(path).unwrap_or_else(|e| {
+        error!("Failed to read the archive {}: {}", path.display(), e);
+        Vec::new()
+    });
+    let mut paths: Vec<PathBuf> = entries
+        .iter()
+        .map(|entry| archive::entry_path(path, entry))
+        .filter(|p| is_image(p, decode_extensions))
+        .filter(|p| {
+            filter.is_none_or(|f| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| f.matches(n))
+            })
+        })
+        .collect();
+    paths.sort();
+    info!(
+        "Found {} images in the archive {}",
+        paths.len(),
+        path.display()
+    );
+    ScanResult {
+        paths,
+        dir: path.to_path_buf(),
+        start_index: 0,
+        is_dir: true,
+        image_formats,
+    }
+}
+
+/// The image at `url`, fetched when it is decoded, see [`remote`].
+fn scan_url(url: &str, image_formats: ImageFormats) -> ScanResult {
+    let path = PathBuf::from(url);
+    let paths = if remote::url(&path).is_some() {
+        vec![path.clone()]
+    } else {
+        error!("Only http and https URLs can be opened: {url}");
+        Vec::new()
+    };
+    ScanResult {
+        paths,
+        dir: path,
+        start_index: 0,
+        is_dir: false,
+        image_formats,
+    }
+}
+
+/// Whether a file name matches a filter typed by the user, case-insensitive.
+///
+/// A filter with `*` or `?` is a glob over the whole name, anything else is a substring.
+pub fn name_matches(name: &str, filter: &str) -> bool {
+    let name = name.to_lowercase();
+    let filter = filter.to_lowercase();
+    if filter.contains(['*', '?']) {
+        let name: Vec<char> = name.chars().collect();
+        let pattern: Vec<char> = filter.chars().collect();
+        glob_match(&pattern, &name)
+    } else {
+        name.contains(&filter)
+    }
+}
+
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it currently swallows up to
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_launch_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("a.png");
+        let archive = dir.path().join("comic.CBZ");
+        fs::write(&image, b"").unwrap();
+        fs::write(&archive, b"").unwrap();
+
+        assert_eq!(
+            classify(dir.path().to_str().unwrap()),
+            LaunchTarget::Directory
+        );
+        assert_eq!(classify(image.to_str().unwrap()), LaunchTarget::File);
+        assert_eq!(classify(archive.to_str().unwrap()), LaunchTarget::Archive);
+        assert_eq!(classify("https://example.com/a.jpg"), LaunchTarget::Url);
+        assert_eq!(
+            classify(dir.path().join("missing.png").to_str().unwrap()),
+            LaunchTarget::Missing
+        );
+    }
+
+    #[test]
+    fn name_filters() {
+        assert!(name_matches("Holiday_001.PNG", "holiday"));
+        assert!(name_matches("Holiday_001.PNG", "*.png"));
+        assert!(name_matches("Holiday_001.PNG", "h*_0?1.*"));
+        assert!(!name_matches("Holiday_001.PNG", "*.jpg"));
+        assert!(!name_matches("Holiday_001.PNG", "holiday?"));
+        assert!(!name_matches("a.png.bak", "*.png"));
+        assert!(name_matches("anything", ""));
+        assert!(name_matches("anything", "*"));
+    }
+
+    #[test]
+    fn scan_with_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.png", "b.jpg", "IMG_001.png", "IMG_x.png"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let names = |scan: ScanResult| -> Vec<String> {
+            scan.paths
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        let dir_str = dir.path().to_str().unwrap();
+
+        let glob = NameFilter::parse("*.png").unwrap();
+        assert_eq!(
+            names(scan(dir_str, &vec![], Some(&glob))),
+            ["IMG_001.png", "IMG_x.png", "a.png"]
+        );
+        let re = NameFilter::parse(r"re:^IMG_\d+").unwrap();
+        assert_eq!(names(scan(dir_str, &vec![], Some(&re))), ["IMG_001.png"]);
+        assert_eq!(re.to_text(), r"re:^IMG_\d+");
+        assert!(NameFilter::parse("re:(").is_err());
+
+        // The image opened explicitly stays even when it doesn't match
+        let opened = dir.path().join("b.jpg");
+        let result = scan(opened.to_str().unwrap(), &vec![], Some(&glob));
+        assert_eq!(result.paths[result.start_index], opened);
+    }
+
+    #[test]
+    fn arrivals_join_the_scan_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.png", "c.png"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let scan = scan(dir.path().to_str().unwrap(), &vec![], None);
+
+        let b = dir.path().join("b.png");
+        fs::write(&b, b"").unwrap();
+        let joined = scan.with_arrival(&b, None).unwrap();
+        assert_eq!(
+            joined.paths,
+            [
+                dir.path().join("a.png"),
+                b.clone(),
+                dir.path().join("c.png")
+            ]
+        );
+        assert!(joined.with_arrival(&b, None).is_none());
+
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, b"").unwrap();
+        assert!(scan.with_arrival(&notes, None).is_none());
+        let only_c = NameFilter::parse("c*").unwrap();
+        assert!(scan.with_arrival(&b, Some(&only_c)).is_none());
+    }
+
+    #[test]
+    fn archives_are_scanned_and_decoded() {
+        use luminous_image_loader::decoder;
+        use luminous_plugins::PluginManager;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(3, 2)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let mut zip = crate::zip_export::ZipWriter::new(Vec::new());
+        zip.add("ch2/002.png", png.get_ref()).unwrap();
+        zip.add("ch1/001.png", png.get_ref()).unwrap();
+        zip.add("notes.txt", b"not an image").unwrap();
+        let archive = dir.path().join("comic.cbz");
+        fs::write(&archive, zip.finish().unwrap()).unwrap();
+
+        assert_eq!(classify(archive.to_str().unwrap()), LaunchTarget::Archive);
+        let result = scan(archive.to_str().unwrap(), &vec![], None);
+        assert!(result.is_dir);
+        assert_eq!(
+            result.paths,
+            [
+                archive.join("ch1").join("001.png"),
+                archive.join("ch2").join("002.png")
+            ]
+        );
+        let decoders = decoder::builtin(Arc::new(PluginManager::new()));
+        let page = decoder::decode(&decoders, &result.paths[1], false).unwrap();
+        assert_eq!((page.width(), page.height()), (3, 2));
+    }
+
+    #[test]
+    fn extensions_added_and_excluded() {
+        let mut formats = ImageFormats::new();
+        formats.apply(&ExtensionOverrides {
+            extra: vec!["jfif".into()],
+            excluded: vec!["png".into()],
+        });
+        // Plugins come later and are excluded all the same
+        formats.add_format(ImageFormat {
+            exts: vec!["png".into(), "xyz".into()],
+            decoding_support: true,
+            encoding_support: false,
+        });
+        let exts = formats.get_all_decoding_exts();
+        assert!(exts.contains("jfif") && exts.contains("xyz") && exts.contains("jpg"));
+        assert!(!exts.contains("png"));
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.png", "b.gif"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        fs::write(
+            dir.path().join(crate::folder_config::FILE_NAME),
+            "exclude_extensions = [\".GIF\"]\n",
+        )
+        .unwrap();
+        let result = scan(dir.path().to_str().unwrap(), &vec![], None);
+        assert_eq!(result.paths, [dir.path().join("a.png")]);
+        // Unless it is the image opened
+        let opened = dir.path().join("b.gif");
+        let result = scan(opened.to_str().unwrap(), &vec![], None);
+        assert_eq!(result.paths[result.start_index], opened);
+    }
+}
@@ -0,0 +1,146 @@
+use log::{debug, error, trace};
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Time a file must go without events before it's considered fully written.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Files still being written, keyed by the time of their last event.
+#[derive(Default)]
+struct Pending {
+    files: HashMap<PathBuf, Instant>,
+}
+
+impl Pending {
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        self.files.insert(path, now);
+    }
+
+    /// Remove and return files quiet for at least `SETTLE_TIME`, oldest first.
+    fn take_settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut settled: Vec<(PathBuf, Instant)> = self
+            .files
+            .iter()
+            .filter(|(_, last)| now.duration_since(**last) >= SETTLE_TIME)
+            .map(|(p, last)| (p.clone(), *last))
+            .collect();
+        settled.sort_by_key(|(_, last)| *last);
+        for (p, _) in &settled {
+            self.files.remove(p);
+        }
+        settled.into_iter().map(|(p, _)| p).collect()
+    }
+}
+
+fn has_ext(path: &Path, exts: &HashSet<String>) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| exts.contains(&e.to_lowercase()))
+        .unwrap_or(false)
+}
+
+/// Watch `dir` for new or modified images and call `on_arrival` once each has settled.
+/// Watching stops when the returned watcher is dropped.
+pub fn watch<F>(
+    dir: &Path,
+    exts: HashSet<String>,
+    on_arrival: F,
+) -> Option<notify::RecommendedWatcher>
+where
+    F: Fn(PathBuf) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Hot folder watch error: {}", e);
+                return;
+            }
+        };
+        // Plain reads (our own decoding included) must not count as arrivals
+        if !matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        ) {
+            return;
+        }
+        for path in event.paths.into_iter().filter(|p| has_ext(p, &exts)) {
+            let _ = tx.send(path);
+        }
+    })
+    .map_err(|e| error!("Failed to create hot folder watcher: {}", e))
+    .ok()?;
+
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| error!("Failed to watch {:?}: {}", dir, e))
+        .ok()?;
+    debug!("Watching hot folder {:?}", dir);
+
+    std::thread::Builder::new()
+        .name("hot-folder".to_string())
+        .spawn(move || {
+            let mut pending = Pending::default();
+            loop {
+                match rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(path) => pending.touch(path, Instant::now()),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+                for path in pending.take_settled(Instant::now()) {
+                    if path.is_file() {
+                        trace!("Hot folder arrival {:?}", path);
+                        on_arrival(path);
+                    }
+                }
+            }
+            debug!("Hot folder watcher stopped");
+        })
+        .map_err(|e| error!("Failed to spawn hot folder thread: {}", e))
+        .ok()?;
+
+    Some(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_settle_after_quiet_period_in_arrival_order() {
+        let t0 = Instant::now();
+        let mut pending = Pending::default();
+        pending.touch("b.jpg".into(), t0);
+        pending.touch("a.jpg".into(), t0 + Duration::from_millis(10));
+        pending.touch("c.jpg".into(), t0 + Duration::from_millis(400));
+
+        assert!(
+            pending
+                .take_settled(t0 + Duration::from_millis(100))
+                .is_empty()
+        );
+        assert_eq!(
+            pending.take_settled(t0 + SETTLE_TIME + Duration::from_millis(10)),
+            vec![PathBuf::from("b.jpg"), PathBuf::from("a.jpg")]
+        );
+        // Still being written
+        pending.touch("c.jpg".into(), t0 + Duration::from_millis(800));
+        assert!(
+            pending
+                .take_settled(t0 + Duration::from_millis(1000))
+                .is_empty()
+        );
+        assert_eq!(
+            pending.take_settled(t0 + Duration::from_millis(1300)),
+            vec![PathBuf::from("c.jpg")]
+        );
+    }
+}
@@ -2,17 +2,22 @@ use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba};
 use log::{debug, error};
 use rayon::prelude::*;
 use slint::{Rgba8Pixel, SharedPixelBuffer};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::{path::PathBuf, time::Instant};
+use std::time::Instant;
 
 use crate::ImgFmt; // TODO: Consider rename
-use luminous_plugins::PluginManager;
+use crate::error::{self, LuminousError};
+use crate::watermark::Stamp;
+use luminous_plugins::{ImageFormat as ImgFormat, PluginManager};
 
 pub fn save_image(
     image_buffer: Option<SharedPixelBuffer<Rgba8Pixel>>,
     image_path: Option<PathBuf>,
     format: String,
+    quality: u8,
     plugin_manager: Arc<PluginManager>,
+    watermark: Option<Arc<Stamp>>,
 ) {
     if let Some(path) = image_path {
         let new_name = path
@@ -34,35 +39,17 @@ pub fn save_image(
                 );
 
                 let img: DynamicImage = if let Some(buffer) = image_buffer {
-                    let width = buffer.width();
-                    let height = buffer.height();
-                    let pixels = buffer.as_bytes().to_vec();
-
-                    let img_buf = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels)
-                        .expect("Failed to create image buffer from Slint pixels");
-
-                    DynamicImage::ImageRgba8(img_buf)
+                    to_dynamic(buffer)
                 } else {
                     image::open(path).map_err(|e| e.to_string()).unwrap()
                 };
+                let img = match &watermark {
+                    Some(stamp) => stamp.apply(img),
+                    None => img,
+                };
 
-                if let Some(native_format) = image::ImageFormat::from_extension(&format) {
-                    if format.to_lowercase() == "jpg" || format.to_lowercase() == "jpeg" {
-                        let mut out = std::fs::File::create(&dst_file)
-                            .map_err(|e| e.to_string())
-                            .unwrap();
-                        let encoder =
-                            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 90);
-                        img.write_with_encoder(encoder)
-                            .map_err(|e| e.to_string())
-                            .unwrap();
-                    } else {
-                        img.save_with_format(&dst_file, native_format)
-                            .map_err(|e| e.to_string())
-                            .unwrap();
-                    }
-                } else if !plugin_manager.encode(&dst_file, &img) {
-                    error!("No native or plugin encoder found for format: {}", format);
+                if let Err(e) = encode(&img, &dst_file, &format, quality, &plugin_manager) {
+                    error!("Failed to save {:?}: {}", dst_file, e);
                     return;
                 }
                 debug!("Saved to: {:?}", dst_file);
@@ -71,7 +58,120 @@ pub fn save_image(
     }
 }
 
-pub fn batch_save_images(paths: Vec<PathBuf>, format: ImgFmt) {
+/// Ask where to save `buffer` under another name, the format following the extension picked
+/// in the dialog. `formats` are the encodable ones, the first is preselected. `done` gets the
+/// written file or why it failed, from the encoding thread.
+pub fn save_as(
+    buffer: SharedPixelBuffer<Rgba8Pixel>,
+    path: &Path,
+    formats: Vec<ImgFormat>,
+    quality: u8,
+    plugin_manager: Arc<PluginManager>,
+    done: impl FnOnce(error::Result<PathBuf>) + Send + 'static,
+) {
+    let Some(first) = formats.first().and_then(|f| f.exts.first()).cloned() else {
+        return;
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut dialog = rfd::FileDialog::new()
+        .set_title("Save as")
+        .set_file_name(format!("{stem}.{first}"));
+    for format in &formats {
+        let name = format
+            .exts
+            .first()
+            .map(|e| e.to_uppercase())
+            .unwrap_or_default();
+        dialog = dialog.add_filter(name, &format.exts);
+    }
+    if let Some(parent) = path.parent() {
+        dialog = dialog.set_directory(parent);
+    }
+    let Some(mut dst) = dialog.save_file() else {
+        return;
+    };
+    let ext = match dst.extension().map(|e| e.to_string_lossy().to_lowercase()) {
+        Some(ext) if formats.iter().any(|f| f.exts.contains(&ext)) => ext,
+        Some(ext) => {
+            done(Err(LuminousError::Plugin(format!(
+                "no encoder for .{ext} files"
+            ))));
+            return;
+        }
+        // No extension typed, the preselected format gets added
+        None => {
+            dst.set_extension(&first);
+            first
+        }
+    };
+    std::thread::spawn(move || {
+        debug!("Saving as {:?} (quality {quality})", dst);
+        let result = encode(&to_dynamic(buffer), &dst, &ext, quality, &plugin_manager);
+        done(result.map(|()| dst));
+    });
+}
+
+pub(crate) fn to_dynamic(buffer: SharedPixelBuffer<Rgba8Pixel>) -> DynamicImage {
+    let img_buf = ImageBuffer::<Rgba<u8>, _>::from_raw(
+        buffer.width(),
+        buffer.height(),
+        buffer.as_bytes().to_vec(),
+    )
+    .expect("Failed to create image buffer from Slint pixels");
+    DynamicImage::ImageRgba8(img_buf)
+}
+
+/// Write `img` to `dst` as `format` (an extension), through a plugin when `image` has no
+/// encoder for it. `quality` (1-100) applies to JPEG, WebP is always lossless.
+pub fn encode(
+    img: &DynamicImage,
+    dst: &Path,
+    format: &str,
+    quality: u8,
+    plugin_manager: &PluginManager,
+) -> error::Result<()> {
+    let Some(native_format) = ImageFormat::from_extension(format) else {
+        return if plugin_manager.encode(dst, img) {
+            Ok(())
+        } else {
+            Err(LuminousError::Plugin(format!(
+                "no native or plugin encoder found for format: {format}"
+            )))
+        };
+    };
+    if native_format == ImageFormat::Jpeg {
+        let mut out = std::fs::File::create(dst)?;
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality);
+        // JPEG has no alpha channel
+        DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)?;
+    } else {
+        img.save_with_format(dst, native_format)?;
+    }
+    Ok(())
+}
+
+/// `photo-crop.jpg` next to `photo.jpg`, numbered when taken.
+pub fn crop_copy_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| match n {
+            1 => path.with_file_name(format!("{stem}-crop{ext}")),
+            n => path.with_file_name(format!("{stem}-crop-{n}{ext}")),
+        })
+        .find(|p| !p.exists())
+        .expect("some name is free")
+}
+
+pub fn batch_save_images(
+    paths: Vec<PathBuf>,
+    format: ImgFmt,
+    quality: u8,
+    watermark: Option<Arc<Stamp>>,
+) {
     if paths.is_empty() {
         debug!("Batch save received no image");
         return;
@@ -96,11 +196,13 @@ pub fn batch_save_images(paths: Vec<PathBuf>, format: ImgFmt) {
                     let mut out = std::fs::File::create(&dst_file)
                         .map_err(|e| e.to_string())
                         .unwrap();
-                    let img = image::open(path).map_err(|e| e.to_string()).unwrap();
+                    let mut img = image::open(path).map_err(|e| e.to_string()).unwrap();
+                    if let Some(stamp) = &watermark {
+                        img = stamp.apply(img);
+                    }
 
                     match format {
                         ImgFmt::Jpeg => {
-                            let quality = 90;
                             let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
                                 &mut out, quality,
                             );
@@ -141,3 +243,44 @@ fn format_to_image_format(image_format: ImgFmt) -> ImageFormat {
         ImgFmt::Webp => ImageFormat::WebP,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crop_copies_get_free_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("photo.jpg");
+        assert_eq!(crop_copy_path(&photo), dir.path().join("photo-crop.jpg"));
+        std::fs::write(dir.path().join("photo-crop.jpg"), b"").unwrap();
+        assert_eq!(crop_copy_path(&photo), dir.path().join("photo-crop-2.jpg"));
+        assert_eq!(
+            crop_copy_path(&dir.path().join("notes")),
+            dir.path().join("notes-crop")
+        );
+    }
+
+    #[test]
+    fn jpeg_drops_alpha() {
+        let dir = tempfile::tempdir().unwrap();
+        let dst = dir.path().join("a.jpg");
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 3));
+        encode(&img, &dst, "jpg", 90, &PluginManager::new()).unwrap();
+        assert_eq!(image::image_dimensions(&dst).unwrap(), (4, 3));
+    }
+
+    #[test]
+    fn jpeg_quality_changes_size() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x ^ y) * 4) as u8])
+        }));
+        let dir = tempfile::tempdir().unwrap();
+        let size = |quality| {
+            let dst = dir.path().join(format!("{quality}.jpg"));
+            encode(&img, &dst, "jpeg", quality, &PluginManager::new()).unwrap();
+            std::fs::metadata(&dst).unwrap().len()
+        };
+        assert!(size(30) < size(95));
+    }
+}
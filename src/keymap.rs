@@ -0,0 +1,283 @@
+//! Keys of the `[bindings]`: a key with modifiers like `Ctrl+Shift+D`, or a sequence of them
+//! separated by spaces like `g g`. Bindings and key presses are brought to the same canonical
+//! text, which the window compares its bindings against.
+
+use crate::config::Config;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+/// Time allowed between the keys of a sequence.
+pub const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Shift, Control, Alt, AltGr, CapsLock and Meta as Slint sends them, only modifiers of the
+/// next key.
+const MODIFIER_KEYS: RangeInclusive<char> = '\u{10}'..='\u{18}';
+
+/// A key with the modifiers held for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+/// Canonical text of a key press, `text` as Slint reports it.
+pub fn chord(text: &str, mods: Modifiers) -> String {
+    let mut key = text.to_string();
+    let mut chars = text.chars();
+    // Named keys are control characters or in the private use area
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && !c.is_control()
+        && !('\u{f700}'..='\u{f8ff}').contains(&c)
+    {
+        if !(mods.ctrl || mods.alt || mods.meta) {
+            // The text already has the shift in it, `:` rather than Shift+;
+            return key;
+        }
+        key = key.to_lowercase();
+    }
+    let mut text = String::new();
+    for (held, name) in [
+        (mods.ctrl, "ctrl+"),
+        (mods.alt, "alt+"),
+        (mods.shift, "shift+"),
+        (mods.meta, "meta+"),
+    ] {
+        if held {
+            text.push_str(name);
+        }
+    }
+    text + &key
+}
+
+/// Canonical text of a key with modifiers as written in the config, like `Ctrl+Shift+D`.
+fn parse_chord(binding: &str) -> Result<String, String> {
+    let (mods, key) = if binding == "+" {
+        ("", "+")
+    } else if let Some(mods) = binding.strip_suffix("++") {
+        (mods, "+")
+    } else {
+        binding.rsplit_once('+').unwrap_or(("", binding))
+    };
+    let mut held = Modifiers::default();
+    for m in mods.split('+').filter(|m| !m.is_empty()) {
+        match m.to_lowercase().as_str() {
+            "ctrl" | "control" => held.ctrl = true,
+            "alt" | "option" => held.alt = true,
+            "shift" => held.shift = true,
+            "meta" | "super" | "cmd" | "win" => held.meta = true,
+            _ => return Err(format!("unknown modifier '{m}' in '{binding}'")),
+        }
+    }
+    let text = Config::get_slint_key_string(key);
+    if key.chars().count() != 1 && text == key {
+        return Err(format!("unknown key '{key}'"));
+    }
+    let mut text = text.to_string();
+    if held.shift && !(held.ctrl || held.alt || held.meta) && key.chars().count() == 1 {
+        // Shift+v is typed as V
+        held.shift = false;
+        text = text.to_uppercase();
+    }
+    Ok(chord(&text, held))
+}
+
+/// Canonical text of a binding, its keys separated by spaces.
+pub fn parse(binding: &str) -> Result<String, String> {
+    // A lone space is the space key, not a separator
+    if binding == " " {
+        return Ok(" ".to_string());
+    }
+    let keys = binding
+        .split_whitespace()
+        .map(parse_chord)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("no key".to_string());
+    }
+    Ok(keys.join(" "))
+}
+
+/// Bindings that can't all work: the same keys for two actions, or a sequence whose first keys
+/// are bound on their own, which then never run.
+pub fn conflicts(bindings: &HashMap<String, String>) -> Vec<String> {
+    let mut keys: Vec<(&str, &str, String)> = bindings
+        .iter()
+        .filter_map(|(action, binding)| {
+            Some((action.as_str(), binding.as_str(), parse(binding).ok()?))
+        })
+        .collect();
+    keys.sort();
+    let mut found = Vec::new();
+    for (i, (action, binding, canonical)) in keys.iter().enumerate() {
+        for (other, other_binding, other_canonical) in &keys[i + 1..] {
+            if canonical == other_canonical {
+                found.push(format!(
+                    "{action} and {other} are both bound to '{binding}'"
+                ));
+            } else if other_canonical.starts_with(&format!("{canonical} ")) {
+                found.push(format!(
+                    "{other} on '{other_binding}' hides {action} on '{binding}'"
+                ));
+            } else if canonical.starts_with(&format!("{other_canonical} ")) {
+                found.push(format!(
+                    "{action} on '{binding}' hides {other} on '{other_binding}'"
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// Every action with its keys, one per line and sorted, followed by the conflicts.
+pub fn help(bindings: &HashMap<String, String>) -> String {
+    let mut actions: Vec<(&String, &String)> = bindings.iter().collect();
+    actions.sort();
+    let width = actions.iter().map(|(a, _)| a.len()).max().unwrap_or(0);
+    let mut text = String::new();
+    for (action, binding) in actions {
+        let binding = match binding.as_str() {
+            " " => "Space",
+            b => b,
+        };
+        let _ = writeln!(text, "{action:width$}  {binding}");
+    }
+    let conflicts = conflicts(bindings);
+    if !conflicts.is_empty() {
+        text.push_str("\nConflicts:\n");
+        for conflict in conflicts {
+            let _ = writeln!(text, "  {conflict}");
+        }
+    }
+    text
+}
+
+/// Follows key presses through the sequences of the bindings.
+pub struct Keymap {
+    /// Canonical keys of every binding
+    bindings: Vec<String>,
+    pending: Option<(String, Instant)>,
+}
+
+impl Keymap {
+    pub fn new(bindings: &HashMap<String, String>) -> Self {
+        Self {
+            bindings: bindings.values().filter_map(|b| parse(b).ok()).collect(),
+            pending: None,
+        }
+    }
+
+    /// The keys to run the binding of for a press of `chord` at `now`. Nothing while a
+    /// sequence is being typed, its first keys don't run bindings of their own.
+    pub fn press(&mut self, chord: String, now: Instant) -> Option<String> {
+        let mut chars = chord.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => return Some(chord),
+            (Some(c), None) if MODIFIER_KEYS.contains(&c) => return Some(chord),
+            _ => {}
+        }
+        let keys = match self.pending.take() {
+            Some((pending, at)) if now.duration_since(at) < SEQUENCE_TIMEOUT => {
+                let keys = format!("{pending} {chord}");
+                if self.starts_sequence(&keys) || self.bindings.contains(&keys) {
+                    keys
+                } else {
+                    // Not a sequence after all, the key counts on its own
+                    chord
+                }
+            }
+            _ => chord,
+        };
+        if self.starts_sequence(&keys) {
+            self.pending = Some((keys, now));
+            return None;
+        }
+        Some(keys)
+    }
+
+    /// Whether a binding goes on with more keys after `keys`.
+    fn starts_sequence(&self, keys: &str) -> bool {
+        let longer = format!("{keys} ");
+        self.bindings.iter().any(|b| b.starts_with(&longer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        ctrl: true,
+        alt: false,
+        shift: true,
+        meta: false,
+    };
+    const SHIFT: Modifiers = Modifiers {
+        ctrl: false,
+        alt: false,
+        shift: true,
+        meta: false,
+    };
+
+    #[test]
+    fn bindings_match_their_key_presses() {
+        assert_eq!(parse("Ctrl+Shift+D"), Ok(chord("D", CTRL_SHIFT)));
+        assert_eq!(parse("ctrl+shift+d"), parse("Ctrl+Shift+D"));
+        assert_eq!(parse("Shift+v"), Ok(chord("V", SHIFT)));
+        assert_eq!(parse("V"), Ok("V".to_string()));
+        assert_eq!(parse(":"), Ok(chord(":", SHIFT)));
+        assert_eq!(parse("Ctrl++"), parse("control++"));
+        assert_eq!(parse("Shift+PageDown").unwrap(), chord("\u{f72d}", SHIFT));
+        assert_eq!(parse("g g"), Ok("g g".to_string()));
+        assert_eq!(parse("Space"), parse(" "));
+        assert_eq!(
+            parse("Hyper+x"),
+            Err("unknown modifier 'Hyper' in 'Hyper+x'".into())
+        );
+        assert_eq!(parse("Ctrl+F12"), Err("unknown key 'F12'".into()));
+    }
+
+    #[test]
+    fn sequences_wait_for_their_last_key() {
+        let bindings = HashMap::from([
+            ("first".to_string(), "g g".to_string()),
+            ("last".to_string(), "G".to_string()),
+        ]);
+        let mut keymap = Keymap::new(&bindings);
+        let t0 = Instant::now();
+        assert_eq!(keymap.press("g".into(), t0), None);
+        assert_eq!(keymap.press("g".into(), t0), Some("g g".into()));
+        // Shift on its own doesn't break a sequence
+        assert_eq!(keymap.press("g".into(), t0), None);
+        assert_eq!(keymap.press("\u{10}".into(), t0), Some("\u{10}".into()));
+        assert_eq!(keymap.press("G".into(), t0), Some("G".into()));
+        // Too slow
+        assert_eq!(keymap.press("g".into(), t0), None);
+        assert_eq!(keymap.press("g".into(), t0 + SEQUENCE_TIMEOUT), None);
+    }
+
+    #[test]
+    fn conflicts_are_listed_with_the_help() {
+        let bindings = HashMap::from([
+            ("quit".to_string(), "q".to_string()),
+            ("close".to_string(), "q".to_string()),
+            ("go".to_string(), "g".to_string()),
+            ("first".to_string(), "g g".to_string()),
+        ]);
+        assert_eq!(
+            conflicts(&bindings),
+            [
+                "close and quit are both bound to 'q'",
+                "first on 'g g' hides go on 'g'",
+            ]
+        );
+        let help = help(&bindings);
+        assert!(help.starts_with("close  q\nfirst  g g\n"));
+        assert!(help.ends_with(
+            "Conflicts:\n  close and quit are both bound to 'q'\n  first on 'g g' hides go on 'g'\n"
+        ));
+    }
+}
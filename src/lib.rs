@@ -1,40 +1,199 @@
 slint::include_modules!();
 
+pub mod accessibility;
+pub mod actions;
+pub mod adjust;
 mod app_state_cache;
+pub mod auto_rotate;
+pub mod color_search;
 pub mod config;
+mod config_check;
+pub mod convert;
+#[cfg(target_os = "linux")]
+pub mod dbus;
+pub mod demo;
+pub mod display_filter;
+pub mod error;
+pub mod false_color;
+pub mod file_association;
+pub mod folder_config;
+pub mod folder_tree;
+pub mod frame_cache;
 pub mod fs_scan;
+pub mod hot_folder;
 pub mod image_processing;
+pub mod keymap;
+pub mod library;
+pub mod markup;
+pub mod metadata;
+pub mod naming;
+pub mod overlay;
 pub mod pipeline;
+pub mod power;
+pub mod recording;
+pub mod remote;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod sequence;
+pub mod sidecar;
+pub mod similarity;
+pub mod size_filter;
+pub mod test_patterns;
+pub mod toast;
 mod ui;
+pub mod video_export;
+pub mod wallpaper;
+pub mod watermark;
+pub mod zip_export;
 
-use config::Config;
-use fs_scan::ScanResult;
+use actions::Action;
+use color_search::ColorIndex;
+use config::{Config, FileTarget, Naming, StartMode, TransferMode};
+use folder_config::FolderConfig;
+use folder_tree::FolderTree;
+use fs_scan::{LaunchTarget, NameFilter, ScanResult};
+use library::{ColorLabel, FAVORITES_DIR, Flag, Library, MarkFilter, Marks, ViewProfile};
 use luminous_image_loader::ImageLoader;
+use luminous_image_loader::tone_map::{self, ToneMap};
 use luminous_plugins::PluginManager;
+use metadata::{ShotKinds, TagEdit, TextTag};
+use naming::Template;
 use pipeline::StepFactory;
+use recording::Recorder;
+use sequence::Sequence;
+use size_filter::{SizeFilter, SizeIndex};
+use ui::grid_model::{self, GridModel};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
+use rayon::prelude::*;
 use slint::{Image, Model, ModelRc, Rgba8Pixel, SharedPixelBuffer, VecModel};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp;
-use std::collections::HashSet;
-use std::error::Error;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::Arc;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub(crate) struct AppController {
     pub(crate) loader: Arc<ImageLoader>,
     pub(crate) scan: Arc<ScanResult>,
+    pub(crate) library: Arc<Library>,
     pub(crate) active_grid_indices: HashSet<usize>,
     pub(crate) filtered_indices: Vec<usize>,
+    pub(crate) targets: Vec<FileTarget>,
+    /// File name templates, see [`naming::Template`]
+    naming: Naming,
+    /// Quality JPEGs are written with
+    pub(crate) jpeg_quality: u8,
+    /// Stamped on exported images, see [`watermark::Stamp`]
+    pub(crate) watermark: Option<Arc<watermark::Stamp>>,
+    /// Images the open rename dialog applies to
+    rename_indices: Vec<usize>,
+    /// Images the open metadata dialog applies to, and the value of each tag they share, `None`
+    /// where they differ. The tags are read in the background.
+    metadata_indices: Vec<usize>,
+    metadata_shared: Arc<Mutex<HashMap<TextTag, Option<String>>>>,
+    pub(crate) sequences: Vec<Sequence>,
+    /// Sequence the current image belongs to, the loop region resets when it changes
+    active_sequence: Cell<Option<usize>>,
+    /// Drag and drop position of each scanned image, empty when its folder was never reordered
+    custom_rank: Vec<usize>,
+    /// Name sort picked in the grid, `None` keeps the scan or custom order
+    sort_ascending: Option<bool>,
+    /// Last grid search, as typed
+    query: String,
+    /// Star rating of each scanned image, 0 when unrated
+    ratings: Vec<u8>,
+    /// Images rated below this are left out of the grid and navigation
+    min_rating: u8,
+    /// Pick/reject flag and color label of each scanned image
+    marks: Vec<Marks>,
+    /// Like `min_rating`, by marks
+    mark_filter: MarkFilter,
+    /// Like `min_rating`, by shape and resolution
+    size_filter: SizeFilter,
+    /// Dimensions the size filter is evaluated on, see [`Self::handle_size_filter`]
+    sizes: Arc<SizeIndex>,
+    /// Favorite images of every folder, see [`Library::favorites`]
+    favorites: HashSet<PathBuf>,
+    /// Panorama, HDR and burst hints of the images shown in the grid so far, read in the
+    /// background, see [`metadata::read_shot_kinds`]
+    shot_kinds: Arc<Mutex<HashMap<PathBuf, ShotKinds>>>,
+    /// Dominant colors for finding images by color, see [`Self::handle_find_by_color`]
+    colors: Arc<ColorIndex>,
+    /// Scans only pick up files matching this, see [`Self::handle_set_scan_filter`]
+    pub(crate) name_filter: Option<NameFilter>,
+    /// Settings a folder's `.luminous.toml` replaced, put back when another folder is opened
+    outside_folder: OutsideFolder,
+    /// Image blended over the full view for comparison, see [`overlay::blend`]
+    reference: Arc<Mutex<Option<SharedPixelBuffer<Rgba8Pixel>>>>,
+    /// Source values of the image shown in false color and its index, at full bit depth
+    false_color_samples: Arc<Mutex<Option<IndexedSamples>>>,
+    /// Index whose false color samples are being decoded, cleared when they arrive or can't be
+    /// had
+    false_color_pending: Arc<Mutex<Option<usize>>>,
+    /// Latest adjustments request, older results are dropped
+    adjust_generation: Arc<AtomicUsize>,
+    /// Index of the image the adjusted view was computed for
+    adjusted_index: Cell<Option<usize>>,
+    /// Failures and notices for the user from any thread, see [`toast`]
+    pub(crate) toaster: toast::Toaster,
+    /// Adjusted, reference and filtered frames already computed, see [`frame_cache`]
+    frames: Arc<Mutex<frame_cache::FrameCache>>,
+    /// Annotations of the markup mode, the last one is being drawn while `markup_drawing` is set
+    markup: RefCell<Vec<markup::Shape>>,
+    markup_drawing: Cell<bool>,
+    /// Set to stop the running zip export, see [`Self::handle_export_zip`]
+    zip_cancel: Arc<AtomicBool>,
+    /// Set to stop the running video export, see [`Self::handle_export_video`]
+    video_cancel: Arc<AtomicBool>,
+    /// Music track of the next video export
+    video_music: RefCell<Option<PathBuf>>,
+    /// Writes user actions to a session script when recording
+    recorder: Option<Recorder>,
+    /// Folders expanded in the folder tree panel
+    folder_tree: FolderTree,
+    /// Opened on the first copy, see [`with_clipboard`]
+    clipboard: Arc<Mutex<Option<arboard::Clipboard>>>,
+    /// Clipboard image shown in the full view, see [`Self::handle_paste_image`]
+    pasted: Arc<Mutex<Option<SharedPixelBuffer<Rgba8Pixel>>>>,
     pub(crate) window_weak: slint::Weak<MainWindow>,
 }
 
+type IndexedSamples = (usize, Arc<false_color::Samples>);
+
+/// Values in effect before a folder's `.luminous.toml` overrode them, each `None` when the file
+/// left it alone.
+#[derive(Default)]
+struct OutsideFolder {
+    /// Interval and progress bar
+    slideshow: Option<(i64, bool)>,
+    fit_mode: Option<FitMode>,
+    grid_cols: Option<i32>,
+    /// Sort direction of the grid, the sort itself is reset for every scan
+    sort_ascending: Option<bool>,
+    right_to_left: Option<bool>,
+    spread: Option<bool>,
+}
+
+/// Exposure adjustments for HDR sources are limited to this many stops either way.
+const MAX_EXPOSURE: f32 = 8.0;
+
+/// Names the rename dialog previews, the rest are only counted.
+const RENAME_PREVIEW_ROWS: usize = 8;
+
+/// How long [`show_osd`] messages stay up.
+const OSD_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Cell labels of the compare grid, which also caps how many images it shows.
+const COMPARE_LABELS: [&str; 4] = ["A", "B", "C", "D"];
+
 impl AppController {
     fn new(
         plugin_manager: PluginManager,
+        library: Arc<Library>,
         scan: Arc<ScanResult>,
         config: &Config,
         window: &MainWindow,
@@ -48,33 +207,72 @@ impl AppController {
             Arc::clone(&plugin_manager),
         );
 
+        let toaster = ui::toast_presenter::toaster(window);
+        let t = toaster.clone();
+        plugin_manager.on_error(move |plugin, e| {
+            t.error(format!(
+                "Plugin '{plugin}' failed, {}",
+                error::LuminousError::from(e)
+            ))
+        });
+        let t = toaster.clone();
+        loader.on_failed(move |path| {
+            t.error(format!("Can't open {}", file_name(&path)));
+        });
+
         let weak_thumb = window_weak.clone();
         loader.on_thumb_ready(move |index, buffer| {
             let _ = weak_thumb.upgrade_in_event_loop(move |ui| {
                 let gv = ui.global::<GridViewState>();
                 let img = Image::from_rgba8(buffer);
-                let m = gv.get_model();
-
-                for row in 0..m.row_count() {
-                    if let Some(mut item) = m.row_data(row) {
-                        if item.abs_index == index as i32 {
-                            item.image = img.clone();
-                            m.set_row_data(row, item);
-                            break; // Found it
-                        }
-                    }
-                }
+                grid_model::update(&gv.get_model(), index, |item| item.image = img.clone());
 
                 let vm = gv.get_visible_model();
                 for i in 0..vm.row_count() {
                     if let Some(mut v) = vm.row_data(i) {
                         if v.abs_index == index as i32 {
-                            v.image = img;
+                            v.image = img.clone();
                             vm.set_row_data(i, v);
                             break;
                         }
                     }
                 }
+
+                let fm = ui.global::<FullViewState>().get_filmstrip();
+                for i in 0..fm.row_count() {
+                    if let Some(mut f) = fm.row_data(i)
+                        && f.abs_index == index as i32
+                    {
+                        f.image = img;
+                        fm.set_row_data(i, f);
+                        break;
+                    }
+                }
+            });
+        });
+
+        if config.demo.is_some() {
+            loader.set_generator(demo::generate);
+        }
+        loader.set_edits(|path, buffer| sidecar::Sidecar::load(path).apply(buffer));
+        let colors = Arc::new(ColorIndex::new(library.clone()));
+        let sizes = Arc::new(SizeIndex::new(library.clone()));
+        if config.demo.is_none() {
+            let colors = colors.clone();
+            loader.set_thumb_analyzer(move |path, buffer| colors.note_thumb(path, buffer));
+        }
+        loader.set_tone_map(ToneMap {
+            operator: config.tone_map,
+            exposure: 0.0,
+        });
+        loader.set_validate_cache(config.validate_cache);
+        loader.set_readahead(config.readahead);
+
+        let weak_missing = window_weak.clone();
+        loader.on_missing(move |path| {
+            let _ = weak_missing.upgrade_in_event_loop(move |ui| {
+                ui.global::<FullViewState>()
+                    .invoke_image_vanished(path.to_string_lossy().to_string().into());
             });
         });
 
@@ -93,7 +291,8 @@ impl AppController {
             let _ = weak_full.upgrade_in_event_loop(move |ui| {
                 let img = Image::from_rgba8(buffer);
                 let fv = ui.global::<FullViewState>();
-                if index == fv.get_curr_image_index() as usize {
+                // A pasted image is shown over the current index until another one is loaded
+                if index == fv.get_curr_image_index() as usize && !fv.get_curr_pasted() {
                     fv.set_curr_image(img);
                     fv.set_mask_overlay(Image::default());
                 }
@@ -101,13 +300,141 @@ impl AppController {
         });
 
         let total = scan.paths.len();
-        Self {
+        let sequences = sequence::detect(&scan.paths);
+        let ratings = library.ratings(&scan.paths);
+        let marks = library.marks(&scan.paths);
+        let favorites = library.favorites().into_iter().collect();
+        let recorder = config.record.as_ref().and_then(|path| {
+            info!("Recording session to {}", path.display());
+            Recorder::create(path, &scan.dir)
+                .map_err(|e| error!("Failed to record to {}: {}", path.display(), e))
+                .ok()
+        });
+        let mut controller = Self {
             loader: Arc::new(loader),
             scan,
+            library,
             active_grid_indices: HashSet::new(),
             filtered_indices: (0..total).collect(),
+            targets: config.targets.clone(),
+            naming: config.naming.clone(),
+            jpeg_quality: config.jpeg_quality,
+            watermark: config
+                .watermark
+                .as_ref()
+                .and_then(watermark::Stamp::new)
+                .map(Arc::new),
+            rename_indices: Vec::new(),
+            metadata_indices: Vec::new(),
+            metadata_shared: Arc::new(Mutex::new(HashMap::new())),
+            sequences,
+            active_sequence: Cell::new(None),
+            custom_rank: Vec::new(),
+            sort_ascending: None,
+            query: String::new(),
+            ratings,
+            min_rating: 0,
+            marks,
+            mark_filter: MarkFilter::All,
+            size_filter: SizeFilter::All,
+            sizes,
+            favorites,
+            shot_kinds: Arc::new(Mutex::new(HashMap::new())),
+            colors,
+            name_filter: config.filter.clone(),
+            outside_folder: OutsideFolder::default(),
+            reference: Arc::default(),
+            false_color_samples: Arc::new(Mutex::new(None)),
+            false_color_pending: Arc::new(Mutex::new(None)),
+            adjust_generation: Arc::new(AtomicUsize::new(0)),
+            adjusted_index: Cell::new(None),
+            toaster,
+            frames: Arc::new(Mutex::new(frame_cache::FrameCache::default())),
+            markup: RefCell::new(Vec::new()),
+            markup_drawing: Cell::new(false),
+            zip_cancel: Arc::new(AtomicBool::new(false)),
+            video_cancel: Arc::new(AtomicBool::new(false)),
+            video_music: RefCell::new(None),
+            recorder,
+            folder_tree: FolderTree::default(),
+            clipboard: Arc::new(Mutex::new(None)),
+            pasted: Arc::new(Mutex::new(None)),
             window_weak: window.as_weak(),
+        };
+        controller.load_custom_order();
+        sort_by_rank(&mut controller.filtered_indices, &controller.custom_rank);
+        controller
+    }
+
+    /// Read the manual order of the scanned folder, images missing from it go last by name.
+    fn load_custom_order(&mut self) {
+        self.custom_rank.clear();
+        if !self.scan.is_dir {
+            return;
+        }
+        let names = self.library.custom_order(&self.scan.dir);
+        if names.is_empty() {
+            return;
         }
+        let positions: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(pos, name)| (name.as_str(), pos))
+            .collect();
+        self.custom_rank = self
+            .scan
+            .paths
+            .iter()
+            .map(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| positions.get(n).copied())
+                    .unwrap_or(usize::MAX)
+            })
+            .collect();
+        debug!("Restored custom order of {} images", names.len());
+    }
+
+    /// Move the grid row `from` to `to` and remember the new order for the folder.
+    fn handle_move_item(&mut self, from: usize, to: usize) {
+        let len = self.filtered_indices.len();
+        if from == to || from >= len || to >= len {
+            return;
+        }
+        if !self.scan.is_dir {
+            return;
+        }
+        // Rows hidden by a search would have no place in the stored order
+        if len != self.scan.paths.len() {
+            warn!("Clear the search to reorder images");
+            return;
+        }
+        let abs = self.filtered_indices.remove(from);
+        self.filtered_indices.insert(to, abs);
+
+        let names: Vec<String> = self
+            .filtered_indices
+            .iter()
+            .filter_map(|&i| self.scan.paths[i].file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect();
+        self.library.set_custom_order(&self.scan.dir, &names);
+        self.custom_rank = vec![0; len];
+        for (pos, &i) in self.filtered_indices.iter().enumerate() {
+            self.custom_rank[i] = pos;
+        }
+        debug!("Moved image {abs} from row {from} to {to}");
+        self.rebuild_grid_model();
+    }
+
+    fn handle_reset_custom_order(&mut self) {
+        if self.custom_rank.is_empty() {
+            return;
+        }
+        info!("Dropping custom order of {:?}", self.scan.dir);
+        self.library.set_custom_order(&self.scan.dir, &[]);
+        self.custom_rank.clear();
+        self.handle_sort(true);
     }
 
     fn handle_grid_request(&mut self, start: usize, count: usize) {
@@ -124,17 +451,9 @@ impl AppController {
         const MARGIN: usize = 30;
         let keep_start = start.saturating_sub(MARGIN);
         let keep_end = start + count + MARGIN;
-        for row in 0..total {
-            if row < keep_start || row > keep_end {
-                if let Some(mut item) = model.row_data(row) {
-                    if item.image != Image::default() {
-                        item.image = Image::default();
-                        model.set_row_data(row, item);
-                    }
-                }
-            }
-        }
+        grid_model::drop_images(&model, keep_start..=keep_end);
         self.loader.prune_grid_thumbs(start, count);
+        self.read_badges(start..end);
 
         let visible: Vec<GridItem> = (start..end).filter_map(|i| model.row_data(i)).collect();
         gv.set_visible_model(ModelRc::from(Rc::from(VecModel::from(visible))));
@@ -192,9 +511,22 @@ impl AppController {
             fv.set_curr_image(display_img);
             fv.set_mask_overlay(Image::default());
             fv.set_curr_image_index(index as i32);
+            fv.set_curr_pasted(false);
             if let Some(name) = loader.get_file_name(index) {
                 fv.set_curr_image_name(name.into());
             }
+            fv.set_curr_image_hdr(
+                loader
+                    .get_path(index)
+                    .is_some_and(|p| tone_map::is_hdr_path(&p)),
+            );
+            self.update_sequence_state(&fv, index);
+            self.update_info_overlay(&fv, index);
+            self.restore_edits(&fv, index);
+            self.update_spread_page(&fv);
+            if fv.get_caption_visible() {
+                self.handle_load_caption();
+            }
             if loader.full_cache_contains(index) {
                 for plugin in pm.get_interactive_plugins() {
                     // TODO: auto send image in GUI
@@ -203,25 +535,249 @@ impl AppController {
             }
         }
 
+        self.update_filmstrip(index);
+
         let window_indices = self.build_window_indices(index);
         loader.update_sliding_window(index, window_indices);
     }
 
-    fn handle_navigate(&self, delta: isize) {
-        let ui = match self.window_weak.upgrade() {
-            Some(ui) => ui,
-            None => return,
+    /// Decode the page facing the current image, the next shown one, when the full view shows
+    /// two-page spreads. The last page is shown alone.
+    pub(crate) fn update_spread_page(&self, fv: &FullViewState) {
+        fv.set_spread_image(Image::default());
+        if !fv.get_spread() {
+            return;
+        }
+        let Some(&facing) = self
+            .current_row()
+            .and_then(|row| self.filtered_indices.get(row + 1))
+        else {
+            return;
         };
+        let index = fv.get_curr_image_index();
+        let loader = self.loader.clone();
+        let weak = self.window_weak.clone();
+        self.loader.pool.spawn(move || {
+            let Some(buffer) = loader.load_full_blocking(facing) else {
+                return;
+            };
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                let fv = ui.global::<FullViewState>();
+                if fv.get_spread() && fv.get_curr_image_index() == index {
+                    fv.set_spread_image(Image::from_rgba8(buffer));
+                }
+            });
+        });
+    }
+
+    /// Position, resolution, file size, rating and marks shown by the on-screen info overlay, and the
+    /// images a slideshow has left before it wraps around.
+    fn update_info_overlay(&self, fv: &FullViewState, index: usize) {
         let total = self.filtered_indices.len();
-        if total == 0 {
+        let pos = self
+            .filtered_indices
+            .iter()
+            .position(|&i| i == index)
+            .unwrap_or(index);
+        fv.set_info_position(format!("{}/{}", pos + 1, total).into());
+        fv.set_slideshow_remaining(total.saturating_sub(pos + 1) as i32);
+        fv.set_info_resolution(Default::default());
+        fv.set_info_file_size(Default::default());
+        if let Some(path) = self.loader.get_path(index) {
+            let known = self.sizes.get(&path);
+            let weak = self.window_weak.clone();
+            // Slow disks and network mounts would hold up navigation
+            self.loader.pool.spawn(move || {
+                // Only the header is read, plugin formats the image crate can't parse stay blank
+                let resolution = known
+                    .or_else(|| image::image_dimensions(&path).ok())
+                    .map(|(w, h)| format!("{w}x{h}"))
+                    .unwrap_or_default();
+                let size = std::fs::metadata(&path)
+                    .map(|m| {
+                        format!(
+                            "{:.1} MiB",
+                            ui::perf_overlay_presenter::mib(m.len() as usize)
+                        )
+                    })
+                    .unwrap_or_default();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let fv = ui.global::<FullViewState>();
+                    if fv.get_curr_image_index() as usize == index {
+                        fv.set_info_resolution(resolution.into());
+                        fv.set_info_file_size(size.into());
+                    }
+                });
+            });
+        }
+        fv.set_curr_favorite(
+            self.scan
+                .paths
+                .get(index)
+                .is_some_and(|p| self.favorites.contains(p)),
+        );
+        fv.set_curr_rating(self.rating(index));
+        let marks = self.marks(index);
+        fv.set_curr_flag(marks.flag.name().into());
+        fv.set_curr_label(marks.label.name().into());
+    }
+
+    /// Read the caption of the current image for the caption bar.
+    fn handle_load_caption(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let caption = if fv.get_curr_pasted() {
+            None
+        } else {
+            self.loader
+                .get_curr_img_path()
+                .and_then(|p| metadata::read_caption(&p))
+        };
+        fv.set_caption(caption.unwrap_or_default().into());
+    }
+
+    /// Write the caption typed into the caption bar to the current image.
+    fn handle_save_caption(&self, caption: &str) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let Some(path) = self.loader.get_curr_img_path() else {
+            return;
+        };
+        if fv.get_curr_pasted() {
             return;
         }
-        let curr = ui.global::<FullViewState>().get_curr_image_index() as usize;
-        let curr_pos = self
+        match metadata::write_caption(&path, caption) {
+            Ok(()) => fv.set_caption(caption.trim().into()),
+            Err(e) => {
+                error!("Failed to save the caption of {:?}: {}", path, e);
+                show_osd(&ui, format!("Caption not saved: {e}"));
+            }
+        }
+    }
+
+    fn update_sequence_state(&self, fv: &FullViewState, index: usize) {
+        let found = self
+            .sequences
+            .iter()
+            .enumerate()
+            .find_map(|(id, s)| s.position(index).map(|pos| (id, s, pos)));
+        let Some((id, seq, pos)) = found else {
+            self.active_sequence.set(None);
+            fv.set_sequence_length(0);
+            fv.set_playing(false);
+            return;
+        };
+        if self.active_sequence.replace(Some(id)) != Some(id) {
+            debug!("Showing image sequence of {} frames", seq.frames.len());
+            fv.set_sequence_length(seq.frames.len() as i32);
+            fv.set_loop_start(0);
+            fv.set_loop_end(seq.frames.len() as i32 - 1);
+        }
+        fv.set_sequence_frame(pos as i32);
+    }
+
+    /// Advance the current sequence by `delta` frames within the loop region.
+    fn handle_sequence_step(&self, delta: isize) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let Some(seq) = self
+            .active_sequence
+            .get()
+            .and_then(|id| self.sequences.get(id))
+        else {
+            fv.set_playing(false);
+            return;
+        };
+        let last = seq.frames.len() - 1;
+        let start = (fv.get_loop_start().max(0) as usize).min(last);
+        let end = (fv.get_loop_end().max(0) as usize).min(last);
+        let pos = sequence::step(fv.get_sequence_frame() as usize, delta, start, end);
+        self.handle_full_view_load(seq.frames[pos]);
+    }
+
+    fn handle_sequence_seek(&self, pos: usize) {
+        let Some(seq) = self
+            .active_sequence
+            .get()
+            .and_then(|id| self.sequences.get(id))
+        else {
+            return;
+        };
+        if let Some(&abs) = seq.frames.get(pos) {
+            self.handle_full_view_load(abs);
+        }
+    }
+
+    fn update_filmstrip(&self, center: usize) {
+        const RADIUS: usize = 10;
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let pos = self
             .filtered_indices
             .iter()
-            .position(|&i| i == curr)
+            .position(|&x| x == center)
             .unwrap_or(0);
+        let lo = pos.saturating_sub(RADIUS);
+        let hi = cmp::min(pos + RADIUS + 1, self.filtered_indices.len());
+
+        let items: Vec<GridItem> = self.filtered_indices[lo..hi]
+            .iter()
+            .enumerate()
+            .map(|(i, &abs)| GridItem {
+                image: self
+                    .loader
+                    .load_grid_thumb(abs)
+                    .map(Image::from_rgba8)
+                    .unwrap_or_default(),
+                index: (lo + i) as i32,
+                abs_index: abs as i32,
+                selected: abs == center,
+                name: self.loader.get_file_name(abs).unwrap_or_default().into(),
+                rating: self.rating(abs),
+                flag: self.marks(abs).flag.name().into(),
+                label: self.marks(abs).label.name().into(),
+                badges: self.badges(abs),
+            })
+            .collect();
+
+        let fv = ui.global::<FullViewState>();
+        fv.set_filmstrip_center((pos - lo) as i32);
+        fv.set_filmstrip(Rc::new(VecModel::from(items)).into());
+    }
+
+    /// Row of the current image among the shown ones. The full view index is the one current
+    /// item the grid and full view share, `None` when it is filtered out.
+    pub(crate) fn current_row(&self) -> Option<usize> {
+        let ui = self.window_weak.upgrade()?;
+        let curr = ui.global::<FullViewState>().get_curr_image_index() as usize;
+        self.filtered_indices.iter().position(|&i| i == curr)
+    }
+
+    /// Scroll the grid to the current image if it is out of view.
+    fn reveal_current_row(&self) {
+        if let (Some(ui), Some(row)) = (self.window_weak.upgrade(), self.current_row()) {
+            ui.invoke_reveal_grid_row(row as i32);
+        }
+    }
+
+    fn handle_navigate(&self, delta: isize) {
+        let total = self.filtered_indices.len();
+        if total == 0 {
+            return;
+        }
+        // A spread turns both of its pages
+        let delta = match self.window_weak.upgrade() {
+            Some(ui) if ui.global::<FullViewState>().get_spread() => delta * 2,
+            _ => delta,
+        };
+        let curr_pos = self.current_row().unwrap_or(0);
         let next_pos = (curr_pos as isize + delta).rem_euclid(total as isize) as usize;
         if let Some(&next_abs) = self.filtered_indices.get(next_pos) {
             self.handle_full_view_load(next_abs);
@@ -229,105 +785,386 @@ impl AppController {
     }
 
     // TODO: How to not reload images from disk and keep the cache consistent?
-    fn handle_edit_op(&mut self, op: EditOp) {
-        let Some(buffer) = self.loader.get_curr_active_buffer() else {
-            return;
-        };
-
-        let loader = self.loader.clone();
-        let before_idx = loader.active_idx.load(Ordering::Relaxed);
-        if let EditOpKind::Delete = op.kind {
-            if let Some(p) = loader.get_path(before_idx) {
-                let _ = trash::delete(&p);
-            }
-            loader.rm_img(before_idx);
+    /// Drop the image at `abs_idx` from the scan, loader and grid. The full view moves on to
+    /// its successor when it was the current image.
+    fn remove_from_view(&mut self, abs_idx: usize) {
+        let curr = self.loader.active_idx.load(Ordering::Relaxed);
+        self.loader.rm_img(abs_idx);
+        let paths = &mut Arc::make_mut(&mut self.scan).paths;
+        if abs_idx < paths.len() {
+            paths.remove(abs_idx);
+        }
+        if abs_idx < self.ratings.len() {
+            self.ratings.remove(abs_idx);
+        }
+        if abs_idx < self.marks.len() {
+            self.marks.remove(abs_idx);
+        }
 
-            let pos = self.filtered_indices.iter().position(|&i| i == before_idx);
-            if let Some(p) = pos {
-                self.filtered_indices.remove(p);
+        let pos = self.filtered_indices.iter().position(|&i| i == abs_idx);
+        if let Some(p) = pos {
+            self.filtered_indices.remove(p);
+        }
+        if abs_idx < self.custom_rank.len() {
+            self.custom_rank.remove(abs_idx);
+        }
+        self.filtered_indices.iter_mut().for_each(|idx| {
+            if *idx > abs_idx {
+                *idx -= 1;
             }
-            self.filtered_indices.iter_mut().for_each(|idx| {
-                if *idx > before_idx {
+        });
+        for seq in &mut self.sequences {
+            seq.frames.retain(|&i| i != abs_idx);
+            seq.frames.iter_mut().for_each(|idx| {
+                if *idx > abs_idx {
                     *idx -= 1;
                 }
             });
+        }
+        self.active_sequence.set(None);
 
-            self.active_grid_indices.clear();
-            loader.clear_thumbs();
+        self.active_grid_indices.clear();
+        self.loader.clear_thumbs();
 
-            if let Some(ui) = self.window_weak.upgrade() {
-                let filtered_items: Vec<GridItem> = self
-                    .filtered_indices
-                    .iter()
-                    .enumerate()
-                    .map(|(r, &idx)| GridItem {
-                        image: Image::default(),
-                        index: r as i32,
-                        abs_index: idx as i32,
-                        selected: false,
-                    })
-                    .collect();
+        if let Some(ui) = self.window_weak.upgrade() {
+            let gv = ui.global::<GridViewState>();
+            gv.set_model(self.grid_model());
 
-                let gv = ui.global::<GridViewState>();
-                gv.set_model(Rc::new(VecModel::from(filtered_items)).into());
+            if self.filtered_indices.is_empty() {
+                let fv = ui.global::<FullViewState>();
+                fv.set_curr_image(Image::default());
+                fv.set_curr_image_name("No images".into());
+            } else {
+                let shown = match curr.cmp(&abs_idx) {
+                    cmp::Ordering::Less => Some(curr),
+                    cmp::Ordering::Greater => Some(curr - 1),
+                    cmp::Ordering::Equal => None,
+                };
+                let next_abs = shown
+                    .filter(|i| self.filtered_indices.contains(i))
+                    .unwrap_or_else(|| {
+                        let next_pos = pos
+                            .unwrap_or(0)
+                            .min(self.filtered_indices.len().saturating_sub(1));
+                        self.filtered_indices[next_pos]
+                    });
+                self.handle_full_view_load(next_abs);
+            }
+            self.handle_grid_request(0, 50);
+        }
+    }
 
-                if self.filtered_indices.is_empty() {
-                    let fv = ui.global::<FullViewState>();
-                    fv.set_curr_image(Image::default());
-                    fv.set_curr_image_name("No images".into());
-                } else {
-                    let next_pos = pos
-                        .unwrap_or(0)
-                        .min(self.filtered_indices.len().saturating_sub(1));
-                    let next_abs = self.filtered_indices[next_pos];
-                    self.handle_full_view_load(next_abs);
+    /// Copy or move the current image to the target bound to `key`. Returns
+    /// false when no target is bound to it.
+    fn handle_transfer_to_target(&mut self, key: &str) -> bool {
+        let Some(target) = self.targets.iter().find(|t| t.key == key).cloned() else {
+            return false;
+        };
+        let Some(ui) = self.window_weak.upgrade() else {
+            return true;
+        };
+        let abs_idx = ui.global::<FullViewState>().get_curr_image_index() as usize;
+        let Some(src) = self.loader.get_path(abs_idx) else {
+            return true;
+        };
+        let Some(file_name) = src.file_name() else {
+            return true;
+        };
+        let dst = match &self.naming.ingest {
+            // Numbered on from what the target already holds
+            Some(template) => {
+                let seq = std::fs::read_dir(&target.dir).map_or(0, |d| d.count()) + 1;
+                let ctx = template.context(&src, seq, self.rating(abs_idx) as u8);
+                target.dir.join(template.file_name(&ctx))
+            }
+            None => target.dir.join(file_name),
+        };
+        if dst.exists() {
+            warn!("{:?} already exists, not overwriting", dst);
+            return true;
+        }
+        if let Err(e) = std::fs::create_dir_all(&target.dir) {
+            self.toaster
+                .error(format!("Can't create {}: {e}", target.dir.display()));
+            return true;
+        }
+
+        let result = match target.mode {
+            TransferMode::Copy => std::fs::copy(&src, &dst).map(|_| ()),
+            // Rename fails across filesystems, fall back to copy and remove
+            TransferMode::Move => std::fs::rename(&src, &dst)
+                .or_else(|_| std::fs::copy(&src, &dst).and_then(|_| std::fs::remove_file(&src))),
+        };
+        if let Err(e) = result {
+            let verb = match target.mode {
+                TransferMode::Copy => "copy",
+                TransferMode::Move => "move",
+            };
+            self.toaster.error(format!(
+                "Can't {verb} {} to {}: {e}",
+                file_name.to_string_lossy(),
+                target.dir.display()
+            ));
+            return true;
+        }
+        info!("{:?} {:?} -> {:?}", target.mode, src, dst);
+
+        match target.mode {
+            TransferMode::Copy => {
+                let edits = sidecar::Sidecar::load(&src);
+                if !edits.is_empty()
+                    && let Err(e) = edits.save(&dst)
+                {
+                    warn!("Failed to copy the edits of {:?}: {}", src, e);
                 }
-                self.handle_grid_request(0, 50);
+                self.handle_navigate(1);
+            }
+            TransferMode::Move => {
+                sidecar::carry(&[(src.clone(), dst.clone())]);
+                self.remove_from_view(abs_idx);
             }
-            return;
         }
+        true
+    }
 
-        let weak = self.window_weak.clone();
-        let selection = weak
-            .upgrade()
-            .map(|window| window.global::<FullViewState>().get_selection())
+    /// Put the decoded RGBA pixels of the current image on the clipboard, edits included.
+    fn handle_copy_image(&self) {
+        let Some(buffer) = self.loader.get_curr_active_buffer() else {
+            return;
+        };
+        let name = self
+            .loader
+            .get_file_name(self.loader.active_idx.load(Ordering::Relaxed))
             .unwrap_or_default();
-
+        let clipboard = self.clipboard.clone();
+        // Some platforms encode the image right away, which takes a while for large ones
         self.loader.pool.spawn(move || {
-            let bytes: &[u8] = bytemuck::cast_slice(buffer.as_slice());
-            let Some(rgba) =
-                image::RgbaImage::from_raw(buffer.width(), buffer.height(), bytes.to_vec())
-            else {
-                return;
+            let image = arboard::ImageData {
+                width: buffer.width() as usize,
+                height: buffer.height() as usize,
+                bytes: std::borrow::Cow::Borrowed(bytemuck::cast_slice(buffer.as_slice())),
             };
+            match with_clipboard(&clipboard, |c| c.set_image(image)) {
+                Ok(()) => info!("Copied {name} to the clipboard"),
+                Err(e) => error!("Clipboard copy of {name} failed: {e}"),
+            }
+        });
+    }
 
-            let img = image::DynamicImage::ImageRgba8(rgba);
-
-            let mut save_to_cache = false;
-            let result = match op.kind {
-                EditOpKind::RotateCW => {
-                    save_to_cache = true;
-                    image::DynamicImage::ImageRgba8(image::imageops::rotate90(&img.to_rgba8()))
-                }
-                EditOpKind::RotateCCW => {
-                    save_to_cache = true;
-                    image::DynamicImage::ImageRgba8(image::imageops::rotate270(&img.to_rgba8()))
-                }
-                EditOpKind::FlipH => {
-                    save_to_cache = true;
-                    image::DynamicImage::ImageRgba8(image::imageops::flip_horizontal(
-                        &img.to_rgba8(),
-                    ))
+    /// Show the image on the clipboard in the full view. It stays out of the collection until
+    /// [`Self::handle_save_pasted_image`] writes it to the folder.
+    fn handle_paste_image(&self) {
+        let clipboard = self.clipboard.clone();
+        let pasted = self.pasted.clone();
+        let weak = self.window_weak.clone();
+        self.loader.pool.spawn(move || {
+            let image = match with_clipboard(&clipboard, |c| c.get_image()) {
+                Ok(image) => image,
+                Err(arboard::Error::ContentNotAvailable) => {
+                    info!("Paste: no image on the clipboard");
+                    return;
                 }
-                EditOpKind::FlipV => {
-                    save_to_cache = true;
-                    image::DynamicImage::ImageRgba8(image::imageops::flip_vertical(&img.to_rgba8()))
+                Err(e) => {
+                    error!("Clipboard paste failed: {e}");
+                    return;
                 }
-                EditOpKind::Brighten => img.brighten(op.int_val),
-                EditOpKind::Contrast => img.adjust_contrast(op.float_val),
-                EditOpKind::Crop => {
-                    save_to_cache = true;
-                    img.crop_imm(
+            };
+            let (width, height) = (image.width as u32, image.height as u32);
+            let buffer = SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+                bytemuck::cast_slice(&image.bytes),
+                width,
+                height,
+            );
+            *pasted.lock().unwrap() = Some(buffer.clone());
+            info!("Pasted a {width}x{height} image from the clipboard");
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                let fv = ui.global::<FullViewState>();
+                fv.set_curr_image(Image::from_rgba8(buffer));
+                fv.set_mask_overlay(Image::default());
+                fv.set_curr_image_name(format!("Pasted image {width}x{height}").into());
+                fv.set_curr_image_hdr(false);
+                fv.set_curr_pasted(true);
+                ui.set_view_mode(ViewMode::Full);
+                ui.invoke_reset_zoom();
+            });
+        });
+    }
+
+    /// Write the pasted image as a PNG to the current folder, where it joins the collection.
+    fn handle_save_pasted_image(&self) {
+        let Some(buffer) = self.pasted.lock().unwrap().clone() else {
+            return;
+        };
+        let name = format!(
+            "pasted-{}.png",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let path = self.scan.dir.join(name);
+        let weak = self.window_weak.clone();
+        self.loader.pool.spawn(move || {
+            let bytes: Vec<u8> = bytemuck::cast_slice(buffer.as_slice()).to_vec();
+            let saved = image::RgbaImage::from_raw(buffer.width(), buffer.height(), bytes)
+                .expect("pixel buffer matches its size")
+                .save(&path);
+            if let Err(e) = saved {
+                error!("Failed to save pasted image to {:?}: {}", path, e);
+                return;
+            }
+            info!("Saved pasted image to {:?}", path);
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                ui.global::<FullViewState>()
+                    .invoke_image_arrived(path.to_string_lossy().to_string().into(), true);
+            });
+        });
+    }
+
+    /// Crop the shown image to `rect` (image pixels) and write it over the original or next
+    /// to it as a copy, then show the result.
+    fn handle_apply_crop(&self, rect: ImgRect, overwrite: bool) {
+        let Some(buffer) = self.loader.get_curr_active_buffer() else {
+            return;
+        };
+        let idx = self.loader.active_idx.load(Ordering::Relaxed);
+        let Some(path) = self.loader.get_path(idx) else {
+            return;
+        };
+        let (x, y) = (rect.x.max(0.0) as u32, rect.y.max(0.0) as u32);
+        let w = (rect.w.max(0.0) as u32).min(buffer.width().saturating_sub(x));
+        let h = (rect.h.max(0.0) as u32).min(buffer.height().saturating_sub(y));
+        if w == 0 || h == 0 {
+            return;
+        }
+        let plugin_manager = self.loader.plugin_manager.clone();
+        let quality = self.jpeg_quality;
+        let weak = self.window_weak.clone();
+        self.loader.pool.spawn(move || {
+            let bytes: Vec<u8> = bytemuck::cast_slice(buffer.as_slice()).to_vec();
+            let img = image::RgbaImage::from_raw(buffer.width(), buffer.height(), bytes)
+                .expect("pixel buffer matches its size");
+            let cropped = image::DynamicImage::ImageRgba8(
+                image::imageops::crop_imm(&img, x, y, w, h).to_image(),
+            );
+            let dst = if overwrite {
+                path.clone()
+            } else {
+                image_processing::crop_copy_path(&path)
+            };
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // Encode next to the destination and rename, so a failed write keeps the original
+            let tmp = dst.with_file_name(format!(
+                ".{}.crop-tmp.{ext}",
+                dst.file_stem().unwrap_or_default().to_string_lossy()
+            ));
+            let written = image_processing::encode(&cropped, &tmp, &ext, quality, &plugin_manager)
+                .and_then(|()| Ok(std::fs::rename(&tmp, &dst)?));
+            if let Err(e) = written {
+                error!("Failed to write cropped image to {:?}: {}", dst, e);
+                let _ = std::fs::remove_file(&tmp);
+                return;
+            }
+            info!(
+                "Cropped {:?} to {w}x{h} at {x},{y}, saved to {:?}",
+                path, dst
+            );
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                ui.global::<FullViewState>()
+                    .invoke_image_arrived(dst.to_string_lossy().to_string().into(), !overwrite);
+            });
+        });
+    }
+
+    fn handle_edit_op(&mut self, op: EditOp) {
+        let Some(buffer) = self.loader.get_curr_active_buffer() else {
+            return;
+        };
+
+        if let EditOpKind::Copy = op.kind {
+            self.handle_copy_image();
+            return;
+        }
+        let loader = self.loader.clone();
+        let before_idx = loader.active_idx.load(Ordering::Relaxed);
+        if let EditOpKind::Delete = op.kind {
+            if let Some(p) = loader.get_path(before_idx) {
+                let _ = trash::delete(&p);
+                // Its edits go to the trash with it
+                let edits = sidecar::path_for(&p);
+                if edits.exists() {
+                    let _ = trash::delete(&edits);
+                }
+            }
+            self.remove_from_view(before_idx);
+            return;
+        }
+
+        let weak = self.window_weak.clone();
+        let selection = weak
+            .upgrade()
+            .map(|window| window.global::<FullViewState>().get_selection())
+            .unwrap_or_default();
+
+        let recorded = match op.kind {
+            EditOpKind::RotateCW => Some(sidecar::Op::RotateCw),
+            EditOpKind::RotateCCW => Some(sidecar::Op::RotateCcw),
+            EditOpKind::FlipH => Some(sidecar::Op::FlipH),
+            EditOpKind::FlipV => Some(sidecar::Op::FlipV),
+            EditOpKind::Crop => {
+                // Clamped like `crop_imm` does
+                let x = (selection.x.max(0.0) as u32).min(buffer.width());
+                let y = (selection.y.max(0.0) as u32).min(buffer.height());
+                Some(sidecar::Op::Crop {
+                    x,
+                    y,
+                    w: (selection.w.max(0.0) as u32).min(buffer.width() - x),
+                    h: (selection.h.max(0.0) as u32).min(buffer.height() - y),
+                })
+            }
+            _ => None,
+        };
+        match recorded {
+            Some(op) => self.edit_sidecar(|s| s.ops.push(op)),
+            None if op.kind == EditOpKind::Reset => self.clear_edits(),
+            None => {}
+        }
+
+        self.loader.pool.spawn(move || {
+            let bytes: &[u8] = bytemuck::cast_slice(buffer.as_slice());
+            let Some(rgba) =
+                image::RgbaImage::from_raw(buffer.width(), buffer.height(), bytes.to_vec())
+            else {
+                return;
+            };
+
+            let img = image::DynamicImage::ImageRgba8(rgba);
+
+            let mut save_to_cache = false;
+            let result = match op.kind {
+                EditOpKind::RotateCW => {
+                    save_to_cache = true;
+                    image::DynamicImage::ImageRgba8(image::imageops::rotate90(&img.to_rgba8()))
+                }
+                EditOpKind::RotateCCW => {
+                    save_to_cache = true;
+                    image::DynamicImage::ImageRgba8(image::imageops::rotate270(&img.to_rgba8()))
+                }
+                EditOpKind::FlipH => {
+                    save_to_cache = true;
+                    image::DynamicImage::ImageRgba8(image::imageops::flip_horizontal(
+                        &img.to_rgba8(),
+                    ))
+                }
+                EditOpKind::FlipV => {
+                    save_to_cache = true;
+                    image::DynamicImage::ImageRgba8(image::imageops::flip_vertical(&img.to_rgba8()))
+                }
+                EditOpKind::Brighten => img.brighten(op.int_val),
+                EditOpKind::Contrast => img.adjust_contrast(op.float_val),
+                EditOpKind::Crop => {
+                    save_to_cache = true;
+                    img.crop_imm(
                         selection.x as u32,
                         selection.y as u32,
                         selection.w as u32,
@@ -415,31 +1252,8 @@ impl AppController {
                     loader.load_full_progressive(before_idx, true);
                     return;
                 }
-                EditOpKind::Copy => {
-                    match arboard::Clipboard::new() {
-                        Ok(mut clipboard) => {
-                            let image_data = arboard::ImageData {
-                                width: buffer.width() as usize,
-                                height: buffer.height() as usize,
-                                bytes: std::borrow::Cow::Borrowed(bytemuck::cast_slice(
-                                    buffer.as_slice(),
-                                )),
-                            };
-                            if let Err(e) = clipboard.set_image(image_data) {
-                                error!("Clipboard copy failed: {e}");
-                            } else {
-                                debug!(
-                                    "Clipboard copy of {:?} successful",
-                                    loader.get_file_name(before_idx)
-                                );
-                            }
-                        }
-                        Err(e) => error!("Could not initialize clipboard: {e}"),
-                    }
-                    return;
-                }
-                EditOpKind::Delete => {
-                    unreachable!("Delete should have been handled already");
+                EditOpKind::Copy | EditOpKind::Delete => {
+                    unreachable!("{:?} should have been handled already", op.kind);
                 }
                 // TODO: Some edit are not saved, implement a proper save
                 EditOpKind::Save => {
@@ -475,132 +1289,1707 @@ impl AppController {
         });
     }
 
-    fn handle_bucket_resolution(&mut self, resolution: u32) {
-        self.loader.set_bucket_resolution(resolution);
-        self.active_grid_indices.clear();
-    }
-
-    fn handle_search(&mut self, query: String) {
-        let start = std::time::Instant::now();
-        let query = query.to_lowercase();
-
-        // First pass by file name
-        self.filtered_indices = self
-            .scan
-            .paths
-            .iter()
-            .enumerate()
-            .filter(|(_, path)| {
-                query.is_empty()
-                    || path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .map(|s| s.to_lowercase().contains(&query))
-                        .unwrap_or(false)
-            })
-            .map(|(idx, _)| idx)
-            .collect();
-
-        // Second pass with plugins
-        if !query.is_empty() {
-            // TODO: set deadline for plugin(s) search
-            for search_plugin in self.loader.plugin_manager.get_search_plugins() {
-                debug!("Available search plugin: {}", search_plugin.id);
-                if !search_plugin.is_running() {
-                    warn!(
-                        "Search plugin {} is registered but not running.",
-                        search_plugin.id
-                    );
-                } else {
-                    if let Some(semantic_search_paths) =
-                        search_plugin.semantic_image_search(&self.scan.paths, &query)
-                    {
-                        debug!("semantic image search paths: {:?}", semantic_search_paths);
-                        let semantic_indices: Vec<usize> = semantic_search_paths
-                            .iter()
-                            .filter_map(|p| self.scan.paths.iter().position(|sp| sp == p))
-                            .collect();
+    /// Shift the exposure of HDR sources by `delta` stops and re-render the current image.
+    fn handle_adjust_exposure(&self, delta: f32) {
+        let idx = self.loader.active_idx.load(Ordering::Relaxed);
+        if !self
+            .loader
+            .get_path(idx)
+            .is_some_and(|p| tone_map::is_hdr_path(&p))
+        {
+            return;
+        }
+        let mut tm = self.loader.tone_map();
+        tm.exposure = (tm.exposure + delta).clamp(-MAX_EXPOSURE, MAX_EXPOSURE);
+        self.loader.set_tone_map(tm);
+        debug!("HDR exposure {:+.1} EV", tm.exposure);
 
-                        let mut combined = self.filtered_indices.clone();
-                        for idx in semantic_indices {
-                            if !combined.contains(&idx) {
-                                combined.push(idx);
-                            }
-                        }
-                        self.filtered_indices = combined;
-                    }
-                }
-            }
+        // Cached neighbours were mapped with the old exposure
+        self.loader.evict_all();
+        self.loader.load_full_progressive(idx, true);
+        if let Some(ui) = self.window_weak.upgrade() {
+            ui.global::<FullViewState>().set_exposure(tm.exposure);
         }
+    }
 
-        self.active_grid_indices.clear();
-        self.loader.clear_thumbs();
+    /// Decode the picked reference on the pool, so RAW, HEIF and plugin formats work too.
+    fn handle_pick_reference(&self) {
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = self
+            .loader
+            .get_curr_img_path()
+            .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+        {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(path) = dialog.pick_file() else {
+            return;
+        };
+        let loader = self.loader.clone();
+        let reference = self.reference.clone();
+        let frames = self.frames.clone();
+        let weak = self.window_weak.clone();
+        self.loader.pool.spawn(move || {
+            let Some(buffer) = loader.load_file_blocking(&path) else {
+                error!("Failed to open reference {}", path.display());
+                return;
+            };
+            info!("Overlaying reference {}", path.display());
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                *reference.lock().unwrap() = Some(buffer);
+                // Composites of the previous reference
+                frames.lock().unwrap().clear();
+                let fv = ui.global::<FullViewState>();
+                fv.set_reference_loaded(true);
+                fv.invoke_update_reference();
+            });
+        });
+    }
 
-        debug!("query=\"{query}\" filtered={}", self.filtered_indices.len());
+    fn handle_clear_reference(&mut self) {
+        *self.reference.lock().unwrap() = None;
+        if let Some(ui) = self.window_weak.upgrade() {
+            let fv = ui.global::<FullViewState>();
+            fv.set_reference_loaded(false);
+            fv.set_reference_composite(Image::default());
+        }
+        self.handle_update_display_filter();
+    }
 
+    /// Recomposite the reference over the displayed image with the current overlay settings.
+    fn handle_update_reference(&self) {
+        let Some(reference) = self.reference.lock().unwrap().clone() else {
+            return;
+        };
         let Some(ui) = self.window_weak.upgrade() else {
             return;
         };
-
-        let filtered_items: Vec<GridItem> = self
-            .filtered_indices
-            .iter()
-            .enumerate()
-            .map(|(row, _)| GridItem {
-                image: Image::default(),
-                index: row as i32,
-                abs_index: self.filtered_indices[row] as i32,
-                selected: false,
-            })
-            .collect();
-
-        let gv = ui.global::<GridViewState>();
-        gv.set_selected_count(0);
-        gv.set_model(Rc::new(VecModel::from(filtered_items)).into());
-
-        if let Some(&first) = self.filtered_indices.first() {
-            self.handle_full_view_load(first);
-        }
-        self.handle_grid_request(0, 50);
-        let weak_ui = self.window_weak.clone();
-        let _ = slint::invoke_from_event_loop(move || {
-            if let Some(ui) = weak_ui.upgrade() {
-                ui.invoke_return_focus();
-            }
+        let fv = ui.global::<FullViewState>();
+        let Some(base) = fv.get_curr_image().to_rgba8() else {
+            return;
+        };
+        let mode = overlay::BlendMode::from_name(&fv.get_reference_blend()).unwrap_or_default();
+        let (opacity, dx, dy) = (
+            fv.get_reference_opacity(),
+            fv.get_reference_offset_x(),
+            fv.get_reference_offset_y(),
+        );
+        let settings = format!("reference {mode:?} {opacity} {dx} {dy}");
+        let cached = self.frames.lock().unwrap().get(&base, &settings);
+        let composite = cached.unwrap_or_else(|| {
+            let start = std::time::Instant::now();
+            let composite = overlay::blend(&base, &reference, mode, opacity, dx, dy);
+            debug!(
+                "Reference composite ({mode:?}) took {:.2}ms",
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+            self.frames
+                .lock()
+                .unwrap()
+                .insert(base, settings, composite.clone());
+            composite
         });
-        debug!("Search in {}ms", start.elapsed().as_secs_f64() * 1000.0);
+        fv.set_reference_composite(Image::from_rgba8(composite));
+        self.handle_update_display_filter();
     }
 
-    fn handle_toggle_selection(&self, index: i32) {
+    /// Redraw the false color view of the current image, decoding its source values first when
+    /// they aren't loaded yet.
+    fn handle_update_false_color(&self) {
         let Some(ui) = self.window_weak.upgrade() else {
             return;
         };
-        let gv = ui.global::<GridViewState>();
-        let model = gv.get_model();
-        let row = index as usize;
-
-        let Some(mut item) = model.row_data(row) else {
+        let fv = ui.global::<FullViewState>();
+        if !fv.get_false_color_active() {
+            fv.set_false_color_image(Image::default());
+            return;
+        }
+        let index = fv.get_curr_image_index() as usize;
+        let samples = self
+            .false_color_samples
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|(i, _)| *i == index)
+            .map(|(_, s)| s.clone());
+        let Some(samples) = samples else {
+            self.load_false_color_samples(index);
             return;
         };
-        item.selected = !item.selected;
-        model.set_row_data(row, item.clone());
-
-        gv.set_selected_count(gv.get_selected_count() + if item.selected { 1 } else { -1 });
+        let start = std::time::Instant::now();
+        let palette =
+            false_color::Palette::from_name(&fv.get_false_color_palette()).unwrap_or_default();
+        let image = false_color::apply(
+            &samples,
+            palette,
+            fv.get_false_color_min(),
+            fv.get_false_color_max(),
+        );
+        fv.set_false_color_image(Image::from_rgba8(image));
+        debug!(
+            "False color ({palette:?}) took {:.2}ms",
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+    }
 
-        let vm = gv.get_visible_model();
-        for i in 0..vm.row_count() {
-            if let Some(mut v) = vm.row_data(i) {
-                if v.index == item.index {
-                    v.selected = item.selected;
-                    vm.set_row_data(i, v);
-                    break;
-                }
-            }
+    /// Decode the values of image `index` for the false color view on a background thread, then
+    /// redraw it.
+    fn load_false_color_samples(&self, index: usize) {
+        let Some(path) = self.scan.paths.get(index).cloned() else {
+            return;
+        };
+        if self.false_color_pending.lock().unwrap().replace(index) == Some(index) {
+            return;
         }
+        let pending = self.false_color_pending.clone();
+        // Lets the next request for `index` try again
+        let done = move || {
+            let mut pending = pending.lock().unwrap();
+            if *pending == Some(index) {
+                *pending = None;
+            }
+        };
+        let slot = self.false_color_samples.clone();
+        let loader = self.loader.clone();
+        let weak = self.window_weak.clone();
+        std::thread::Builder::new()
+            .name("false-color".into())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let (samples, window) = match image::open(&path) {
+                    Ok(img) => (false_color::Samples::from_image(&img), None),
+                    Err(e) => {
+                        // Plugin formats are only available as display pixels, their decoder may
+                        // know which part of them to show, e.g. the window of a DICOM file
+                        debug!("False color of {} from display pixels: {e}", path.display());
+                        let Some(buffer) = loader.load_full_blocking(index) else {
+                            let _ = weak.upgrade_in_event_loop(move |_| done());
+                            return;
+                        };
+                        let window = loader.plugin_manager.default_window(&path);
+                        (false_color::Samples::from_buffer(&buffer), window)
+                    }
+                };
+                let (min, max) = (samples.min, samples.max);
+                debug!(
+                    "False color samples of {} ({min}..{max}) in {:.2}ms",
+                    path.display(),
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+                *slot.lock().unwrap() = Some((index, Arc::new(samples)));
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    done();
+                    let fv = ui.global::<FullViewState>();
+                    fv.set_false_color_range_min(min);
+                    fv.set_false_color_range_max(max);
+                    // Keep the window while it fits the new image, otherwise show its whole range
+                    let low = fv.get_false_color_min().max(min);
+                    let high = fv.get_false_color_max().min(max);
+                    let (low, high) = match window {
+                        Some(window) => window,
+                        None if low < high => (low, high),
+                        None => (min, max),
+                    };
+                    fv.set_false_color_min(low);
+                    fv.set_false_color_max(high);
+                    fv.invoke_update_false_color();
+                });
+            })
+            .expect("Failed to spawn false color thread");
     }
 
-    fn handle_segmentation(
-        &self,
+    /// Recompute the adjusted view of the current image on the decode pool, or clear it when the
+    /// adjustments are neutral.
+    fn handle_update_adjustments(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let generation = self.adjust_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let adj = Self::panel_adjustments(&fv);
+        // Another image must not be drawn over until its own result arrives
+        let index = fv.get_curr_image_index() as usize;
+        if adj.is_identity() || self.adjusted_index.replace(Some(index)) != Some(index) {
+            fv.set_adjusted_image(Image::default());
+        }
+        if adj.is_identity() {
+            self.handle_update_display_filter();
+            return;
+        }
+        let Some(base) = fv.get_curr_image().to_rgba8() else {
+            return;
+        };
+        let settings = format!("adjust {adj:?}");
+        if let Some(adjusted) = self.frames.lock().unwrap().get(&base, &settings) {
+            fv.set_adjusted_image(Image::from_rgba8(adjusted));
+            self.handle_update_display_filter();
+            return;
+        }
+        let latest = self.adjust_generation.clone();
+        let frames = self.frames.clone();
+        let weak = self.window_weak.clone();
+        self.loader.pool.spawn(move || {
+            if latest.load(Ordering::Relaxed) != generation {
+                return;
+            }
+            let start = std::time::Instant::now();
+            let adjusted = adjust::apply(&base, adj);
+            debug!(
+                "Adjustments took {:.2}ms",
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+            frames
+                .lock()
+                .unwrap()
+                .insert(base, settings, adjusted.clone());
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                if latest.load(Ordering::Relaxed) != generation {
+                    return;
+                }
+                let fv = ui.global::<FullViewState>();
+                fv.set_adjusted_image(Image::from_rgba8(adjusted));
+                fv.invoke_update_display_filter();
+            });
+        });
+    }
+
+    /// Change the sidecar of the current image with `f`, see [`sidecar`]. Pasted images have none.
+    fn edit_sidecar(&self, f: impl FnOnce(&mut sidecar::Sidecar)) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        if fv.get_curr_pasted() {
+            return;
+        }
+        let Some(path) = self.loader.get_curr_img_path() else {
+            return;
+        };
+        let mut edits = sidecar::Sidecar::load(&path);
+        f(&mut edits);
+        match edits.save(&path) {
+            Ok(()) => fv.set_has_edits(!edits.is_empty()),
+            Err(e) => self
+                .toaster
+                .error(format!("Edits of {} not saved: {e}", file_name(&path))),
+        }
+    }
+
+    /// Drop the sidecar of the current image and neutralize the Adjust panel, the caller reloads
+    /// the original.
+    fn clear_edits(&self) {
+        self.edit_sidecar(|s| *s = sidecar::Sidecar::default());
+        if let Some(ui) = self.window_weak.upgrade() {
+            Self::show_adjustments(
+                &ui.global::<FullViewState>(),
+                adjust::Adjustments::default(),
+            );
+        }
+        self.handle_update_adjustments();
+    }
+
+    /// Adjust panel values and edit flag of the image at `index`, from its sidecar.
+    fn restore_edits(&self, fv: &FullViewState, index: usize) {
+        let edits = self
+            .loader
+            .get_path(index)
+            .map(|p| sidecar::Sidecar::load(&p))
+            .unwrap_or_default();
+        fv.set_has_edits(!edits.is_empty());
+        let adj = edits.adjustments.unwrap_or_default();
+        if adj != Self::panel_adjustments(fv) {
+            Self::show_adjustments(fv, adj);
+            self.handle_update_adjustments();
+        }
+    }
+
+    fn panel_adjustments(fv: &FullViewState) -> adjust::Adjustments {
+        adjust::Adjustments {
+            brightness: fv.get_adjust_brightness(),
+            contrast: fv.get_adjust_contrast(),
+            saturation: fv.get_adjust_saturation(),
+            gamma: fv.get_adjust_gamma(),
+        }
+    }
+
+    fn show_adjustments(fv: &FullViewState, adj: adjust::Adjustments) {
+        fv.set_adjust_brightness(adj.brightness);
+        fv.set_adjust_contrast(adj.contrast);
+        fv.set_adjust_saturation(adj.saturation);
+        fv.set_adjust_gamma(adj.gamma);
+    }
+
+    fn handle_save_adjustments(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let adj = Self::panel_adjustments(&ui.global::<FullViewState>());
+        self.edit_sidecar(|s| s.adjustments = (!adj.is_identity()).then_some(adj));
+    }
+
+    /// Save a copy of the current image with its sidecar edits and adjustments baked in.
+    fn handle_export_flattened(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let Some(path) = self.loader.get_curr_img_path() else {
+            return;
+        };
+        // The loaded image already has the ops applied
+        let buffer = ui
+            .global::<FullViewState>()
+            .get_adjusted_image()
+            .to_rgba8()
+            .or_else(|| self.loader.get_curr_active_buffer());
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "png".to_string());
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let suggested = path.with_file_name(format!("{stem}-edited.{ext}"));
+        image_processing::save_image(
+            buffer,
+            Some(suggested),
+            ext,
+            self.jpeg_quality,
+            self.loader.plugin_manager.clone(),
+            self.watermark.clone(),
+        );
+        ui.invoke_return_focus();
+    }
+
+    /// Save the current image with the adjustments baked in, through the save dialog.
+    fn handle_export_adjusted(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let Some(path) = self.loader.get_curr_img_path() else {
+            return;
+        };
+        let adj = Self::panel_adjustments(&fv);
+        let buffer = if adj.is_identity() {
+            self.loader.get_curr_active_buffer()
+        } else {
+            // The shown result may be of older settings until the new one arrives
+            let adjusted = fv.get_curr_image().to_rgba8().and_then(|base| {
+                self.frames
+                    .lock()
+                    .unwrap()
+                    .get(&base, &format!("adjust {adj:?}"))
+            });
+            if adjusted.is_none() {
+                show_osd(&ui, "Adjustments are still being applied".into());
+                return;
+            }
+            adjusted
+        };
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "png".to_string());
+        image_processing::save_image(
+            buffer,
+            Some(path),
+            ext,
+            self.jpeg_quality,
+            self.loader.plugin_manager.clone(),
+            self.watermark.clone(),
+        );
+        ui.invoke_return_focus();
+    }
+
+    /// Make the current image the desktop wallpaper, the file as it is without view edits.
+    fn handle_set_wallpaper(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        if ui.global::<FullViewState>().get_curr_pasted() {
+            return;
+        }
+        let Some(path) = self.loader.get_curr_img_path() else {
+            return;
+        };
+        let weak = self.window_weak.clone();
+        std::thread::spawn(move || {
+            let message = match wallpaper::set(&path) {
+                Ok(()) => {
+                    info!("Wallpaper set to {:?}", path);
+                    "Set as wallpaper".to_string()
+                }
+                Err(e) => {
+                    error!("Failed to set {:?} as wallpaper: {}", path, e);
+                    format!("Wallpaper not set: {e}")
+                }
+            };
+            let _ = weak.upgrade_in_event_loop(move |ui| show_osd(&ui, message));
+        });
+    }
+
+    /// Re-encode the current image to the name and format picked in a save dialog.
+    fn handle_save_as(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let (Some(path), Some(buffer)) = (
+            self.loader.get_curr_img_path(),
+            self.loader.get_curr_active_buffer(),
+        ) else {
+            return;
+        };
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let mut formats: Vec<_> = self
+            .scan
+            .image_formats
+            .image_formats
+            .iter()
+            .filter(|f| f.encoding_support)
+            .cloned()
+            .collect();
+        // The current format is preselected, the rest by name
+        formats.sort_by_key(|f| (!f.exts.contains(&ext), f.exts.first().cloned()));
+        let dir = path.parent().map(std::path::Path::to_path_buf);
+        let weak = self.window_weak.clone();
+        image_processing::save_as(
+            buffer,
+            &path,
+            formats,
+            self.jpeg_quality,
+            self.loader.plugin_manager.clone(),
+            move |result| {
+                let _ = weak.upgrade_in_event_loop(move |ui| match result {
+                    Ok(dst) => {
+                        let name = dst.file_name().unwrap_or_default().to_string_lossy();
+                        show_osd(&ui, format!("Saved as {name}"));
+                        // Listed in the current folder, the view stays on this image
+                        if dst.parent() == dir.as_deref() {
+                            ui.global::<FullViewState>().invoke_image_arrived(
+                                dst.to_string_lossy().to_string().into(),
+                                false,
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        error!("Save as failed: {}", e);
+                        show_osd(&ui, format!("Not saved: {e}"));
+                    }
+                });
+            },
+        );
+        ui.invoke_return_focus();
+    }
+
+    /// Start a shape at image pixel `x`, `y`. Text needs something typed first.
+    fn handle_markup_begin(
+        &self,
+        tool: &str,
+        x: f32,
+        y: f32,
+        color: slint::Color,
+        width: f32,
+        text: &str,
+    ) {
+        let Some(tool) = markup::Tool::from_name(tool) else {
+            warn!("Unknown markup tool '{tool}'");
+            return;
+        };
+        if tool == markup::Tool::Text && text.trim().is_empty() {
+            return;
+        }
+        let color = Rgba8Pixel {
+            r: color.red(),
+            g: color.green(),
+            b: color.blue(),
+            a: color.alpha(),
+        };
+        self.markup
+            .borrow_mut()
+            .push(markup::Shape::new(tool, (x, y), color, width, text));
+        self.markup_drawing.set(true);
+        self.sync_markup();
+    }
+
+    fn handle_markup_extend(&self, x: f32, y: f32) {
+        if !self.markup_drawing.get() {
+            return;
+        }
+        if let Some(shape) = self.markup.borrow_mut().last_mut() {
+            shape.extend((x, y));
+        }
+        self.sync_markup();
+    }
+
+    /// Finish the shape being drawn, dropped if it came out empty.
+    fn handle_markup_end(&self) {
+        if !self.markup_drawing.replace(false) {
+            return;
+        }
+        let mut shapes = self.markup.borrow_mut();
+        if shapes.last().is_some_and(|s| s.is_empty()) {
+            shapes.pop();
+        }
+        drop(shapes);
+        self.sync_markup();
+    }
+
+    fn handle_markup_undo(&self) {
+        self.markup_drawing.set(false);
+        self.markup.borrow_mut().pop();
+        self.sync_markup();
+    }
+
+    fn handle_markup_clear(&self) {
+        self.markup_drawing.set(false);
+        if std::mem::take(&mut *self.markup.borrow_mut()).is_empty() {
+            return;
+        }
+        self.sync_markup();
+    }
+
+    /// Push the shapes to the overlay.
+    fn sync_markup(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let shapes: Vec<MarkupShape> = self
+            .markup
+            .borrow()
+            .iter()
+            .map(|s| MarkupShape {
+                commands: s.path_commands().into(),
+                text: if s.tool == markup::Tool::Text {
+                    s.text.as_str().into()
+                } else {
+                    Default::default()
+                },
+                x: s.points[0].0,
+                y: s.points[0].1,
+                color: slint::Color::from_argb_u8(s.color.a, s.color.r, s.color.g, s.color.b),
+                width: s.width,
+            })
+            .collect();
+        ui.global::<FullViewState>()
+            .set_markup_shapes(Rc::new(VecModel::from(shapes)).into());
+    }
+
+    /// Save a copy of the current image with the annotations drawn in, through the save dialog.
+    /// The original and the overlay are left as they are.
+    fn handle_export_markup(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let Some(path) = self.loader.get_curr_img_path() else {
+            return;
+        };
+        let Some(mut buffer) = fv
+            .get_adjusted_image()
+            .to_rgba8()
+            .or_else(|| self.loader.get_curr_active_buffer())
+        else {
+            error!("No image to export markup for");
+            return;
+        };
+        markup::flatten(&mut buffer, &self.markup.borrow());
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "png".to_string());
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let suggested = path.with_file_name(format!("{stem}-markup.{ext}"));
+        image_processing::save_image(
+            Some(buffer),
+            Some(suggested),
+            ext,
+            self.jpeg_quality,
+            self.loader.plugin_manager.clone(),
+            self.watermark.clone(),
+        );
+        ui.invoke_return_focus();
+    }
+
+    /// Step to the next single channel view, see [`display_filter::DisplayFilter::next_channel`].
+    fn handle_cycle_channel_view(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let filter = display_filter::DisplayFilter::from_name(&fv.get_display_filter())
+            .unwrap_or_default()
+            .next_channel();
+        debug!("Channel view: {filter:?}");
+        fv.set_display_filter(filter.name().into());
+        self.handle_update_display_filter();
+    }
+
+    /// Refilter what is shown, the reference composite when there is one, for the active display
+    /// filter.
+    fn handle_update_display_filter(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let filter =
+            display_filter::DisplayFilter::from_name(&fv.get_display_filter()).unwrap_or_default();
+        if filter == display_filter::DisplayFilter::None {
+            fv.set_filtered_image(Image::default());
+            return;
+        }
+        let adjusted = fv.get_adjusted_image();
+        let shown = if fv.get_reference_loaded() {
+            fv.get_reference_composite()
+        } else if adjusted.size().width > 0 {
+            adjusted
+        } else {
+            fv.get_curr_image()
+        };
+        let Some(base) = shown.to_rgba8() else {
+            return;
+        };
+        let settings = format!("filter {filter:?}");
+        let cached = self.frames.lock().unwrap().get(&base, &settings);
+        let filtered = cached.unwrap_or_else(|| {
+            let start = std::time::Instant::now();
+            let filtered = display_filter::apply(&base, filter);
+            debug!(
+                "Display filter ({filter:?}) took {:.2}ms",
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+            self.frames
+                .lock()
+                .unwrap()
+                .insert(base, settings, filtered.clone());
+            filtered
+        });
+        fv.set_filtered_image(Image::from_rgba8(filtered));
+    }
+
+    /// Show the color of the displayed image at `(x, y)` in the pixel inspector.
+    fn handle_inspect_pixel(&self, x: i32, y: i32) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        // Shares the pixels with the displayed image, no copy
+        let Some(buffer) = fv.get_curr_image().to_rgba8() else {
+            return;
+        };
+        let (w, h) = (buffer.width() as i32, buffer.height() as i32);
+        // The right and bottom edges map to one past the last pixel
+        let (x, y) = (x.min(w - 1), y.min(h - 1));
+        if x < 0 || y < 0 {
+            fv.set_inspector_hex("".into());
+            return;
+        }
+        let p = buffer.as_slice()[(y * w + x) as usize];
+        fv.set_inspector_color(slint::Color::from_argb_u8(p.a, p.r, p.g, p.b));
+        fv.set_inspector_rgba(format!("RGBA {} {} {} {}", p.r, p.g, p.b, p.a).into());
+        fv.set_inspector_hex(pixel_hex(p).into());
+    }
+
+    fn handle_purge_caches(&mut self, include_disk: bool) {
+        self.loader.purge_memory_caches();
+        self.frames.lock().unwrap().clear();
+        if include_disk {
+            self.loader.clear_disk_cache();
+        }
+        // Rows on screen get their thumbnails again on the next grid request
+        self.active_grid_indices.clear();
+    }
+
+    fn handle_bucket_resolution(&mut self, resolution: u32) {
+        self.loader.set_bucket_resolution(resolution);
+        self.active_grid_indices.clear();
+    }
+
+    fn handle_search(&mut self, query: String) {
+        let start = std::time::Instant::now();
+        self.query = query.clone();
+        let query = query.to_lowercase();
+
+        // First pass by file name
+        self.filtered_indices = self.indices_matching_name(&query);
+        self.order_filtered();
+
+        // Second pass by keywords from tagger plugins
+        if !query.is_empty() {
+            for path in self.library.search_keyword(&query) {
+                if let Some(idx) = self.scan.paths.iter().position(|p| *p == path)
+                    && !self.filtered_indices.contains(&idx)
+                {
+                    self.filtered_indices.push(idx);
+                }
+            }
+        }
+
+        // Third pass with plugins
+        if !query.is_empty() {
+            // TODO: set deadline for plugin(s) search
+            for search_plugin in self.loader.plugin_manager.get_search_plugins() {
+                debug!("Available search plugin: {}", search_plugin.id);
+                if !search_plugin.is_running() {
+                    warn!(
+                        "Search plugin {} is registered but not running.",
+                        search_plugin.id
+                    );
+                } else {
+                    if let Some(semantic_search_paths) =
+                        search_plugin.semantic_image_search(&self.scan.paths, &query)
+                    {
+                        debug!("semantic image search paths: {:?}", semantic_search_paths);
+                        let semantic_indices: Vec<usize> = semantic_search_paths
+                            .iter()
+                            .filter_map(|p| self.scan.paths.iter().position(|sp| sp == p))
+                            .collect();
+
+                        let mut combined = self.filtered_indices.clone();
+                        for idx in semantic_indices {
+                            if !combined.contains(&idx) {
+                                combined.push(idx);
+                            }
+                        }
+                        self.filtered_indices = combined;
+                    }
+                }
+            }
+        }
+
+        self.retain_culled();
+        self.active_grid_indices.clear();
+        self.loader.clear_thumbs();
+
+        debug!("query=\"{query}\" filtered={}", self.filtered_indices.len());
+
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+
+        let gv = ui.global::<GridViewState>();
+        gv.set_selected_count(0);
+        gv.set_model(self.grid_model());
+
+        if let Some(&first) = self.filtered_indices.first() {
+            self.handle_full_view_load(first);
+        }
+        self.handle_grid_request(0, 50);
+        let weak_ui = self.window_weak.clone();
+        let _ = slint::invoke_from_event_loop(move || {
+            if let Some(ui) = weak_ui.upgrade() {
+                ui.invoke_return_focus();
+            }
+        });
+        debug!("Search in {}ms", start.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    /// Narrow the grid to file names matching `filter` while it is being typed, without the
+    /// keyword and plugin passes of a submitted search.
+    fn handle_filter_names(&mut self, filter: String) {
+        let start = std::time::Instant::now();
+        let indices = self.indices_matching_name(&filter);
+        self.query = filter;
+        self.filtered_indices = indices;
+        self.retain_culled();
+        self.order_filtered();
+        let indices = std::mem::take(&mut self.filtered_indices);
+        debug!("filter=\"{}\" filtered={}", self.query, indices.len());
+        self.show_collection(indices);
+        debug!(
+            "Name filter in {:.2}ms",
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+    }
+
+    fn rating(&self, abs_index: usize) -> i32 {
+        self.ratings.get(abs_index).copied().unwrap_or(0) as i32
+    }
+
+    fn marks(&self, abs_index: usize) -> Marks {
+        self.marks.get(abs_index).copied().unwrap_or_default()
+    }
+
+    fn badges(&self, abs_index: usize) -> ModelRc<slint::SharedString> {
+        badges(&self.scan, &self.shot_kinds, abs_index)
+    }
+
+    /// Grid rows for `filtered_indices`, made as the grid scrolls to them.
+    fn grid_model(&self) -> ModelRc<GridItem> {
+        let scan = self.scan.clone();
+        let ratings = self.ratings.clone();
+        let marks = self.marks.clone();
+        let shot_kinds = self.shot_kinds.clone();
+        let model = GridModel::new(self.filtered_indices.clone(), move |row, abs| {
+            let marks = marks.get(abs).copied().unwrap_or_default();
+            GridItem {
+                image: Image::default(),
+                index: row as i32,
+                abs_index: abs as i32,
+                selected: false,
+                name: scan
+                    .paths
+                    .get(abs)
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().as_ref().into())
+                    .unwrap_or_default(),
+                rating: ratings.get(abs).copied().unwrap_or(0) as i32,
+                flag: marks.flag.name().into(),
+                label: marks.label.name().into(),
+                badges: badges(&scan, &shot_kinds, abs),
+            }
+        });
+        Rc::new(model).into()
+    }
+
+    /// Read the shot kinds of `rows` not read yet on a background thread, their badges are
+    /// filled in when done.
+    fn read_badges(&self, rows: std::ops::Range<usize>) {
+        let paths: Vec<(usize, PathBuf)> = {
+            let mut known = self.shot_kinds.lock().unwrap();
+            rows.filter_map(|row| {
+                let abs = *self.filtered_indices.get(row)?;
+                let path = self.scan.paths.get(abs)?;
+                // Claimed here so that scrolling back and forth reads each file once
+                known
+                    .insert(path.clone(), ShotKinds::default())
+                    .is_none()
+                    .then(|| (abs, path.clone()))
+            })
+            .collect()
+        };
+        if paths.is_empty() {
+            return;
+        }
+        let shot_kinds = self.shot_kinds.clone();
+        let weak = self.window_weak.clone();
+        std::thread::spawn(move || {
+            let found: Vec<(usize, String, Vec<&'static str>)> = paths
+                .par_iter()
+                .filter_map(|(abs, path)| {
+                    let kinds = metadata::read_shot_kinds(path);
+                    shot_kinds.lock().unwrap().insert(path.clone(), kinds);
+                    let name = path.file_name()?.to_string_lossy().into_owned();
+                    (kinds != ShotKinds::default()).then(|| (*abs, name, kinds.badges()))
+                })
+                .collect();
+            if found.is_empty() {
+                return;
+            }
+            debug!("{} of {} images have shot badges", found.len(), paths.len());
+            let _ = weak.upgrade_in_event_loop(move |ui| set_badges(&ui, &found));
+        });
+    }
+
+    /// Drop images below the minimum rating or not passing the mark or size filter.
+    fn retain_culled(&mut self) {
+        if self.min_rating == 0
+            && self.mark_filter == MarkFilter::All
+            && self.size_filter == SizeFilter::All
+        {
+            return;
+        }
+        let (ratings, marks) = (&self.ratings, &self.marks);
+        let (min, filter, size) = (self.min_rating, self.mark_filter, self.size_filter);
+        let (paths, sizes) = (&self.scan.paths, &self.sizes);
+        self.filtered_indices.retain(|&i| {
+            ratings.get(i).is_some_and(|&r| r >= min)
+                && marks.get(i).is_some_and(|&m| filter.accepts(m))
+                && (size == SizeFilter::All
+                    || paths
+                        .get(i)
+                        .and_then(|p| sizes.get(p))
+                        .is_some_and(|d| size.accepts(d)))
+        });
+    }
+
+    /// Show only images passing `filter`, a [`MarkFilter`] name, keeping the current search.
+    fn handle_mark_filter(&mut self, filter: &str) {
+        let Some(filter) = MarkFilter::from_name(filter) else {
+            warn!("Unknown mark filter {filter:?}");
+            return;
+        };
+        info!("Mark filter: {filter:?}");
+        self.mark_filter = filter;
+        self.handle_search(self.query.clone());
+    }
+
+    /// Show only images whose dimensions pass `filter`, a [`SizeFilter`] name, keeping the
+    /// current search. Headers of images not looked up yet are read in the background first.
+    fn handle_size_filter(&mut self, filter: &str) {
+        let Some(size_filter) = SizeFilter::from_name(filter) else {
+            warn!("Unknown size filter {filter:?}");
+            return;
+        };
+        let missing = match size_filter {
+            SizeFilter::All => Vec::new(),
+            _ => self.sizes.missing(&self.scan.paths),
+        };
+        if missing.is_empty() {
+            info!("Size filter: {size_filter:?}");
+            self.size_filter = size_filter;
+            self.handle_search(self.query.clone());
+            return;
+        }
+        let sizes = self.sizes.clone();
+        let weak = self.window_weak.clone();
+        let filter = filter.to_string();
+        std::thread::Builder::new()
+            .name("image sizes".to_string())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                sizes.read(&missing);
+                debug!(
+                    "Read the dimensions of {} images in {:.2}ms",
+                    missing.len(),
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let gv = ui.global::<GridViewState>();
+                    // Unless another filter was picked meanwhile
+                    if gv.get_size_filter() == filter.as_str() {
+                        gv.invoke_size_filter_changed(filter.into());
+                    }
+                });
+            })
+            .expect("Failed to spawn image size thread");
+    }
+
+    /// Flag the image shown in the full view.
+    fn handle_set_flag(&mut self, flag: Flag) {
+        self.update_current_marks(|marks| marks.flag = flag);
+    }
+
+    /// Label the image shown in the full view, the label it already has clears it.
+    fn handle_set_color_label(&mut self, label: ColorLabel) {
+        self.update_current_marks(|marks| {
+            marks.label = if marks.label == label {
+                ColorLabel::None
+            } else {
+                label
+            }
+        });
+    }
+
+    fn update_current_marks(&mut self, update: impl FnOnce(&mut Marks)) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let index = fv.get_curr_image_index() as usize;
+        let Some(path) = self.scan.paths.get(index) else {
+            return;
+        };
+        let mut marks = self.marks(index);
+        update(&mut marks);
+        if !self.library.set_marks(path, marks) {
+            return;
+        }
+        debug!("Marked {:?} {marks:?}", path);
+        self.marks[index] = marks;
+        fv.set_curr_flag(marks.flag.name().into());
+        fv.set_curr_label(marks.label.name().into());
+        let model = ui.global::<GridViewState>().get_model();
+        if let Some(row) = self.filtered_indices.iter().position(|&i| i == index)
+            && let Some(mut item) = model.row_data(row)
+        {
+            item.flag = marks.flag.name().into();
+            item.label = marks.label.name().into();
+            model.set_row_data(row, item);
+        }
+    }
+
+    /// Add the image shown in the full view to the favorites, or take it out.
+    fn handle_toggle_favorite(&mut self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let index = fv.get_curr_image_index() as usize;
+        let Some(path) = self.scan.paths.get(index).cloned() else {
+            return;
+        };
+        let Some(favorite) = self.library.toggle_favorite(&path) else {
+            return;
+        };
+        info!(
+            "{} {:?} {} favorites",
+            if favorite { "Added" } else { "Removed" },
+            path,
+            if favorite { "to" } else { "from" }
+        );
+        if favorite {
+            self.favorites.insert(path);
+        } else {
+            self.favorites.remove(&path);
+        }
+        fv.set_curr_favorite(favorite);
+    }
+
+    /// Show the favorites of every folder together in the grid, leaving out deleted files.
+    fn handle_show_favorites(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .library
+            .favorites()
+            .into_iter()
+            .filter(|p| p.is_file())
+            .collect();
+        if paths.is_empty() {
+            info!("No favorites yet");
+            return;
+        }
+        let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+        self.replace_scan(Arc::new(fs_scan::from_paths(
+            paths,
+            std::path::Path::new(FAVORITES_DIR),
+            &extra_exts,
+        )));
+    }
+
+    /// Open the folder of the favorite added last, at that image.
+    fn handle_jump_to_last_bookmark(&mut self) {
+        let favorites = self.library.favorites();
+        let Some(path) = favorites.iter().rev().find(|p| p.is_file()) else {
+            info!("No favorites to jump to");
+            return;
+        };
+        let Some(path) = path.to_str() else {
+            return;
+        };
+        let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+        let scan = fs_scan::scan(path, &extra_exts, self.name_filter.as_ref());
+        if scan.paths.is_empty() {
+            return;
+        }
+        self.replace_scan(Arc::new(scan));
+    }
+
+    /// Write the selected images, or every shown one without a selection, into a zip archive
+    /// picked by the user, scaled down to `max_edge` unless it is 0.
+    fn handle_export_zip(&self, max_edge: u32) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<ZipExportState>();
+        if state.get_running() {
+            warn!("A zip export is already running");
+            return;
+        }
+        let items = self.naming_items(&self.selected_or_shown());
+        if items.is_empty() {
+            return;
+        }
+        let entries: Vec<(PathBuf, String)> = match &self.naming.export {
+            Some(template) => items
+                .iter()
+                .map(|(p, _)| p.clone())
+                .zip(template.names(&items))
+                .collect(),
+            None => items
+                .into_iter()
+                .map(|(p, _)| {
+                    let name = p.file_name().unwrap_or_default().to_string_lossy().into();
+                    (p, name)
+                })
+                .collect(),
+        };
+
+        // Virtual folders like `favorites:` lose their colon
+        let name = self
+            .scan
+            .dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.trim_end_matches(':'))
+            .filter(|n| !n.is_empty())
+            .unwrap_or("images");
+        let mut dialog = rfd::FileDialog::new()
+            .set_file_name(format!("{name}.zip"))
+            .add_filter("Zip archive", &["zip"]);
+        if self.scan.dir.is_dir() {
+            dialog = dialog.set_directory(&self.scan.dir);
+        }
+        let Some(dst) = dialog.save_file() else {
+            debug!("Zip export: user cancelled file picker");
+            return;
+        };
+
+        state.set_archive_name(
+            dst.file_name()
+                .map(|n| n.to_string_lossy().as_ref().into())
+                .unwrap_or_default(),
+        );
+        state.set_done(0);
+        state.set_total(entries.len() as i32);
+        state.set_running(true);
+        self.zip_cancel.store(false, Ordering::Relaxed);
+        let cancel = self.zip_cancel.clone();
+        let weak = self.window_weak.clone();
+        let max_edge = (max_edge > 0).then_some(max_edge);
+        std::thread::Builder::new()
+            .name("zip-export".into())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let progress_weak = weak.clone();
+                let result = zip_export::export(&entries, &dst, max_edge, &cancel, |done, _| {
+                    let _ = progress_weak.upgrade_in_event_loop(move |ui| {
+                        ui.global::<ZipExportState>().set_done(done as i32);
+                    });
+                });
+                match result {
+                    Ok(Some(added)) => info!(
+                        "Exported {added} images to {} in {:.2}s",
+                        dst.display(),
+                        start.elapsed().as_secs_f64()
+                    ),
+                    Ok(None) => info!("Zip export to {} cancelled", dst.display()),
+                    Err(e) => {
+                        error!("Zip export to {} failed: {e}", dst.display());
+                        let _ = std::fs::remove_file(&dst);
+                    }
+                }
+                let _ = weak.upgrade_in_event_loop(|ui| {
+                    ui.global::<ZipExportState>().set_running(false);
+                });
+            })
+            .expect("Failed to spawn zip export thread");
+    }
+
+    fn handle_cancel_zip_export(&self) {
+        info!("Cancelling zip export");
+        self.zip_cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Open the slideshow video dialog for the shown images, timed like the slideshow.
+    fn handle_open_video_export(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<VideoExportState>();
+        if state.get_running() {
+            state.set_open(true);
+            return;
+        }
+        let interval = ui.global::<FullViewState>().get_slideshow_interval();
+        state.set_count(self.filtered_indices.len() as i32);
+        state.set_seconds_per_image(((interval as f32 / 1000.0).round() as i32).clamp(1, 60));
+        state.set_ffmpeg_found(video_export::ffmpeg_available());
+        state.set_error("".into());
+        state.set_open(true);
+    }
+
+    fn handle_pick_video_music(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let Some(music) = rfd::FileDialog::new()
+            .add_filter(
+                "Audio",
+                &["mp3", "m4a", "aac", "ogg", "opus", "flac", "wav"],
+            )
+            .pick_file()
+        else {
+            return;
+        };
+        let name = music.file_name().unwrap_or_default().to_string_lossy();
+        ui.global::<VideoExportState>()
+            .set_music_name(name.as_ref().into());
+        *self.video_music.borrow_mut() = Some(music);
+    }
+
+    fn handle_clear_video_music(&self) {
+        if let Some(ui) = self.window_weak.upgrade() {
+            ui.global::<VideoExportState>().set_music_name("".into());
+        }
+        *self.video_music.borrow_mut() = None;
+    }
+
+    /// Render the shown images into a video at a path picked in a save dialog, with the options
+    /// of the video export dialog.
+    fn handle_export_video(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<VideoExportState>();
+        let Some((width, height)) = state
+            .get_resolution()
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+        else {
+            error!("Invalid video resolution {}", state.get_resolution());
+            return;
+        };
+        let format = video_export::VideoFormat::from_name(&state.get_format())
+            .unwrap_or(video_export::VideoFormat::Mp4);
+        let options = video_export::VideoOptions {
+            width,
+            height,
+            format,
+            seconds_per_image: state.get_seconds_per_image() as f32,
+            fade: if state.get_crossfade() { 1.0 } else { 0.0 },
+            music: self.video_music.borrow().clone(),
+        };
+
+        let name = self
+            .scan
+            .dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.trim_end_matches(':'))
+            .filter(|n| !n.is_empty())
+            .unwrap_or("slideshow");
+        let mut dialog = rfd::FileDialog::new()
+            .set_file_name(format!("{name}.{}", format.ext()))
+            .add_filter(format.ext().to_uppercase(), &[format.ext()]);
+        if self.scan.dir.is_dir() {
+            dialog = dialog.set_directory(&self.scan.dir);
+        }
+        let Some(mut dst) = dialog.save_file() else {
+            debug!("Video export: user cancelled file picker");
+            return;
+        };
+        if dst.extension().is_none() {
+            dst.set_extension(format.ext());
+        }
+
+        let indices = self.filtered_indices.clone();
+        state.set_count(indices.len() as i32);
+        state.set_done(0);
+        state.set_error("".into());
+        state.set_running(true);
+        self.video_cancel.store(false, Ordering::Relaxed);
+        let cancel = self.video_cancel.clone();
+        let loader = self.loader.clone();
+        let weak = self.window_weak.clone();
+        std::thread::Builder::new()
+            .name("video-export".into())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let progress_weak = weak.clone();
+                let load = |i: usize| {
+                    loader
+                        .load_full_blocking(indices[i])
+                        .map(image_processing::to_dynamic)
+                };
+                let result = video_export::export(
+                    indices.len(),
+                    load,
+                    &dst,
+                    &options,
+                    &cancel,
+                    |done, _| {
+                        let _ = progress_weak.upgrade_in_event_loop(move |ui| {
+                            ui.global::<VideoExportState>().set_done(done as i32);
+                        });
+                    },
+                );
+                let error = match result {
+                    Ok(Some(added)) => {
+                        info!(
+                            "Exported {added} images to {} in {:.2}s",
+                            dst.display(),
+                            start.elapsed().as_secs_f64()
+                        );
+                        None
+                    }
+                    Ok(None) => {
+                        info!("Video export to {} cancelled", dst.display());
+                        None
+                    }
+                    Err(e) => {
+                        error!("Video export to {} failed: {e}", dst.display());
+                        Some(e.to_string())
+                    }
+                };
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let state = ui.global::<VideoExportState>();
+                    state.set_running(false);
+                    match error {
+                        // Kept open so the options can be changed and tried again
+                        Some(e) => state.set_error(e.into()),
+                        None => {
+                            state.set_open(false);
+                            let name = dst.file_name().unwrap_or_default().to_string_lossy();
+                            show_osd(&ui, format!("Exported {name}"));
+                        }
+                    }
+                });
+            })
+            .expect("Failed to spawn video export thread");
+    }
+
+    fn handle_cancel_video_export(&self) {
+        info!("Cancelling video export");
+        self.video_cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// The selected images, or every shown one without a selection.
+    fn selected_or_shown(&self) -> Vec<usize> {
+        let selected = self.selected_indices();
+        if selected.is_empty() {
+            self.filtered_indices.clone()
+        } else {
+            selected
+        }
+    }
+
+    /// Paths and ratings of `indices`, what naming templates are evaluated against.
+    fn naming_items(&self, indices: &[usize]) -> Vec<(PathBuf, u8)> {
+        indices
+            .iter()
+            .filter_map(|&i| Some((self.scan.paths.get(i)?.clone(), self.rating(i) as u8)))
+            .collect()
+    }
+
+    /// Open the rename dialog for the selected images, or every shown one, with the template
+    /// used last.
+    fn handle_open_rename(&mut self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        if self.scan.dir.starts_with(demo::DEMO_DIR) {
+            warn!("Demo images can't be renamed");
+            return;
+        }
+        let indices = self.selected_or_shown();
+        if indices.is_empty() {
+            return;
+        }
+        self.rename_indices = indices;
+        let state = ui.global::<RenameState>();
+        state.set_count(self.rename_indices.len() as i32);
+        state.set_template(self.naming.rename.as_str().into());
+        state.set_open(true);
+        ui.set_view_mode(ViewMode::Grid);
+        self.handle_rename_preview(&self.naming.rename);
+    }
+
+    /// Open the metadata dialog for the selected images, or every shown one, filled with the
+    /// tags they share once they are read.
+    fn handle_open_metadata(&mut self) {
+        if self.scan.dir.starts_with(demo::DEMO_DIR) {
+            warn!("Demo images can't be edited");
+            return;
+        }
+        let indices = self.selected_or_shown();
+        if indices.is_empty() {
+            return;
+        }
+        let paths: Vec<PathBuf> = indices
+            .iter()
+            .filter_map(|&i| self.scan.paths.get(i).cloned())
+            .collect();
+        self.metadata_indices = indices;
+        let count = self.metadata_indices.len() as i32;
+        let metadata_shared = self.metadata_shared.clone();
+        let weak = self.window_weak.clone();
+
+        std::thread::Builder::new()
+            .name("metadata read".to_string())
+            .spawn(move || {
+                let tags: Vec<HashMap<TextTag, String>> =
+                    paths.par_iter().map(|p| metadata::read_tags(p)).collect();
+                let shared: HashMap<TextTag, Option<String>> = TextTag::ALL
+                    .into_iter()
+                    .map(|tag| {
+                        let mut values = tags
+                            .iter()
+                            .map(|t| t.get(&tag).cloned().unwrap_or_default());
+                        let first = values.next().unwrap_or_default();
+                        let shared = values.all(|v| v == first).then_some(first);
+                        (tag, shared)
+                    })
+                    .collect();
+                if let Ok(mut metadata_shared) = metadata_shared.lock() {
+                    *metadata_shared = shared.clone();
+                }
+
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let state = ui.global::<MetadataState>();
+                    let shown = |tag| shared[&tag].clone().unwrap_or_default();
+                    state.set_count(count);
+                    state.set_artist(shown(TextTag::Artist).into());
+                    state.set_copyright(shown(TextTag::Copyright).into());
+                    state.set_description(shown(TextTag::Description).into());
+                    state.set_artist_mixed(shared[&TextTag::Artist].is_none());
+                    state.set_copyright_mixed(shared[&TextTag::Copyright].is_none());
+                    state.set_description_mixed(shared[&TextTag::Description].is_none());
+                    state.set_error("".into());
+                    state.set_open(true);
+                    ui.set_view_mode(ViewMode::Grid);
+                });
+            })
+            .expect("Failed to spawn metadata read thread");
+    }
+
+    /// Write the fields of the metadata dialog that were changed. Tags the images disagree on
+    /// are only touched when something was typed, a cleared field removes the tag.
+    fn handle_apply_metadata(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<MetadataState>();
+        let typed = [
+            (TextTag::Artist, state.get_artist()),
+            (TextTag::Copyright, state.get_copyright()),
+            (TextTag::Description, state.get_description()),
+        ];
+        let Ok(shared) = self.metadata_shared.lock() else {
+            return;
+        };
+        let edits: Vec<TagEdit> = typed
+            .into_iter()
+            .filter_map(|(tag, text)| {
+                let text = text.trim();
+                match shared.get(&tag).and_then(Option::as_deref) {
+                    Some(shared) if shared == text => None,
+                    None if text.is_empty() => None,
+                    _ => Some(TagEdit {
+                        tag,
+                        value: (!text.is_empty()).then(|| text.to_string()),
+                    }),
+                }
+            })
+            .collect();
+        drop(shared);
+        if edits.is_empty() {
+            state.set_open(false);
+            return;
+        }
+
+        let paths: Vec<PathBuf> = self
+            .metadata_indices
+            .iter()
+            .filter_map(|&i| self.scan.paths.get(i).cloned())
+            .collect();
+        let weak = self.window_weak.clone();
+
+        std::thread::Builder::new()
+            .name("metadata write".to_string())
+            .spawn(move || {
+                let failed: Vec<String> = paths
+                    .par_iter()
+                    .filter_map(|path| {
+                        let e = metadata::edit_tags(path, &edits).err()?;
+                        error!("Failed to edit metadata of {:?}: {}", path, e);
+                        Some(format!(
+                            "{}: {e}",
+                            path.file_name().unwrap_or_default().to_string_lossy()
+                        ))
+                    })
+                    .collect();
+                info!(
+                    "Edited metadata of {} of {} images",
+                    paths.len() - failed.len(),
+                    paths.len()
+                );
+
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let state = ui.global::<MetadataState>();
+                    if let Some(first) = failed.first() {
+                        let more = match failed.len() {
+                            1 => String::new(),
+                            n => format!(" (and {} more)", n - 1),
+                        };
+                        state.set_error(format!("{first}{more}").into());
+                        return;
+                    }
+                    state.set_open(false);
+                });
+            })
+            .expect("Failed to spawn metadata write thread");
+    }
+
+    /// Show the names `template` gives the first few images, or why it doesn't parse.
+    fn handle_rename_preview(&self, template: &str) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<RenameState>();
+        let preview = match Template::parse(template) {
+            Ok(template) => {
+                state.set_error("".into());
+                template.preview(
+                    &self.naming_items(&self.rename_indices),
+                    RENAME_PREVIEW_ROWS,
+                )
+            }
+            Err(e) => {
+                state.set_error(e.into());
+                Vec::new()
+            }
+        };
+        let rows: Vec<RenamePreview> = preview
+            .into_iter()
+            .map(|(old, new)| RenamePreview {
+                old: old.into(),
+                new: new.into(),
+            })
+            .collect();
+        state.set_preview(Rc::new(VecModel::from(rows)).into());
+    }
+
+    /// Rename the images of the dialog by its template. Nothing is renamed when a new name is
+    /// taken by a file outside the batch.
+    fn handle_apply_rename(&mut self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<RenameState>();
+        let text = state.get_template().to_string();
+        let Ok(template) = Template::parse(&text) else {
+            return;
+        };
+        let items = self.naming_items(&self.rename_indices);
+        let renames: Vec<(usize, PathBuf, PathBuf)> = self
+            .rename_indices
+            .iter()
+            .zip(&items)
+            .zip(template.names(&items))
+            .map(|((&i, (old, _)), name)| (i, old.clone(), old.with_file_name(name)))
+            .filter(|(_, old, new)| old != new)
+            .collect();
+
+        let sources: HashSet<&PathBuf> = renames.iter().map(|(_, old, _)| old).collect();
+        if let Some((_, _, taken)) = renames
+            .iter()
+            .find(|(_, _, new)| new.exists() && !sources.contains(new))
+        {
+            let name = taken.file_name().unwrap_or_default().to_string_lossy();
+            state.set_error(format!("{name} already exists").into());
+            return;
+        }
+
+        // Through temporary names, so files can take names others in the batch give up
+        let temp = |i: usize, old: &PathBuf| old.with_file_name(format!(".luminous-rename-{i}"));
+        for (n, (i, old, _)) in renames.iter().enumerate() {
+            if let Err(e) = std::fs::rename(old, temp(*i, old)) {
+                error!("Failed to rename {:?}: {}", old, e);
+                for (i, old, _) in &renames[..n] {
+                    let _ = std::fs::rename(temp(*i, old), old);
+                }
+                state.set_error(format!("Failed to rename: {e}").into());
+                return;
+            }
+        }
+        let mut done = Vec::new();
+        for (i, old, new) in &renames {
+            match std::fs::rename(temp(*i, old), new) {
+                Ok(()) => done.push((*i, old.clone(), new.clone())),
+                Err(e) => {
+                    self.toaster.error(format!(
+                        "Can't rename {} to {}: {e}",
+                        file_name(old),
+                        file_name(new)
+                    ));
+                    let _ = std::fs::rename(temp(*i, old), old);
+                }
+            }
+        }
+        info!(
+            "Renamed {} of {} images with {text:?}",
+            done.len(),
+            items.len()
+        );
+
+        let moved: Vec<(PathBuf, PathBuf)> = done
+            .iter()
+            .map(|(_, old, new)| (old.clone(), new.clone()))
+            .collect();
+        sidecar::carry(&moved);
+        self.library.rename_paths(&moved);
+        self.favorites = self.library.favorites().into_iter().collect();
+        self.naming.rename = text;
+        self.rename_indices.clear();
+        state.set_open(false);
+
+        let mut paths = self.scan.paths.clone();
+        for (i, _, new) in done {
+            paths[i] = new;
+        }
+        let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+        let dir = self.scan.dir.clone();
+        self.set_scan(Arc::new(fs_scan::from_paths(paths, &dir, &extra_exts)));
+    }
+
+    /// Show only images rated `min` stars or more, 0 for all, keeping the current search.
+    fn handle_min_rating(&mut self, min: u8) {
+        info!("Minimum rating: {min}");
+        self.min_rating = min;
+        self.handle_search(self.query.clone());
+    }
+
+    /// Rate the image shown in the full view, 0 clears its rating.
+    fn handle_rate(&mut self, rating: u8) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let index = fv.get_curr_image_index() as usize;
+        let Some(path) = self.scan.paths.get(index) else {
+            return;
+        };
+        if !self.library.set_rating(path, rating) {
+            return;
+        }
+        debug!("Rated {:?} {rating} stars", path);
+        self.ratings[index] = rating;
+        fv.set_curr_rating(rating as i32);
+        let model = ui.global::<GridViewState>().get_model();
+        if let Some(row) = self.filtered_indices.iter().position(|&i| i == index)
+            && let Some(mut item) = model.row_data(row)
+        {
+            item.rating = rating as i32;
+            model.set_row_data(row, item);
+        }
+    }
+
+    fn indices_matching_name(&self, filter: &str) -> Vec<usize> {
+        self.scan
+            .paths
+            .iter()
+            .enumerate()
+            .filter(|(_, path)| {
+                filter.is_empty()
+                    || path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| fs_scan::name_matches(n, filter))
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    fn handle_toggle_selection(&self, index: i32) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let gv = ui.global::<GridViewState>();
+        let model = gv.get_model();
+        let row = index as usize;
+
+        let Some(mut item) = model.row_data(row) else {
+            return;
+        };
+        item.selected = !item.selected;
+        model.set_row_data(row, item.clone());
+
+        gv.set_selected_count(gv.get_selected_count() + if item.selected { 1 } else { -1 });
+
+        let vm = gv.get_visible_model();
+        for i in 0..vm.row_count() {
+            if let Some(mut v) = vm.row_data(i) {
+                if v.index == item.index {
+                    v.selected = item.selected;
+                    vm.set_row_data(i, v);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle_segmentation(
+        &self,
         plugin_id: String,
         x1: i32,
         y1: i32,
@@ -610,55 +2999,525 @@ impl AppController {
     ) {
         let weak = self.window_weak.clone();
         let loader = self.loader.clone();
-        let before_idx = loader.active_idx.load(Ordering::Relaxed);
+        let before_idx = loader.active_idx.load(Ordering::Relaxed);
+
+        std::thread::Builder::new()
+            .name("segm".to_string())
+            .spawn(move || {
+                if let Some(plugin) = loader.plugin_manager.get_plugin_by_id(&plugin_id) {
+                    if txt.len() > 0 {
+                        if let Some(mask) = plugin.text_to_mask(txt) {
+                            if before_idx == loader.active_idx.load(Ordering::Relaxed) {
+                                let _ = weak.upgrade_in_event_loop(move |ui| {
+                                    ui.global::<FullViewState>()
+                                        .set_mask_overlay(Image::from_rgba8(mask));
+                                });
+                            } else {
+                                debug!("Index has moved, not applying mask");
+                            }
+                        } else {
+                            warn!("Text to mask failed");
+                        }
+                    } else if x2 < 0 || y2 < 0 {
+                        if let Some(mask) = plugin.interactive_click(x1 as u32, y1 as u32) {
+                            if before_idx == loader.active_idx.load(Ordering::Relaxed) {
+                                let _ = weak.upgrade_in_event_loop(move |ui| {
+                                    ui.global::<FullViewState>()
+                                        .set_mask_overlay(Image::from_rgba8(mask));
+                                });
+                            } else {
+                                debug!("Index has moved, not applying mask");
+                            }
+                        } else {
+                            warn!("Interactive click failed");
+                        }
+                    } else if let Some(mask) =
+                        plugin.interactive_rect_select(x1 as u32, y1 as u32, x2 as u32, y2 as u32)
+                    {
+                        if before_idx == loader.active_idx.load(Ordering::Relaxed) {
+                            let _ = weak.upgrade_in_event_loop(move |ui| {
+                                ui.global::<FullViewState>()
+                                    .set_mask_overlay(Image::from_rgba8(mask));
+                            });
+                        } else {
+                            debug!("Index has moved, not applying mask");
+                        }
+                    } else {
+                        warn!("Interactive select failed");
+                    }
+                }
+            })
+            .expect("Failed to spawn segmentation thread");
+    }
+
+    fn handle_upscale(&self, scale: u32) {
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        let Some(plugin) = loader.plugin_manager.get_upscaler_plugin() else {
+            warn!("No upscaler plugin available");
+            return;
+        };
+        if !plugin.is_running() {
+            warn!("Upscaler plugin '{}' is not running", plugin.id);
+            return;
+        }
+        let Some(path) = loader.get_curr_img_path() else {
+            error!("No image path for curr idx");
+            return;
+        };
+        let before_idx = loader.active_idx.load(Ordering::Relaxed);
+        let file_stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "image".into());
+        let dst_path = std::env::temp_dir().join(format!("luminous_{file_stem}_x{scale}.png"));
+
+        if let Some(ui) = weak.upgrade() {
+            ui.global::<FullViewState>().set_upscale_busy(true);
+        }
+
+        std::thread::Builder::new()
+            .name("upscale".to_string())
+            .spawn(move || {
+                let buffer = plugin.upscale(&path, &dst_path, scale).and_then(|out| {
+                    let img = image::open(&out)
+                        .map_err(|e| error!("Failed to open upscaled image {:?}: {}", out, e))
+                        .ok();
+                    let _ = std::fs::remove_file(&out);
+                    let rgba = img?.to_rgba8();
+                    Some(SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+                        rgba.as_raw(),
+                        rgba.width(),
+                        rgba.height(),
+                    ))
+                });
+                let moved = before_idx != loader.active_idx.load(Ordering::Relaxed);
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let fv = ui.global::<FullViewState>();
+                    fv.set_upscale_busy(false);
+                    match buffer {
+                        Some(_) if moved => debug!("Index has moved, not showing upscale"),
+                        Some(buf) => fv.set_upscale_preview(Image::from_rgba8(buf)),
+                        None => warn!("Upscale failed"),
+                    }
+                });
+            })
+            .expect("Failed to spawn upscale thread");
+    }
+
+    /// Rank the library by similarity to `abs_index`, using a search plugin's
+    /// embeddings when one is running and perceptual hashes otherwise.
+    fn handle_find_similar(&self, abs_index: usize) {
+        let Some(query_path) = self.scan.paths.get(abs_index).cloned() else {
+            return;
+        };
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        let scan = self.scan.clone();
+        let search_plugin = loader
+            .plugin_manager
+            .get_search_plugins()
+            .find(|p| p.is_running())
+            .cloned();
+
+        std::thread::Builder::new()
+            .name("similar".to_string())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let from_plugin = search_plugin.and_then(|plugin| {
+                    let paths = plugin.find_similar(&scan.paths, &query_path)?;
+                    debug!("{} similar images from plugin {}", paths.len(), plugin.id);
+                    let positions: HashMap<_, _> =
+                        scan.paths.iter().enumerate().map(|(i, p)| (p, i)).collect();
+                    let mut indices = vec![abs_index];
+                    for i in paths.iter().filter_map(|p| positions.get(p).copied()) {
+                        if !indices.contains(&i) {
+                            indices.push(i);
+                        }
+                    }
+                    Some(indices)
+                });
+
+                let indices = from_plugin.unwrap_or_else(|| {
+                    let hashes: Vec<(usize, u64)> = scan
+                        .paths
+                        .par_iter()
+                        .enumerate()
+                        .filter_map(|(i, p)| {
+                            similarity::dhash_buffer(&loader.load_thumb_blocking_at(i, p))
+                                .map(|h| (i, h))
+                        })
+                        .collect();
+                    let query = hashes
+                        .iter()
+                        .find(|&&(i, _)| i == abs_index)
+                        .map(|&(_, h)| h);
+                    query
+                        .map(|q| similarity::rank(q, &hashes))
+                        .unwrap_or_default()
+                });
+                debug!(
+                    "Found {} similar images in {:.2}ms",
+                    indices.len(),
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+
+                let indices: Vec<i32> = indices.into_iter().map(|i| i as i32).collect();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_view_mode(ViewMode::Grid);
+                    ui.global::<GridViewState>()
+                        .invoke_show_collection(Rc::new(VecModel::from(indices)).into());
+                });
+            })
+            .expect("Failed to spawn similarity thread");
+    }
+
+    /// Rank the scanned images by how much of `color` they show. Colors of images not
+    /// thumbnailed yet are computed from their thumbnails first.
+    fn handle_find_by_color(&self, color: slint::Color) {
+        let query = [color.red(), color.green(), color.blue()];
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        let scan = self.scan.clone();
+        let colors = self.colors.clone();
+
+        std::thread::Builder::new()
+            .name("color search".to_string())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let palettes: Vec<(usize, Vec<color_search::Swatch>)> = colors
+                    .palettes(&scan.paths, |i, p| loader.load_thumb_blocking_at(i, p))
+                    .into_iter()
+                    .enumerate()
+                    .collect();
+                let indices = color_search::rank(query, &palettes);
+                debug!(
+                    "Found {} images with much of {:?} in {:.2}ms",
+                    indices.len(),
+                    query,
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+
+                let indices: Vec<i32> = indices.into_iter().map(|i| i as i32).collect();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    if indices.is_empty() {
+                        show_osd(&ui, "No images with much of that color".into());
+                        return;
+                    }
+                    ui.set_view_mode(ViewMode::Grid);
+                    ui.global::<GridViewState>()
+                        .invoke_show_collection(Rc::new(VecModel::from(indices)).into());
+                });
+            })
+            .expect("Failed to spawn color search thread");
+    }
+
+    /// Order the grid by the keys of a plugin sorter. Keys are computed on first use and
+    /// cached in the library until the file changes.
+    fn handle_sort_by(&self, sorter: String) {
+        let Some(plugin) = self
+            .loader
+            .plugin_manager
+            .get_sorter_plugins()
+            .find(|p| p.is_running() && p.sorters().contains(&sorter))
+            .cloned()
+        else {
+            warn!("Sort by {sorter}: no running plugin provides it");
+            return;
+        };
+        let indices = self.filtered_indices.clone();
+        let paths: Vec<PathBuf> = indices
+            .iter()
+            .map(|&i| self.loader.get_path(i).unwrap_or_default())
+            .collect();
+        let weak = self.window_weak.clone();
+        let library = self.library.clone();
+
+        std::thread::Builder::new()
+            .name("sorter".to_string())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let files: Vec<(PathBuf, i64)> = paths
+                    .into_iter()
+                    .map(|p| {
+                        let mtime = std::fs::metadata(&p)
+                            .and_then(|m| m.modified())
+                            .ok()
+                            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                            .map_or(0, |d| d.as_secs() as i64);
+                        (p, mtime)
+                    })
+                    .collect();
+                let mut keys = library.sort_keys(&sorter, &files);
+                let missing: Vec<&(PathBuf, i64)> = files
+                    .iter()
+                    .filter(|(p, _)| !keys.contains_key(p))
+                    .collect();
+                debug!(
+                    "Sort by {sorter}: {} cached, {} from plugin {}",
+                    keys.len(),
+                    missing.len(),
+                    plugin.id
+                );
+                if !missing.is_empty() {
+                    let missing_paths: Vec<PathBuf> =
+                        missing.iter().map(|(p, _)| p.clone()).collect();
+                    match plugin.sort_keys(&sorter, &missing_paths) {
+                        Some(values) => {
+                            let fresh: Vec<(PathBuf, i64, f64)> = missing
+                                .iter()
+                                .zip(values)
+                                .filter_map(|((p, mtime), v)| Some((p.clone(), *mtime, v?)))
+                                .collect();
+                            library.set_sort_keys(&sorter, &fresh);
+                            keys.extend(fresh.into_iter().map(|(p, _, v)| (p, v)));
+                        }
+                        None => warn!("Sort by {sorter}: plugin {} failed", plugin.id),
+                    }
+                }
+
+                // Stable, images without a key keep their order at the end
+                let mut order: Vec<(usize, f64)> = indices
+                    .iter()
+                    .zip(&files)
+                    .map(|(&i, (p, _))| (i, keys.get(p).copied().unwrap_or(f64::INFINITY)))
+                    .collect();
+                order.sort_by(|a, b| a.1.total_cmp(&b.1));
+                debug!(
+                    "Sorted {} images by {sorter} in {:.2}ms",
+                    order.len(),
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+
+                let indices: Vec<i32> = order.into_iter().map(|(i, _)| i as i32).collect();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    ui.global::<GridViewState>()
+                        .invoke_show_collection(Rc::new(VecModel::from(indices)).into());
+                });
+            })
+            .expect("Failed to spawn sorter thread");
+    }
+
+    /// Show `indices` in the grid as a virtual collection, in the given order.
+    fn show_collection(&mut self, indices: Vec<usize>) {
+        self.filtered_indices = indices;
+        self.active_grid_indices.clear();
+        self.loader.clear_thumbs();
+
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+
+        let gv = ui.global::<GridViewState>();
+        gv.set_selected_count(0);
+        gv.set_model(self.grid_model());
+
+        if let Some(&first_abs) = self.filtered_indices.first() {
+            self.handle_full_view_load(first_abs);
+        }
+        self.handle_grid_request(0, 50);
+    }
+
+    /// Open the compare grid with up to four images. Cells start empty and are
+    /// filled in as the full images are decoded.
+    fn handle_compare(&self, abs_indices: Vec<usize>) {
+        if abs_indices.len() < 2 {
+            warn!("Compare: select 2 to {} images", COMPARE_LABELS.len());
+            return;
+        }
+        if abs_indices.len() > COMPARE_LABELS.len() {
+            warn!(
+                "Compare: showing the first {} of {} selected images",
+                COMPARE_LABELS.len(),
+                abs_indices.len()
+            );
+        }
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let items: Vec<CompareItem> = abs_indices
+            .iter()
+            .zip(COMPARE_LABELS)
+            .map(|(&abs, label)| CompareItem {
+                image: Image::default(),
+                label: format!(
+                    "{label}: {}",
+                    self.loader.get_file_name(abs).unwrap_or_default()
+                )
+                .into(),
+                abs_index: abs as i32,
+            })
+            .collect();
+
+        for (row, &abs) in abs_indices.iter().take(COMPARE_LABELS.len()).enumerate() {
+            let weak = self.window_weak.clone();
+            let loader = self.loader.clone();
+            self.loader.pool.spawn(move || {
+                let Some(buffer) = loader.load_full_blocking(abs) else {
+                    return;
+                };
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let model = ui.global::<FullViewState>().get_compare_items();
+                    // The grid may have been closed or reopened with other images meanwhile
+                    if let Some(mut item) = model.row_data(row)
+                        && item.abs_index == abs as i32
+                    {
+                        item.image = Image::from_rgba8(buffer);
+                        model.set_row_data(row, item);
+                    }
+                });
+            });
+        }
+
+        debug!("Comparing {} images", items.len());
+        ui.global::<FullViewState>()
+            .set_compare_items(Rc::new(VecModel::from(items)).into());
+        ui.set_view_mode(ViewMode::Full);
+    }
+
+    /// Look for sideways images among `abs_indices` and show them for review.
+    fn handle_suggest_rotations(&self, abs_indices: Vec<usize>) {
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        let scan = self.scan.clone();
+        std::thread::Builder::new()
+            .name("auto-rotate".to_string())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let suggestions: Vec<RotationSuggestion> = abs_indices
+                    .par_iter()
+                    .filter_map(|&abs| {
+                        let path = scan.paths.get(abs)?;
+                        if auto_rotate::has_exif_orientation(path) {
+                            return None;
+                        }
+                        let thumb = loader.load_thumb_blocking_at(abs, path);
+                        let rgba = image::RgbaImage::from_raw(
+                            thumb.width(),
+                            thumb.height(),
+                            thumb.as_bytes().to_vec(),
+                        )?;
+                        let rotation =
+                            auto_rotate::suggest(&image::DynamicImage::ImageRgba8(rgba))?;
+                        Some(RotationSuggestion {
+                            abs_index: abs as i32,
+                            clockwise: rotation == auto_rotate::Rotation::Clockwise,
+                        })
+                    })
+                    .collect();
+                info!(
+                    "{} of {} images look sideways ({:.2}ms)",
+                    suggestions.len(),
+                    abs_indices.len(),
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+                if suggestions.is_empty() {
+                    return;
+                }
+
+                let indices: Vec<i32> = suggestions.iter().map(|s| s.abs_index).collect();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let gv = ui.global::<GridViewState>();
+                    gv.set_rotation_suggestions(Rc::new(VecModel::from(suggestions)).into());
+                    gv.invoke_show_collection(Rc::new(VecModel::from(indices)).into());
+                });
+            })
+            .expect("Failed to spawn auto-rotate thread");
+    }
+
+    /// Add the suggested rotations to the sidecars of the images, see [`sidecar`], then refresh
+    /// the review collection. The files themselves are left alone.
+    fn handle_apply_rotations(&self, suggestions: Vec<RotationSuggestion>) {
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        let scan = self.scan.clone();
+        let toaster = self.toaster.clone();
+        std::thread::Builder::new()
+            .name("auto-rotate".to_string())
+            .spawn(move || {
+                suggestions.par_iter().for_each(|s| {
+                    let Some(path) = scan.paths.get(s.abs_index as usize) else {
+                        return;
+                    };
+                    let op = if s.clockwise {
+                        sidecar::Op::RotateCw
+                    } else {
+                        sidecar::Op::RotateCcw
+                    };
+                    let mut edits = sidecar::Sidecar::load(path);
+                    edits.ops.push(op);
+                    match edits.save(path) {
+                        Ok(()) => debug!("Rotated {:?} {:?}", path, op),
+                        Err(e) => toaster.error(format!("Can't rotate {}: {e}", file_name(path))),
+                    }
+                });
+                loader.evict_all();
+
+                let indices: Vec<i32> = suggestions.iter().map(|s| s.abs_index).collect();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let gv = ui.global::<GridViewState>();
+                    gv.set_rotation_suggestions(ModelRc::default());
+                    gv.invoke_show_collection(Rc::new(VecModel::from(indices)).into());
+                });
+            })
+            .expect("Failed to spawn auto-rotate thread");
+    }
+
+    fn handle_auto_tag(&self, paths: Vec<std::path::PathBuf>) {
+        if paths.is_empty() {
+            debug!("Auto-tag: no images selected");
+            return;
+        }
+        let taggers: Vec<_> = self
+            .loader
+            .plugin_manager
+            .get_tagger_plugins()
+            .filter(|p| {
+                if !p.is_running() {
+                    warn!("Tagger plugin {} is registered but not running.", p.id);
+                }
+                p.is_running()
+            })
+            .cloned()
+            .collect();
+        if taggers.is_empty() {
+            warn!("Auto-tag: no running tagger plugin");
+            return;
+        }
 
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        let library = self.library.clone();
         std::thread::Builder::new()
-            .name("segm".to_string())
+            .name("tagger".to_string())
             .spawn(move || {
-                if let Some(plugin) = loader.plugin_manager.get_plugin_by_id(&plugin_id) {
-                    if txt.len() > 0 {
-                        if let Some(mask) = plugin.text_to_mask(txt) {
-                            if before_idx == loader.active_idx.load(Ordering::Relaxed) {
-                                let _ = weak.upgrade_in_event_loop(move |ui| {
-                                    ui.global::<FullViewState>()
-                                        .set_mask_overlay(Image::from_rgba8(mask));
-                                });
-                            } else {
-                                debug!("Index has moved, not applying mask");
-                            }
-                        } else {
-                            warn!("Text to mask failed");
-                        }
-                    } else if x2 < 0 || y2 < 0 {
-                        if let Some(mask) = plugin.interactive_click(x1 as u32, y1 as u32) {
-                            if before_idx == loader.active_idx.load(Ordering::Relaxed) {
-                                let _ = weak.upgrade_in_event_loop(move |ui| {
-                                    ui.global::<FullViewState>()
-                                        .set_mask_overlay(Image::from_rgba8(mask));
-                                });
-                            } else {
-                                debug!("Index has moved, not applying mask");
+                let start = std::time::Instant::now();
+                let total = paths.len() * taggers.len();
+                let mut done = 0;
+                for plugin in &taggers {
+                    for path in &paths {
+                        let thumb = loader.load_thumb_blocking(path);
+                        match plugin.tag(path, &thumb) {
+                            Some(tags) => {
+                                debug!("{:?} tagged by {}: {:?}", path, plugin.id, tags);
+                                library.set_keywords(path, &tags, &plugin.id);
                             }
-                        } else {
-                            warn!("Interactive click failed");
-                        }
-                    } else if let Some(mask) =
-                        plugin.interactive_rect_select(x1 as u32, y1 as u32, x2 as u32, y2 as u32)
-                    {
-                        if before_idx == loader.active_idx.load(Ordering::Relaxed) {
-                            let _ = weak.upgrade_in_event_loop(move |ui| {
-                                ui.global::<FullViewState>()
-                                    .set_mask_overlay(Image::from_rgba8(mask));
-                            });
-                        } else {
-                            debug!("Index has moved, not applying mask");
+                            None => warn!("Tagging {:?} with {} failed", path, plugin.id),
                         }
-                    } else {
-                        warn!("Interactive select failed");
+                        done += 1;
+                        let progress = done as f32 / total as f32;
+                        let _ = weak.upgrade_in_event_loop(move |ui| {
+                            ui.set_pipeline_progress(progress);
+                        });
                     }
                 }
+                info!(
+                    "Auto-tagged {} images in {:.2}ms",
+                    paths.len(),
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
             })
-            .expect("Failed to spawn segmentation thread");
+            .expect("Failed to spawn tagger thread");
     }
 
     fn build_window_indices(&self, center: usize) -> Vec<usize> {
@@ -672,117 +3531,704 @@ impl AppController {
             .position(|&x| x == center)
             .unwrap_or(0);
 
-        (1..=self.loader.window_size)
-            .flat_map(|i| {
-                let prev = (pos as isize - i as isize).rem_euclid(len as isize) as usize;
-                let next = (pos + i).rem_euclid(len);
-                [self.filtered_indices[prev], self.filtered_indices[next]]
-            })
-            .collect()
-    }
+        (1..=self.loader.window_size())
+            .flat_map(|i| {
+                let prev = (pos as isize - i as isize).rem_euclid(len as isize) as usize;
+                let next = (pos + i).rem_euclid(len);
+                [self.filtered_indices[prev], self.filtered_indices[next]]
+            })
+            .collect()
+    }
+
+    fn notify_interactive_plugin(plugin_id: String, loader: &Arc<ImageLoader>) {
+        let loader = loader.clone();
+        let plugin_manager = loader.plugin_manager.clone();
+        let curr_active_path = loader.get_curr_img_path();
+        let curr_active_buffer = loader.get_curr_active_buffer();
+        loader.pool.spawn(move || {
+            if let Some(plugin) = plugin_manager.get_plugin_by_id(&plugin_id) {
+                if let Some(buf) = curr_active_buffer
+                    && let Some(path) = curr_active_path
+                {
+                    plugin.set_interactive_image(&buf, &path);
+                }
+            }
+        });
+    }
+
+    pub(crate) fn collect_selected_paths(&self) -> Vec<std::path::PathBuf> {
+        self.selected_indices()
+            .into_iter()
+            .filter_map(|abs| self.scan.paths.get(abs).cloned())
+            .collect()
+    }
+
+    /// Scan indices of the images selected in the grid, in grid order.
+    fn selected_indices(&self) -> Vec<usize> {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return Vec::new();
+        };
+        let model = ui.global::<GridViewState>().get_model();
+        grid_model::selected_rows(&model)
+            .into_iter()
+            .filter_map(|row| self.filtered_indices.get(row).copied())
+            .collect()
+    }
+
+    fn handle_open_images(controller_rc: Rc<RefCell<Self>>) {
+        let extra_exts = controller_rc
+            .borrow()
+            .loader
+            .plugin_manager
+            .get_supported_extensions();
+        let name_filter = controller_rc.borrow().name_filter.clone();
+
+        if let Some(path) = rfd::FileDialog::new()
+            .pick_folder()
+            .and_then(|p| p.to_str().map(|s| s.to_string()))
+        {
+            let scan = Arc::new(fs_scan::scan(&path, &extra_exts, name_filter.as_ref()));
+            if scan.paths.is_empty() {
+                return;
+            }
+
+            controller_rc.borrow_mut().replace_scan(scan);
+        }
+    }
+
+    /// Open an image picked in a file dialog, together with the rest of its folder, starting in
+    /// the full view at that image.
+    fn handle_open_image_file(controller_rc: Rc<RefCell<Self>>) {
+        let (extra_exts, name_filter, dir) = {
+            let controller = controller_rc.borrow();
+            (
+                controller.loader.plugin_manager.get_supported_extensions(),
+                controller.name_filter.clone(),
+                controller.browse_dir(),
+            )
+        };
+        let mut formats = fs_scan::ImageFormats::new();
+        for format in &extra_exts {
+            formats.add_format(format.clone());
+        }
+        let mut exts: Vec<String> = formats.get_all_decoding_exts().into_iter().collect();
+        exts.sort();
+
+        let mut dialog = rfd::FileDialog::new().add_filter("Images", &exts);
+        if let Some(dir) = dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(path) = dialog
+            .pick_file()
+            .and_then(|p| p.to_str().map(|s| s.to_string()))
+        else {
+            return;
+        };
+        let scan = Arc::new(fs_scan::scan(&path, &extra_exts, name_filter.as_ref()));
+        if scan.paths.is_empty() {
+            return;
+        }
+        controller_rc.borrow_mut().replace_scan(scan);
+    }
+
+    fn replace_scan(&mut self, scan: Arc<ScanResult>) {
+        self.save_view_profile();
+        self.sort_ascending = None;
+        self.query.clear();
+        self.set_scan(scan.clone());
+        self.restore_view_profile();
+        self.remember_location();
+        self.reveal_folder_tree();
+
+        if let Some(ui) = self.window_weak.upgrade() {
+            ui.set_view_mode(if scan.is_dir {
+                ViewMode::Grid
+            } else {
+                ViewMode::Full
+            });
+
+            if !scan.paths.is_empty() {
+                self.handle_full_view_load(scan.start_index);
+            }
+        }
+    }
+
+    /// Put the open folder, or the file opened on its own, at the front of the recent locations.
+    fn remember_location(&self) {
+        let virtual_dir =
+            self.scan.dir.starts_with(demo::DEMO_DIR) || self.scan.dir.starts_with(FAVORITES_DIR);
+        let location = if self.scan.is_dir {
+            Some(self.scan.dir.clone())
+        } else {
+            self.scan.paths.get(self.scan.start_index).cloned()
+        };
+        if let Some(location) = location.filter(|_| !virtual_dir && !self.scan.paths.is_empty()) {
+            let location = std::fs::canonicalize(&location).unwrap_or(location);
+            self.library.add_recent(&location);
+        }
+        self.update_recent_list();
+    }
+
+    /// Recent locations that still exist, as listed in the grid and the command palette.
+    pub(crate) fn recent_locations(&self) -> Vec<PathBuf> {
+        self.library
+            .recent()
+            .into_iter()
+            .filter(|p| p.exists())
+            .collect()
+    }
+
+    fn update_recent_list(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let recent: Vec<slint::SharedString> = self
+            .recent_locations()
+            .iter()
+            .map(|p| p.to_string_lossy().as_ref().into())
+            .collect();
+        ui.global::<GridViewState>()
+            .set_recent(Rc::new(VecModel::from(recent)).into());
+    }
+
+    /// Open a recent location again, like at startup.
+    fn handle_open_recent(&mut self, path: &str) {
+        let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+        let scan = fs_scan::scan(path, &extra_exts, self.name_filter.as_ref());
+        if scan.paths.is_empty() {
+            warn!("No images in {path}");
+            return;
+        }
+        self.replace_scan(Arc::new(scan));
+    }
+
+    /// Show a folder picked in the folder tree, also without images so its subfolders stay a
+    /// click away.
+    fn handle_open_folder(&mut self, path: &str) {
+        let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+        self.replace_scan(Arc::new(fs_scan::scan(
+            path,
+            &extra_exts,
+            self.name_filter.as_ref(),
+        )));
+    }
+
+    fn handle_toggle_folder(&mut self, path: &str) {
+        self.folder_tree.toggle(std::path::Path::new(path));
+        self.update_folder_tree();
+    }
+
+    /// Real folder the folder tree and file dialogs start from: the scanned one, or the working
+    /// directory for demo images and favorites.
+    fn browse_dir(&self) -> Option<PathBuf> {
+        let dir = if self.scan.dir.starts_with(demo::DEMO_DIR)
+            || self.scan.dir.starts_with(FAVORITES_DIR)
+        {
+            std::env::current_dir().ok()?
+        } else {
+            self.scan.dir.clone()
+        };
+        std::fs::canonicalize(dir).ok()
+    }
+
+    /// Expand the folder tree down to the open folder and scroll it into view.
+    fn reveal_folder_tree(&mut self) {
+        let Some(dir) = self.browse_dir() else {
+            return;
+        };
+        self.folder_tree.reveal(&dir);
+        if let Some(ui) = self.window_weak.upgrade() {
+            ui.global::<FolderTreeState>().set_current_row(-1);
+        }
+        self.update_folder_tree();
+    }
+
+    /// Refill the folder tree panel, only while it is shown since listing folders hits the disk.
+    fn update_folder_tree(&self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let state = ui.global::<FolderTreeState>();
+        if !state.get_visible() {
+            return;
+        }
+        let Some(dir) = self.browse_dir() else {
+            return;
+        };
+        let rows = self.folder_tree.rows(&dir);
+        let current_row = rows.iter().position(|r| r.current).map_or(-1, |i| i as i32);
+        let folders: Vec<FolderNode> = rows
+            .into_iter()
+            .map(|r| FolderNode {
+                path: r.path.to_string_lossy().as_ref().into(),
+                name: r.name.into(),
+                depth: r.depth as i32,
+                expanded: r.expanded,
+                current: r.current,
+            })
+            .collect();
+        state.set_folders(Rc::new(VecModel::from(folders)).into());
+        state.set_current_row(current_row);
+    }
+
+    /// Rescan the open folder keeping only files whose names match `filter`, everything when it
+    /// is empty.
+    fn handle_set_scan_filter(&mut self, filter: String) {
+        let filter = filter.trim();
+        let name_filter = if filter.is_empty() {
+            None
+        } else {
+            match NameFilter::parse(filter) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    error!("Invalid file filter '{filter}': {e}");
+                    return;
+                }
+            }
+        };
+        if self.scan.dir.starts_with(demo::DEMO_DIR) || self.scan.dir.starts_with(FAVORITES_DIR) {
+            warn!("The file filter does not apply to demo images or favorites");
+            return;
+        }
+        info!("File filter set to {filter:?}");
+        self.name_filter = name_filter;
+        let Some(dir) = self.scan.dir.to_str().map(str::to_string) else {
+            return;
+        };
+        let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+        self.replace_scan(Arc::new(fs_scan::scan(
+            &dir,
+            &extra_exts,
+            self.name_filter.as_ref(),
+        )));
+    }
+
+    /// Swap in a new scan and rebuild the grid model, without navigating.
+    fn set_scan(&mut self, scan: Arc<ScanResult>) {
+        self.scan = scan.clone();
+        self.loader.update_paths(scan.paths.clone());
+        self.ratings = self.library.ratings(&scan.paths);
+        self.marks = self.library.marks(&scan.paths);
+        // The dimensions of the new files aren't read yet
+        self.size_filter = SizeFilter::All;
+        self.filtered_indices = (0..scan.paths.len()).collect();
+        self.retain_culled();
+        self.load_custom_order();
+        self.order_filtered();
+        self.sequences = sequence::detect(&scan.paths);
+        self.active_sequence.set(None);
+        // Indices now point at other files
+        *self.false_color_samples.lock().unwrap() = None;
+        *self.false_color_pending.lock().unwrap() = None;
+        self.active_grid_indices.clear();
+
+        if let Some(ui) = self.window_weak.upgrade() {
+            let gv = ui.global::<GridViewState>();
+            gv.set_model(self.grid_model());
+            gv.set_selected_count(0);
+            gv.set_has_custom_order(!self.custom_rank.is_empty());
+            gv.set_size_filter("all".into());
+            self.handle_grid_request(0, 50);
+        }
+    }
+
+    /// Show a generated calibration pattern at the window's physical size, 1:1.
+    fn handle_show_test_pattern(&self, pattern: test_patterns::TestPattern) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let size = ui.window().size();
+        let start = std::time::Instant::now();
+        let img = pattern.render(size.width, size.height);
+        debug!(
+            "Rendered {:?} test pattern {}x{} in {:.2}ms",
+            pattern,
+            img.width(),
+            img.height(),
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+
+        let fv = ui.global::<FullViewState>();
+        fv.set_curr_image(Image::from_rgba8(SharedPixelBuffer::clone_from_slice(
+            img.as_raw(),
+            img.width(),
+            img.height(),
+        )));
+        fv.set_mask_overlay(Image::default());
+        fv.set_curr_image_name(format!("Test pattern: {}", pattern.title()).into());
+        // Any resampling would defeat the pattern
+        fv.set_fit_mode(FitMode::Actual);
+        ui.set_view_mode(ViewMode::Full);
+        ui.invoke_reset_zoom();
+    }
+
+    /// Trigger the first running capture plugin, frames land in the session folder.
+    fn handle_capture(&self) {
+        let Some(plugin) = self
+            .loader
+            .plugin_manager
+            .get_capture_plugins()
+            .find(|p| p.is_running())
+            .cloned()
+        else {
+            warn!("Capture: no running capture plugin");
+            return;
+        };
+        let dst_dir = self.scan.dir.clone();
+        let weak = self.window_weak.clone();
+        if let Some(ui) = weak.upgrade() {
+            ui.global::<FullViewState>().set_capture_busy(true);
+        }
+
+        std::thread::Builder::new()
+            .name("capture".to_string())
+            .spawn(move || {
+                let start = std::time::Instant::now();
+                let paths = plugin.capture(&dst_dir).unwrap_or_default();
+                info!(
+                    "Captured {} files with {} in {:.2}ms",
+                    paths.len(),
+                    plugin.id,
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    let fv = ui.global::<FullViewState>();
+                    fv.set_capture_busy(false);
+                    // RAW + JPEG pairs arrive together, the last one is shown
+                    for path in paths {
+                        fv.invoke_image_arrived(path.to_string_lossy().to_string().into(), true);
+                    }
+                });
+            })
+            .expect("Failed to spawn capture thread");
+    }
+
+    /// Add a new image in the session folder (hot folder or camera capture).
+    /// Jumps to it when `follow` is set, with the pipeline steps pre-applied
+    /// when the recipe toggle is on. Files already in the collection were
+    /// modified instead, see [`Self::handle_file_changed`].
+    fn handle_image_arrival(
+        &mut self,
+        path: std::path::PathBuf,
+        follow: bool,
+        factory: Arc<StepFactory>,
+    ) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        if let Some(idx) = self.scan.paths.iter().position(|p| *p == path) {
+            self.handle_file_changed(idx);
+            return;
+        }
+        let Some(dir) = path.parent().and_then(|d| d.to_str()) else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let prev_path = self
+            .scan
+            .paths
+            .get(fv.get_curr_image_index() as usize)
+            .cloned();
+
+        if path.parent() == Some(self.scan.dir.as_path()) {
+            // Only the new file has to be looked at
+            match self.scan.with_arrival(&path, self.name_filter.as_ref()) {
+                Some(scan) => self.set_scan(Arc::new(scan)),
+                None => return,
+            }
+        } else {
+            let extra_exts = self.loader.plugin_manager.get_supported_extensions();
+            self.set_scan(Arc::new(fs_scan::scan(
+                dir,
+                &extra_exts,
+                self.name_filter.as_ref(),
+            )));
+        }
+
+        let position = |p: &std::path::Path| self.scan.paths.iter().position(|q| q == p);
+        if !follow {
+            if let Some(idx) = prev_path.as_deref().and_then(position) {
+                self.handle_full_view_load(idx);
+            }
+            return;
+        }
+        let Some(idx) = position(&path) else {
+            return;
+        };
+        info!("New image: showing {:?}", path);
+        ui.set_view_mode(ViewMode::Full);
 
-    fn notify_interactive_plugin(plugin_id: String, loader: &Arc<ImageLoader>) {
-        let loader = loader.clone();
-        let plugin_manager = loader.plugin_manager.clone();
-        let curr_active_path = loader.get_curr_img_path();
-        let curr_active_buffer = loader.get_curr_active_buffer();
-        loader.pool.spawn(move || {
-            if let Some(plugin) = plugin_manager.get_plugin_by_id(&plugin_id) {
-                if let Some(buf) = curr_active_buffer
-                    && let Some(path) = curr_active_path
-                {
-                    plugin.set_interactive_image(&buf, &path);
+        let steps: Vec<PipelineStep> = ui.get_pipeline_steps().iter().collect();
+        if !fv.get_hot_folder_apply_recipe() || steps.is_empty() {
+            self.handle_full_view_load(idx);
+            return;
+        }
+
+        let weak = self.window_weak.clone();
+        let loader = self.loader.clone();
+        self.loader.pool.spawn(move || {
+            let start = std::time::Instant::now();
+            let img = match image::open(&path) {
+                Ok(img) => img,
+                Err(e) => {
+                    error!("Failed to open new image {:?}: {}", path, e);
+                    return;
                 }
-            }
+            };
+            let result = factory.apply_pipeline(img, &steps);
+            loader.cache_buffer(idx, luminous_image_loader::to_pixel_buffer(result));
+            debug!(
+                "Recipe applied to {:?} in {:.2}ms",
+                path,
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+            let _ = weak.upgrade_in_event_loop(move |ui| {
+                ui.global::<FullViewState>()
+                    .invoke_request_image(idx as i32);
+            });
         });
     }
 
-    pub(crate) fn collect_selected_paths(&self) -> Vec<std::path::PathBuf> {
+    /// Drop an image whose file another program deleted, the decode threads report it.
+    fn handle_image_vanished(&mut self, path: PathBuf) {
+        // Every decode job that ran into it reports it, and it may be back already
+        let Some(idx) = self.scan.paths.iter().position(|p| *p == path) else {
+            return;
+        };
+        if path.exists() {
+            return;
+        }
+        warn!("{:?} was deleted outside luminous", path);
+        self.remove_from_view(idx);
+        if let Some(ui) = self.window_weak.upgrade() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            show_osd(&ui, format!("{name} was deleted"));
+        }
+    }
+
+    /// Decode a file that changed on disk again: its thumbnail arrives through the thumbnail
+    /// hook, and the full view reloads it when it is the current image.
+    fn handle_file_changed(&self, idx: usize) {
+        info!("Changed on disk: {:?}", self.scan.paths[idx]);
+        self.loader.invalidate(idx);
+        self.loader.load_grid_thumb(idx);
         let Some(ui) = self.window_weak.upgrade() else {
-            return Vec::new();
+            return;
         };
-        let model = ui.global::<GridViewState>().get_model();
-        (0..model.row_count())
-            .filter_map(|i| {
-                let item = model.row_data(i)?;
-                if !item.selected {
-                    return None;
-                }
-                let abs = *self.filtered_indices.get(item.index as usize)?;
-                self.scan.paths.get(abs).cloned()
-            })
-            .collect()
+        let fv = ui.global::<FullViewState>();
+        if fv.get_curr_image_index() as usize == idx && !fv.get_curr_pasted() {
+            // The old pixels stay on screen until the new ones are decoded
+            let _ = self.loader.load_full_progressive(idx, true);
+            self.update_info_overlay(&fv, idx);
+        }
     }
 
-    fn handle_open_images(controller_rc: Rc<RefCell<Self>>) {
-        let extra_exts = controller_rc
-            .borrow()
-            .loader
-            .plugin_manager
-            .get_supported_extensions();
+    fn handle_sort(&mut self, ascending: bool) {
+        self.sort_ascending = Some(ascending);
+        self.order_filtered();
 
-        if let Some(path) = rfd::FileDialog::new()
-            .pick_folder()
-            .and_then(|p| p.to_str().map(|s| s.to_string()))
-        {
-            let scan = Arc::new(fs_scan::scan(&path, &extra_exts));
-            if scan.paths.is_empty() {
-                return;
+        self.rebuild_grid_model();
+        if let Some(&first_abs) = self.filtered_indices.first() {
+            self.handle_full_view_load(first_abs);
+        }
+    }
+
+    /// Sort `filtered_indices` by name if a sort was picked, else by the custom order.
+    fn order_filtered(&mut self) {
+        let Some(ascending) = self.sort_ascending else {
+            sort_by_rank(&mut self.filtered_indices, &self.custom_rank);
+            return;
+        };
+        let paths = &self.scan.paths;
+        self.filtered_indices.sort_by(|&a, &b| {
+            if ascending {
+                paths[a].cmp(&paths[b])
+            } else {
+                paths[b].cmp(&paths[a])
             }
+        });
+    }
 
-            controller_rc.borrow_mut().replace_scan(scan);
+    /// Write `action` to the session script when `--record` is given.
+    pub(crate) fn record(&self, action: Action) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(action);
         }
     }
 
-    fn replace_scan(&mut self, scan: Arc<ScanResult>) {
-        self.scan = scan.clone();
-        self.loader.update_paths(scan.paths.clone());
-        self.filtered_indices = (0..scan.paths.len()).collect();
-        self.active_grid_indices.clear();
+    /// Record showing the image at `abs_index` by its name.
+    pub(crate) fn record_open(&self, abs_index: usize) {
+        if let Some(name) = self.loader.get_file_name(abs_index) {
+            self.record(Action::Show(name));
+        }
+    }
 
-        if let Some(ui) = self.window_weak.upgrade() {
-            let grid_data: Vec<GridItem> = scan
-                .paths
-                .iter()
-                .enumerate()
-                .map(|(i, _)| GridItem {
-                    image: Image::default(),
-                    index: i as i32,
-                    abs_index: i as i32,
-                    selected: false,
-                })
-                .collect();
+    /// Index of the image named `name` in the scanned folder.
+    pub(crate) fn index_named(&self, name: &str) -> Result<usize, String> {
+        self.scan
+            .paths
+            .iter()
+            .position(|p| p.file_name().is_some_and(|n| n == name))
+            .ok_or_else(|| format!("{name} is not in {}", self.scan.dir.display()))
+    }
 
-            let gv = ui.global::<GridViewState>();
-            gv.set_model(Rc::new(VecModel::from(grid_data)).into());
-            gv.set_selected_count(0);
+    /// Where the next start without a path picks up, `None` for demo images, favorites and
+    /// empty folders.
+    fn session(&self) -> Option<app_state_cache::Session> {
+        if self.scan.paths.is_empty()
+            || self.scan.dir.starts_with(demo::DEMO_DIR)
+            || self.scan.dir.starts_with(FAVORITES_DIR)
+        {
+            return None;
+        }
+        let ui = self.window_weak.upgrade()?;
+        let fv = ui.global::<FullViewState>();
+        let index = fv.get_curr_image_index().max(0) as usize;
+        Some(app_state_cache::Session {
+            dir: self.scan.dir.clone(),
+            image: self.scan.paths.get(index).cloned(),
+            index,
+            full_view: ui.get_view_mode() == ViewMode::Full,
+            zoom: fv.get_zoom(),
+        })
+    }
 
-            ui.set_view_mode(if scan.is_dir {
-                ViewMode::Grid
-            } else {
-                ViewMode::Full
-            });
+    /// Remember the grid and full view settings for the scanned folder.
+    fn save_view_profile(&self) {
+        if !self.scan.is_dir {
+            return;
+        }
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let profile = ViewProfile {
+            sort_ascending: self.sort_ascending,
+            query: self.query.clone(),
+            grid_cols: ui.global::<GridViewState>().get_grid_cols(),
+            fit_mode: Config::fit_mode_name(ui.global::<FullViewState>().get_fit_mode()).into(),
+        };
+        debug!("Saving view profile for {:?}: {:?}", self.scan.dir, profile);
+        self.library.set_view_profile(&self.scan.dir, &profile);
+    }
 
-            if !scan.paths.is_empty() {
-                self.handle_full_view_load(scan.start_index);
-            }
+    /// Apply the settings last used in the scanned folder, if it was opened before, then those
+    /// of its `.luminous.toml`.
+    fn restore_view_profile(&mut self) {
+        self.restore_outside_folder();
+        if self.scan.is_dir
+            && let Some(profile) = self.library.view_profile(&self.scan.dir)
+        {
+            self.apply_view_profile(profile);
+        }
+        self.apply_folder_config();
+    }
 
-            self.handle_grid_request(0, 50);
+    fn apply_view_profile(&mut self, profile: ViewProfile) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        debug!(
+            "Restoring view profile for {:?}: {:?}",
+            self.scan.dir, profile
+        );
+        let gv = ui.global::<GridViewState>();
+        gv.set_grid_cols(profile.grid_cols.clamp(2, 20));
+        if let Some(mode) = Config::fit_mode_from_name(&profile.fit_mode) {
+            ui.global::<FullViewState>().set_fit_mode(mode);
+        }
+        self.sort_ascending = profile.sort_ascending;
+        if let Some(ascending) = profile.sort_ascending {
+            gv.set_sort_ascending(ascending);
+        }
+        gv.set_search_query(profile.query.clone().into());
+        if !profile.query.is_empty() {
+            self.handle_search(profile.query);
+        } else if self.sort_ascending.is_some() {
+            self.order_filtered();
+            self.rebuild_grid_model();
         }
     }
 
-    fn handle_sort(&mut self, ascending: bool) {
-        // TODO: collective function for refresh image models
-        self.filtered_indices.sort_by(|&a, &b| {
-            let path_a = &self.scan.paths[a];
-            let path_b = &self.scan.paths[b];
-            if ascending {
-                path_a.cmp(path_b)
-            } else {
-                path_b.cmp(path_a)
+    /// Put back the settings the `.luminous.toml` of the previous folder replaced.
+    fn restore_outside_folder(&mut self) {
+        let outside = std::mem::take(&mut self.outside_folder);
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        if let Some((interval, progress)) = outside.slideshow {
+            fv.set_slideshow_interval(interval);
+            fv.set_slideshow_progress_visible(progress);
+        }
+        if let Some(mode) = outside.fit_mode {
+            fv.set_fit_mode(mode);
+        }
+        if let Some(right_to_left) = outside.right_to_left {
+            fv.set_right_to_left(right_to_left);
+        }
+        if let Some(spread) = outside.spread {
+            fv.set_spread(spread);
+        }
+        let gv = ui.global::<GridViewState>();
+        if let Some(cols) = outside.grid_cols {
+            gv.set_grid_cols(cols);
+        }
+        if let Some(ascending) = outside.sort_ascending {
+            gv.set_sort_ascending(ascending);
+        }
+    }
+
+    /// Apply the `.luminous.toml` of the scanned folder, see [`FolderConfig`]. Its file filter
+    /// was already applied by the scan. The settings it replaces are kept in
+    /// [`Self::outside_folder`].
+    fn apply_folder_config(&mut self) {
+        let Some(ui) = self.window_weak.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let gv = ui.global::<GridViewState>();
+        let folder = FolderConfig::load(&self.scan.dir);
+        if folder.has_slideshow() {
+            self.outside_folder.slideshow = Some((
+                fv.get_slideshow_interval(),
+                fv.get_slideshow_progress_visible(),
+            ));
+        }
+        if let Some(interval) = folder.slideshow_interval {
+            fv.set_slideshow_interval((interval * 1000.0) as i64);
+        }
+        if let Some(progress) = folder.slideshow_progress {
+            fv.set_slideshow_progress_visible(progress);
+        }
+        if let Some(mode) = folder.fit_mode {
+            self.outside_folder.fit_mode = Some(fv.get_fit_mode());
+            fv.set_fit_mode(mode);
+        }
+        if let Some(right_to_left) = folder.right_to_left {
+            self.outside_folder.right_to_left = Some(fv.get_right_to_left());
+            fv.set_right_to_left(right_to_left);
+        }
+        if let Some(spread) = folder.spread {
+            self.outside_folder.spread = Some(fv.get_spread());
+            fv.set_spread(spread);
+        }
+        if let Some(cols) = folder.grid_columns {
+            self.outside_folder.grid_cols = Some(gv.get_grid_cols());
+            gv.set_grid_cols(cols);
+        }
+        if let Some(sort) = folder.sort {
+            self.outside_folder.sort_ascending = Some(gv.get_sort_ascending());
+            self.sort_ascending = sort.ascending();
+            if let Some(ascending) = self.sort_ascending {
+                gv.set_sort_ascending(ascending);
             }
-        });
+            self.order_filtered();
+            self.rebuild_grid_model();
+        }
+    }
 
+    /// Recreate the grid model in `filtered_indices` order, dropping the selection.
+    fn rebuild_grid_model(&mut self) {
         self.active_grid_indices.clear();
         self.loader.clear_thumbs();
 
@@ -790,38 +4236,131 @@ impl AppController {
             return;
         };
 
-        let filtered_items: Vec<GridItem> = self
-            .filtered_indices
-            .iter()
-            .enumerate()
-            .map(|(row, &abs_idx)| GridItem {
-                image: Image::default(),
-                index: row as i32,
-                abs_index: abs_idx as i32,
-                selected: false,
-            })
-            .collect();
-
         let gv = ui.global::<GridViewState>();
         gv.set_selected_count(0);
-        gv.set_model(Rc::new(VecModel::from(filtered_items)).into());
+        gv.set_has_custom_order(!self.custom_rank.is_empty());
+        gv.set_model(self.grid_model());
+        self.handle_grid_request(0, 50);
+    }
+}
 
-        if let Some(&first_abs) = self.filtered_indices.first() {
-            self.handle_full_view_load(first_abs);
+/// Order `indices` by their manual position, a no-op when there is no custom order.
+fn sort_by_rank(indices: &mut [usize], rank: &[usize]) {
+    if !rank.is_empty() {
+        indices.sort_by_key(|&i| (rank.get(i).copied().unwrap_or(usize::MAX), i));
+    }
+}
+
+/// Fill in the badges of grid and filmstrip items, matched by index and file name so that a
+/// folder opened in the meantime is left alone.
+fn set_badges(ui: &MainWindow, found: &[(usize, String, Vec<&'static str>)]) {
+    let badge_model = |badges: &[&'static str]| -> ModelRc<slint::SharedString> {
+        let badges: Vec<slint::SharedString> = badges.iter().map(|&b| b.into()).collect();
+        Rc::new(VecModel::from(badges)).into()
+    };
+    let grid = ui.global::<GridViewState>().get_model();
+    for (abs, name, badges) in found {
+        grid_model::update(&grid, *abs, |item| {
+            if item.name == name.as_str() {
+                item.badges = badge_model(badges);
+            }
+        });
+    }
+
+    let models = [
+        ui.global::<GridViewState>().get_visible_model(),
+        ui.global::<FullViewState>().get_filmstrip(),
+    ];
+    for model in models {
+        for row in 0..model.row_count() {
+            let Some(mut item) = model.row_data(row) else {
+                continue;
+            };
+            let Some((_, _, badges)) = found
+                .iter()
+                .find(|(abs, name, _)| item.abs_index == *abs as i32 && item.name == name.as_str())
+            else {
+                continue;
+            };
+            item.badges = badge_model(badges);
+            model.set_row_data(row, item);
         }
-        self.handle_grid_request(0, 50);
     }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    info!("Starting Luminous");
-    let init_start = std::time::Instant::now();
-    let mut plugin_manager = luminous_plugins::PluginManager::new();
+/// Show `message` over the views for a few seconds, replacing the one shown before.
+pub(crate) fn show_osd(ui: &MainWindow, message: String) {
+    ui.global::<OsdState>().set_message(message.clone().into());
+    let weak = ui.as_weak();
+    slint::Timer::single_shot(OSD_DURATION, move || {
+        let Some(ui) = weak.upgrade() else {
+            return;
+        };
+        let osd = ui.global::<OsdState>();
+        // A newer message is cleared by its own timer
+        if osd.get_message() == message.as_str() {
+            osd.set_message("".into());
+        }
+    });
+}
+
+/// PANO, HDR and BURST badges of the image at `abs_index`, once its shot kinds are read.
+fn badges(
+    scan: &ScanResult,
+    shot_kinds: &Mutex<HashMap<PathBuf, ShotKinds>>,
+    abs_index: usize,
+) -> ModelRc<slint::SharedString> {
+    let Some(path) = scan.paths.get(abs_index) else {
+        return ModelRc::default();
+    };
+    let kinds = shot_kinds.lock().unwrap().get(path).copied();
+    let badges: Vec<slint::SharedString> = kinds
+        .unwrap_or_default()
+        .badges()
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Rc::new(VecModel::from(badges)).into()
+}
+
+/// Name of the file at `path` for messages.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Run `f` on the clipboard, opened on first use and then kept open: on X11 the contents this
+/// process copied are only served while a clipboard handle is alive.
+fn with_clipboard<T>(
+    clipboard: &Mutex<Option<arboard::Clipboard>>,
+    f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+) -> Result<T, arboard::Error> {
+    let mut clipboard = clipboard.lock().unwrap();
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+    f(clipboard.as_mut().unwrap())
+}
+
+/// `#rrggbb`, with an alpha byte appended for translucent pixels.
+fn pixel_hex(p: Rgba8Pixel) -> String {
+    if p.a == u8::MAX {
+        format!("#{:02x}{:02x}{:02x}", p.r, p.g, p.b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", p.r, p.g, p.b, p.a)
+    }
+}
+
+/// Discover the installed plugins and start the ones set to auto start, none in safe mode.
+pub fn start_plugins(safe_mode: bool) -> PluginManager {
+    let mut plugin_manager = PluginManager::new();
 
     let mut settings = ui::settings_presenter::read_settings()
         .unwrap_or_else(|| ui::settings_presenter::Settings { plugins: vec![] });
 
-    if config.safe_mode {
+    if safe_mode {
         info!("Starting in safe mode");
     } else {
         let auto_start_ids: Vec<String> = settings
@@ -836,13 +4375,78 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
             error!("Failed to save plugins settings: {}", e);
         }
     }
+    plugin_manager
+}
+
+pub fn run(config: Config) -> error::Result<()> {
+    info!("Starting Luminous");
+    let init_start = std::time::Instant::now();
+    let plugin_manager = start_plugins(config.safe_mode);
 
     let extra_exts = plugin_manager.get_supported_extensions();
-    let scan = fs_scan::scan(&config.path, &extra_exts);
+    let library = Arc::new(Library::open());
+    let cached_state = app_state_cache::load_app_state();
+    // Tethering watches the working directory, not wherever the last session was
+    let no_path = !config.path_given
+        && !config.hot_folder
+        && config.demo.is_none()
+        && config.replay.is_none();
+    let session = cached_state
+        .session
+        .clone()
+        .filter(|s| no_path && config.restore_session && s.dir.is_dir());
+    // Without a session to pick up, the empty grid lists the recent locations
+    let startup_screen =
+        no_path && session.is_none() && library.recent().iter().any(|p| p.exists());
+    let target = match (config.demo, &session) {
+        (Some(_), _) | (None, Some(_)) => LaunchTarget::Directory,
+        _ if startup_screen => LaunchTarget::Missing,
+        (None, None) => fs_scan::classify(&config.path),
+    };
+    debug!("Startup path {:?} is a {:?}", config.path, target);
+    let scan = match (config.demo, target) {
+        (Some(count), _) => {
+            info!("Demo mode with {count} generated images");
+            ScanResult {
+                paths: demo::paths(count),
+                dir: demo::DEMO_DIR.into(),
+                start_index: 0,
+                is_dir: true,
+                image_formats: fs_scan::ImageFormats::new(),
+            }
+        }
+        (None, _) if startup_screen => {
+            info!("No path given, listing recent locations");
+            ScanResult {
+                paths: vec![],
+                dir: config.path.clone().into(),
+                start_index: 0,
+                is_dir: false,
+                image_formats: fs_scan::ImageFormats::new(),
+            }
+        }
+        (None, _) => match &session {
+            Some(session) => {
+                info!("Restoring session in {}", session.dir.display());
+                let mut scan = fs_scan::scan(
+                    &session.dir.to_string_lossy(),
+                    &extra_exts,
+                    config.filter.as_ref(),
+                );
+                scan.start_index = session
+                    .image
+                    .as_ref()
+                    .and_then(|image| scan.paths.iter().position(|p| p == image))
+                    .unwrap_or(session.index)
+                    .min(scan.paths.len().saturating_sub(1));
+                scan
+            }
+            None => fs_scan::scan(&config.path, &extra_exts, config.filter.as_ref()),
+        },
+    };
 
     let main_window = MainWindow::new()?;
 
-    let cached_state = app_state_cache::load_app_state();
     {
         let win = main_window.window();
         if cached_state.width > 0 && cached_state.height > 0 {
@@ -853,71 +4457,172 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
             win.set_position(slint::PhysicalPosition::new(cached_state.x, cached_state.y));
         }
         win.set_fullscreen(cached_state.fullscreen);
+        main_window.set_is_fullscreen(cached_state.fullscreen);
 
         let fv = main_window.global::<FullViewState>();
         fv.set_footer_visible(cached_state.full_view_footer_visible);
         fv.set_side_panel_visible(cached_state.full_view_side_panel_visible);
+        fv.set_filmstrip_enabled(cached_state.full_view_filmstrip_enabled);
 
         let gv = main_window.global::<GridViewState>();
         gv.set_side_panel_visible(cached_state.grid_view_side_panel_visible);
     }
 
-    let grid_data: Vec<GridItem> = scan
-        .paths
-        .iter()
-        .enumerate()
-        .map(|(i, _)| GridItem {
-            image: Image::default(),
-            index: i as i32,
-            abs_index: i as i32,
-            selected: false,
-        })
-        .collect();
-    main_window
-        .global::<GridViewState>()
-        .set_model(Rc::new(VecModel::from(grid_data)).into());
-
     let scan = Arc::new(scan);
     let app_controller = Rc::new(RefCell::new(AppController::new(
         plugin_manager,
+        library,
         scan.clone(),
         &config,
         &main_window,
     )));
 
+    // Rows are made as the grid gets to them, however many files the folder has
+    {
+        let acc = app_controller.borrow();
+        let gv = main_window.global::<GridViewState>();
+        gv.set_model(acc.grid_model());
+        gv.set_has_custom_order(!acc.custom_rank.is_empty());
+    }
+    app_controller.borrow().remember_location();
+
     let factory = Arc::new(StepFactory::new(false));
 
+    let dispatcher = ui::dispatcher::Dispatcher::new(
+        &main_window,
+        app_controller.clone(),
+        &cached_state,
+        config.macros.clone(),
+    );
+    ui::dispatcher::register(&main_window, dispatcher.clone());
     ui::grid_view_presenter::register(&main_window, app_controller.clone());
     ui::full_view_presenter::register(&main_window, app_controller.clone());
-    ui::pipeline_presenter::register(&main_window, app_controller.clone(), factory);
+    ui::pipeline_presenter::register(&main_window, app_controller.clone(), factory.clone());
     ui::settings_presenter::register(&main_window, app_controller.clone());
+    ui::command_palette_presenter::register(
+        &main_window,
+        app_controller.clone(),
+        dispatcher.clone(),
+    );
+    ui::perf_overlay_presenter::register(&main_window, app_controller.clone());
+    ui::status_bar_presenter::register(&main_window, app_controller.clone());
+    ui::background_presenter::register(
+        &main_window,
+        app_controller.clone(),
+        config.pause_in_background,
+    );
     ui::bindings::setup(&main_window, &config);
-
-    let acc = app_controller.clone();
-    main_window.on_open_images(move || {
-        AppController::handle_open_images(acc.clone());
+    ui::onboarding_presenter::register(&main_window, &config);
+    ui::hot_folder_presenter::register(&main_window, app_controller.clone(), factory);
+    ui::remote_presenter::register(&main_window, dispatcher.clone());
+    let _instance = config
+        .single_instance
+        .then(|| ui::remote_presenter::serve_instance(&main_window))
+        .flatten();
+    let _control = config
+        .control_socket
+        .as_ref()
+        .and_then(|socket| ui::remote_presenter::serve(&main_window, socket));
+    #[cfg(target_os = "linux")]
+    let _dbus = ui::remote_presenter::serve_dbus(&main_window);
+    #[cfg(feature = "scripting")]
+    ui::script_presenter::register(
+        &main_window,
+        app_controller.borrow().loader.plugin_manager.clone(),
+        &config,
+    );
+    #[cfg(not(feature = "scripting"))]
+    if !config.scripts.is_empty() {
+        warn!("[scripts] needs a build with the scripting feature, ignoring it");
+    }
+    let _config_watch = ui::config_presenter::watch(
+        &main_window,
+        app_controller.borrow().loader.clone(),
+        app_controller.borrow().toaster.clone(),
+        dispatcher.shared_macros(),
+        config.clone(),
+    );
+    let _hot_folder = config.hot_folder.then(|| {
+        ui::hot_folder_presenter::watch(
+            &main_window,
+            &app_controller,
+            std::path::Path::new(&config.path),
+        )
     });
 
-    let win_weak = main_window.as_weak();
-    main_window.on_quit_app(move || {
-        if let Some(mw) = win_weak.upgrade() {
-            app_state_cache::save_app_state(&mw);
+    let acc = app_controller.clone();
+    main_window.on_view_mode_changed(move |mode| {
+        acc.borrow().record(Action::View(mode));
+        // Where the full view was left, not where the grid was last scrolled to
+        if mode == ViewMode::Grid {
+            acc.borrow().reveal_current_row();
         }
-        let _ = slint::quit_event_loop();
     });
 
     main_window.set_app_background(config.background);
-    main_window.set_view_mode(if scan.is_dir {
-        ViewMode::Grid
-    } else {
-        ViewMode::Full
+    if let Some(profile) = &config.profile {
+        info!("Using config profile '{profile}'");
+        main_window.set_profile(profile.into());
+    }
+    main_window
+        .global::<GridViewState>()
+        .set_prefetch_rows(config.prefetch_rows as i32);
+    main_window
+        .global::<GridViewState>()
+        .set_captions_visible(config.grid_captions);
+    main_window
+        .global::<FullViewState>()
+        .set_fit_mode(config.fit_mode);
+    main_window
+        .global::<FullViewState>()
+        .set_playback_fps(config.playback_fps as i32);
+    main_window
+        .global::<FullViewState>()
+        .set_slideshow_interval((config.slideshow_interval * 1000.0) as i64);
+    main_window
+        .global::<FullViewState>()
+        .set_slideshow_progress_visible(config.slideshow_progress);
+    main_window
+        .global::<Accessibility>()
+        .set_reduced_motion(config.reduced_motion);
+    main_window
+        .global::<Theme>()
+        .set_high_contrast(config.high_contrast);
+    let target_view = match target {
+        LaunchTarget::Directory => config.directory_view,
+        LaunchTarget::File | LaunchTarget::Url => config.file_view,
+        LaunchTarget::Archive => config.archive_view,
+        // Nothing was opened, the grid offers to pick a folder
+        LaunchTarget::Missing => ViewMode::Grid,
+    };
+    main_window.set_view_mode(match config.mode {
+        Some(StartMode::Grid) => ViewMode::Grid,
+        Some(StartMode::Full) => ViewMode::Full,
+        Some(StartMode::Slideshow) => {
+            main_window
+                .global::<FullViewState>()
+                .set_slideshow_active(true);
+            ViewMode::Full
+        }
+        None => match &session {
+            Some(session) if session.full_view => ViewMode::Full,
+            Some(_) => ViewMode::Grid,
+            None => target_view,
+        },
     });
+    app_controller.borrow_mut().restore_view_profile();
 
     if !scan.paths.is_empty() {
         app_controller
             .borrow()
             .handle_full_view_load(scan.start_index);
-        ui::full_view_presenter::set_exif(app_controller);
+        if let Some(session) = session.as_ref().filter(|s| s.full_view) {
+            main_window
+                .global::<FullViewState>()
+                .set_zoom(session.zoom.clamp(1.0, 100.0));
+        }
+        ui::full_view_presenter::set_keywords(&app_controller);
+        ui::full_view_presenter::set_exif(app_controller.clone());
     }
 
     let encoder_extensions = scan.image_formats.get_all_encoding_exts();
@@ -929,6 +4634,19 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     let exts_model = std::rc::Rc::new(slint::VecModel::from(sorted_exts));
     main_window.set_encoder_extensions(exts_model.into());
 
+    if let Some(path) = &config.replay {
+        match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|script| recording::parse(&script))
+        {
+            Ok(steps) => {
+                info!("Replaying {} steps from {}", steps.len(), path.display());
+                dispatcher.clone().replay(Rc::new(steps), 0);
+            }
+            Err(e) => error!("Failed to replay {}: {}", path.display(), e),
+        }
+    }
+
     debug!(
         "Init in {:.1} ms",
         init_start.elapsed().as_secs_f64() * 1000.0
@@ -0,0 +1,979 @@
+use directories::ProjectDirs;
+use log::{debug, error, warn};
+use luminous_plugins::Tag;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Labels below this confidence are stored but not matched by search.
+const MIN_SEARCH_CONFIDENCE: f32 = 0.25;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS keywords (
+    path TEXT NOT NULL,
+    keyword TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    source TEXT NOT NULL,
+    PRIMARY KEY (path, keyword, source)
+);
+CREATE INDEX IF NOT EXISTS keywords_keyword ON keywords (keyword);
+CREATE TABLE IF NOT EXISTS custom_order (
+    dir TEXT NOT NULL,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (dir, name)
+);
+CREATE TABLE IF NOT EXISTS view_profiles (
+    dir TEXT PRIMARY KEY,
+    sort_ascending INTEGER,
+    query TEXT NOT NULL,
+    grid_cols INTEGER NOT NULL,
+    fit_mode TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS sort_keys (
+    path TEXT NOT NULL,
+    sorter TEXT NOT NULL,
+    mtime INTEGER NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (path, sorter)
+);
+CREATE TABLE IF NOT EXISTS ratings (
+    path TEXT PRIMARY KEY,
+    rating INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS marks (
+    path TEXT PRIMARY KEY,
+    flag TEXT NOT NULL,
+    label TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS favorites (
+    path TEXT PRIMARY KEY,
+    added INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS recent (
+    path TEXT PRIMARY KEY,
+    opened INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS colors (
+    path TEXT PRIMARY KEY,
+    mtime INTEGER NOT NULL,
+    palette TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS dimensions (
+    path TEXT PRIMARY KEY,
+    mtime INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL
+);
+";
+
+/// Root of the virtual folder the favorites view is shown as.
+pub const FAVORITES_DIR: &str = "favorites:";
+
+/// Recently opened locations kept, older ones are forgotten.
+pub const MAX_RECENT: usize = 12;
+
+/// View settings remembered for a folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewProfile {
+    /// By name, `None` keeps the scan or custom order
+    pub sort_ascending: Option<bool>,
+    pub query: String,
+    pub grid_cols: i32,
+    pub fit_mode: String,
+}
+
+/// Pick or reject flag set while culling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Flag {
+    #[default]
+    None,
+    Pick,
+    Reject,
+}
+
+/// Color label set while culling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorLabel {
+    #[default]
+    None,
+    Red,
+    Yellow,
+    Green,
+    Blue,
+}
+
+impl Flag {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "" | "none" => Some(Self::None),
+            "pick" => Some(Self::Pick),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Pick => "pick",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+impl ColorLabel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "" | "none" => Some(Self::None),
+            "red" => Some(Self::Red),
+            "yellow" => Some(Self::Yellow),
+            "green" => Some(Self::Green),
+            "blue" => Some(Self::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Red => "red",
+            Self::Yellow => "yellow",
+            Self::Green => "green",
+            Self::Blue => "blue",
+        }
+    }
+}
+
+/// Culling marks of an image, stored next to its rating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Marks {
+    pub flag: Flag,
+    pub label: ColorLabel,
+}
+
+/// Which images the grid keeps while culling, by their marks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkFilter {
+    #[default]
+    All,
+    Picks,
+    NoRejects,
+    Unflagged,
+    Label(ColorLabel),
+}
+
+impl MarkFilter {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(Self::All),
+            "picks" => Some(Self::Picks),
+            "no-rejects" => Some(Self::NoRejects),
+            "unflagged" => Some(Self::Unflagged),
+            label => match ColorLabel::from_name(label)? {
+                ColorLabel::None => None,
+                label => Some(Self::Label(label)),
+            },
+        }
+    }
+
+    pub fn accepts(self, marks: Marks) -> bool {
+        match self {
+            Self::All => true,
+            Self::Picks => marks.flag == Flag::Pick,
+            Self::NoRejects => marks.flag != Flag::Reject,
+            Self::Unflagged => marks.flag == Flag::None,
+            Self::Label(label) => marks.label == label,
+        }
+    }
+}
+
+/// Persistent per-image metadata, stored in `library.db` in the data directory.
+pub struct Library {
+    conn: Mutex<Connection>,
+}
+
+impl Library {
+    pub fn open() -> Self {
+        let conn = ProjectDirs::from("", "", "luminous")
+            .and_then(|dirs| {
+                let data_dir = dirs.data_dir();
+                std::fs::create_dir_all(data_dir)
+                    .map_err(|e| error!("Failed to create data dir: {e}"))
+                    .ok()?;
+                let path = data_dir.join("library.db");
+                debug!("Opening library at {:?}", path);
+                Connection::open(&path)
+                    .map_err(|e| error!("Failed to open library {:?}: {}", path, e))
+                    .ok()
+            })
+            .unwrap_or_else(|| {
+                warn!("Falling back to an in-memory library");
+                Connection::open_in_memory().expect("Failed to open in-memory library")
+            });
+        Self::with_connection(conn)
+    }
+
+    fn with_connection(conn: Connection) -> Self {
+        if let Err(e) = conn.execute_batch(SCHEMA) {
+            error!("Failed to initialize library schema: {}", e);
+        }
+        Self {
+            conn: Mutex::new(conn),
+        }
+    }
+
+    /// Replace all keywords previously stored for `path` by `source`.
+    pub fn set_keywords(&self, path: &Path, tags: &[Tag], source: &str) -> bool {
+        let Ok(mut conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let path_str = path.to_string_lossy();
+        let result = conn.transaction().and_then(|tx| {
+            tx.execute(
+                "DELETE FROM keywords WHERE path = ?1 AND source = ?2",
+                params![path_str, source],
+            )?;
+            for tag in tags {
+                tx.execute(
+                    "INSERT OR REPLACE INTO keywords (path, keyword, confidence, source)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![path_str, tag.label.to_lowercase(), tag.confidence, source],
+                )?;
+            }
+            tx.commit()
+        });
+        result
+            .map_err(|e| error!("Failed to store keywords for {:?}: {}", path, e))
+            .is_ok()
+    }
+
+    pub fn keywords(&self, path: &Path) -> Vec<Tag> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return Vec::new();
+        };
+        let query = || -> rusqlite::Result<Vec<Tag>> {
+            let mut stmt = conn.prepare(
+                "SELECT keyword, MAX(confidence) FROM keywords WHERE path = ?1
+                 GROUP BY keyword ORDER BY 2 DESC",
+            )?;
+            stmt.query_map(params![path.to_string_lossy()], |row| {
+                Ok(Tag {
+                    label: row.get(0)?,
+                    confidence: row.get(1)?,
+                })
+            })?
+            .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read keywords for {:?}: {}", path, e);
+            Vec::new()
+        })
+    }
+
+    /// Paths with a keyword starting with `query`, case-insensitive.
+    pub fn search_keyword(&self, query: &str) -> Vec<PathBuf> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return Vec::new();
+        };
+        let pattern = format!("{}%", query.to_lowercase().replace(['%', '_'], ""));
+        let query = || -> rusqlite::Result<Vec<PathBuf>> {
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT path FROM keywords WHERE keyword LIKE ?1 AND confidence >= ?2",
+            )?;
+            stmt.query_map(params![pattern, MIN_SEARCH_CONFIDENCE], |row| {
+                row.get::<_, String>(0).map(PathBuf::from)
+            })?
+            .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Keyword search failed: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Replace the manual ordering of the file names in `dir`.
+    pub fn set_custom_order(&self, dir: &Path, names: &[String]) -> bool {
+        let Ok(mut conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let dir_str = dir.to_string_lossy();
+        let result = conn.transaction().and_then(|tx| {
+            tx.execute("DELETE FROM custom_order WHERE dir = ?1", params![dir_str])?;
+            for (position, name) in names.iter().enumerate() {
+                tx.execute(
+                    "INSERT OR REPLACE INTO custom_order (dir, name, position) VALUES (?1, ?2, ?3)",
+                    params![dir_str, name, position as i64],
+                )?;
+            }
+            tx.commit()
+        });
+        result
+            .map_err(|e| error!("Failed to store custom order for {:?}: {}", dir, e))
+            .is_ok()
+    }
+
+    /// File names of `dir` in their manual order, empty if it was never reordered.
+    pub fn custom_order(&self, dir: &Path) -> Vec<String> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return Vec::new();
+        };
+        let query = || -> rusqlite::Result<Vec<String>> {
+            let mut stmt =
+                conn.prepare("SELECT name FROM custom_order WHERE dir = ?1 ORDER BY position")?;
+            stmt.query_map(params![dir.to_string_lossy()], |row| row.get(0))?
+                .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read custom order for {:?}: {}", dir, e);
+            Vec::new()
+        })
+    }
+
+    pub fn set_view_profile(&self, dir: &Path, profile: &ViewProfile) -> bool {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO view_profiles (dir, sort_ascending, query, grid_cols, fit_mode)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                dir.to_string_lossy(),
+                profile.sort_ascending,
+                profile.query,
+                profile.grid_cols,
+                profile.fit_mode
+            ],
+        )
+        .map_err(|e| error!("Failed to store view profile for {:?}: {}", dir, e))
+        .is_ok()
+    }
+
+    pub fn view_profile(&self, dir: &Path) -> Option<ViewProfile> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return None;
+        };
+        conn.query_row(
+            "SELECT sort_ascending, query, grid_cols, fit_mode FROM view_profiles WHERE dir = ?1",
+            params![dir.to_string_lossy()],
+            |row| {
+                Ok(ViewProfile {
+                    sort_ascending: row.get(0)?,
+                    query: row.get(1)?,
+                    grid_cols: row.get(2)?,
+                    fit_mode: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+        .unwrap_or_else(|e| {
+            error!("Failed to read view profile for {:?}: {}", dir, e);
+            None
+        })
+    }
+
+    /// Store plugin sort keys, each with the modification time of the file it was computed for.
+    pub fn set_sort_keys(&self, sorter: &str, keys: &[(PathBuf, i64, f64)]) -> bool {
+        let Ok(mut conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let result = conn.transaction().and_then(|tx| {
+            for (path, mtime, value) in keys {
+                tx.execute(
+                    "INSERT OR REPLACE INTO sort_keys (path, sorter, mtime, value)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![path.to_string_lossy(), sorter, mtime, value],
+                )?;
+            }
+            tx.commit()
+        });
+        result
+            .map_err(|e| error!("Failed to store {} sort keys: {}", sorter, e))
+            .is_ok()
+    }
+
+    /// Cached sort keys of the `(path, mtime)` files, files changed since are left out.
+    pub fn sort_keys(&self, sorter: &str, files: &[(PathBuf, i64)]) -> HashMap<PathBuf, f64> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return HashMap::new();
+        };
+        let query = || -> rusqlite::Result<HashMap<PathBuf, f64>> {
+            let mut stmt = conn.prepare(
+                "SELECT value FROM sort_keys WHERE path = ?1 AND sorter = ?2 AND mtime = ?3",
+            )?;
+            let mut keys = HashMap::new();
+            for (path, mtime) in files {
+                let value: Option<f64> = stmt
+                    .query_row(params![path.to_string_lossy(), sorter, mtime], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                if let Some(value) = value {
+                    keys.insert(path.clone(), value);
+                }
+            }
+            Ok(keys)
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read {} sort keys: {}", sorter, e);
+            HashMap::new()
+        })
+    }
+
+    /// Store the dominant colors of `path`, as written by `color_search::to_text`.
+    pub fn set_palette(&self, path: &Path, mtime: i64, palette: &str) -> bool {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO colors (path, mtime, palette) VALUES (?1, ?2, ?3)",
+            params![path.to_string_lossy(), mtime, palette],
+        )
+        .map_err(|e| error!("Failed to store the colors of {:?}: {}", path, e))
+        .is_ok()
+    }
+
+    /// Cached dominant colors of the `(path, mtime)` files, files changed since are left out.
+    pub fn palettes(&self, files: &[(PathBuf, i64)]) -> HashMap<PathBuf, String> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return HashMap::new();
+        };
+        let query = || -> rusqlite::Result<HashMap<PathBuf, String>> {
+            let mut stmt =
+                conn.prepare("SELECT palette FROM colors WHERE path = ?1 AND mtime = ?2")?;
+            let mut palettes = HashMap::new();
+            for (path, mtime) in files {
+                let palette: Option<String> = stmt
+                    .query_row(params![path.to_string_lossy(), mtime], |row| row.get(0))
+                    .optional()?;
+                if let Some(palette) = palette {
+                    palettes.insert(path.clone(), palette);
+                }
+            }
+            Ok(palettes)
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read image colors: {}", e);
+            HashMap::new()
+        })
+    }
+
+    /// Store the width and height of `path`, read from its header.
+    pub fn set_dimensions(&self, path: &Path, mtime: i64, (width, height): (u32, u32)) -> bool {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO dimensions VALUES (?1, ?2, ?3, ?4)",
+            params![path.to_string_lossy(), mtime, width, height],
+        )
+        .map_err(|e| error!("Failed to store the dimensions of {:?}: {}", path, e))
+        .is_ok()
+    }
+
+    /// Cached width and height of the `(path, mtime)` files, files changed since are left out.
+    pub fn dimensions(&self, files: &[(PathBuf, i64)]) -> HashMap<PathBuf, (u32, u32)> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return HashMap::new();
+        };
+        let query = || -> rusqlite::Result<HashMap<PathBuf, (u32, u32)>> {
+            let mut stmt = conn
+                .prepare("SELECT width, height FROM dimensions WHERE path = ?1 AND mtime = ?2")?;
+            let mut dimensions = HashMap::new();
+            for (path, mtime) in files {
+                let size: Option<(u32, u32)> = stmt
+                    .query_row(params![path.to_string_lossy(), mtime], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })
+                    .optional()?;
+                if let Some(size) = size {
+                    dimensions.insert(path.clone(), size);
+                }
+            }
+            Ok(dimensions)
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read image dimensions: {}", e);
+            HashMap::new()
+        })
+    }
+
+    /// Star rating of `path` from 1 to 5, 0 clears it.
+    pub fn set_rating(&self, path: &Path, rating: u8) -> bool {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let path_str = path.to_string_lossy();
+        let result = if rating == 0 {
+            conn.execute("DELETE FROM ratings WHERE path = ?1", params![path_str])
+        } else {
+            conn.execute(
+                "INSERT OR REPLACE INTO ratings (path, rating) VALUES (?1, ?2)",
+                params![path_str, rating.min(5)],
+            )
+        };
+        result
+            .map_err(|e| error!("Failed to store rating for {:?}: {}", path, e))
+            .is_ok()
+    }
+
+    /// Ratings of `paths` in the same order, 0 for unrated images.
+    pub fn ratings(&self, paths: &[PathBuf]) -> Vec<u8> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return vec![0; paths.len()];
+        };
+        let query = || -> rusqlite::Result<Vec<u8>> {
+            let mut stmt = conn.prepare("SELECT rating FROM ratings WHERE path = ?1")?;
+            paths
+                .iter()
+                .map(|path| {
+                    stmt.query_row(params![path.to_string_lossy()], |row| row.get(0))
+                        .optional()
+                        .map(Option::unwrap_or_default)
+                })
+                .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read ratings: {}", e);
+            vec![0; paths.len()]
+        })
+    }
+
+    pub fn set_marks(&self, path: &Path, marks: Marks) -> bool {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let path_str = path.to_string_lossy();
+        let result = if marks == Marks::default() {
+            conn.execute("DELETE FROM marks WHERE path = ?1", params![path_str])
+        } else {
+            conn.execute(
+                "INSERT OR REPLACE INTO marks (path, flag, label) VALUES (?1, ?2, ?3)",
+                params![path_str, marks.flag.name(), marks.label.name()],
+            )
+        };
+        result
+            .map_err(|e| error!("Failed to store marks for {:?}: {}", path, e))
+            .is_ok()
+    }
+
+    /// Marks of `paths` in the same order, unknown names read as unmarked.
+    pub fn marks(&self, paths: &[PathBuf]) -> Vec<Marks> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return vec![Marks::default(); paths.len()];
+        };
+        let query = || -> rusqlite::Result<Vec<Marks>> {
+            let mut stmt = conn.prepare("SELECT flag, label FROM marks WHERE path = ?1")?;
+            paths
+                .iter()
+                .map(|path| {
+                    stmt.query_row(params![path.to_string_lossy()], |row| {
+                        Ok(Marks {
+                            flag: Flag::from_name(&row.get::<_, String>(0)?).unwrap_or_default(),
+                            label: ColorLabel::from_name(&row.get::<_, String>(1)?)
+                                .unwrap_or_default(),
+                        })
+                    })
+                    .optional()
+                    .map(Option::unwrap_or_default)
+                })
+                .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read marks: {}", e);
+            vec![Marks::default(); paths.len()]
+        })
+    }
+
+    /// Add `path` to the favorites or remove it, returns whether it is a favorite now.
+    pub fn toggle_favorite(&self, path: &Path) -> Option<bool> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return None;
+        };
+        let path_str = path.to_string_lossy();
+        let toggle = || -> rusqlite::Result<bool> {
+            if conn.execute("DELETE FROM favorites WHERE path = ?1", params![path_str])? > 0 {
+                return Ok(false);
+            }
+            conn.execute(
+                "INSERT INTO favorites (path, added) VALUES (?1, ?2)",
+                params![path_str, chrono::Utc::now().timestamp_millis()],
+            )?;
+            Ok(true)
+        };
+        toggle()
+            .map_err(|e| error!("Failed to toggle favorite {:?}: {}", path, e))
+            .ok()
+    }
+
+    /// Favorite images from every folder, the last added last.
+    pub fn favorites(&self) -> Vec<PathBuf> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return Vec::new();
+        };
+        let query = || -> rusqlite::Result<Vec<PathBuf>> {
+            let mut stmt = conn.prepare("SELECT path FROM favorites ORDER BY added, rowid")?;
+            stmt.query_map([], |row| row.get::<_, String>(0).map(PathBuf::from))?
+                .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read favorites: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Move `path`, a folder or a file opened on its own, to the front of the recent locations.
+    pub fn add_recent(&self, path: &Path) -> bool {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let add = || -> rusqlite::Result<()> {
+            conn.execute(
+                "INSERT OR REPLACE INTO recent (path, opened) VALUES (?1, ?2)",
+                params![
+                    path.to_string_lossy(),
+                    chrono::Utc::now().timestamp_millis()
+                ],
+            )?;
+            conn.execute(
+                "DELETE FROM recent WHERE path NOT IN
+                    (SELECT path FROM recent ORDER BY opened DESC, rowid DESC LIMIT ?1)",
+                params![MAX_RECENT as i64],
+            )?;
+            Ok(())
+        };
+        add()
+            .map_err(|e| error!("Failed to remember {:?} as recent: {}", path, e))
+            .is_ok()
+    }
+
+    /// Recently opened locations, the last one first.
+    pub fn recent(&self) -> Vec<PathBuf> {
+        let Ok(conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return Vec::new();
+        };
+        let query = || -> rusqlite::Result<Vec<PathBuf>> {
+            let mut stmt =
+                conn.prepare("SELECT path FROM recent ORDER BY opened DESC, rowid DESC")?;
+            stmt.query_map([], |row| row.get::<_, String>(0).map(PathBuf::from))?
+                .collect()
+        };
+        query().unwrap_or_else(|e| {
+            error!("Failed to read recent locations: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Carry keywords, ratings, marks, favorites and manual order over to renamed files. Names
+    /// may be swapped within the batch, so rows pass through a placeholder first.
+    pub fn rename_paths(&self, renames: &[(PathBuf, PathBuf)]) -> bool {
+        let Ok(mut conn) = self.conn.lock() else {
+            error!("Library lock poisoned");
+            return false;
+        };
+        let result = conn.transaction().and_then(|tx| {
+            // Path and file name rows are keyed by, custom_order by the folder and the name
+            let rename =
+                |dir: &str, (old, old_name): (&str, &str), (new, new_name): (&str, &str)| {
+                    for table in [
+                        "keywords",
+                        "sort_keys",
+                        "ratings",
+                        "marks",
+                        "favorites",
+                        "colors",
+                        "dimensions",
+                    ] {
+                        tx.execute(
+                            &format!("UPDATE {table} SET path = ?1 WHERE path = ?2"),
+                            params![new, old],
+                        )?;
+                    }
+                    tx.execute(
+                        "UPDATE custom_order SET name = ?1 WHERE dir = ?2 AND name = ?3",
+                        params![new_name, dir, old_name],
+                    )
+                };
+            let parts = |p: &Path| {
+                (
+                    p.parent()
+                        .unwrap_or(Path::new(""))
+                        .to_string_lossy()
+                        .into_owned(),
+                    p.to_string_lossy().into_owned(),
+                    p.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            };
+            let placeholder = |i: usize| format!("\0rename:{i}");
+            for (i, (old, _)) in renames.iter().enumerate() {
+                let (dir, path, name) = parts(old);
+                rename(&dir, (&path, &name), (&placeholder(i), &placeholder(i)))?;
+            }
+            for (i, (_, new)) in renames.iter().enumerate() {
+                let (dir, path, name) = parts(new);
+                rename(&dir, (&placeholder(i), &placeholder(i)), (&path, &name))?;
+            }
+            tx.commit()
+        });
+        result
+            .map_err(|e| error!("Failed to carry library data over to renamed files: {}", e))
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(label: &str, confidence: f32) -> Tag {
+        Tag {
+            label: label.to_string(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn search_matches_confident_keywords() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let dog = Path::new("/photos/dog.jpg");
+        let cat = Path::new("/photos/cat.jpg");
+        assert!(lib.set_keywords(dog, &[tag("Dog", 0.9), tag("grass", 0.4)], "tagger"));
+        assert!(lib.set_keywords(cat, &[tag("cat", 0.8), tag("dog", 0.1)], "tagger"));
+
+        assert_eq!(lib.search_keyword("dog"), vec![dog.to_path_buf()]);
+        assert_eq!(lib.search_keyword("GRA"), vec![dog.to_path_buf()]);
+        assert!(lib.search_keyword("car").is_empty());
+    }
+
+    #[test]
+    fn set_keywords_replaces_previous_from_same_source() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let path = Path::new("/photos/a.jpg");
+        lib.set_keywords(path, &[tag("dog", 0.9)], "tagger");
+        lib.set_keywords(path, &[tag("beach", 0.7)], "tagger");
+        lib.set_keywords(path, &[tag("holiday", 1.0)], "user");
+
+        let labels: Vec<String> = lib.keywords(path).into_iter().map(|t| t.label).collect();
+        assert_eq!(labels, vec!["holiday", "beach"]);
+    }
+
+    #[test]
+    fn custom_order_is_per_dir() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let names = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let a = Path::new("/photos/a");
+        assert!(lib.custom_order(a).is_empty());
+
+        lib.set_custom_order(a, &names(&["3.jpg", "1.jpg", "2.jpg"]));
+        lib.set_custom_order(Path::new("/photos/b"), &names(&["x.jpg"]));
+        assert_eq!(lib.custom_order(a), names(&["3.jpg", "1.jpg", "2.jpg"]));
+
+        lib.set_custom_order(a, &names(&["2.jpg", "3.jpg"]));
+        assert_eq!(lib.custom_order(a), names(&["2.jpg", "3.jpg"]));
+
+        lib.set_custom_order(a, &[]);
+        assert!(lib.custom_order(a).is_empty());
+    }
+
+    #[test]
+    fn view_profile_roundtrip() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let dir = Path::new("/photos/trip");
+        assert_eq!(lib.view_profile(dir), None);
+
+        let mut profile = ViewProfile {
+            sort_ascending: None,
+            query: "beach".to_string(),
+            grid_cols: 8,
+            fit_mode: "fill".to_string(),
+        };
+        assert!(lib.set_view_profile(dir, &profile));
+        assert_eq!(lib.view_profile(dir), Some(profile.clone()));
+
+        profile.sort_ascending = Some(false);
+        lib.set_view_profile(dir, &profile);
+        assert_eq!(lib.view_profile(dir), Some(profile));
+        assert_eq!(lib.view_profile(Path::new("/photos")), None);
+    }
+
+    #[test]
+    fn sort_keys_skip_modified_files() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let a = PathBuf::from("/photos/a.jpg");
+        let b = PathBuf::from("/photos/b.jpg");
+        assert!(lib.set_sort_keys(
+            "aspect_ratio",
+            &[(a.clone(), 10, 1.5), (b.clone(), 10, 0.75)]
+        ));
+        lib.set_sort_keys("dominant_hue", &[(a.clone(), 10, 200.0)]);
+
+        let keys = lib.sort_keys("aspect_ratio", &[(a.clone(), 10), (b.clone(), 20)]);
+        assert_eq!(keys, HashMap::from([(a.clone(), 1.5)]));
+        assert_eq!(lib.sort_keys("dominant_hue", &[(a.clone(), 10)])[&a], 200.0);
+        assert!(lib.sort_keys("size", &[(a, 10)]).is_empty());
+    }
+
+    #[test]
+    fn palettes_skip_modified_files() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let a = PathBuf::from("/photos/a.jpg");
+        let b = PathBuf::from("/photos/b.jpg");
+        assert!(lib.set_palette(&a, 10, "ff0000:0.600"));
+        assert!(lib.set_palette(&b, 10, "0000ff:1.000"));
+        assert!(lib.set_palette(&a, 12, "00ff00:0.500"));
+
+        let palettes = lib.palettes(&[(a.clone(), 12), (b.clone(), 20)]);
+        assert_eq!(palettes, HashMap::from([(a, "00ff00:0.500".to_string())]));
+    }
+
+    #[test]
+    fn dimensions_skip_modified_files() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let a = PathBuf::from("/photos/a.jpg");
+        let b = PathBuf::from("/photos/b.jpg");
+        assert!(lib.set_dimensions(&a, 10, (4000, 3000)));
+        assert!(lib.set_dimensions(&b, 10, (1080, 1920)));
+
+        let dimensions = lib.dimensions(&[(a.clone(), 10), (b, 11)]);
+        assert_eq!(dimensions, HashMap::from([(a, (4000, 3000))]));
+    }
+
+    #[test]
+    fn ratings_roundtrip() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let a = PathBuf::from("/photos/a.jpg");
+        let b = PathBuf::from("/photos/b.jpg");
+        assert!(lib.set_rating(&a, 4));
+        assert!(lib.set_rating(&b, 9));
+        assert_eq!(lib.ratings(&[a.clone(), b.clone()]), [4, 5]);
+
+        assert!(lib.set_rating(&a, 0));
+        assert_eq!(lib.ratings(&[a, PathBuf::from("/photos/c.jpg")]), [0, 0]);
+    }
+
+    #[test]
+    fn marks_roundtrip() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let a = PathBuf::from("/photos/a.jpg");
+        let b = PathBuf::from("/photos/b.jpg");
+        let picked_red = Marks {
+            flag: Flag::Pick,
+            label: ColorLabel::Red,
+        };
+        assert!(lib.set_marks(&a, picked_red));
+        lib.set_marks(
+            &b,
+            Marks {
+                flag: Flag::Reject,
+                ..Marks::default()
+            },
+        );
+        lib.set_rating(&a, 3);
+        let marks = lib.marks(&[a.clone(), b.clone()]);
+        assert_eq!(marks[0], picked_red);
+        assert_eq!(marks[1].flag, Flag::Reject);
+        assert_eq!(lib.ratings(std::slice::from_ref(&a)), [3]);
+
+        assert!(lib.set_marks(&a, Marks::default()));
+        assert_eq!(lib.marks(&[a]), [Marks::default()]);
+    }
+
+    #[test]
+    fn mark_filters() {
+        let rejected_blue = Marks {
+            flag: Flag::Reject,
+            label: ColorLabel::Blue,
+        };
+        assert!(MarkFilter::from_name("all").unwrap().accepts(rejected_blue));
+        assert!(
+            !MarkFilter::from_name("no-rejects")
+                .unwrap()
+                .accepts(rejected_blue)
+        );
+        assert!(
+            MarkFilter::from_name("blue")
+                .unwrap()
+                .accepts(rejected_blue)
+        );
+        assert!(!MarkFilter::Picks.accepts(Marks::default()));
+        assert!(MarkFilter::Unflagged.accepts(Marks::default()));
+        assert_eq!(MarkFilter::from_name("none"), None);
+    }
+
+    #[test]
+    fn favorites_toggle_in_order() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let a = PathBuf::from("/photos/a.jpg");
+        let b = PathBuf::from("/other/b.jpg");
+        assert_eq!(lib.toggle_favorite(&b), Some(true));
+        assert_eq!(lib.toggle_favorite(&a), Some(true));
+        assert_eq!(lib.favorites(), [b.clone(), a.clone()]);
+
+        assert_eq!(lib.toggle_favorite(&b), Some(false));
+        assert_eq!(lib.favorites(), [a]);
+        // Adding it again makes it the last bookmark
+        assert_eq!(lib.toggle_favorite(&b), Some(true));
+        assert_eq!(lib.favorites().last(), Some(&b));
+    }
+
+    #[test]
+    fn recent_is_most_recent_first_and_capped() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let paths: Vec<PathBuf> = (0..MAX_RECENT + 2)
+            .map(|i| PathBuf::from(format!("/photos/{i}")))
+            .collect();
+        for path in &paths {
+            lib.add_recent(path);
+        }
+        // Reopening moves it to the front
+        lib.add_recent(&paths[5]);
+
+        let recent = lib.recent();
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0], paths[5]);
+        assert_eq!(recent[1], paths[MAX_RECENT + 1]);
+        assert!(!recent.contains(&paths[0]) && !recent.contains(&paths[1]));
+    }
+
+    #[test]
+    fn rename_paths_carries_data_and_swaps() {
+        let lib = Library::with_connection(Connection::open_in_memory().unwrap());
+        let dir = Path::new("/photos");
+        let (a, b, c) = (dir.join("a.jpg"), dir.join("b.jpg"), dir.join("c.jpg"));
+        lib.set_rating(&a, 5);
+        lib.set_rating(&b, 1);
+        lib.toggle_favorite(&b);
+        lib.set_custom_order(dir, &["b.jpg".to_string(), "a.jpg".to_string()]);
+
+        // a takes the name b gives up for c
+        assert!(lib.rename_paths(&[(a.clone(), b.clone()), (b.clone(), c.clone())]));
+        assert_eq!(lib.ratings(&[a, b, c.clone()]), [0, 5, 1]);
+        assert_eq!(lib.favorites(), [c]);
+        assert_eq!(lib.custom_order(dir), ["c.jpg", "b.jpg"]);
+    }
+}
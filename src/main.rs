@@ -1,8 +1,15 @@
 use color_print::cformat;
 use std::io::Write;
 use std::process;
+use std::sync::Arc;
 
-use luminous::config::Config;
+use luminous::config::{Command, Config, ConfigAction, ExifAction};
+use luminous::convert::{self, ConvertOptions};
+use luminous::error::LuminousError;
+use luminous::library::{FAVORITES_DIR, Library};
+use luminous::watermark::Stamp;
+use luminous::{file_association, fs_scan, keymap, metadata, remote, wallpaper};
+use std::path::{Path, PathBuf};
 
 fn main() {
     let config = Config::load();
@@ -43,11 +50,282 @@ fn main() {
         .filter_module("naga", log::LevelFilter::Warn)
         .filter_module("wgpu_hal", log::LevelFilter::Warn)
         .init();
+    fs_scan::set_extension_overrides(config.extensions.clone());
+
+    if let Some(command) = &config.command {
+        let (result, done) = match command {
+            Command::InstallIntegration { default } => (
+                file_association::install(*default),
+                "Installed desktop integration",
+            ),
+            Command::UninstallIntegration => {
+                (file_association::uninstall(), "Removed desktop integration")
+            }
+            Command::Exif { action } => process::exit(edit_exif(action)),
+            Command::Info { json, paths } => process::exit(print_info(paths, *json)),
+            Command::Config { action } => process::exit(manage_config(action, &config)),
+            Command::Bindings => {
+                print!("{}", keymap::help(&config.key_bindings()));
+                process::exit(0)
+            }
+            Command::Wallpaper {
+                path,
+                interval,
+                shuffle,
+            } => {
+                let path = path.clone().unwrap_or_else(|| config.path.clone());
+                process::exit(cycle_wallpaper(&path, &config, *interval, *shuffle))
+            }
+            Command::Convert {
+                to,
+                max_size,
+                quality,
+                overwrite,
+                no_watermark,
+                src,
+                dst,
+            } => {
+                let options = ConvertOptions {
+                    format: to.trim_start_matches('.').to_lowercase(),
+                    max_size: *max_size,
+                    quality: quality.unwrap_or(config.jpeg_quality),
+                    overwrite: *overwrite,
+                    watermark: config
+                        .watermark
+                        .as_ref()
+                        .filter(|_| !no_watermark)
+                        .and_then(Stamp::new),
+                };
+                process::exit(convert_images(src, dst, &options, &config))
+            }
+        };
+        if let Err(e) = result {
+            log::error!("Desktop integration failed: {e}");
+            process::exit(1);
+        }
+        println!("{done}");
+        return;
+    }
+
+    if config.single_instance && forward_to_instance(&config) {
+        return;
+    }
 
     log::info!("Starting with {} worker threads", config.threads);
+    if config.on_battery {
+        log::info!("On battery power, prefetching and animations are off and playback is limited");
+    }
 
     if let Err(e) = luminous::run(config) {
         log::error!("Application error: {e}");
         process::exit(1);
     };
 }
+
+/// Hand the path to an already running viewer, false when there is none and this process
+/// should open it.
+fn forward_to_instance(config: &Config) -> bool {
+    // Demos, tethering and session scripts get a window of their own
+    if config.demo.is_some()
+        || config.hot_folder
+        || config.record.is_some()
+        || config.replay.is_some()
+    {
+        return false;
+    }
+    let Some(socket) = remote::instance_socket() else {
+        return false;
+    };
+    let request = if config.path_given {
+        // The running viewer has another working directory
+        match std::path::absolute(&config.path) {
+            Ok(path) => remote::Request::Open(path),
+            Err(e) => {
+                log::warn!("Can't resolve {}: {e}", config.path);
+                return false;
+            }
+        }
+    } else {
+        remote::Request::Focus
+    };
+    match remote::send(&socket, &request) {
+        Ok(_) => {
+            log::info!("Handed {request:?} to the running viewer");
+            true
+        }
+        Err(e) => {
+            log::debug!("No running viewer to hand {request:?} to: {e}");
+            false
+        }
+    }
+}
+
+/// Run `luminous config init/path`, the exit code is 1 if the file couldn't be written.
+fn manage_config(action: &ConfigAction, config: &Config) -> i32 {
+    let Some(path) = config.config_path.clone().or_else(Config::default_path) else {
+        eprintln!("No config folder on this system, use --config-file");
+        return 1;
+    };
+    match action {
+        ConfigAction::Path => {
+            println!("{}", path.display());
+            if !path.exists() {
+                eprintln!("Not written yet, luminous config init writes the defaults there");
+            }
+            0
+        }
+        ConfigAction::Init { force } => match Config::write_default(&path, *force) {
+            Ok(()) => {
+                println!("Wrote the default config to {}", path.display());
+                0
+            }
+            Err(e @ LuminousError::Config(_)) => {
+                eprintln!("{e}, --force replaces it");
+                1
+            }
+            Err(e) => {
+                eprintln!("Can't write {}: {e}", path.display());
+                1
+            }
+        },
+    }
+}
+
+/// Run `luminous exif set/del`, the exit code is 1 if any file failed.
+fn edit_exif(action: &ExifAction) -> i32 {
+    let (edits, files) = action.edits();
+    if edits.is_empty() {
+        eprintln!("No tags given, see --help");
+        return 2;
+    }
+    let mut failed = 0;
+    for file in files {
+        if let Err(e) = metadata::edit_tags(file, &edits) {
+            log::error!("{}: {e}", file.display());
+            failed += 1;
+        }
+    }
+    let tags: Vec<&str> = edits.iter().map(|e| e.tag.name()).collect();
+    println!(
+        "Edited {} in {} of {} files",
+        tags.join(", "),
+        files.len() - failed,
+        files.len()
+    );
+    i32::from(failed > 0)
+}
+
+/// Run `luminous info`, the exit code is 1 if any file couldn't be read.
+fn print_info(paths: &[PathBuf], json: bool) -> i32 {
+    let mut infos = Vec::new();
+    for path in paths {
+        match metadata::read_info(path) {
+            Ok(info) => infos.push(info),
+            Err(e) => eprintln!("{}: {e}", path.display()),
+        }
+    }
+    let failed = infos.len() < paths.len();
+    if json {
+        match serde_json::to_string_pretty(&infos) {
+            Ok(text) => println!("{text}"),
+            Err(e) => {
+                eprintln!("Can't write JSON: {e}");
+                return 1;
+            }
+        }
+        return i32::from(failed);
+    }
+    let unknown = || "unknown".to_string();
+    for (i, info) in infos.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", info.path);
+        println!("  Size: {} bytes", info.size);
+        println!("  Format: {}", info.format.clone().unwrap_or_else(unknown));
+        match (info.width, info.height) {
+            (Some(w), Some(h)) => println!("  Dimensions: {w}x{h}"),
+            _ => println!("  Dimensions: {}", unknown()),
+        }
+        println!(
+            "  Color type: {}",
+            info.color_type.clone().unwrap_or_else(unknown)
+        );
+        if info.exif.is_empty() {
+            println!("  No EXIF data");
+        }
+        for (tag, value) in &info.exif {
+            println!("  {tag}: {value}");
+        }
+    }
+    i32::from(failed)
+}
+
+/// Run `luminous wallpaper`, only returns when it can't go on.
+fn cycle_wallpaper(path: &str, config: &Config, interval: u64, shuffle: bool) -> i32 {
+    let list = || {
+        if path == FAVORITES_DIR {
+            Library::open().favorites()
+        } else {
+            fs_scan::scan(path, &Vec::new(), config.filter.as_ref()).paths
+        }
+    };
+    log::info!("Switching the wallpaper to an image of {path} every {interval}s");
+    let interval = std::time::Duration::from_secs(interval);
+    match wallpaper::cycle(list, interval, shuffle) {
+        Ok(()) => 0,
+        Err(e) => {
+            log::error!("Wallpaper: {e}");
+            1
+        }
+    }
+}
+
+/// Run `luminous convert`, the exit code is 1 if any image failed.
+fn convert_images(src: &Path, dst: &Path, options: &ConvertOptions, config: &Config) -> i32 {
+    let plugin_manager = Arc::new(luminous::start_plugins(config.safe_mode));
+    let extra_exts = plugin_manager.get_supported_extensions();
+    let scan = fs_scan::scan(&src.to_string_lossy(), &extra_exts, config.filter.as_ref());
+    let encodable = scan
+        .image_formats
+        .image_formats
+        .iter()
+        .any(|f| f.encoding_support && f.exts.contains(&options.format));
+    if !encodable {
+        eprintln!("No encoder for .{} files", options.format);
+        return 2;
+    }
+    // A file converts only itself, not the folder it is in
+    let paths = if src.is_file() {
+        vec![src.to_path_buf()]
+    } else {
+        scan.paths
+    };
+    if paths.is_empty() {
+        eprintln!("No images found in {}", src.display());
+        return 1;
+    }
+    log::info!(
+        "Converting {} images to {} with {} threads",
+        paths.len(),
+        options.format,
+        config.threads
+    );
+    match convert::convert(&paths, dst, options, &plugin_manager, config.threads) {
+        Ok(summary) => {
+            println!(
+                "Converted {} of {} images into {} ({} skipped, {} failed)",
+                summary.converted,
+                paths.len(),
+                dst.display(),
+                summary.skipped,
+                summary.failed
+            );
+            i32::from(summary.failed > 0)
+        }
+        Err(e) => {
+            log::error!("Convert: {e}");
+            1
+        }
+    }
+}
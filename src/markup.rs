@@ -0,0 +1,341 @@
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use log::{debug, warn};
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use std::sync::OnceLock;
+
+/// Text is drawn this many times as tall as the line width.
+pub const TEXT_SCALE: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Arrow,
+    Rect,
+    Pen,
+    Text,
+}
+
+impl Tool {
+    /// From the names in the markup bar's tool picker.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Arrow" => Some(Self::Arrow),
+            "Box" => Some(Self::Rect),
+            "Pen" => Some(Self::Pen),
+            "Text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// One annotation, in image pixels, kept apart from the image until flattened.
+#[derive(Debug, Clone)]
+pub struct Shape {
+    pub tool: Tool,
+    /// Start and end of arrows and boxes, the path of a pen stroke, the top left of text
+    pub points: Vec<(f32, f32)>,
+    pub color: Rgba8Pixel,
+    pub width: f32,
+    pub text: String,
+}
+
+impl Shape {
+    pub fn new(tool: Tool, at: (f32, f32), color: Rgba8Pixel, width: f32, text: &str) -> Self {
+        let points = match tool {
+            Tool::Arrow | Tool::Rect => vec![at, at],
+            Tool::Pen | Tool::Text => vec![at],
+        };
+        Self {
+            tool,
+            points,
+            color,
+            width: width.max(1.0),
+            text: text.to_string(),
+        }
+    }
+
+    /// Drag to `to`: moves the end of an arrow or box, continues a stroke.
+    pub fn extend(&mut self, to: (f32, f32)) {
+        match self.tool {
+            Tool::Arrow | Tool::Rect => self.points[1] = to,
+            Tool::Pen => {
+                let last = self.points[self.points.len() - 1];
+                if (to.0 - last.0).hypot(to.1 - last.1) >= 1.0 {
+                    self.points.push(to);
+                }
+            }
+            Tool::Text => {}
+        }
+    }
+
+    /// Nothing would be drawn: a click without a drag, or text with no characters.
+    pub fn is_empty(&self) -> bool {
+        match self.tool {
+            Tool::Text => self.text.trim().is_empty(),
+            _ => self.segments().iter().all(|[a, b]| a == b),
+        }
+    }
+
+    /// Line segments of the outline, arrows include their head. Empty for text.
+    fn segments(&self) -> Vec<[(f32, f32); 2]> {
+        let p = &self.points;
+        match self.tool {
+            Tool::Arrow => {
+                let (a, b) = (p[0], p[1]);
+                let len = (b.0 - a.0).hypot(b.1 - a.1);
+                if len < 1.0 {
+                    return vec![[a, b]];
+                }
+                let head = (self.width * 4.0).max(10.0).min(len * 0.5);
+                let (dx, dy) = ((a.0 - b.0) / len, (a.1 - b.1) / len);
+                let (sin, cos) = 0.5f32.sin_cos();
+                let side = |s: f32| {
+                    (
+                        b.0 + head * (dx * cos - dy * sin * s),
+                        b.1 + head * (dy * cos + dx * sin * s),
+                    )
+                };
+                vec![[a, b], [b, side(1.0)], [b, side(-1.0)]]
+            }
+            Tool::Rect => {
+                let ((x0, y0), (x1, y1)) = (p[0], p[1]);
+                vec![
+                    [(x0, y0), (x1, y0)],
+                    [(x1, y0), (x1, y1)],
+                    [(x1, y1), (x0, y1)],
+                    [(x0, y1), (x0, y0)],
+                ]
+            }
+            Tool::Pen if p.len() == 1 => vec![[p[0], p[0]]],
+            Tool::Pen => p.windows(2).map(|w| [w[0], w[1]]).collect(),
+            Tool::Text => Vec::new(),
+        }
+    }
+
+    /// SVG path data of the outline for the overlay preview.
+    pub fn path_commands(&self) -> String {
+        self.segments()
+            .iter()
+            .map(|[a, b]| format!("M {} {} L {} {}", a.0, a.1, b.0, b.1))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Sans serif system font for text shapes, looked up once.
+pub(crate) fn font() -> Option<&'static FontVec> {
+    static FONT: OnceLock<Option<FontVec>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        let families = [
+            fontdb::Family::Name("DejaVu Sans"),
+            fontdb::Family::Name("Noto Sans"),
+            fontdb::Family::Name("Liberation Sans"),
+            fontdb::Family::Name("Segoe UI"),
+            fontdb::Family::Name("Helvetica"),
+            fontdb::Family::SansSerif,
+        ];
+        let id = db
+            .query(&fontdb::Query {
+                families: &families,
+                ..Default::default()
+            })
+            .or_else(|| db.faces().next().map(|f| f.id))?;
+        let font = db.with_face_data(id, |data, index| {
+            FontVec::try_from_vec_and_index(data.to_vec(), index).ok()
+        })??;
+        debug!("Markup font: {:?}", db.face(id).map(|f| &f.families));
+        Some(font)
+    })
+    .as_ref()
+}
+
+/// Draw `shapes` into `buf` in order, anti-aliased.
+pub fn flatten(buf: &mut SharedPixelBuffer<Rgba8Pixel>, shapes: &[Shape]) {
+    for shape in shapes {
+        match shape.tool {
+            Tool::Text => match font() {
+                Some(font) => draw_text(buf, shape, font),
+                None => warn!("No font found, skipping markup text '{}'", shape.text),
+            },
+            _ => draw_segments(buf, &shape.segments(), shape.width, shape.color),
+        }
+    }
+}
+
+/// Source-over blend of `color` at `coverage` (0..1).
+fn blend(px: &mut Rgba8Pixel, color: Rgba8Pixel, coverage: f32) {
+    let a = coverage.clamp(0.0, 1.0) * color.a as f32 / 255.0;
+    let mix = |d: u8, s: u8| (s as f32 * a + d as f32 * (1.0 - a)).round() as u8;
+    px.r = mix(px.r, color.r);
+    px.g = mix(px.g, color.g);
+    px.b = mix(px.b, color.b);
+    px.a = (255.0 * a + px.a as f32 * (1.0 - a)).round() as u8;
+}
+
+fn distance_to_segment(p: (f32, f32), [a, b]: [(f32, f32); 2]) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+/// Round capped lines. Coverage is collected per pixel first so joints aren't blended twice.
+fn draw_segments(
+    buf: &mut SharedPixelBuffer<Rgba8Pixel>,
+    segments: &[[(f32, f32); 2]],
+    width: f32,
+    color: Rgba8Pixel,
+) {
+    let (w, h) = (buf.width() as i64, buf.height() as i64);
+    if segments.is_empty() || w == 0 || h == 0 {
+        return;
+    }
+    let r = width.max(1.0) / 2.0;
+    let bounds = |seg: &[(f32, f32); 2]| {
+        let x0 = ((seg[0].0.min(seg[1].0) - r - 1.0).floor() as i64).clamp(0, w);
+        let y0 = ((seg[0].1.min(seg[1].1) - r - 1.0).floor() as i64).clamp(0, h);
+        let x1 = ((seg[0].0.max(seg[1].0) + r + 1.0).ceil() as i64).clamp(0, w);
+        let y1 = ((seg[0].1.max(seg[1].1) + r + 1.0).ceil() as i64).clamp(0, h);
+        (x0, y0, x1, y1)
+    };
+    let (mut bx0, mut by0, mut bx1, mut by1) = (w, h, 0, 0);
+    for seg in segments {
+        let (x0, y0, x1, y1) = bounds(seg);
+        (bx0, by0, bx1, by1) = (bx0.min(x0), by0.min(y0), bx1.max(x1), by1.max(y1));
+    }
+    if bx0 >= bx1 || by0 >= by1 {
+        return;
+    }
+    let mask_w = (bx1 - bx0) as usize;
+    let mut mask = vec![0f32; mask_w * (by1 - by0) as usize];
+    for seg in segments {
+        let (x0, y0, x1, y1) = bounds(seg);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let d = distance_to_segment((x as f32 + 0.5, y as f32 + 0.5), *seg);
+                let m = &mut mask[(y - by0) as usize * mask_w + (x - bx0) as usize];
+                *m = m.max((r + 0.5 - d).clamp(0.0, 1.0));
+            }
+        }
+    }
+    let pixels = buf.make_mut_slice();
+    for y in by0..by1 {
+        for x in bx0..bx1 {
+            let coverage = mask[(y - by0) as usize * mask_w + (x - bx0) as usize];
+            if coverage > 0.0 {
+                blend(&mut pixels[(y * w + x) as usize], color, coverage);
+            }
+        }
+    }
+}
+
+fn draw_text(buf: &mut SharedPixelBuffer<Rgba8Pixel>, shape: &Shape, font: &FontVec) {
+    let (w, h) = (buf.width() as i64, buf.height() as i64);
+    let scaled = font.as_scaled(PxScale::from(shape.width * TEXT_SCALE));
+    let (x, top) = shape.points[0];
+    let pixels = buf.make_mut_slice();
+    let mut baseline = top + scaled.ascent();
+    for line in shape.text.lines() {
+        let mut caret = x;
+        let mut prev = None;
+        for ch in line.chars() {
+            let id = scaled.glyph_id(ch);
+            if let Some(prev) = prev {
+                caret += scaled.kern(prev, id);
+            }
+            let glyph =
+                id.with_scale_and_position(scaled.scale(), ab_glyph::point(caret, baseline));
+            caret += scaled.h_advance(id);
+            prev = Some(id);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let b = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let (px, py) = (b.min.x as i64 + gx as i64, b.min.y as i64 + gy as i64);
+                if (0..w).contains(&px) && (0..h).contains(&py) {
+                    blend(&mut pixels[(py * w + px) as usize], shape.color, coverage);
+                }
+            });
+        }
+        baseline += scaled.height() + scaled.line_gap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba8Pixel = Rgba8Pixel {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+
+    fn white(w: u32, h: u32) -> SharedPixelBuffer<Rgba8Pixel> {
+        let mut buf = SharedPixelBuffer::new(w, h);
+        buf.make_mut_slice().fill(Rgba8Pixel {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        });
+        buf
+    }
+
+    fn at(buf: &SharedPixelBuffer<Rgba8Pixel>, x: u32, y: u32) -> Rgba8Pixel {
+        buf.as_slice()[(y * buf.width() + x) as usize]
+    }
+
+    #[test]
+    fn boxes_draw_only_their_outline() {
+        let mut buf = white(40, 40);
+        let mut rect = Shape::new(Tool::Rect, (5.0, 5.0), RED, 2.0, "");
+        rect.extend((35.0, 30.0));
+        flatten(&mut buf, &[rect]);
+        assert_eq!(at(&buf, 20, 5), RED);
+        assert_eq!(at(&buf, 34, 20), RED);
+        assert_eq!(at(&buf, 20, 18).g, 255);
+        assert_eq!(at(&buf, 2, 2).g, 255);
+    }
+
+    #[test]
+    fn strokes_and_arrows() {
+        let mut pen = Shape::new(Tool::Pen, (2.0, 10.0), RED, 3.0, "");
+        pen.extend((2.5, 10.0));
+        assert_eq!(pen.points.len(), 1);
+        pen.extend((20.0, 10.0));
+        pen.extend((20.0, 30.0));
+        assert!(!pen.is_empty());
+        let mut arrow = Shape::new(Tool::Arrow, (30.0, 35.0), RED, 2.0, "");
+        assert!(arrow.is_empty());
+        arrow.extend((30.0, 5.0));
+        assert_eq!(arrow.path_commands().matches('M').count(), 3);
+
+        let mut buf = white(40, 40);
+        flatten(&mut buf, &[pen, arrow]);
+        assert_eq!(at(&buf, 10, 10), RED);
+        assert_eq!(at(&buf, 20, 25), RED);
+        assert_eq!(at(&buf, 30, 20), RED);
+        // The head spreads out from the tip
+        assert!(at(&buf, 27, 10).g < 255);
+        assert_eq!(at(&buf, 10, 30).g, 255);
+    }
+
+    #[test]
+    fn text_marks_the_image() {
+        let mut buf = white(80, 40);
+        let text = Shape::new(Tool::Text, (2.0, 2.0), RED, 4.0, "Hi");
+        assert!(Shape::new(Tool::Text, (2.0, 2.0), RED, 4.0, " ").is_empty());
+        flatten(&mut buf, &[text]);
+        if font().is_some() {
+            assert!(buf.as_slice().iter().any(|p| p.g < 128));
+        }
+    }
+}
@@ -0,0 +1,912 @@
+use crate::size_filter::PANORAMA_RATIO;
+use exif::experimental::Writer;
+use exif::{Field, In, Tag, Value};
+use image::ImageDecoder;
+use log::debug;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::ops::Range;
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_PNG_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Text tags that can be edited in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextTag {
+    Artist,
+    Copyright,
+    Description,
+}
+
+impl TextTag {
+    pub const ALL: [TextTag; 3] = [TextTag::Artist, TextTag::Copyright, TextTag::Description];
+
+    fn exif_tag(self) -> Tag {
+        match self {
+            TextTag::Artist => Tag::Artist,
+            TextTag::Copyright => Tag::Copyright,
+            TextTag::Description => Tag::ImageDescription,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextTag::Artist => "artist",
+            TextTag::Copyright => "copyright",
+            TextTag::Description => "description",
+        }
+    }
+}
+
+/// Set a tag to the text, or remove it with `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagEdit {
+    pub tag: TextTag,
+    pub value: Option<String>,
+}
+
+fn read_exif(data: &[u8]) -> Result<Option<exif::Exif>, String> {
+    match exif::Reader::new().read_from_container(&mut Cursor::new(data)) {
+        Ok(exif) => Ok(Some(exif)),
+        Err(exif::Error::NotFound(_)) => Ok(None),
+        Err(e) => Err(format!("Unreadable EXIF: {e}")),
+    }
+}
+
+/// The text tags set in the file, empty when it has no EXIF.
+pub fn read_tags(path: &Path) -> HashMap<TextTag, String> {
+    let Ok(file) = File::open(path) else {
+        return HashMap::new();
+    };
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return HashMap::new();
+    };
+    TextTag::ALL
+        .into_iter()
+        .filter_map(|tag| {
+            let Value::Ascii(parts) = &exif.get_field(tag.exif_tag(), In::PRIMARY)?.value else {
+                return None;
+            };
+            let text = parts
+                .iter()
+                .map(|p| String::from_utf8_lossy(p))
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some((tag, text))
+        })
+        .collect()
+}
+
+/// What `luminous info` prints about a file. The image fields are `None` for formats only a
+/// plugin decodes.
+#[derive(Debug, Serialize)]
+pub struct FileInfo {
+    pub path: String,
+    /// In bytes
+    pub size: u64,
+    pub format: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub color_type: Option<String>,
+    /// EXIF tag names and values with their units, in file order
+    pub exif: Vec<(String, String)>,
+}
+
+/// Read the header and EXIF of `path`, without decoding the image.
+pub fn read_info(path: &Path) -> Result<FileInfo, String> {
+    let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let reader = image::ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| e.to_string())?;
+    let format = reader.format().map(|f| format!("{f:?}").to_lowercase());
+    let (dimensions, color_type) = match reader.into_decoder() {
+        Ok(decoder) => (
+            Some(decoder.dimensions()),
+            Some(format!("{:?}", decoder.color_type())),
+        ),
+        Err(e) => {
+            debug!("No image header in {:?}: {}", path, e);
+            (None, None)
+        }
+    };
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let exif = read_exif(&data)
+        .ok()
+        .flatten()
+        .map(|exif| {
+            exif.fields()
+                .map(|f| {
+                    let value = f.display_value().with_unit(&exif).to_string();
+                    (f.tag.to_string(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(FileInfo {
+        path: path.display().to_string(),
+        size,
+        format,
+        width: dimensions.map(|d| d.0),
+        height: dimensions.map(|d| d.1),
+        color_type,
+        exif,
+    })
+}
+
+/// Apply `edits` to the EXIF of a JPEG or PNG file. Only the metadata block is rewritten, the
+/// image data is copied as is. Fields this EXIF writer can't encode (unknown types) are dropped.
+pub fn edit_tags(path: &Path, edits: &[TagEdit]) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let out = with_tags(&data, edits)?;
+    write_replacing(path, &out)?;
+    debug!("Edited {:?}: {:?}", path, edits);
+    Ok(())
+}
+
+fn with_tags(data: &[u8], edits: &[TagEdit]) -> Result<Vec<u8>, String> {
+    let container = Container::of(data)?;
+    let exif = read_exif(data)?;
+    let payload = rewrite_exif(exif.as_ref(), edits)?;
+    replace_block(data, container, Block::Exif, payload.as_deref())
+}
+
+/// The caption of the image: the XMP description, else the IPTC caption, else the EXIF image
+/// description. `None` when the file has none of them or can't be read.
+pub fn read_caption(path: &Path) -> Option<String> {
+    let data = std::fs::read(path).ok()?;
+    let container = Container::of(&data).ok()?;
+    if let Some(caption) = find_xmp(&data, container).and_then(|xmp| xmp_description(&xmp)) {
+        return Some(caption);
+    }
+    if container == Container::Jpeg {
+        let caption = jpeg_segments(&data)
+            .ok()?
+            .into_iter()
+            .filter(|(marker, _)| *marker == 0xED)
+            .find_map(|(_, range)| iptc_caption(&data[range.start + 4..range.end]));
+        if caption.is_some() {
+            return caption;
+        }
+    }
+    read_tags(path).remove(&TextTag::Description)
+}
+
+/// Store `caption` as the XMP description (added to the packet the file has, or in a new one)
+/// and the EXIF image description. An empty caption clears both, the IPTC caption is left as is.
+pub fn write_caption(path: &Path, caption: &str) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let container = Container::of(&data)?;
+    let caption = caption.trim();
+    let xmp = with_xmp_description(find_xmp(&data, container).as_deref(), caption);
+    let data = replace_block(&data, container, Block::Xmp, Some(xmp.as_bytes()))?;
+    let edit = TagEdit {
+        tag: TextTag::Description,
+        value: (!caption.is_empty()).then(|| caption.to_string()),
+    };
+    let out = with_tags(&data, &[edit])?;
+    write_replacing(path, &out)?;
+    debug!("Caption of {:?} set to {:?}", path, caption);
+    Ok(())
+}
+
+/// Written next to the original first, so a failed write leaves it untouched.
+fn write_replacing(path: &Path, data: &[u8]) -> Result<(), String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{name}.meta-tmp"));
+    std::fs::write(&tmp, data)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&tmp);
+            e.to_string()
+        })
+}
+
+/// TIFF structured EXIF with the edits applied, `None` when no fields are left.
+fn rewrite_exif(exif: Option<&exif::Exif>, edits: &[TagEdit]) -> Result<Option<Vec<u8>>, String> {
+    let edited = |tag: Tag| edits.iter().any(|e| e.tag.exif_tag() == tag);
+    let mut fields: Vec<Field> = exif
+        .map(|exif| {
+            exif.fields()
+                .filter(|f| !matches!(f.value, Value::Unknown(..)))
+                .filter(|f| f.ifd_num != In::PRIMARY || !edited(f.tag))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    fields.extend(edits.iter().filter_map(|e| {
+        Some(Field {
+            tag: e.tag.exif_tag(),
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![e.value.clone()?.into_bytes()]),
+        })
+    }));
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    let mut writer = Writer::new();
+    for field in &fields {
+        writer.push_field(field);
+    }
+    let thumbnail = exif.and_then(thumbnail);
+    if let Some(jpeg) = thumbnail {
+        writer.set_jpeg(jpeg, In::THUMBNAIL);
+    }
+    let mut buf = Cursor::new(Vec::new());
+    writer
+        .write(&mut buf, exif.is_some_and(|e| e.little_endian()))
+        .map_err(|e| format!("Failed to encode EXIF: {e}"))?;
+    Ok(Some(buf.into_inner()))
+}
+
+/// Embedded JPEG preview of the thumbnail IFD, which the writer only keeps if handed back.
+fn thumbnail(exif: &exif::Exif) -> Option<&[u8]> {
+    let uint = |tag| {
+        exif.get_field(tag, In::THUMBNAIL)?
+            .value
+            .get_uint(0)
+            .map(|v| v as usize)
+    };
+    let offset = uint(Tag::JPEGInterchangeFormat)?;
+    let len = uint(Tag::JPEGInterchangeFormatLength)?;
+    exif.buf().get(offset..offset.checked_add(len)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Jpeg,
+    Png,
+}
+
+impl Container {
+    fn of(data: &[u8]) -> Result<Self, String> {
+        if data.starts_with(&[0xFF, 0xD8]) {
+            Ok(Container::Jpeg)
+        } else if data.starts_with(PNG_SIGNATURE) {
+            Ok(Container::Png)
+        } else {
+            Err("Only JPEG and PNG metadata can be edited".to_string())
+        }
+    }
+}
+
+/// Metadata blocks that are rewritten as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Exif,
+    Xmp,
+}
+
+impl Block {
+    /// Whether a JPEG segment or PNG chunk holds this block, going by its payload.
+    fn jpeg_matches(self, marker: u8, payload: &[u8]) -> bool {
+        marker == 0xE1 && payload.starts_with(self.jpeg_header())
+    }
+
+    fn png_matches(self, kind: &[u8], payload: &[u8]) -> bool {
+        match self {
+            Block::Exif => kind == b"eXIf",
+            Block::Xmp => kind == b"iTXt" && payload.starts_with(XMP_PNG_KEYWORD),
+        }
+    }
+
+    fn jpeg_header(self) -> &'static [u8] {
+        match self {
+            Block::Exif => JPEG_EXIF_HEADER,
+            Block::Xmp => JPEG_XMP_HEADER,
+        }
+    }
+}
+
+/// Marker and byte range of every JPEG segment up to and including the start of scan.
+fn jpeg_segments(data: &[u8]) -> Result<Vec<(u8, Range<usize>)>, String> {
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    loop {
+        if pos + 4 > data.len() || data[pos] != 0xFF {
+            return Err("Malformed JPEG".to_string());
+        }
+        let marker = data[pos + 1];
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > data.len() {
+            return Err("Malformed JPEG".to_string());
+        }
+        segments.push((marker, pos..end));
+        // Entropy coded data follows the start of scan
+        if marker == 0xDA {
+            return Ok(segments);
+        }
+        pos = end;
+    }
+}
+
+/// Chunk type and the byte range of the whole chunk.
+type PngChunk = ([u8; 4], Range<usize>);
+
+/// Every chunk of a PNG.
+fn png_chunks(data: &[u8]) -> Result<Vec<PngChunk>, String> {
+    let mut chunks = Vec::new();
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < data.len() {
+        if pos + 8 > data.len() {
+            return Err("Malformed PNG".to_string());
+        }
+        let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let end = pos + 12 + len;
+        if end > data.len() {
+            return Err("Malformed PNG".to_string());
+        }
+        chunks.push((kind, pos..end));
+        pos = end;
+    }
+    Ok(chunks)
+}
+
+/// The file with `block` replaced by `content`, or removed when it is `None`.
+fn replace_block(
+    data: &[u8],
+    container: Container,
+    block: Block,
+    content: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len() + content.map_or(0, |c| c.len() + 64));
+    match container {
+        Container::Jpeg => {
+            let header = block.jpeg_header();
+            let segment = match content {
+                Some(content) => {
+                    let len = content.len() + header.len() + 2;
+                    if len > u16::MAX as usize {
+                        return Err("Metadata too large for a JPEG segment".to_string());
+                    }
+                    [
+                        &[0xFF, 0xE1],
+                        &(len as u16).to_be_bytes()[..],
+                        header,
+                        content,
+                    ]
+                    .concat()
+                }
+                None => Vec::new(),
+            };
+            let mut segment = Some(segment);
+            out.extend_from_slice(&data[..2]);
+            let segments = jpeg_segments(data)?;
+            let scan = segments.last().map_or(2, |(_, r)| r.start);
+            for (marker, range) in segments {
+                let payload = &data[range.start + 4..range.end];
+                if block.jpeg_matches(marker, payload) {
+                    continue;
+                }
+                // A JFIF APP0 stays first and EXIF right after it, anything else follows
+                let leading = marker == 0xE0 || Block::Exif.jpeg_matches(marker, payload);
+                if !leading && let Some(segment) = segment.take() {
+                    out.extend_from_slice(&segment);
+                }
+                if marker != 0xDA {
+                    out.extend_from_slice(&data[range]);
+                }
+            }
+            out.extend_from_slice(&data[scan..]);
+        }
+        Container::Png => {
+            let kind: &[u8; 4] = match block {
+                Block::Exif => b"eXIf",
+                Block::Xmp => b"iTXt",
+            };
+            let chunk = content.map(|content| {
+                let body = match block {
+                    Block::Exif => content.to_vec(),
+                    // Uncompressed, no language or translated keyword
+                    Block::Xmp => [XMP_PNG_KEYWORD, &[0, 0, 0, 0], content].concat(),
+                };
+                let mut crc = crc32fast::Hasher::new();
+                crc.update(kind);
+                crc.update(&body);
+                [
+                    &(body.len() as u32).to_be_bytes()[..],
+                    kind,
+                    &body,
+                    &crc.finalize().to_be_bytes(),
+                ]
+                .concat()
+            });
+            let mut chunk = Some(chunk.unwrap_or_default());
+            out.extend_from_slice(PNG_SIGNATURE);
+            for (kind, range) in png_chunks(data)? {
+                if block.png_matches(&kind, &data[range.start + 8..range.end - 4]) {
+                    continue;
+                }
+                // Metadata has to come before the image data
+                if &kind == b"IDAT"
+                    && let Some(chunk) = chunk.take()
+                {
+                    out.extend_from_slice(&chunk);
+                }
+                out.extend_from_slice(&data[range]);
+            }
+            if chunk.is_some() {
+                return Err("PNG without image data".to_string());
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The XMP packet of the file as text.
+fn find_xmp(data: &[u8], container: Container) -> Option<String> {
+    let packet = match container {
+        Container::Jpeg => jpeg_segments(data)
+            .ok()?
+            .into_iter()
+            .find_map(|(marker, r)| {
+                let payload = &data[r.start + 4..r.end];
+                Block::Xmp
+                    .jpeg_matches(marker, payload)
+                    .then(|| &payload[JPEG_XMP_HEADER.len()..])
+            })?,
+        Container::Png => png_chunks(data).ok()?.into_iter().find_map(|(kind, r)| {
+            let payload = &data[r.start + 8..r.end - 4];
+            if !Block::Xmp.png_matches(&kind, payload) {
+                return None;
+            }
+            // Compressed packets aren't read, they are replaced on write
+            let rest = &payload[XMP_PNG_KEYWORD.len()..];
+            if rest.first() != Some(&0) {
+                return None;
+            }
+            // Language tag and translated keyword, both null terminated
+            let mut rest = rest.get(2..)?;
+            for _ in 0..2 {
+                rest = &rest[rest.iter().position(|&b| b == 0)? + 1..];
+            }
+            Some(rest)
+        })?,
+    };
+    Some(String::from_utf8_lossy(packet).into_owned())
+}
+
+const DC_DESCRIPTION_END: &str = "</dc:description>";
+
+/// The `x-default` (or else first) entry of `dc:description`, `None` when the packet has none.
+fn xmp_description(xmp: &str) -> Option<String> {
+    let start = xmp.find("<dc:description")?;
+    let inner = &xmp[start..start + xmp[start..].find(DC_DESCRIPTION_END)?];
+    let li = inner
+        .find("xml:lang=\"x-default\"")
+        .and_then(|i| inner[..i].rfind("<rdf:li"))
+        .or_else(|| inner.find("<rdf:li"));
+    let Some(li) = li else {
+        return Some(String::new());
+    };
+    let text_start = li + inner[li..].find('>')? + 1;
+    if inner[..text_start].ends_with("/>") {
+        return Some(String::new());
+    }
+    let text_end = text_start + inner[text_start..].find("</rdf:li>")?;
+    Some(xml_unescape(&inner[text_start..text_end]))
+}
+
+/// `xmp` with its description set to `caption`, a new packet when there is none.
+fn with_xmp_description(xmp: Option<&str>, caption: &str) -> String {
+    let element = format!(
+        "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>{DC_DESCRIPTION_END}",
+        xml_escape(caption)
+    );
+    let new_packet = || {
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+             <rdf:Description rdf:about=\"\" xmlns:dc=\"{DC_NAMESPACE}\">{element}</rdf:Description>\
+             </rdf:RDF></x:xmpmeta><?xpacket end=\"w\"?>"
+        )
+    };
+    let Some(xmp) = xmp else {
+        return new_packet();
+    };
+    if let Some(start) = xmp.find("<dc:description")
+        && let Some(end) = xmp[start..].find(DC_DESCRIPTION_END)
+    {
+        let end = start + end + DC_DESCRIPTION_END.len();
+        return format!("{}{element}{}", &xmp[..start], &xmp[end..]);
+    }
+    // Into the first description, declaring the namespace there if it isn't
+    let Some(desc) = xmp.find("<rdf:Description") else {
+        return new_packet();
+    };
+    let Some(tag_end) = xmp[desc..].find('>').map(|i| desc + i) else {
+        return new_packet();
+    };
+    let self_closing = xmp[..tag_end].ends_with('/');
+    let open_end = if self_closing { tag_end - 1 } else { tag_end };
+    let ns = if xmp[desc..tag_end].contains("xmlns:dc=") {
+        String::new()
+    } else {
+        format!(" xmlns:dc=\"{DC_NAMESPACE}\"")
+    };
+    let close = if self_closing {
+        "</rdf:Description>"
+    } else {
+        ""
+    };
+    format!(
+        "{}{ns}>{element}{close}{}",
+        &xmp[..open_end],
+        &xmp[tag_end + 1..]
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let ch = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .unwrap_or_else(|| entity.strip_prefix('#').unwrap_or("").parse())
+                .ok()
+                .and_then(char::from_u32),
+        };
+        match ch {
+            Some(ch) => {
+                out.push(ch);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Caption-Abstract (2:120) of the IPTC block in a Photoshop APP13 segment.
+fn iptc_caption(app13: &[u8]) -> Option<String> {
+    let mut irb = app13.strip_prefix(b"Photoshop 3.0\0")?;
+    while irb.starts_with(b"8BIM") && irb.len() >= 12 {
+        let id = u16::from_be_bytes([irb[4], irb[5]]);
+        // Pascal string name, padded to an even length
+        let name_size = (irb[6] as usize + 2) & !1;
+        let size_at = 6 + name_size;
+        let size = u32::from_be_bytes(irb.get(size_at..size_at + 4)?.try_into().ok()?) as usize;
+        let start = size_at + 4;
+        let resource = irb.get(start..start + size)?;
+        if id == 0x0404 {
+            return iim_caption(resource);
+        }
+        irb = irb.get(start + size + (size & 1)..)?;
+    }
+    None
+}
+
+fn iim_caption(mut iim: &[u8]) -> Option<String> {
+    while iim.len() >= 5 && iim[0] == 0x1C {
+        let size = u16::from_be_bytes([iim[3], iim[4]]) as usize;
+        // Extended lengths are only used for large binary datasets
+        if size & 0x8000 != 0 {
+            return None;
+        }
+        let value = iim.get(5..5 + size)?;
+        if (iim[1], iim[2]) == (2, 120) {
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        iim = &iim[5 + size..];
+    }
+    None
+}
+
+/// Bytes read from the start of a file when looking for shot kinds, where EXIF and XMP sit.
+const SHOT_KIND_PREFIX: u64 = 512 * 1024;
+
+/// How a photo was taken, as far as the camera said so, shown as badges in the grid.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShotKinds {
+    pub panorama: bool,
+    pub hdr: bool,
+    pub burst: bool,
+}
+
+impl ShotKinds {
+    pub fn badges(self) -> Vec<&'static str> {
+        [
+            (self.panorama, "PANO"),
+            (self.hdr, "HDR"),
+            (self.burst, "BURST"),
+        ]
+        .into_iter()
+        .filter_map(|(set, badge)| set.then_some(badge))
+        .collect()
+    }
+}
+
+/// Panorama, HDR and burst hints of the file: EXIF CustomRendered, the Apple MakerNote, Google
+/// camera XMP, the aspect ratio and the `_BURST` names Android cameras give.
+pub fn read_shot_kinds(path: &Path) -> ShotKinds {
+    let mut kinds = ShotKinds::default();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_uppercase())
+        .unwrap_or_default();
+    kinds.burst = name.contains("_BURST");
+    if let Ok((w, h)) = image::image_dimensions(path) {
+        let (long, short) = (w.max(h) as f32, w.min(h).max(1) as f32);
+        kinds.panorama = long / short >= PANORAMA_RATIO;
+    }
+    let mut prefix = Vec::new();
+    let read = File::open(path).and_then(|f| {
+        std::io::Read::read_to_end(&mut std::io::Read::take(f, SHOT_KIND_PREFIX), &mut prefix)
+    });
+    if read.is_err() {
+        return kinds;
+    }
+
+    if let Ok(Some(exif)) = read_exif(&prefix) {
+        let custom = exif
+            .get_field(Tag::CustomRendered, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0));
+        // Apple's values: 2 and 3 HDR, 6 panorama
+        kinds.hdr |= matches!(custom, Some(2 | 3));
+        kinds.panorama |= custom == Some(6);
+        if let Some(Value::Undefined(note, _)) = exif
+            .get_field(Tag::MakerNote, In::PRIMARY)
+            .map(|f| &f.value)
+        {
+            let (hdr, burst) = apple_maker_note(note);
+            kinds.hdr |= hdr;
+            kinds.burst |= burst;
+        }
+    }
+
+    if let Some(xmp) = raw_xmp(&prefix) {
+        kinds.panorama |= xmp_value(xmp, "GPano:ProjectionType").is_some_and(|v| v != "flat")
+            || xmp_value(xmp, "GPano:UsePanoramaViewer").is_some_and(|v| v == "True");
+        kinds.hdr |= xmp_value(xmp, "GCamera:HdrPlusMakernote").is_some()
+            || xmp_value(xmp, "hdrgm:Version").is_some();
+        kinds.burst |= xmp_value(xmp, "GCamera:BurstID").is_some();
+    }
+    kinds
+}
+
+/// HDR and burst flags of an Apple MakerNote: HDRImageType (0x0a) 3 and a BurstUUID (0x0b).
+fn apple_maker_note(note: &[u8]) -> (bool, bool) {
+    // Header, version 1, then a big endian IFD with offsets from the note's start
+    let Some(ifd) = note.strip_prefix(b"Apple iOS\0\0\x01MM") else {
+        return (false, false);
+    };
+    let Some(count) = ifd.get(..2) else {
+        return (false, false);
+    };
+    let count = u16::from_be_bytes([count[0], count[1]]) as usize;
+    let (mut hdr, mut burst) = (false, false);
+    for entry in ifd[2..].chunks_exact(12).take(count) {
+        let tag = u16::from_be_bytes([entry[0], entry[1]]);
+        let value = u32::from_be_bytes([entry[8], entry[9], entry[10], entry[11]]);
+        match tag {
+            0x000A => hdr = value == 3,
+            0x000B => burst = true,
+            _ => {}
+        }
+    }
+    (hdr, burst)
+}
+
+/// The XMP packet in the first bytes of any container, found by its envelope.
+fn raw_xmp(data: &[u8]) -> Option<&str> {
+    let find = |needle: &[u8], from: usize| {
+        data.get(from..)?
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|i| i + from)
+    };
+    let start = find(b"<x:xmpmeta", 0)?;
+    let end = find(b"</x:xmpmeta>", start)?;
+    std::str::from_utf8(&data[start..end]).ok()
+}
+
+/// Value of an XMP property, written either as an attribute or as an element.
+fn xmp_value<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    if let Some(i) = xmp.find(&format!("{name}=\"")) {
+        let rest = &xmp[i + name.len() + 2..];
+        return Some(&rest[..rest.find('"')?]);
+    }
+    let open = format!("<{name}>");
+    let i = xmp.find(&open)? + open.len();
+    let rest = &xmp[i..];
+    Some(rest[..rest.find('<')?].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(format: image::ImageFormat) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(4, 3, image::Rgb([200, 40, 10]));
+        let mut buf = Cursor::new(Vec::new());
+        img.write_to(&mut buf, format).unwrap();
+        buf.into_inner()
+    }
+
+    fn set(tag: TextTag, value: &str) -> TagEdit {
+        TagEdit {
+            tag,
+            value: Some(value.to_string()),
+        }
+    }
+
+    #[test]
+    fn set_and_remove_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, format) in [
+            ("a.jpg", image::ImageFormat::Jpeg),
+            ("a.png", image::ImageFormat::Png),
+        ] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, encoded(format)).unwrap();
+            assert!(read_tags(&path).is_empty());
+
+            let edits = [
+                set(TextTag::Artist, "Ann Example"),
+                set(TextTag::Copyright, "CC BY 4.0"),
+            ];
+            edit_tags(&path, &edits).unwrap();
+            assert_eq!(read_tags(&path)[&TextTag::Artist], "Ann Example");
+            edit_tags(&path, &[set(TextTag::Artist, "Bo")]).unwrap();
+            let removed = TagEdit {
+                tag: TextTag::Copyright,
+                value: None,
+            };
+            edit_tags(&path, &[removed]).unwrap();
+            let tags = read_tags(&path);
+            assert_eq!(tags[&TextTag::Artist], "Bo");
+            assert!(!tags.contains_key(&TextTag::Copyright));
+
+            // The pixels are still there
+            let img = image::open(&path).unwrap();
+            assert_eq!((img.width(), img.height()), (4, 3));
+        }
+    }
+
+    #[test]
+    fn info_reads_header_and_exif() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.jpg");
+        std::fs::write(&path, encoded(image::ImageFormat::Jpeg)).unwrap();
+        edit_tags(&path, &[set(TextTag::Artist, "Ann Example")]).unwrap();
+
+        let info = read_info(&path).unwrap();
+        assert_eq!(info.format.as_deref(), Some("jpeg"));
+        assert_eq!((info.width, info.height), (Some(4), Some(3)));
+        assert_eq!(info.color_type.as_deref(), Some("Rgb8"));
+        assert_eq!(info.size, std::fs::metadata(&path).unwrap().len());
+        assert!(info.exif.iter().any(|(tag, _)| tag == "Artist"));
+        assert!(read_info(&dir.path().join("missing.jpg")).is_err());
+    }
+
+    #[test]
+    fn captions_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, format) in [
+            ("a.jpg", image::ImageFormat::Jpeg),
+            ("a.png", image::ImageFormat::Png),
+        ] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, encoded(format)).unwrap();
+            assert_eq!(read_caption(&path), None);
+
+            write_caption(&path, "Harbour at <dusk> & fog").unwrap();
+            assert_eq!(
+                read_caption(&path).as_deref(),
+                Some("Harbour at <dusk> & fog")
+            );
+            edit_tags(&path, &[set(TextTag::Artist, "Ann")]).unwrap();
+            write_caption(&path, "Second").unwrap();
+            assert_eq!(read_caption(&path).as_deref(), Some("Second"));
+            let tags = read_tags(&path);
+            assert_eq!(tags[&TextTag::Description], "Second");
+            assert_eq!(tags[&TextTag::Artist], "Ann");
+
+            write_caption(&path, "").unwrap();
+            assert_eq!(read_caption(&path).as_deref(), Some(""));
+            assert!(!read_tags(&path).contains_key(&TextTag::Description));
+            assert!(image::open(&path).is_ok());
+        }
+    }
+
+    #[test]
+    fn xmp_description_goes_into_existing_packets() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="4"/></rdf:RDF></x:xmpmeta>"#;
+        assert_eq!(xmp_description(xmp), None);
+        let edited = with_xmp_description(Some(xmp), "Pier");
+        assert!(edited.contains(r#"xmp:Rating="4""#), "{edited}");
+        assert!(edited.contains(DC_NAMESPACE), "{edited}");
+        assert_eq!(xmp_description(&edited).as_deref(), Some("Pier"));
+        let again = with_xmp_description(Some(&edited), "Dock &amp;");
+        assert_eq!(again.matches("<dc:description>").count(), 1);
+        assert_eq!(xmp_description(&again).as_deref(), Some("Dock &amp;"));
+
+        let languages = r#"<dc:description><rdf:Alt><rdf:li xml:lang="de">Hafen</rdf:li><rdf:li xml:lang="x-default">Harbour &#233;</rdf:li></rdf:Alt></dc:description>"#;
+        assert_eq!(xmp_description(languages).as_deref(), Some("Harbour é"));
+    }
+
+    #[test]
+    fn iptc_captions_are_read() {
+        let caption = b"Old mill";
+        let mut iim = vec![0x1C, 2, 0, 0, 2, 0, 4];
+        iim.extend_from_slice(&[0x1C, 2, 120, 0, caption.len() as u8]);
+        iim.extend_from_slice(caption);
+        let mut app13 = b"Photoshop 3.0\08BIM".to_vec();
+        app13.extend_from_slice(&[0x04, 0x04, 0, 0]);
+        app13.extend_from_slice(&(iim.len() as u32).to_be_bytes());
+        app13.extend_from_slice(&iim);
+        assert_eq!(iptc_caption(&app13).as_deref(), Some("Old mill"));
+        assert_eq!(iptc_caption(b"Photoshop 3.0\0"), None);
+    }
+
+    #[test]
+    fn other_formats_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.gif");
+        std::fs::write(&path, encoded(image::ImageFormat::Gif)).unwrap();
+        assert!(edit_tags(&path, &[set(TextTag::Artist, "x")]).is_err());
+    }
+
+    #[test]
+    fn shot_kinds_from_xmp_maker_notes_and_shape() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("plain.png");
+        std::fs::write(&plain, encoded(image::ImageFormat::Png)).unwrap();
+        assert_eq!(read_shot_kinds(&plain), ShotKinds::default());
+
+        let xmp = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF><rdf:Description \
+                   GPano:ProjectionType=\"equirectangular\"><GCamera:BurstID>42</GCamera:BurstID>\
+                   </rdf:Description></rdf:RDF></x:xmpmeta>";
+        let data = encoded(image::ImageFormat::Png);
+        let tagged =
+            replace_block(&data, Container::Png, Block::Xmp, Some(xmp.as_bytes())).unwrap();
+        let sphere = dir.path().join("sphere.png");
+        std::fs::write(&sphere, tagged).unwrap();
+        let kinds = read_shot_kinds(&sphere);
+        assert!(kinds.panorama && kinds.burst && !kinds.hdr);
+        assert_eq!(kinds.badges(), ["PANO", "BURST"]);
+
+        let wide = dir.path().join("IMG_20240101_BURST001.png");
+        image::RgbImage::new(300, 100).save(&wide).unwrap();
+        assert_eq!(read_shot_kinds(&wide).badges(), ["PANO", "BURST"]);
+
+        let mut note = b"Apple iOS\0\0\x01MM\0\x02".to_vec();
+        note.extend([0, 0x0A, 0, 9, 0, 0, 0, 1, 0, 0, 0, 3]);
+        note.extend([0, 0x0B, 0, 2, 0, 0, 0, 4, 0, 0, 0, 0]);
+        assert_eq!(apple_maker_note(&note), (true, true));
+        assert_eq!(apple_maker_note(b"Nikon\0"), (false, false));
+    }
+}
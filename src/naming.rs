@@ -0,0 +1,359 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Format of `{exif.date}` and `{date}` without one of their own.
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    /// File name without its extension
+    Name,
+    /// Name of the containing folder
+    Parent,
+    /// Position in the batch from 1, zero padded to the width
+    Seq(usize),
+    Rating,
+    /// Capture time from EXIF, the modification time for files without one
+    ExifDate(String),
+    /// Modification time
+    Date(String),
+}
+
+/// File naming pattern like `{exif.date:%Y-%m-%d}_{seq:3}`, shared by batch rename, zip export
+/// and target transfers. It names the file stem, the extension is always kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    tokens: Vec<Token>,
+    text: String,
+}
+
+/// What a template is evaluated against for one file.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub path: PathBuf,
+    pub seq: usize,
+    pub rating: u8,
+    pub taken: Option<NaiveDateTime>,
+    pub modified: Option<NaiveDateTime>,
+}
+
+fn date_format(fmt: Option<&str>) -> Result<String, String> {
+    let fmt = fmt.unwrap_or(DEFAULT_DATE_FORMAT);
+    if fmt.is_empty() || StrftimeItems::new(fmt).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid date format '{fmt}'"));
+    }
+    Ok(fmt.to_string())
+}
+
+fn parse_token(token: &str) -> Result<Token, String> {
+    let (name, arg) = match token.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (token, None),
+    };
+    match (name, arg) {
+        ("name", None) => Ok(Token::Name),
+        ("parent", None) => Ok(Token::Parent),
+        ("rating", None) => Ok(Token::Rating),
+        ("seq", None) => Ok(Token::Seq(1)),
+        ("seq", Some(width)) => match width.parse() {
+            Ok(width @ 1..=9) => Ok(Token::Seq(width)),
+            _ => Err(format!("invalid {{seq}} width '{width}', expected 1 to 9")),
+        },
+        ("exif.date", fmt) => date_format(fmt).map(Token::ExifDate),
+        ("date", fmt) => date_format(fmt).map(Token::Date),
+        ("name" | "parent" | "rating", Some(_)) => Err(format!("{{{name}}} takes no format")),
+        _ => Err(format!("unknown token {{{token}}}")),
+    }
+}
+
+/// Replace characters that can't be part of a file name on some platform.
+fn sanitize(s: &str) -> String {
+    let s: String = s
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    s.trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string()
+}
+
+impl Template {
+    /// Parse a template, `{{` and `}}` stand for literal braces.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err("unclosed '{'".to_string());
+                    };
+                    if !literal.is_empty() {
+                        tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(parse_token(rest[..end].trim())?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched '}'".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        if tokens.is_empty() {
+            return Err("empty template".to_string());
+        }
+        Ok(Self {
+            tokens,
+            text: text.to_string(),
+        })
+    }
+
+    /// The template as it was typed
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    fn uses_exif(&self) -> bool {
+        self.tokens.iter().any(|t| matches!(t, Token::ExifDate(_)))
+    }
+
+    fn uses_modified(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|t| matches!(t, Token::ExifDate(_) | Token::Date(_)))
+    }
+
+    /// Context for `path`, only reading the metadata the template refers to.
+    pub fn context(&self, path: &Path, seq: usize, rating: u8) -> Context {
+        Context {
+            path: path.to_path_buf(),
+            seq,
+            rating,
+            taken: self.uses_exif().then(|| capture_time(path)).flatten(),
+            modified: self
+                .uses_modified()
+                .then(|| modification_time(path))
+                .flatten(),
+        }
+    }
+
+    /// New file stem for `ctx`. Empty results fall back to the original stem.
+    pub fn render(&self, ctx: &Context) -> String {
+        let stem = ctx
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let date = |time: Option<NaiveDateTime>, fmt: &str| {
+            time.map(|t| t.format(fmt).to_string()).unwrap_or_default()
+        };
+        let mut out = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(s) => out.push_str(s),
+                Token::Name => out.push_str(&stem),
+                Token::Parent => {
+                    if let Some(parent) = ctx.path.parent().and_then(Path::file_name) {
+                        out.push_str(&parent.to_string_lossy());
+                    }
+                }
+                Token::Seq(width) => out.push_str(&format!("{:0width$}", ctx.seq)),
+                Token::Rating => out.push_str(&ctx.rating.to_string()),
+                Token::ExifDate(fmt) => out.push_str(&date(ctx.taken.or(ctx.modified), fmt)),
+                Token::Date(fmt) => out.push_str(&date(ctx.modified, fmt)),
+            }
+        }
+        let out = sanitize(&out);
+        if out.is_empty() { stem } else { out }
+    }
+
+    /// New file name for `ctx`, keeping its extension.
+    pub fn file_name(&self, ctx: &Context) -> String {
+        let stem = self.render(ctx);
+        match ctx.path.extension() {
+            Some(ext) => format!("{stem}.{}", ext.to_string_lossy()),
+            None => stem,
+        }
+    }
+
+    /// New names for a batch of `(path, rating)` numbered from 1 in order. Names repeating
+    /// within a folder get a ` (2)`, ` (3)`, ... suffix, compared case-insensitively.
+    pub fn names(&self, items: &[(PathBuf, u8)]) -> Vec<String> {
+        let mut taken = HashSet::new();
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, (path, rating))| {
+                let ctx = self.context(path, i + 1, *rating);
+                let name = self.file_name(&ctx);
+                let dir = path.parent().unwrap_or(Path::new(""));
+                let mut candidate = name.clone();
+                let mut n = 2;
+                while !taken.insert((dir, candidate.to_lowercase())) {
+                    let name = Path::new(&name);
+                    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+                    candidate = match name.extension() {
+                        Some(ext) => format!("{stem} ({n}).{}", ext.to_string_lossy()),
+                        None => format!("{stem} ({n})"),
+                    };
+                    n += 1;
+                }
+                candidate
+            })
+            .collect()
+    }
+
+    /// Names for the first `limit` of `items` as `old -> new`, for showing before a rename.
+    pub fn preview(&self, items: &[(PathBuf, u8)], limit: usize) -> Vec<(String, String)> {
+        let items = &items[..items.len().min(limit)];
+        items
+            .iter()
+            .zip(self.names(items))
+            .map(|((path, _), new)| {
+                let old = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (old, new)
+            })
+            .collect()
+    }
+}
+
+/// DateTimeOriginal, or DateTime for files that only have that.
+fn capture_time(path: &Path) -> Option<NaiveDateTime> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| {
+            let field = exif.get_field(tag, exif::In::PRIMARY)?;
+            let exif::Value::Ascii(ref values) = field.value else {
+                return None;
+            };
+            let dt = exif::DateTime::from_ascii(values.first()?).ok()?;
+            NaiveDate::from_ymd_opt(dt.year.into(), dt.month.into(), dt.day.into())?.and_hms_opt(
+                dt.hour.into(),
+                dt.minute.into(),
+                dt.second.into(),
+            )
+        })
+}
+
+fn modification_time(path: &Path) -> Option<NaiveDateTime> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Local>::from(modified).naive_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(path: &str, seq: usize) -> Context {
+        Context {
+            path: PathBuf::from(path),
+            seq,
+            rating: 4,
+            taken: NaiveDate::from_ymd_opt(2024, 5, 17).and_then(|d| d.and_hms_opt(9, 30, 5)),
+            modified: NaiveDate::from_ymd_opt(2025, 1, 2).and_then(|d| d.and_hms_opt(0, 0, 0)),
+        }
+    }
+
+    fn render(template: &str, ctx: &Context) -> String {
+        Template::parse(template).unwrap().render(ctx)
+    }
+
+    #[test]
+    fn tokens_render() {
+        let c = ctx("/photos/trip/IMG_0042.JPG", 7);
+        assert_eq!(render("{name}", &c), "IMG_0042");
+        assert_eq!(render("{parent}-{seq}", &c), "trip-7");
+        assert_eq!(render("{seq:3}_{rating}", &c), "007_4");
+        assert_eq!(render("{exif.date}", &c), "2024-05-17");
+        assert_eq!(render("{exif.date:%Y%m%d_%H%M%S}", &c), "20240517_093005");
+        assert_eq!(render("{date:%Y}", &c), "2025");
+        assert_eq!(render("{{{name}}}", &c), "{IMG_0042}");
+        assert_eq!(
+            Template::parse("{ seq:2 }-x").unwrap().file_name(&c),
+            "07-x.JPG"
+        );
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for bad in [
+            "",
+            "{name",
+            "name}",
+            "{}",
+            "{size}",
+            "{seq:0}",
+            "{seq:abc}",
+            "{name:x}",
+            "{date:%Q}",
+            "{exif.date:}",
+        ] {
+            assert!(Template::parse(bad).is_err(), "{bad:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn edge_cases() {
+        let mut c = ctx("photo", 1234);
+        // Wider numbers aren't cut to the width
+        assert_eq!(render("{seq:2}", &c), "1234");
+        // No folder, no extension
+        assert_eq!(Template::parse("{parent}").unwrap().file_name(&c), "photo");
+        // Without EXIF the modification time is used, without either the date is empty
+        c.taken = None;
+        assert_eq!(render("{exif.date:%Y}_{name}", &c), "2025_photo");
+        c.modified = None;
+        assert_eq!(render("{exif.date}_{name}", &c), "_photo");
+        assert_eq!(render("{exif.date}", &c), "photo");
+        // Separators from formats and literals can't escape the folder
+        assert_eq!(render("{exif.date:%Y/%m}", &ctx("a.png", 1)), "2024_05");
+        assert_eq!(render("../{name}.", &ctx("a.png", 1)), "_a");
+    }
+
+    #[test]
+    fn batch_names_are_unique_per_folder() {
+        let template = Template::parse("shot").unwrap();
+        let items: Vec<(PathBuf, u8)> = ["/a/1.jpg", "/a/2.JPG", "/a/3.png", "/b/4.jpg"]
+            .iter()
+            .map(|p| (PathBuf::from(p), 0))
+            .collect();
+        assert_eq!(
+            template.names(&items),
+            ["shot.jpg", "shot (2).JPG", "shot.png", "shot.jpg"]
+        );
+        assert_eq!(
+            Template::parse("{seq:2}").unwrap().preview(&items, 2),
+            [
+                ("1.jpg".to_string(), "01.jpg".to_string()),
+                ("2.JPG".to_string(), "02.JPG".to_string())
+            ]
+        );
+    }
+}
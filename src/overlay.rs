@@ -0,0 +1,140 @@
+use rayon::prelude::*;
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+
+/// How the reference image is combined with the displayed one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Difference,
+    Multiply,
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "normal" => Some(Self::Normal),
+            "difference" => Some(Self::Difference),
+            "multiply" => Some(Self::Multiply),
+            _ => None,
+        }
+    }
+
+    fn apply(self, base: u8, reference: u8) -> u8 {
+        match self {
+            Self::Normal => reference,
+            Self::Difference => base.abs_diff(reference),
+            Self::Multiply => ((base as u16 * reference as u16 + 127) / 255) as u8,
+        }
+    }
+}
+
+/// Composite `reference`, shifted by `(dx, dy)` pixels, over `base`.
+///
+/// The result has the size of `base`, which shows through unchanged where the reference doesn't
+/// reach. `opacity` (0..1) scales the reference's own alpha.
+pub fn blend(
+    base: &SharedPixelBuffer<Rgba8Pixel>,
+    reference: &SharedPixelBuffer<Rgba8Pixel>,
+    mode: BlendMode,
+    opacity: f32,
+    dx: i32,
+    dy: i32,
+) -> SharedPixelBuffer<Rgba8Pixel> {
+    let w = base.width() as usize;
+    let (rw, rh) = (reference.width() as i64, reference.height() as i64);
+    let opacity = opacity.clamp(0.0, 1.0);
+    let mut out = base.clone();
+    if w == 0 {
+        return out;
+    }
+    let src = reference.as_slice();
+
+    out.make_mut_slice()
+        .par_chunks_mut(w)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let ry = y as i64 - dy as i64;
+            if ry < 0 || ry >= rh {
+                return;
+            }
+            for (x, px) in row.iter_mut().enumerate() {
+                let rx = x as i64 - dx as i64;
+                if rx < 0 || rx >= rw {
+                    continue;
+                }
+                let r = src[(ry * rw + rx) as usize];
+                let t = opacity * r.a as f32 / 255.0;
+                let mix = |b: u8, r: u8| {
+                    let blended = mode.apply(b, r) as f32;
+                    (b as f32 + (blended - b as f32) * t).round() as u8
+                };
+                px.r = mix(px.r, r.r);
+                px.g = mix(px.g, r.g);
+                px.b = mix(px.b, r.b);
+            }
+        });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(w: u32, h: u32, p: Rgba8Pixel) -> SharedPixelBuffer<Rgba8Pixel> {
+        let mut buf = SharedPixelBuffer::new(w, h);
+        buf.make_mut_slice().fill(p);
+        buf
+    }
+
+    fn px(r: u8, g: u8, b: u8, a: u8) -> Rgba8Pixel {
+        Rgba8Pixel { r, g, b, a }
+    }
+
+    #[test]
+    fn normal_mixes_by_opacity() {
+        let base = solid(2, 2, px(0, 0, 0, 255));
+        let reference = solid(2, 2, px(200, 100, 50, 255));
+        let out = blend(&base, &reference, BlendMode::Normal, 0.5, 0, 0);
+        assert_eq!(out.as_slice()[0], px(100, 50, 25, 255));
+
+        let out = blend(&base, &reference, BlendMode::Normal, 1.0, 0, 0);
+        assert_eq!(out.as_slice()[3], px(200, 100, 50, 255));
+    }
+
+    #[test]
+    fn difference_of_identical_images_is_black() {
+        let base = solid(3, 3, px(120, 40, 250, 255));
+        let out = blend(&base, &base, BlendMode::Difference, 1.0, 0, 0);
+        assert!(out.as_slice().iter().all(|&p| p == px(0, 0, 0, 255)));
+    }
+
+    #[test]
+    fn multiply_by_white_keeps_base() {
+        let base = solid(2, 1, px(120, 40, 250, 255));
+        let white = solid(2, 1, px(255, 255, 255, 255));
+        let out = blend(&base, &white, BlendMode::Multiply, 1.0, 0, 0);
+        assert_eq!(out.as_slice(), base.as_slice());
+    }
+
+    #[test]
+    fn offset_leaves_uncovered_area_alone() {
+        let base = solid(3, 1, px(10, 10, 10, 255));
+        let reference = solid(3, 1, px(200, 200, 200, 255));
+        let out = blend(&base, &reference, BlendMode::Normal, 1.0, 2, 0);
+        let row = out.as_slice();
+        assert_eq!(row[0], px(10, 10, 10, 255));
+        assert_eq!(row[1], px(10, 10, 10, 255));
+        assert_eq!(row[2], px(200, 200, 200, 255));
+    }
+
+    #[test]
+    fn mode_names() {
+        assert_eq!(
+            BlendMode::from_name("Difference"),
+            Some(BlendMode::Difference)
+        );
+        assert_eq!(BlendMode::from_name("multiply"), Some(BlendMode::Multiply));
+        assert_eq!(BlendMode::from_name("screen"), None);
+    }
+}
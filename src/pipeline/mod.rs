@@ -316,6 +316,71 @@ pub fn run_pipeline_on_selection(
     });
 }
 
+pub fn run_plugin_batch(
+    paths: Vec<PathBuf>,
+    plugin_id: String,
+    plugin_manager: Arc<PluginManager>,
+    weak_ui: slint::Weak<crate::MainWindow>,
+) {
+    if paths.is_empty() {
+        debug!("Plugin batch: no images selected");
+        return;
+    }
+    let Some(plugin) = plugin_manager.get_plugin_by_id(&plugin_id) else {
+        error!("Plugin batch: no plugin with id {plugin_id}");
+        return;
+    };
+    if !plugin.is_running() {
+        error!("Plugin batch: plugin {plugin_id} is not running");
+        return;
+    }
+
+    let mut dialog = rfd::FileDialog::new();
+    if let Some(parent) = paths[0].parent() {
+        dialog = dialog.set_directory(parent);
+    }
+    let Some(dst_dir) = dialog.pick_folder() else {
+        debug!("Plugin batch: user cancelled folder picker");
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        let ui = weak_ui.clone();
+        let outputs = plugin.process_batch(
+            &paths,
+            &dst_dir,
+            serde_json::Value::Null,
+            move |done, total| {
+                let progress = done as f32 / total.max(1) as f32;
+                let ui = ui.clone();
+                slint::invoke_from_event_loop(move || {
+                    if let Some(ui) = ui.upgrade() {
+                        ui.set_pipeline_progress(progress);
+                    }
+                })
+                .ok();
+            },
+        );
+
+        match outputs {
+            Some(outputs) => debug!(
+                "Plugin batch: {} -> {} images in {:.2}ms",
+                plugin_id,
+                outputs.len(),
+                start.elapsed().as_secs_f64() * 1000.0
+            ),
+            None => error!("Plugin batch: {plugin_id} failed"),
+        }
+        slint::invoke_from_event_loop(move || {
+            if let Some(ui) = weak_ui.upgrade() {
+                ui.set_pipeline_progress(1.0);
+            }
+        })
+        .ok();
+    });
+}
+
 fn save_result(
     img: DynamicImage,
     dst: &PathBuf,
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::Path;
+
+/// Whether the machine runs on its battery. Only known on Linux, elsewhere it's assumed to be
+/// plugged in.
+pub fn on_battery() -> bool {
+    cfg!(target_os = "linux") && on_battery_in(Path::new("/sys/class/power_supply"))
+}
+
+/// Reads a `/sys/class/power_supply` style directory: on battery when a system battery is
+/// present and no mains or USB supply is online.
+fn on_battery_in(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    let read = |supply: &Path, attr: &str| {
+        fs::read_to_string(supply.join(attr))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut battery = false;
+    for supply in entries.flatten().map(|e| e.path()) {
+        match read(&supply, "type").as_str() {
+            // Mice and headsets report their own batteries with the device scope
+            "Battery" if read(&supply, "scope") != "Device" => battery = true,
+            "Mains" | "USB" if read(&supply, "online") == "1" => return false,
+            _ => {}
+        }
+    }
+    battery
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(dir: &Path, name: &str, attrs: &[(&str, &str)]) {
+        let supply = dir.join(name);
+        fs::create_dir_all(&supply).unwrap();
+        for (attr, value) in attrs {
+            fs::write(supply.join(attr), format!("{value}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn battery_counts_only_without_mains() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!on_battery_in(dir.path()), "desktops have no battery");
+
+        supply(
+            dir.path(),
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device")],
+        );
+        assert!(!on_battery_in(dir.path()), "a mouse battery doesn't count");
+
+        supply(
+            dir.path(),
+            "BAT0",
+            &[("type", "Battery"), ("status", "Discharging")],
+        );
+        supply(dir.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+        assert!(on_battery_in(dir.path()));
+
+        supply(dir.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+        assert!(!on_battery_in(dir.path()));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::actions::Action;
+use log::error;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// An action and when it happened, counted from the start of the recording. Images are
+/// referred to by file name, as [`Action::Show`], so a script replays against a copy of the
+/// folder it was recorded in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub at: Duration,
+    pub action: Action,
+}
+
+fn format_step(step: &Step) -> String {
+    format!("{}\t{}", step.at.as_millis(), step.action.step())
+}
+
+/// Read a session script, one `<ms>\t<step>` per line, the step written as in a macro, see
+/// [`Action::parse`]. Blank lines and lines starting with `#` are skipped.
+pub fn parse(script: &str) -> Result<Vec<Step>, String> {
+    script
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let (at, step) = line
+                .trim_end_matches('\r')
+                .split_once('\t')
+                .unwrap_or((line, ""));
+            let at = at
+                .parse()
+                .map(Duration::from_millis)
+                .map_err(|_| format!("line {}: bad timestamp '{at}'", i + 1))?;
+            let action = Action::parse(step).map_err(|e| format!("line {}: {e}", i + 1))?;
+            Ok(Step { at, action })
+        })
+        .collect()
+}
+
+/// Appends every action to a session script as it happens, flushed line by line so a crash
+/// doesn't lose the steps leading up to it.
+pub struct Recorder {
+    out: RefCell<LineWriter<File>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, dir: &Path) -> io::Result<Self> {
+        let mut out = LineWriter::new(File::create(path)?);
+        writeln!(
+            out,
+            "# luminous session in {}, recorded {}",
+            dir.display(),
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        )?;
+        Ok(Self {
+            out: RefCell::new(out),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&self, action: Action) {
+        let line = format_step(&Step {
+            at: self.start.elapsed(),
+            action,
+        });
+        if let Err(e) = writeln!(self.out.borrow_mut(), "{line}") {
+            error!("Failed to record '{line}': {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::{ColorLabel, Flag};
+    use crate::{EditOp, EditOpKind, ViewMode};
+
+    #[test]
+    fn steps_roundtrip() {
+        let actions = [
+            Action::Show("IMG 0001.jpg".into()),
+            Action::Prev,
+            Action::View(ViewMode::Grid),
+            Action::Search("dog beach".into()),
+            Action::Search("".into()),
+            Action::Edit(EditOp {
+                kind: EditOpKind::Brighten,
+                int_val: 0,
+                float_val: 1.25,
+                string_val: "".into(),
+            }),
+            Action::Rate(4),
+            Action::Flag(Flag::Reject),
+            Action::Label(ColorLabel::None),
+            Action::Upscale(2),
+            Action::Batch {
+                plugin: "denoise".into(),
+                names: vec!["a.png".into(), "b c.png".into()],
+            },
+            Action::ToggleSlideshow,
+        ];
+        let steps: Vec<Step> = actions
+            .into_iter()
+            .enumerate()
+            .map(|(i, action)| Step {
+                at: Duration::from_millis(i as u64 * 250),
+                action,
+            })
+            .collect();
+        let script: String = steps.iter().map(|s| format_step(s) + "\n").collect();
+        assert_eq!(parse(&script).unwrap(), steps);
+    }
+
+    #[test]
+    fn bad_lines_name_their_number() {
+        assert_eq!(parse("# header\n\n0\tshow a.png\n").unwrap().len(), 1);
+        assert_eq!(
+            parse("# header\n10\tjump\n").unwrap_err(),
+            "line 2: unknown action 'jump'"
+        );
+        assert!(parse("soon\tnext").unwrap_err().contains("bad timestamp"));
+        assert!(
+            parse("5\tupscale five")
+                .unwrap_err()
+                .contains("expected a number")
+        );
+        // Tabs inside a name can't split the step
+        let step = Step {
+            at: Duration::ZERO,
+            action: Action::Show("a\tb.png".into()),
+        };
+        assert_eq!(
+            parse(&format_step(&step)).unwrap()[0].action,
+            Action::Show("a b.png".into())
+        );
+    }
+}
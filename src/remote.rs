@@ -0,0 +1,354 @@
+//! Local sockets a running viewer listens on: the single instance socket, so that launching
+//! luminous again hands the path to it instead of opening a second window, and the
+//! `--control-socket` scripts and window manager bindings drive it through. One request per
+//! line, each answered with `ok`, `ok <value>` for queries, or `error: <reason>`.
+
+use directories::ProjectDirs;
+use log::{debug, error, info, warn};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Socket of the single instance, in the runtime directory where there is one
+const INSTANCE_SOCKET: &str = "instance.sock";
+
+/// What a client asks the running viewer to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    /// Show this file with its folder, or this folder, and raise the window
+    Open(PathBuf),
+    /// Raise the window
+    Focus,
+    Next,
+    Prev,
+    /// Show the nth of the shown images, counted from 1
+    Goto(usize),
+    /// Position among the shown images, their count and the path of the current image, as
+    /// `12/340 /photos/a.jpg`
+    Current,
+    Quit,
+}
+
+impl Request {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        match command {
+            "open" if !arg.is_empty() => Ok(Self::Open(PathBuf::from(arg))),
+            "open" => Err("open needs a path".to_string()),
+            "focus" => Ok(Self::Focus),
+            "next" => Ok(Self::Next),
+            "prev" => Ok(Self::Prev),
+            "goto" => match arg.parse() {
+                Ok(n) if n > 0 => Ok(Self::Goto(n)),
+                _ => Err(format!("goto needs a position from 1, got '{arg}'")),
+            },
+            "current" => Ok(Self::Current),
+            "quit" => Ok(Self::Quit),
+            "" => Err("empty request".to_string()),
+            other => Err(format!("unknown request '{other}'")),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            Self::Open(path) => format!("open {}", path.display()),
+            Self::Focus => "focus".to_string(),
+            Self::Next => "next".to_string(),
+            Self::Prev => "prev".to_string(),
+            Self::Goto(n) => format!("goto {n}"),
+            Self::Current => "current".to_string(),
+            Self::Quit => "quit".to_string(),
+        }
+    }
+}
+
+/// Where the single instance listens.
+pub fn instance_socket() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "luminous")?;
+    let dir = dirs.runtime_dir().unwrap_or_else(|| dirs.cache_dir());
+    std::fs::create_dir_all(dir)
+        .map_err(|e| error!("Failed to create {:?}: {}", dir, e))
+        .ok()?;
+    Some(dir.join(INSTANCE_SOCKET))
+}
+
+/// Send `request` to the viewer listening on `socket` and wait for its answer, the value of
+/// queries and empty otherwise. Fails when none is running.
+pub fn send(socket: &Path, request: &Request) -> io::Result<String> {
+    imp::send(socket, &request.to_line())
+        .and_then(|reply| parse_reply(&reply).map_err(io::Error::other))
+}
+
+/// The line a request is answered with, see [`parse_reply`].
+pub fn reply_line(result: &Result<String, String>) -> String {
+    match result {
+        Ok(value) if value.is_empty() => "ok".to_string(),
+        Ok(value) => format!("ok {}", value.replace('\n', " ")),
+        Err(e) => format!("error: {}", e.replace('\n', " ")),
+    }
+}
+
+/// The value of an `ok` reply, or the reason of an `error`.
+pub fn parse_reply(line: &str) -> Result<String, String> {
+    let line = line.trim();
+    if line == "ok" {
+        return Ok(String::new());
+    }
+    if let Some(value) = line.strip_prefix("ok ") {
+        return Ok(value.to_string());
+    }
+    Err(line.strip_prefix("error: ").unwrap_or(line).to_string())
+}
+
+/// Position, count and path of `12/340 /photos/a.jpg` as answered to [`Request::Current`].
+pub fn parse_current(reply: &str) -> Option<(usize, usize, String)> {
+    let (position, path) = reply.split_once(' ')?;
+    let (position, count) = position.split_once('/')?;
+    Some((
+        position.parse().ok()?,
+        count.parse().ok()?,
+        path.to_string(),
+    ))
+}
+
+/// Listens until dropped, then removes its socket file.
+pub struct Server {
+    socket: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
+
+/// Listen on `socket` and call `handle` on a background thread with each request. A socket left
+/// behind by a crashed viewer is replaced, one that still answers is an error, and so is any
+/// other file at that path.
+pub fn serve<F>(socket: &Path, handle: F) -> io::Result<Server>
+where
+    F: Fn(Request) -> Result<String, String> + Send + Sync + 'static,
+{
+    if socket.exists() {
+        if imp::send(socket, &Request::Focus.to_line()).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another viewer is listening",
+            ));
+        }
+        if !imp::is_socket(socket)? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", socket.display()),
+            ));
+        }
+        debug!("Removing stale socket {:?}", socket);
+        std::fs::remove_file(socket)?;
+    }
+    imp::listen(socket, move |line| {
+        Request::parse(line).and_then(|request| {
+            debug!("Remote request: {:?}", request);
+            handle(request)
+        })
+    })?;
+    info!("Listening on {:?}", socket);
+    Ok(Server {
+        socket: socket.to_path_buf(),
+    })
+}
+
+/// Answer each line read from `stream` with the result of `handle`, until it is closed.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn answer<S: io::Read + Write>(stream: S, handle: &dyn Fn(&str) -> Result<String, String>) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to read a remote request: {}", e);
+                return;
+            }
+        }
+        let reply = reply_line(&handle(&line)) + "\n";
+        if reader.get_mut().write_all(reply.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::answer;
+    use log::warn;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A busy viewer still answers within this, a hung one is given up on
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// The reply line.
+    pub fn send(socket: &Path, line: &str) -> io::Result<String> {
+        let mut stream = UnixStream::connect(socket)?;
+        stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+        stream.write_all(format!("{}\n", line.trim()).as_bytes())?;
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        Ok(reply)
+    }
+
+    /// Whether `path` itself, not what a symlink points to, is a socket.
+    pub fn is_socket(path: &Path) -> io::Result<bool> {
+        Ok(std::fs::symlink_metadata(path)?.file_type().is_socket())
+    }
+
+    pub fn listen<F>(socket: &Path, handle: F) -> io::Result<()>
+    where
+        F: Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    {
+        let listener = UnixListener::bind(socket)?;
+        let handle = Arc::new(handle);
+        std::thread::Builder::new()
+            .name("remote".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let handle = handle.clone();
+                            // A client that keeps its connection open doesn't hold up others
+                            let _ = std::thread::Builder::new()
+                                .name("remote client".to_string())
+                                .spawn(move || answer(stream, &*handle));
+                        }
+                        Err(e) => warn!("Failed to accept a remote connection: {}", e),
+                    }
+                }
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::io;
+    use std::path::Path;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "local sockets are only supported on Unix",
+        )
+    }
+
+    pub fn send(_socket: &Path, _line: &str) -> io::Result<String> {
+        Err(unsupported())
+    }
+
+    pub fn is_socket(_path: &Path) -> io::Result<bool> {
+        Err(unsupported())
+    }
+
+    pub fn listen<F>(_socket: &Path, _handle: F) -> io::Result<()>
+    where
+        F: Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_requests() {
+        assert_eq!(
+            Request::parse("open /photos/a b.jpg\n"),
+            Ok(Request::Open("/photos/a b.jpg".into()))
+        );
+        assert_eq!(Request::parse("focus"), Ok(Request::Focus));
+        assert!(Request::parse("open").is_err());
+        assert!(Request::parse("launch x").is_err());
+        assert_eq!(Request::parse("goto 12"), Ok(Request::Goto(12)));
+        assert!(Request::parse("goto 0").is_err());
+        assert!(Request::parse("goto last").is_err());
+        assert_eq!(Request::parse(" quit "), Ok(Request::Quit));
+        assert_eq!(Request::parse("current"), Ok(Request::Current));
+        let open = Request::Open("/a/b.png".into());
+        assert_eq!(Request::parse(&open.to_line()), Ok(open));
+    }
+
+    #[test]
+    fn replies_round_trip() {
+        for result in [
+            Ok(String::new()),
+            Ok("1/2 /a.jpg".to_string()),
+            Err("no images".to_string()),
+        ] {
+            assert_eq!(parse_reply(&reply_line(&result)), result);
+        }
+        assert_eq!(
+            parse_current("12/340 /photos/a b.jpg"),
+            Some((12, 340, "/photos/a b.jpg".to_string()))
+        );
+        assert_eq!(parse_current("12 /photos/a.jpg"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn requests_reach_the_server() {
+        use std::sync::{Arc, Mutex};
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("test.sock");
+        // Not ours to remove
+        std::fs::write(&socket, "").unwrap();
+        assert!(serve(&socket, |_| Ok(String::new())).is_err());
+        assert!(socket.exists());
+        std::fs::remove_file(&socket).unwrap();
+        // Left behind by a viewer that didn't shut down
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        let server = serve(&socket, move |request| {
+            log.lock().unwrap().push(request.clone());
+            match request {
+                Request::Open(path) if path.is_relative() => Err("relative path".into()),
+                Request::Current => Ok("3/10 /photos/c.jpg".into()),
+                _ => Ok(String::new()),
+            }
+        })
+        .unwrap();
+        assert!(serve(&socket, |_| Ok(String::new())).is_err());
+
+        assert_eq!(send(&socket, &Request::Open("/photos".into())).unwrap(), "");
+        assert_eq!(
+            send(&socket, &Request::Current).unwrap(),
+            "3/10 /photos/c.jpg"
+        );
+        let e = send(&socket, &Request::Open("photos".into())).unwrap_err();
+        assert_eq!(e.to_string(), "relative path");
+        // The second serve above probed with a focus request
+        assert_eq!(
+            *received.lock().unwrap(),
+            [
+                Request::Focus,
+                Request::Open("/photos".into()),
+                Request::Current,
+                Request::Open("photos".into())
+            ]
+        );
+
+        drop(server);
+        assert!(!socket.exists());
+        assert!(send(&socket, &Request::Focus).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! User scripts in [rhai](https://rhai.rs), bound to keys in `[scripts]`. A script only reaches
+//! the viewer and the system through the functions registered here: the current image,
+//! navigation, image metadata, export and running external commands.
+
+use crate::convert::{self, ConvertOptions};
+use crate::metadata;
+use crate::remote::{self, Request};
+use log::{debug, info};
+use luminous_plugins::PluginManager;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Enough for any sensible script, a runaway loop is stopped instead of hanging the window
+const MAX_OPERATIONS: u64 = 50_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+
+type Fallible<T> = Result<T, Box<EvalAltResult>>;
+
+/// What scripts act on.
+pub struct Host {
+    /// Navigation and the current image, answered like the control socket
+    pub handle: Box<dyn Fn(Request) -> Result<String, String> + Send>,
+    pub plugin_manager: Arc<PluginManager>,
+    /// JPEG quality of exports
+    pub quality: u8,
+}
+
+impl Host {
+    fn request(&self, request: Request) -> Fallible<String> {
+        (self.handle)(request).map_err(Into::into)
+    }
+
+    /// Position, count and path of the current image.
+    fn current(&self) -> Option<(usize, usize, String)> {
+        let reply = (self.handle)(Request::Current).ok()?;
+        remote::parse_current(&reply)
+    }
+
+    fn export(&self, dir: &str, format: &str, max_size: Option<u32>) -> Fallible<String> {
+        let (_, _, path) = self.current().ok_or("no image is shown")?;
+        let src = PathBuf::from(path);
+        let dir = Path::new(dir);
+        let options = ConvertOptions {
+            format: format.trim_start_matches('.').to_lowercase(),
+            max_size,
+            quality: self.quality,
+            overwrite: false,
+            watermark: None,
+        };
+        let summary = convert::convert(
+            std::slice::from_ref(&src),
+            dir,
+            &options,
+            &self.plugin_manager,
+            1,
+        )
+        .map_err(|e| e.to_string())?;
+        let dst = convert::target(&src, dir, &options.format);
+        if summary.failed > 0 {
+            return Err(format!("failed to export {}", src.display()).into());
+        }
+        if summary.skipped > 0 {
+            return Err(format!("{} already exists", dst.display()).into());
+        }
+        Ok(dst.to_string_lossy().into_owned())
+    }
+}
+
+/// Run the script at `path` against `host`, on the calling thread, which should not be the event
+/// loop: a script can run for a long time and `host` waits for the viewer.
+pub fn run(path: &Path, host: Host) -> Result<(), String> {
+    debug!("Running script {:?}", path);
+    engine(Rc::new(host))
+        .run_file(path.to_path_buf())
+        .map_err(|e| e.to_string())
+}
+
+fn engine(host: Rc<Host>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.on_print(|text| info!("Script: {text}"));
+    engine.on_debug(|text, _, pos| debug!("Script {pos}: {text}"));
+
+    let h = host.clone();
+    engine.register_fn("path", move || {
+        h.current().map_or_else(String::new, |c| c.2)
+    });
+    let h = host.clone();
+    engine.register_fn("position", move || h.current().map_or(0, |c| c.0 as i64));
+    let h = host.clone();
+    engine.register_fn("count", move || h.current().map_or(0, |c| c.1 as i64));
+    let h = host.clone();
+    engine.register_fn("next", move || h.request(Request::Next).map(drop));
+    let h = host.clone();
+    engine.register_fn("prev", move || h.request(Request::Prev).map(drop));
+    let h = host.clone();
+    engine.register_fn("goto", move |n: i64| -> Fallible<()> {
+        if n < 1 {
+            return Err(format!("goto needs a position from 1, got {n}").into());
+        }
+        h.request(Request::Goto(n as usize)).map(drop)
+    });
+    let h = host.clone();
+    engine.register_fn("open", move |path: &str| {
+        h.request(Request::Open(PathBuf::from(path))).map(drop)
+    });
+    engine.register_fn("info", |path: &str| -> Fallible<Map> {
+        Ok(info_map(metadata::read_info(Path::new(path))?))
+    });
+    let h = host.clone();
+    engine.register_fn("export", move |dir: &str, format: &str| {
+        h.export(dir, format, None)
+    });
+    let h = host;
+    engine.register_fn(
+        "export",
+        move |dir: &str, format: &str, max_size: i64| -> Fallible<String> {
+            let max_size = u32::try_from(max_size).map_err(|_| "invalid size")?;
+            h.export(dir, format, Some(max_size))
+        },
+    );
+    engine.register_fn("exec", exec);
+    engine
+}
+
+fn info_map(info: metadata::FileInfo) -> Map {
+    let optional = |v: Option<u32>| v.map_or(Dynamic::UNIT, |v| Dynamic::from(v as i64));
+    let exif: Map = info
+        .exif
+        .into_iter()
+        .map(|(tag, value)| (tag.into(), Dynamic::from(value)))
+        .collect();
+    let mut map = Map::new();
+    map.insert("path".into(), Dynamic::from(info.path));
+    map.insert("size".into(), Dynamic::from(info.size as i64));
+    map.insert(
+        "format".into(),
+        info.format.map_or(Dynamic::UNIT, Dynamic::from),
+    );
+    map.insert("width".into(), optional(info.width));
+    map.insert("height".into(), optional(info.height));
+    map.insert("exif".into(), Dynamic::from(exif));
+    map
+}
+
+/// Run `program` with `args` and wait for it, its exit code, stdout and stderr.
+fn exec(program: &str, args: Array) -> Fallible<Map> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    info!("Script runs {program} {args:?}");
+    let output = Command::new(program)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("failed to run {program}: {e}"))?;
+    let mut map = Map::new();
+    // No code when killed by a signal
+    let code = output.status.code().map_or(-1, i64::from);
+    map.insert("code".into(), Dynamic::from(code));
+    map.insert(
+        "stdout".into(),
+        Dynamic::from(String::from_utf8_lossy(&output.stdout).into_owned()),
+    );
+    map.insert(
+        "stderr".into(),
+        Dynamic::from(String::from_utf8_lossy(&output.stderr).into_owned()),
+    );
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[cfg(unix)]
+    #[test]
+    fn scripts_drive_the_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("test.rhai");
+        std::fs::write(
+            &script,
+            "if position() == 3 && path() == \"/photos/c.jpg\" { next(); }\n\
+             goto(count());\n\
+             let out = exec(\"echo\", [\"hi\", 2]).stdout;\n\
+             out.trim();\n\
+             if out != \"hi 2\" { throw \"unexpected \" + out; }\n",
+        )
+        .unwrap();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        let host = Host {
+            handle: Box::new(move |request| {
+                log.lock().unwrap().push(request.clone());
+                match request {
+                    Request::Current => Ok("3/10 /photos/c.jpg".into()),
+                    _ => Ok(String::new()),
+                }
+            }),
+            plugin_manager: Arc::new(PluginManager::new()),
+            quality: 90,
+        };
+        run(&script, host).unwrap();
+        let requests: Vec<Request> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| **r != Request::Current)
+            .cloned()
+            .collect();
+        assert_eq!(requests, [Request::Next, Request::Goto(10)]);
+
+        std::fs::write(&script, "goto(0);").unwrap();
+        let host = Host {
+            handle: Box::new(|_| Ok(String::new())),
+            plugin_manager: Arc::new(PluginManager::new()),
+            quality: 90,
+        };
+        assert!(run(&script, host).unwrap_err().contains("goto"));
+    }
+}
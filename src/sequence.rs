@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Numbered files below this count are left alone, two renders of a scene aren't a clip.
+pub const MIN_FRAMES: usize = 3;
+
+/// Numbered frames sharing a name and extension (frame_0001.png, frame_0002.png, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    /// Indices into the scanned paths, in frame order
+    pub frames: Vec<usize>,
+}
+
+impl Sequence {
+    pub fn position(&self, abs_index: usize) -> Option<usize> {
+        self.frames.iter().position(|&i| i == abs_index)
+    }
+}
+
+/// Split `frame_0012.png` into a grouping key (`frame_####.png` in its directory) and 12.
+fn frame_number(path: &Path) -> Option<(String, u64)> {
+    let stem = path.file_stem()?.to_str()?;
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &stem[prefix.len()..];
+    if digits.is_empty() {
+        return None;
+    }
+    let number = digits.parse().ok()?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let key = format!(
+        "{}/{}{}.{}",
+        dir.display(),
+        prefix,
+        "#".repeat(digits.len()),
+        ext
+    );
+    Some((key, number))
+}
+
+/// Group numbered files into sequences, ordered by where their first frame appears in `paths`.
+pub fn detect(paths: &[PathBuf]) -> Vec<Sequence> {
+    let mut groups: HashMap<String, Vec<(u64, usize)>> = HashMap::new();
+    for (i, path) in paths.iter().enumerate() {
+        if let Some((key, number)) = frame_number(path) {
+            groups.entry(key).or_default().push((number, i));
+        }
+    }
+
+    let mut sequences: Vec<Sequence> = groups
+        .into_values()
+        .filter(|frames| frames.len() >= MIN_FRAMES)
+        .map(|mut frames| {
+            frames.sort_unstable();
+            Sequence {
+                frames: frames.into_iter().map(|(_, i)| i).collect(),
+            }
+        })
+        .collect();
+    sequences.sort_by_key(|s| s.frames.iter().min().copied());
+    sequences
+}
+
+/// Frame after `pos` moving by `delta`, wrapping around inside the loop region `[start, end]`.
+pub fn step(pos: usize, delta: isize, start: usize, end: usize) -> usize {
+    if start >= end {
+        return start;
+    }
+    if pos < start || pos > end {
+        return start;
+    }
+    let len = (end - start + 1) as isize;
+    start + (pos as isize - start as isize + delta).rem_euclid(len) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_groups_by_name_padding_and_extension() {
+        let paths: Vec<PathBuf> = [
+            "/r/cover.png",
+            "/r/frame_0010.png",
+            "/r/frame_0002.png",
+            "/r/frame_0001.png",
+            "/r/frame_0001.exr",
+            "/r/frame_0002.exr",
+            "/r/shot1.jpg",
+            "/r/shot2.jpg",
+            "/r/other/frame_0003.png",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+        let sequences = detect(&paths);
+        assert_eq!(
+            sequences,
+            vec![Sequence {
+                frames: vec![3, 2, 1]
+            }]
+        );
+        assert_eq!(sequences[0].position(1), Some(2));
+        assert_eq!(sequences[0].position(0), None);
+    }
+
+    #[test]
+    fn step_wraps_inside_loop_region() {
+        assert_eq!(step(4, 1, 2, 5), 5);
+        assert_eq!(step(5, 1, 2, 5), 2);
+        assert_eq!(step(2, -1, 2, 5), 5);
+        // Outside the region playback restarts at its start
+        assert_eq!(step(9, 1, 2, 5), 2);
+        assert_eq!(step(3, 1, 3, 3), 3);
+    }
+}
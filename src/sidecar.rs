@@ -0,0 +1,174 @@
+//! Non-destructive edits, kept in a `photo.jpg.luminous.json` file next to the image and applied
+//! again whenever it is opened. The original file is never written.
+
+use crate::adjust::Adjustments;
+use image::imageops;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const SUFFIX: &str = ".luminous.json";
+
+/// Geometric edit, applied in order to the decoded image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    RotateCw,
+    RotateCcw,
+    FlipH,
+    FlipV,
+    /// In pixels of the image as the ops before it left it
+    Crop {
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sidecar {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ops: Vec<Op>,
+    /// Restored into the Adjust panel, a view transform until exported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adjustments: Option<Adjustments>,
+}
+
+pub fn path_for(image: &Path) -> PathBuf {
+    let mut name = image.file_name().unwrap_or_default().to_os_string();
+    name.push(SUFFIX);
+    image.with_file_name(name)
+}
+
+impl Sidecar {
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty() && self.adjustments.is_none_or(|a| a.is_identity())
+    }
+
+    /// Edits of `image`, empty when it has none or they can't be read.
+    pub fn load(image: &Path) -> Self {
+        let path = path_for(image);
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable sidecar {:?}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    /// Write the edits next to `image`, an empty sidecar removes the file.
+    pub fn save(&self, image: &Path) -> io::Result<()> {
+        let path = path_for(image);
+        if self.is_empty() {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// `buffer` with the ops applied, the adjustments are left to the view.
+    pub fn apply(&self, buffer: SharedPixelBuffer<Rgba8Pixel>) -> SharedPixelBuffer<Rgba8Pixel> {
+        if self.ops.is_empty() {
+            return buffer;
+        }
+        let bytes: Vec<u8> = bytemuck::cast_slice(buffer.as_slice()).to_vec();
+        let Some(mut img) = image::RgbaImage::from_raw(buffer.width(), buffer.height(), bytes)
+        else {
+            return buffer;
+        };
+        for op in &self.ops {
+            img = match *op {
+                Op::RotateCw => imageops::rotate90(&img),
+                Op::RotateCcw => imageops::rotate270(&img),
+                Op::FlipH => imageops::flip_horizontal(&img),
+                Op::FlipV => imageops::flip_vertical(&img),
+                Op::Crop { x, y, w, h } => imageops::crop_imm(&img, x, y, w, h).to_image(),
+            };
+        }
+        SharedPixelBuffer::clone_from_slice(img.as_raw(), img.width(), img.height())
+    }
+}
+
+/// Move the sidecars of moved or renamed images along. All are read before any is written, so
+/// images can trade names.
+pub fn carry(moves: &[(PathBuf, PathBuf)]) {
+    let carried: Vec<_> = moves
+        .iter()
+        .map(|(old, new)| (Sidecar::load(old), old, new))
+        .filter(|(sidecar, _, _)| !sidecar.is_empty())
+        .collect();
+    for (_, old, _) in &carried {
+        let _ = std::fs::remove_file(path_for(old));
+    }
+    for (sidecar, _, new) in carried {
+        if let Err(e) = sidecar.save(new) {
+            warn!("Failed to move the edits of {:?}: {}", new, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(w: u32, h: u32) -> SharedPixelBuffer<Rgba8Pixel> {
+        let mut buf = SharedPixelBuffer::<Rgba8Pixel>::new(w, h);
+        for (i, px) in buf.make_mut_slice().iter_mut().enumerate() {
+            px.r = i as u8;
+            px.a = 255;
+        }
+        buf
+    }
+
+    #[test]
+    fn ops_apply_in_order() {
+        let sidecar = Sidecar {
+            ops: vec![
+                Op::RotateCw,
+                Op::Crop {
+                    x: 1,
+                    y: 0,
+                    w: 1,
+                    h: 2,
+                },
+            ],
+            adjustments: None,
+        };
+        // 2x3, rotated to 3x2, the middle column kept
+        let out = sidecar.apply(buffer(2, 3));
+        assert_eq!((out.width(), out.height()), (1, 2));
+        let reds: Vec<u8> = out.as_slice().iter().map(|p| p.r).collect();
+        assert_eq!(reds, [2, 3]);
+    }
+
+    #[test]
+    fn saves_next_to_the_image_and_moves_with_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.jpg"), dir.path().join("b.jpg"));
+        assert_eq!(path_for(&a), dir.path().join("a.jpg.luminous.json"));
+
+        let sidecar = Sidecar {
+            ops: vec![Op::FlipH],
+            adjustments: Some(Adjustments {
+                gamma: 1.5,
+                ..Default::default()
+            }),
+        };
+        sidecar.save(&a).unwrap();
+        assert_eq!(Sidecar::load(&a), sidecar);
+
+        carry(&[(a.clone(), b.clone())]);
+        assert!(Sidecar::load(&a).is_empty());
+        assert_eq!(Sidecar::load(&b), sidecar);
+
+        Sidecar::default().save(&b).unwrap();
+        assert!(!path_for(&b).exists());
+    }
+}
@@ -0,0 +1,72 @@
+use image::{DynamicImage, ImageBuffer, Rgba, imageops::FilterType};
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+
+/// Hamming distance above which images are no longer considered similar.
+pub const MAX_DISTANCE: u32 = 12;
+
+/// Difference hash, one bit per horizontal neighbour comparison on a 9x8 grayscale image.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    hash
+}
+
+pub fn dhash_buffer(buffer: &SharedPixelBuffer<Rgba8Pixel>) -> Option<u64> {
+    let img = ImageBuffer::<Rgba<u8>, _>::from_raw(
+        buffer.width(),
+        buffer.height(),
+        buffer.as_bytes().to_vec(),
+    )?;
+    Some(dhash(&DynamicImage::ImageRgba8(img)))
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Keys of `hashes` within `MAX_DISTANCE` of `query`, most similar first.
+pub fn rank<K: Copy>(query: u64, hashes: &[(K, u64)]) -> Vec<K> {
+    let mut ranked: Vec<(K, u32)> = hashes
+        .iter()
+        .map(|&(key, hash)| (key, distance(query, hash)))
+        .filter(|&(_, d)| d <= MAX_DISTANCE)
+        .collect();
+    ranked.sort_by_key(|&(_, d)| d);
+    ranked.into_iter().map(|(key, _)| key).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(w: u32, h: u32, invert: bool) -> DynamicImage {
+        DynamicImage::ImageLuma8(ImageBuffer::from_fn(w, h, |x, _| {
+            let v = (x * 255 / (w - 1)) as u8;
+            image::Luma([if invert { 255 - v } else { v }])
+        }))
+    }
+
+    #[test]
+    fn dhash_ignores_scale() {
+        let a = dhash(&gradient(64, 64, false));
+        let b = dhash(&gradient(300, 200, false));
+        assert!(distance(a, b) <= 2);
+    }
+
+    #[test]
+    fn rank_orders_by_distance_and_drops_dissimilar() {
+        let query = dhash(&gradient(64, 64, false));
+        let hashes = [
+            (0, dhash(&gradient(64, 64, true))),
+            (1, query ^ 0b111),
+            (2, query),
+        ];
+        assert_eq!(rank(query, &hashes), vec![2, 1]);
+    }
+}
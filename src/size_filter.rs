@@ -0,0 +1,179 @@
+//! Filter images by shape and resolution. Dimensions come from the image headers, read once and
+//! cached in the library, so no pixels are decoded.
+
+use crate::library::Library;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// How far a ratio may be off and still count, relative
+const RATIO_TOLERANCE: f32 = 0.02;
+/// Images at least this many times wider than tall, or taller than wide, are panoramas, for the
+/// filter and the grid badges alike
+pub const PANORAMA_RATIO: f32 = 2.0;
+
+/// Names of the presets offered in the grid, `all` first.
+pub const PRESETS: [&str; 10] = [
+    "all",
+    "landscape",
+    "portrait",
+    "square",
+    "panorama",
+    "16:9",
+    "4:3",
+    "3:2",
+    ">=1080p",
+    ">=4k",
+];
+
+/// Which image dimensions pass. Ratios and minimums apply in either orientation, so a portrait
+/// 9:16 image is 16:9 too.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SizeFilter {
+    #[default]
+    All,
+    Landscape,
+    Portrait,
+    Square,
+    Panorama,
+    /// Long side over short side
+    Ratio(f32),
+    /// Long and short side at least this
+    AtLeast(u32, u32),
+}
+
+impl SizeFilter {
+    /// A preset name, any other `w:h` ratio or `>=wxh` minimum.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        match name.as_str() {
+            "all" => return Some(Self::All),
+            "landscape" => return Some(Self::Landscape),
+            "portrait" => return Some(Self::Portrait),
+            "square" => return Some(Self::Square),
+            "panorama" => return Some(Self::Panorama),
+            ">=1080p" => return Some(Self::AtLeast(1920, 1080)),
+            ">=4k" => return Some(Self::AtLeast(3840, 2160)),
+            _ => {}
+        }
+        if let Some(min) = name.strip_prefix(">=") {
+            let (w, h) = min.split_once('x')?;
+            let (w, h): (u32, u32) = (w.parse().ok()?, h.parse().ok()?);
+            return Some(Self::AtLeast(w.max(h), w.min(h)));
+        }
+        let (w, h) = name.split_once(':')?;
+        let (w, h): (f32, f32) = (w.parse().ok()?, h.parse().ok()?);
+        (w > 0.0 && h > 0.0).then(|| Self::Ratio(w.max(h) / w.min(h)))
+    }
+
+    pub fn accepts(self, (w, h): (u32, u32)) -> bool {
+        if w == 0 || h == 0 {
+            return false;
+        }
+        let (long, short) = (w.max(h), w.min(h));
+        let ratio = long as f32 / short as f32;
+        let near = |r: f32| (ratio / r - 1.0).abs() <= RATIO_TOLERANCE;
+        match self {
+            Self::All => true,
+            Self::Landscape => w > h,
+            Self::Portrait => h > w,
+            Self::Square => near(1.0),
+            Self::Panorama => ratio >= PANORAMA_RATIO,
+            Self::Ratio(r) => near(r),
+            Self::AtLeast(min_long, min_short) => long >= min_long && short >= min_short,
+        }
+    }
+}
+
+/// 0 for files that can't be read.
+fn mtime(path: &Path) -> i64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Image dimensions looked up this run, backed by the library.
+pub struct SizeIndex {
+    library: Arc<Library>,
+    /// `None` for files whose header couldn't be read
+    known: Mutex<HashMap<PathBuf, Option<(u32, u32)>>>,
+}
+
+impl SizeIndex {
+    pub fn new(library: Arc<Library>) -> Self {
+        Self {
+            library,
+            known: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Dimensions of `path` if they were read already, `None` when not read or unreadable.
+    pub fn get(&self, path: &Path) -> Option<(u32, u32)> {
+        self.known.lock().unwrap().get(path).copied().flatten()
+    }
+
+    /// Those of `paths` not looked up yet.
+    pub fn missing(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let known = self.known.lock().unwrap();
+        paths
+            .iter()
+            .filter(|p| !known.contains_key(*p))
+            .cloned()
+            .collect()
+    }
+
+    /// Look up `paths` in the library, reading the headers of files it doesn't have yet.
+    pub fn read(&self, paths: &[PathBuf]) {
+        let files: Vec<(PathBuf, i64)> = paths.iter().map(|p| (p.clone(), mtime(p))).collect();
+        let cached = self.library.dimensions(&files);
+        let found: Vec<(PathBuf, Option<(u32, u32)>)> = files
+            .into_par_iter()
+            .map(|(path, mtime)| {
+                let size = cached.get(&path).copied().or_else(|| {
+                    let size = image::image_dimensions(&path).ok()?;
+                    if mtime != 0 {
+                        self.library.set_dimensions(&path, mtime, size);
+                    }
+                    Some(size)
+                });
+                (path, size)
+            })
+            .collect();
+        self.known.lock().unwrap().extend(found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_in_either_orientation() {
+        let wide = SizeFilter::from_name("16:9").unwrap();
+        assert!(wide.accepts((1920, 1080)));
+        assert!(wide.accepts((1080, 1920)));
+        assert!(!wide.accepts((1600, 1200)));
+        assert!(SizeFilter::from_name("4:3").unwrap().accepts((4000, 3000)));
+
+        let uhd = SizeFilter::from_name(">=4K").unwrap();
+        assert!(uhd.accepts((6000, 4000)));
+        assert!(uhd.accepts((2160, 3840)));
+        assert!(!uhd.accepts((3840, 1600)));
+        assert_eq!(
+            SizeFilter::from_name(">=600x800"),
+            Some(SizeFilter::AtLeast(800, 600))
+        );
+
+        assert!(SizeFilter::Portrait.accepts((3, 4)));
+        assert!(!SizeFilter::Portrait.accepts((4, 4)));
+        assert!(SizeFilter::Square.accepts((1000, 990)));
+        assert!(SizeFilter::Panorama.accepts((8000, 2000)));
+        assert!(!SizeFilter::All.accepts((0, 10)));
+        assert!(PRESETS.iter().all(|p| SizeFilter::from_name(p).is_some()));
+        assert_eq!(SizeFilter::from_name("0:9"), None);
+        assert_eq!(SizeFilter::from_name("wide"), None);
+    }
+}
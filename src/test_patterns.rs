@@ -0,0 +1,201 @@
+use image::{Rgba, RgbaImage};
+use std::f32::consts::PI;
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Gamma values offered by the gamma check, one band each.
+const GAMMAS: [f32; 5] = [1.8, 2.0, 2.2, 2.4, 2.6];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestPattern {
+    /// Smooth and stepped gray ramps plus red, green and blue ramps
+    Gradient,
+    /// 1px black/white stripes next to solid patches, the patch that blends
+    /// in with the stripes from a distance marks the display gamma
+    Gamma,
+    /// Siemens star with 1px checkerboards in the corners
+    Sharpness,
+}
+
+impl TestPattern {
+    pub const ALL: [TestPattern; 3] = [
+        TestPattern::Gradient,
+        TestPattern::Gamma,
+        TestPattern::Sharpness,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            TestPattern::Gradient => "gradient",
+            TestPattern::Gamma => "gamma",
+            TestPattern::Sharpness => "sharpness",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            TestPattern::Gradient => "Gradients",
+            TestPattern::Gamma => "Gamma check",
+            TestPattern::Sharpness => "Sharpness target",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.id() == id)
+    }
+
+    /// Render at `width`x`height`, meant to be shown 1:1 with physical pixels.
+    pub fn render(self, width: u32, height: u32) -> RgbaImage {
+        let width = width.max(64);
+        let height = height.max(64);
+        match self {
+            TestPattern::Gradient => gradient(width, height),
+            TestPattern::Gamma => gamma(width, height),
+            TestPattern::Sharpness => sharpness(width, height),
+        }
+    }
+}
+
+fn gradient(width: u32, height: u32) -> RgbaImage {
+    let bands = 5;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let v = (x * 255 / (width - 1)) as u8;
+        match y * bands / height {
+            0 => Rgba([v, v, v, 255]),
+            1 => {
+                let step = (v / 16) * 17;
+                Rgba([step, step, step, 255])
+            }
+            2 => Rgba([v, 0, 0, 255]),
+            3 => Rgba([0, v, 0, 255]),
+            _ => Rgba([0, 0, v, 255]),
+        }
+    })
+}
+
+/// Solid value with the same perceived brightness as 50% black/white stripes.
+pub fn gamma_gray(gamma: f32) -> u8 {
+    (255.0 * 0.5f32.powf(1.0 / gamma)).round() as u8
+}
+
+fn gamma(width: u32, height: u32) -> RgbaImage {
+    let band_h = height / GAMMAS.len() as u32;
+    let mut img = RgbaImage::from_fn(width, height, |_, y| if y % 2 == 0 { BLACK } else { WHITE });
+    for (i, &g) in GAMMAS.iter().enumerate() {
+        let gray = gamma_gray(g);
+        let top = i as u32 * band_h + band_h / 4;
+        let bottom = top + band_h / 2;
+        for y in top..bottom.min(height) {
+            for x in width / 3..width * 2 / 3 {
+                img.put_pixel(x, y, Rgba([gray, gray, gray, 255]));
+            }
+        }
+        let scale = (band_h / 12).clamp(1, 8);
+        let label_y = top + (bottom - top).saturating_sub(5 * scale) / 2;
+        draw_text(
+            &mut img,
+            &format!("{g:.1}"),
+            width * 2 / 3 + 10,
+            label_y,
+            scale,
+            Rgba([255, 64, 64, 255]),
+        );
+    }
+    img
+}
+
+fn sharpness(width: u32, height: u32) -> RgbaImage {
+    const SPOKES: f32 = 36.0;
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let radius = cx.min(cy) * 0.9;
+    let corner = width.min(height) / 8;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let in_corner_x = x < corner || x >= width - corner;
+        let in_corner_y = y < corner || y >= height - corner;
+        if in_corner_x && in_corner_y {
+            return if (x + y) % 2 == 0 { BLACK } else { WHITE };
+        }
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        if dx.hypot(dy) > radius {
+            return Rgba([128, 128, 128, 255]);
+        }
+        let angle = dy.atan2(dx) + PI;
+        let sector = (angle / (2.0 * PI) * SPOKES * 2.0) as u32;
+        if sector.is_multiple_of(2) {
+            BLACK
+        } else {
+            WHITE
+        }
+    })
+}
+
+/// 3x5 glyphs for labels, rows top to bottom with the 3 low bits as pixels.
+fn glyph(c: char) -> Option<[u8; 5]> {
+    Some(match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        ' ' => [0; 5],
+        _ => return None,
+    })
+}
+
+/// Draw `text` with the built-in 3x5 font, each font pixel `scale` pixels wide.
+/// Unsupported characters are skipped.
+pub fn draw_text(img: &mut RgbaImage, text: &str, x: u32, y: u32, scale: u32, color: Rgba<u8>) {
+    let mut pen_x = x;
+    for rows in text.chars().filter_map(glyph) {
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        let px = pen_x + col * scale + sx;
+                        let py = y + row as u32 * scale + sy;
+                        if px < img.width() && py < img.height() {
+                            img.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+        pen_x += 4 * scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamma_gray_matches_known_values() {
+        assert_eq!(gamma_gray(1.0), 128);
+        assert_eq!(gamma_gray(2.2), 186);
+    }
+
+    #[test]
+    fn patterns_render_at_requested_size() {
+        for pattern in TestPattern::ALL {
+            let img = pattern.render(320, 200);
+            assert_eq!(img.dimensions(), (320, 200));
+            assert_eq!(TestPattern::from_id(pattern.id()), Some(pattern));
+        }
+        // Gradient ramps run from black to full intensity
+        let img = TestPattern::Gradient.render(256, 100);
+        assert_eq!(img.get_pixel(0, 0), &BLACK);
+        assert_eq!(img.get_pixel(255, 0), &WHITE);
+        assert_eq!(img.get_pixel(255, 50), &Rgba([255, 0, 0, 255]));
+    }
+}
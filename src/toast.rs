@@ -0,0 +1,124 @@
+//! Notices of things that went wrong, or right, away from the event loop: failed decodes,
+//! plugin errors and file operations. Any thread posts to a [`Toaster`], the window drains the
+//! channel and stacks the toasts in a corner without taking focus.
+
+use log::{error, info, warn};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+    /// How long a toast stays up, errors long enough to read a path in them.
+    pub fn duration(self) -> Duration {
+        match self {
+            Self::Info => Duration::from_secs(3),
+            Self::Warning => Duration::from_secs(5),
+            Self::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Posts toasts from any thread. Every post is logged too.
+#[derive(Clone)]
+pub struct Toaster {
+    tx: Sender<Toast>,
+    /// Gets the receiving side to drain the channel
+    wake: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl Toaster {
+    /// A toaster and the channel its toasts arrive on, `wake` is called after each post.
+    pub fn new<F>(wake: F) -> (Self, Receiver<Toast>)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let toaster = Self {
+            tx,
+            wake: Arc::new(wake),
+        };
+        (toaster, rx)
+    }
+
+    pub fn post(&self, severity: Severity, message: impl Into<String>) {
+        let message = message.into();
+        match severity {
+            Severity::Info => info!("{message}"),
+            Severity::Warning => warn!("{message}"),
+            Severity::Error => error!("{message}"),
+        }
+        // Gone with the window, the log has it
+        if self.tx.send(Toast { severity, message }).is_ok() {
+            (self.wake)();
+        }
+    }
+
+    pub fn info(&self, message: impl Into<String>) {
+        self.post(Severity::Info, message);
+    }
+
+    pub fn warning(&self, message: impl Into<String>) {
+        self.post(Severity::Warning, message);
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.post(Severity::Error, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn toasts_from_threads_arrive_in_order() {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = wakes.clone();
+        let (toaster, rx) = Toaster::new(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let worker = toaster.clone();
+        std::thread::spawn(move || {
+            worker.error("Can't decode a.jpg");
+            worker.info("Exported b.jpg");
+        })
+        .join()
+        .unwrap();
+        toaster.warning("Low disk space");
+
+        let toasts: Vec<(Severity, String)> =
+            rx.try_iter().map(|t| (t.severity, t.message)).collect();
+        assert_eq!(
+            toasts,
+            [
+                (Severity::Error, "Can't decode a.jpg".into()),
+                (Severity::Info, "Exported b.jpg".into()),
+                (Severity::Warning, "Low disk space".into()),
+            ]
+        );
+        assert_eq!(wakes.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::AppController;
+use crate::MainWindow;
+use crate::config::BackgroundPause;
+use log::info;
+use slint::ComponentHandle;
+use slint::winit_030::winit::event::WindowEvent;
+use slint::winit_030::{EventResult, WinitWindowAccessor};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Pause thumbnail generation and prefetching while the window is in the background, as far
+/// as `pause` reaches, and resume when it comes back.
+pub fn register(
+    window: &MainWindow,
+    app_controller: Rc<RefCell<AppController>>,
+    pause: BackgroundPause,
+) {
+    if pause == BackgroundPause::Never {
+        return;
+    }
+    let loader = app_controller.borrow().loader.clone();
+    let focused = Cell::new(true);
+    let occluded = Cell::new(false);
+    let paused = Cell::new(false);
+    window.window().on_winit_window_event(move |window, event| {
+        match event {
+            WindowEvent::Focused(f) => focused.set(*f),
+            WindowEvent::Occluded(o) => occluded.set(*o),
+            // Some platforms only report minimizing as a resize
+            WindowEvent::Resized(_) => {}
+            _ => return EventResult::Propagate,
+        }
+        let visible = !occluded.get() && !window.is_minimized();
+        let pause_now = pause.applies(focused.get(), visible);
+        if paused.replace(pause_now) != pause_now {
+            info!(
+                "Window {}, background loading {}",
+                if visible { "unfocused" } else { "hidden" },
+                if pause_now { "paused" } else { "resumed" }
+            );
+            loader.set_paused(pause_now);
+        }
+        EventResult::Propagate
+    });
+}
@@ -1,23 +1,100 @@
 use crate::MainWindow;
+use crate::actions::Action;
 use crate::config::Config;
+use crate::keymap::{self, Keymap, Modifiers};
+use log::warn;
+use slint::ComponentHandle;
+use std::cell::RefCell;
+use std::time::Instant;
 
+/// Set the keys of the bindings in `config`, again on a reload. Keys of the bindings a macro is
+/// named after run the macro.
 pub fn setup(window: &MainWindow, config: &Config) {
-    let get_key = |action: &str| {
-        Config::get_slint_key_string(
-            config
-                .bindings
-                .get(action)
-                .unwrap_or_else(|| panic!("Binding '{action}' not in config")),
-        )
+    let get_key = |action: &str| -> slint::SharedString {
+        let binding = config
+            .bindings
+            .get(action)
+            .unwrap_or_else(|| panic!("Binding '{action}' not in config"));
+        keymap::parse(binding)
+            .map_err(|e| warn!("Binding {action}: {e}"))
+            .unwrap_or_default()
+            .into()
     };
+    let keymap = RefCell::new(Keymap::new(&config.key_bindings()));
+    let macro_keys: Vec<(String, String)> = config
+        .macros
+        .iter()
+        .filter_map(|m| {
+            let binding = config.bindings.get(&m.name)?;
+            Some((keymap::parse(binding).ok()?, m.name.clone()))
+        })
+        .collect();
+    let weak = window.as_weak();
+    window.on_run_macro(move |key| {
+        let Some((_, name)) = macro_keys.iter().find(|(k, _)| *k == key.as_str()) else {
+            return false;
+        };
+        if let Some(ui) = weak.upgrade() {
+            ui.invoke_run_action(Action::Macro(name.clone()).id().unwrap_or_default().into());
+        }
+        true
+    });
+    window.on_resolve_key(move |text, ctrl, alt, shift, meta| {
+        let mods = Modifiers {
+            ctrl,
+            alt,
+            shift,
+            meta,
+        };
+        let chord = keymap::chord(&text, mods);
+        keymap
+            .borrow_mut()
+            .press(chord, Instant::now())
+            .unwrap_or_default()
+            .into()
+    });
     window.set_bind_quit(get_key("quit"));
     window.set_bind_fullscreen(get_key("toggle_fullscreen"));
     window.set_bind_switch_view_mode(get_key("switch_view_mode"));
     window.set_bind_reset_zoom(get_key("reset_zoom"));
+    window.set_bind_cycle_fit_mode(get_key("cycle_fit_mode"));
     window.set_bind_grid_pg_dn(get_key("grid_page_down"));
     window.set_bind_grid_pg_up(get_key("grid_page_up"));
     window.set_bind_toggle_side_panel(get_key("toggle_side_panel"));
-    window.set_bind_copy_to_clipboard(get_key("copy_to_clipboard"));
+    window.set_bind_copy_image(get_key("copy_image"));
+    window.set_bind_paste_image(get_key("paste_image"));
+    window.set_bind_crop(get_key("crop"));
+    window.set_bind_markup(get_key("markup"));
     window.set_bind_delete(get_key("delete"));
     window.set_bind_show_settings(get_key("show_settings"));
+    window.set_bind_hot_folder_pause(get_key("hot_folder_pause"));
+    window.set_bind_capture(get_key("capture"));
+    window.set_bind_command_palette(get_key("command_palette"));
+    window.set_bind_perf_overlay(get_key("perf_overlay"));
+    window.set_bind_exposure_up(get_key("exposure_up"));
+    window.set_bind_exposure_down(get_key("exposure_down"));
+    window.set_bind_play_sequence(get_key("play_sequence"));
+    window.set_bind_loop_in(get_key("loop_in"));
+    window.set_bind_loop_out(get_key("loop_out"));
+    window.set_bind_pixel_inspector(get_key("pixel_inspector"));
+    window.set_bind_copy_pixel_hex(get_key("copy_pixel_hex"));
+    window.set_bind_slideshow(get_key("slideshow"));
+    window.set_bind_info_overlay(get_key("info_overlay"));
+    window.set_bind_caption(get_key("caption"));
+    window.set_bind_channel_view(get_key("channel_view"));
+    window.set_bind_rate_0(get_key("rate_0"));
+    window.set_bind_rate_1(get_key("rate_1"));
+    window.set_bind_rate_2(get_key("rate_2"));
+    window.set_bind_rate_3(get_key("rate_3"));
+    window.set_bind_rate_4(get_key("rate_4"));
+    window.set_bind_rate_5(get_key("rate_5"));
+    window.set_bind_flag_pick(get_key("flag_pick"));
+    window.set_bind_flag_reject(get_key("flag_reject"));
+    window.set_bind_flag_clear(get_key("flag_clear"));
+    window.set_bind_label_red(get_key("label_red"));
+    window.set_bind_label_yellow(get_key("label_yellow"));
+    window.set_bind_label_green(get_key("label_green"));
+    window.set_bind_label_blue(get_key("label_blue"));
+    window.set_bind_toggle_favorite(get_key("toggle_favorite"));
+    window.set_bind_last_bookmark(get_key("last_bookmark"));
 }
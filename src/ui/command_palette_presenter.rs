@@ -0,0 +1,76 @@
+use super::dispatcher::Dispatcher;
+use crate::AppController;
+use crate::CommandPaletteState;
+use crate::MainWindow;
+use crate::PaletteCommand;
+use crate::actions::{Action, Macro};
+use log::{debug, warn};
+use slint::{ComponentHandle, VecModel};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+fn all_commands(macros: &[Macro], recent: &[PathBuf]) -> Vec<PaletteCommand> {
+    Action::palette(macros, recent)
+        .into_iter()
+        .filter_map(|action| {
+            Some(PaletteCommand {
+                id: action.id()?.into(),
+                title: action.title().into(),
+            })
+        })
+        .collect()
+}
+
+/// Commands whose title contains every word of `query`, case-insensitive.
+fn filter_commands(query: &str, macros: &[Macro], recent: &[PathBuf]) -> Vec<PaletteCommand> {
+    let query = query.to_lowercase();
+    all_commands(macros, recent)
+        .into_iter()
+        .filter(|c| {
+            let title = c.title.to_lowercase();
+            query.split_whitespace().all(|w| title.contains(w))
+        })
+        .collect()
+}
+
+pub fn register(
+    window: &MainWindow,
+    app_controller: Rc<RefCell<AppController>>,
+    dispatcher: Rc<Dispatcher>,
+) {
+    let cp = window.global::<CommandPaletteState>();
+    let recent = app_controller.borrow().recent_locations();
+    cp.set_commands(Rc::new(VecModel::from(all_commands(&dispatcher.macros(), &recent))).into());
+
+    let acc = app_controller.clone();
+    let d = dispatcher.clone();
+    cp.on_query_changed(move |query| {
+        let Some(ui) = acc.borrow().window_weak.upgrade() else {
+            return;
+        };
+        let recent = acc.borrow().recent_locations();
+        ui.global::<CommandPaletteState>().set_commands(
+            Rc::new(VecModel::from(filter_commands(
+                &query,
+                &d.macros(),
+                &recent,
+            )))
+            .into(),
+        );
+    });
+
+    let weak = window.as_weak();
+    cp.on_run(move |id| {
+        debug!("Command palette: {id}");
+        match Action::from_id(&id) {
+            Some(action) => {
+                let _ = dispatcher.dispatch(action);
+            }
+            None => warn!("Unknown command '{id}'"),
+        }
+        if let Some(ui) = weak.upgrade() {
+            ui.invoke_return_focus();
+        }
+    });
+}
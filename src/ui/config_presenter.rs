@@ -0,0 +1,177 @@
+use crate::Accessibility;
+use crate::FullViewState;
+use crate::GridViewState;
+use crate::MainWindow;
+use crate::actions::Macro;
+use crate::config::Config;
+use crate::power;
+use crate::toast::Toaster;
+use log::{debug, error, info};
+use luminous_image_loader::ImageLoader;
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{EventKind, RecursiveMode, Watcher};
+use slint::ComponentHandle;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Editors write a file in several steps, the config is read once they are done.
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// How often the battery saver looks at the power supply.
+const POWER_POLL: Duration = Duration::from_secs(10);
+
+/// Apply edits of the config file and profile of `config` to `window` as they are saved, the
+/// macros going to `macros`, and take the battery profile on and off as the power supply
+/// changes. Problems are shown as toasts, a file that doesn't parse leaves everything as it was.
+/// The returned watcher, `None` without a config file, must be kept alive for as long as edits
+/// should be picked up.
+pub fn watch(
+    window: &MainWindow,
+    loader: Arc<ImageLoader>,
+    toaster: Toaster,
+    macros: Arc<Mutex<Vec<Macro>>>,
+    mut config: Config,
+) -> Option<notify::RecommendedWatcher> {
+    let profile = config
+        .profile
+        .as_deref()
+        .and_then(Config::find_profile_path);
+    let files: Vec<PathBuf> = config.config_path.iter().cloned().chain(profile).collect();
+    let (tx, rx) = mpsc::channel::<()>();
+    let watcher = watch_files(files, tx.clone());
+
+    let weak = window.as_weak();
+    let spawned = std::thread::Builder::new()
+        .name("config-watch".to_string())
+        .spawn(move || {
+            // Keeps the power supply polled without a config file to watch
+            let _tx = tx;
+            loop {
+                match rx.recv_timeout(POWER_POLL) {
+                    Ok(()) => {
+                        while rx.recv_timeout(SETTLE_TIME).is_ok() {}
+                        let issues = match config.reload() {
+                            Ok(issues) => issues,
+                            Err(e) => {
+                                toaster.error(format!("Config not reloaded, {e}"));
+                                continue;
+                            }
+                        };
+                        info!("Config reloaded");
+                        match issues.as_slice() {
+                            [] => toaster.info("Config reloaded"),
+                            [issue] => toaster.warning(format!("{issue}, ignored")),
+                            [issue, rest @ ..] => toaster.warning(format!(
+                                "{issue}, ignored, and {} more problems, see --check-config",
+                                rest.len()
+                            )),
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let on_battery = config.battery_saver && power::on_battery();
+                        if on_battery == config.on_battery {
+                            continue;
+                        }
+                        if let Err(e) = config.set_on_battery(on_battery) {
+                            error!("Battery profile not switched: {e}");
+                            continue;
+                        }
+                        info!("On battery power: {on_battery}");
+                        toaster.info(if on_battery {
+                            "On battery, saving power"
+                        } else {
+                            "Plugged in, battery saver off"
+                        });
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let config = config.clone();
+                let loader = loader.clone();
+                let macros = macros.clone();
+                let _ = weak.upgrade_in_event_loop(move |ui| {
+                    *macros.lock().unwrap() = config.macros.clone();
+                    apply(&ui, &config, &loader);
+                });
+            }
+            debug!("Config watcher stopped");
+        });
+    if let Err(e) = spawned {
+        error!("Failed to spawn config watch thread: {}", e);
+    }
+
+    watcher
+}
+
+/// Send on `tx` whenever one of `files` is written.
+fn watch_files(files: Vec<PathBuf>, tx: mpsc::Sender<()>) -> Option<notify::RecommendedWatcher> {
+    if files.is_empty() {
+        return None;
+    }
+    let names: HashSet<OsString> = files
+        .iter()
+        .filter_map(|f| f.file_name())
+        .map(|n| n.to_os_string())
+        .collect();
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Config watch error: {}", e);
+                return;
+            }
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        ) {
+            return;
+        }
+        let ours = event
+            .paths
+            .iter()
+            .any(|p| p.file_name().is_some_and(|n| names.contains(n)));
+        if ours {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(|e| error!("Failed to create config watcher: {}", e))
+    .ok()?;
+
+    // Editors save by replacing the file, which only the folder sees
+    for file in &files {
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            error!("Failed to watch {:?}: {}", dir, e);
+        }
+    }
+    debug!("Watching config files {:?}", files);
+
+    Some(watcher)
+}
+
+/// Settings [`Config::reload`] changes, in the window and the loader.
+fn apply(ui: &MainWindow, config: &Config, loader: &ImageLoader) {
+    loader.set_window_size(config.window_size);
+    super::bindings::setup(ui, config);
+    #[cfg(feature = "scripting")]
+    super::script_presenter::register(ui, loader.plugin_manager.clone(), config);
+    ui.set_app_background(config.background);
+    ui.global::<Accessibility>()
+        .set_reduced_motion(config.reduced_motion);
+    ui.global::<GridViewState>()
+        .set_prefetch_rows(config.prefetch_rows as i32);
+    ui.global::<FullViewState>()
+        .set_slideshow_interval((config.slideshow_interval * 1000.0) as i64);
+    ui.global::<FullViewState>()
+        .set_playback_fps(config.playback_fps as i32);
+}
@@ -0,0 +1,285 @@
+use crate::AppController;
+use crate::FullViewState;
+use crate::GridViewState;
+use crate::MainWindow;
+use crate::PerfOverlayState;
+use crate::ViewMode;
+use crate::actions::{Action, Macro};
+use crate::app_state_cache::{self, AppState, Session, WindowGeometry};
+use crate::pipeline;
+use crate::recording::Step;
+use log::{debug, info, warn};
+use slint::ComponentHandle;
+use slint::winit_030::WinitWindowAccessor;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// Runs actions for every way of asking: keys, the command palette, the control socket, D-Bus
+/// and scripts. Actions run on the event loop.
+pub struct Dispatcher {
+    app_controller: Rc<RefCell<AppController>>,
+    window: slint::Weak<MainWindow>,
+    /// Windowed geometry to restore when leaving fullscreen
+    windowed_geometry: Cell<Option<WindowGeometry>>,
+    /// Sessions in demo images or favorites keep the one before
+    last_session: Option<Session>,
+    /// Replaced when the config is reloaded, see [`super::config_presenter::watch`]
+    macros: Arc<Mutex<Vec<Macro>>>,
+}
+
+impl Dispatcher {
+    pub fn new(
+        window: &MainWindow,
+        app_controller: Rc<RefCell<AppController>>,
+        cached_state: &AppState,
+        macros: Vec<Macro>,
+    ) -> Rc<Self> {
+        let windowed_geometry = (cached_state.fullscreen
+            && cached_state.width > 0
+            && cached_state.height > 0)
+            .then(|| WindowGeometry {
+                position: slint::PhysicalPosition::new(cached_state.x, cached_state.y),
+                size: slint::PhysicalSize::new(cached_state.width, cached_state.height),
+            });
+        Rc::new(Self {
+            app_controller,
+            window: window.as_weak(),
+            windowed_geometry: Cell::new(windowed_geometry),
+            last_session: cached_state.session.clone(),
+            macros: Arc::new(Mutex::new(macros)),
+        })
+    }
+
+    /// Run `action`, with the answer to queries. Errors are for the one asking, nothing is
+    /// shown for them.
+    pub fn dispatch(&self, action: Action) -> Result<String, String> {
+        let ui = self.window.upgrade().ok_or("the window is closed")?;
+        let acc = &self.app_controller;
+        let fv = ui.global::<FullViewState>();
+        debug!("Action: {action:?}");
+        match action {
+            Action::OpenFolder => AppController::handle_open_images(acc.clone()),
+            Action::OpenFile => AppController::handle_open_image_file(acc.clone()),
+            Action::Open(path) => {
+                if !path.exists() {
+                    return Err(format!("{} doesn't exist", path.display()));
+                }
+                acc.borrow_mut().handle_open_recent(&path.to_string_lossy());
+                super::full_view_presenter::set_keywords(acc);
+                super::full_view_presenter::set_exif(acc.clone());
+                raise(&ui);
+            }
+            Action::OpenRecent(path) => {
+                acc.borrow_mut().handle_open_recent(&path.to_string_lossy())
+            }
+            Action::CopyImage => acc.borrow().handle_copy_image(),
+            Action::PasteImage => acc.borrow().handle_paste_image(),
+            Action::ToggleFullscreen => self.toggle_fullscreen(&ui),
+            Action::SwitchView => ui.set_view_mode(match ui.get_view_mode() {
+                ViewMode::Grid => ViewMode::Full,
+                ViewMode::Full => ViewMode::Grid,
+            }),
+            Action::View(mode) => ui.set_view_mode(mode),
+            Action::ToggleSpread => {
+                fv.set_spread(!fv.get_spread());
+                acc.borrow().update_spread_page(&fv);
+            }
+            Action::ToggleReadingDirection => fv.set_right_to_left(!fv.get_right_to_left()),
+            Action::ShowFavorites => acc.borrow_mut().handle_show_favorites(),
+            Action::LastBookmark => acc.borrow_mut().handle_jump_to_last_bookmark(),
+            Action::ExportZip => acc.borrow().handle_export_zip(0),
+            Action::Rename => acc.borrow_mut().handle_open_rename(),
+            Action::ShowSettings => ui.invoke_show_settings(),
+            Action::CommandPalette => ui.invoke_show_command_palette(),
+            Action::TogglePerfOverlay => {
+                let perf = ui.global::<PerfOverlayState>();
+                perf.set_visible(!perf.get_visible());
+                if perf.get_visible() {
+                    perf.invoke_sample();
+                }
+            }
+            Action::ToggleSlideshow => {
+                fv.set_slideshow_active(!fv.get_slideshow_active());
+                fv.set_slideshow_paused(false);
+                if fv.get_slideshow_active() {
+                    ui.set_view_mode(ViewMode::Full);
+                }
+            }
+            Action::PurgeCaches { disk } => acc.borrow_mut().handle_purge_caches(disk),
+            Action::TestPattern(pattern) => acc.borrow().handle_show_test_pattern(pattern),
+            Action::Next => fv.invoke_request_next_image(),
+            Action::Prev => fv.invoke_request_prev_image(),
+            Action::Goto(n) => {
+                let shown = acc.borrow().filtered_indices.clone();
+                let Some(&abs_index) = shown.get(n.wrapping_sub(1)) else {
+                    return Err(format!("{} images are shown", shown.len()));
+                };
+                ui.set_view_mode(ViewMode::Full);
+                fv.invoke_request_image(abs_index as i32);
+            }
+            Action::Show(name) => {
+                let abs_index = acc.borrow().index_named(&name)?;
+                ui.set_view_mode(ViewMode::Full);
+                fv.invoke_request_image(abs_index as i32);
+            }
+            Action::Current => {
+                let acc = acc.borrow();
+                let row = acc.current_row().ok_or("no image is shown")?;
+                let path = acc.scan.paths.get(acc.filtered_indices[row]).cloned();
+                let path = path.ok_or("no image is shown")?;
+                return Ok(format!(
+                    "{}/{} {}",
+                    row + 1,
+                    acc.filtered_indices.len(),
+                    path.display()
+                ));
+            }
+            Action::Focus => raise(&ui),
+            Action::Quit => self.quit(&ui),
+            // Through the full view, which records them
+            Action::Rate(rating) => fv.invoke_rate(rating.into()),
+            Action::Flag(flag) => fv.invoke_set_flag(flag.name().into()),
+            Action::Label(label) => fv.invoke_set_color_label(label.name().into()),
+            Action::ToggleFavorite => fv.invoke_toggle_favorite(),
+            Action::Edit(op) => fv.invoke_apply_edit(op),
+            Action::Upscale(scale) => fv.invoke_request_upscale(scale as i32),
+            Action::Search(query) => {
+                let gv = ui.global::<GridViewState>();
+                gv.set_search_query(query.as_str().into());
+                gv.invoke_search_submitted(query.into());
+            }
+            Action::Batch { plugin, names } => {
+                let acc = acc.borrow();
+                let paths = names
+                    .iter()
+                    .map(|name| Ok(acc.scan.paths[acc.index_named(name)?].clone()))
+                    .collect::<Result<_, String>>()?;
+                acc.record(Action::Batch {
+                    plugin: plugin.clone(),
+                    names,
+                });
+                pipeline::run_plugin_batch(
+                    paths,
+                    plugin,
+                    acc.loader.plugin_manager.clone(),
+                    acc.window_weak.clone(),
+                );
+            }
+            Action::Macro(name) => return self.run_macro(&name),
+        }
+        Ok(String::new())
+    }
+
+    /// Macros from the config, by name.
+    pub fn macros(&self) -> Vec<Macro> {
+        self.macros.lock().unwrap().clone()
+    }
+
+    /// Where the config watcher puts the macros of a reloaded config.
+    pub fn shared_macros(&self) -> Arc<Mutex<Vec<Macro>>> {
+        self.macros.clone()
+    }
+
+    /// Run the steps of the macro `name` in one go, up to the first that fails.
+    fn run_macro(&self, name: &str) -> Result<String, String> {
+        let m = self
+            .macros()
+            .into_iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| format!("unknown macro '{name}'"))?;
+        let mut replies = Vec::new();
+        for step in &m.steps {
+            let reply = self
+                .dispatch(step.clone())
+                .map_err(|e| format!("{name}: {e}"))?;
+            if !reply.is_empty() {
+                replies.push(reply);
+            }
+        }
+        Ok(replies.join("\n"))
+    }
+
+    /// Play back a session script with its original timing, each step once the previous one was
+    /// dispatched. Steps that fail are logged and skipped.
+    pub fn replay(self: Rc<Self>, steps: Rc<Vec<Step>>, next: usize) {
+        let Some(step) = steps.get(next) else {
+            info!("Replay finished");
+            return;
+        };
+        let since_prev = next
+            .checked_sub(1)
+            .map_or(step.at, |prev| step.at.saturating_sub(steps[prev].at));
+        slint::Timer::single_shot(since_prev, move || {
+            let action = steps[next].action.clone();
+            info!("Replay {}/{}: {:?}", next + 1, steps.len(), action);
+            if let Err(e) = self.dispatch(action) {
+                warn!("Replay: {e}");
+            }
+            self.replay(steps, next + 1);
+        });
+    }
+
+    fn toggle_fullscreen(&self, mw: &MainWindow) {
+        let win = mw.window();
+        if win.is_fullscreen() {
+            debug!("Leaving fullscreen");
+            win.set_fullscreen(false);
+            mw.set_is_fullscreen(false);
+            if let Some(prev) = self.windowed_geometry.take() {
+                win.set_size(prev.size);
+                win.set_position(prev.position);
+            }
+        } else {
+            debug!("Entering fullscreen");
+            self.windowed_geometry.set(Some(WindowGeometry {
+                position: win.position(),
+                size: win.size(),
+            }));
+            mw.set_is_fullscreen(true);
+            win.set_fullscreen(true);
+        }
+    }
+
+    fn quit(&self, mw: &MainWindow) {
+        let acc = self.app_controller.borrow();
+        acc.save_view_profile();
+        let session = acc.session().or_else(|| self.last_session.clone());
+        app_state_cache::save_app_state(mw, self.windowed_geometry.get(), session);
+        let _ = slint::quit_event_loop();
+    }
+}
+
+/// Bring the window to the front, out of the taskbar if it was minimized.
+fn raise(ui: &MainWindow) {
+    ui.window().with_winit_window(|w| {
+        w.set_minimized(false);
+        w.focus_window();
+    });
+}
+
+/// Route the window's own callbacks for actions through `dispatcher`.
+pub fn register(window: &MainWindow, dispatcher: Rc<Dispatcher>) {
+    let d = dispatcher.clone();
+    window.on_run_action(move |id| match Action::from_id(&id) {
+        Some(action) => {
+            let _ = d.dispatch(action);
+        }
+        None => warn!("Unknown action '{id}'"),
+    });
+    let d = dispatcher.clone();
+    window.on_open_images(move || {
+        let _ = d.dispatch(Action::OpenFolder);
+    });
+    let d = dispatcher.clone();
+    window.on_open_image_file(move || {
+        let _ = d.dispatch(Action::OpenFile);
+    });
+    let d = dispatcher.clone();
+    window.on_toggle_fullscreen(move || {
+        let _ = d.dispatch(Action::ToggleFullscreen);
+    });
+    window.on_quit_app(move || {
+        let _ = dispatcher.dispatch(Action::Quit);
+    });
+}
@@ -1,7 +1,10 @@
 use crate::AppController;
 use crate::FullViewState;
 use crate::MainWindow;
+use crate::VideoExportState;
+use crate::actions::Action;
 use crate::image_processing::save_image;
+use crate::library::{ColorLabel, Flag};
 use cocotools::coco::object_detection::{
     Annotation, Bbox, Dataset, Image as CocoImage, Rle, Segmentation,
 };
@@ -24,34 +27,74 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
 
     let acc = app_controller.clone();
     fv.on_request_next_image(move || {
+        acc.borrow().record(Action::Next);
         acc.borrow().handle_navigate(1);
+        set_keywords(&acc);
         set_exif(acc.clone());
     });
 
     let acc = app_controller.clone();
     fv.on_request_prev_image(move || {
+        acc.borrow().record(Action::Prev);
         acc.borrow().handle_navigate(-1);
+        set_keywords(&acc);
+        set_exif(acc.clone());
+    });
+
+    let acc = app_controller.clone();
+    fv.on_transfer_to_target(move |key| {
+        let handled = acc.borrow_mut().handle_transfer_to_target(&key);
+        if handled {
+            set_keywords(&acc);
+            set_exif(acc.clone());
+        }
+        handled
+    });
+
+    let acc = app_controller.clone();
+    fv.on_request_image(move |abs_index| {
+        acc.borrow().record_open(abs_index as usize);
+        acc.borrow().handle_full_view_load(abs_index as usize);
+        set_keywords(&acc);
+        set_exif(acc.clone());
+    });
+
+    let acc = app_controller.clone();
+    fv.on_filmstrip_clicked(move |abs_index| {
+        acc.borrow().record_open(abs_index as usize);
+        acc.borrow().handle_full_view_load(abs_index as usize);
+        set_keywords(&acc);
         set_exif(acc.clone());
     });
 
     let acc = app_controller.clone();
     fv.on_apply_edit(move |op| {
+        acc.borrow().record(Action::Edit(op.clone()));
         acc.borrow_mut().handle_edit_op(op);
     });
 
     let acc = app_controller.clone();
     fv.on_save_with_format(move |format| {
-        let (img, path, weak_ui, plugin_manager) = {
+        let (img, path, weak_ui, quality, plugin_manager, watermark) = {
             let c_ref = acc.borrow();
             let idx = c_ref.loader.active_idx.load(Ordering::Relaxed);
             (
                 c_ref.loader.get_curr_active_buffer(),
                 c_ref.loader.get_path(idx),
                 c_ref.window_weak.clone(),
+                c_ref.jpeg_quality,
                 c_ref.loader.plugin_manager.clone(),
+                c_ref.watermark.clone(),
             )
         };
-        save_image(img, path, format.as_str().into(), plugin_manager);
+        save_image(
+            img,
+            path,
+            format.as_str().into(),
+            quality,
+            plugin_manager,
+            watermark,
+        );
         slint::invoke_from_event_loop(move || {
             if let Some(ui) = weak_ui.upgrade() {
                 ui.invoke_return_focus();
@@ -60,6 +103,25 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
         .unwrap();
     });
 
+    let acc = app_controller.clone();
+    fv.on_save_as(move || acc.borrow().handle_save_as());
+
+    let acc = app_controller.clone();
+    fv.on_set_wallpaper(move || acc.borrow().handle_set_wallpaper());
+
+    let acc = app_controller.clone();
+    fv.on_export_slideshow(move || acc.borrow().handle_open_video_export());
+
+    let video = window.global::<VideoExportState>();
+    let acc = app_controller.clone();
+    video.on_pick_music(move || acc.borrow().handle_pick_video_music());
+    let acc = app_controller.clone();
+    video.on_clear_music(move || acc.borrow().handle_clear_video_music());
+    let acc = app_controller.clone();
+    video.on_export(move || acc.borrow().handle_export_video());
+    let acc = app_controller.clone();
+    video.on_cancel(move || acc.borrow().handle_cancel_video_export());
+
     let acc = app_controller.clone();
     fv.on_request_segmentation(move |plugin_id, x1, y1, x2, y2, txt| {
         acc.borrow().handle_segmentation(
@@ -79,6 +141,278 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
         .unwrap();
     });
 
+    fv.set_upscaler_available(
+        app_controller
+            .borrow()
+            .loader
+            .plugin_manager
+            .get_upscaler_plugin()
+            .is_some(),
+    );
+
+    fv.set_capture_available(
+        app_controller
+            .borrow()
+            .loader
+            .plugin_manager
+            .get_capture_plugins()
+            .next()
+            .is_some(),
+    );
+
+    let acc = app_controller.clone();
+    fv.on_request_capture(move || {
+        acc.borrow().handle_capture();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_image_vanished(move |path| {
+        acc.borrow_mut().handle_image_vanished(path.as_str().into());
+    });
+
+    let acc = app_controller.clone();
+    fv.on_paste_image(move || {
+        acc.borrow().handle_paste_image();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_save_pasted_image(move || {
+        acc.borrow().handle_save_pasted_image();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_apply_crop(move |rect, overwrite| {
+        acc.borrow().handle_apply_crop(rect, overwrite);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_adjust_exposure(move |delta| {
+        acc.borrow().handle_adjust_exposure(delta);
+    });
+
+    let acc = app_controller.clone();
+    let window_weak = window.as_weak();
+    fv.on_sequence_step(move |delta| {
+        acc.borrow().handle_sequence_step(delta as isize);
+        // Reading metadata every frame would hold back playback
+        if window_weak
+            .upgrade()
+            .is_some_and(|ui| !ui.global::<FullViewState>().get_playing())
+        {
+            set_keywords(&acc);
+            set_exif(acc.clone());
+        }
+    });
+
+    let acc = app_controller.clone();
+    fv.on_sequence_seek(move |pos| {
+        acc.borrow().handle_sequence_seek(pos.max(0) as usize);
+        set_keywords(&acc);
+        set_exif(acc.clone());
+    });
+
+    let acc = app_controller.clone();
+    fv.on_pick_reference(move || {
+        acc.borrow().handle_pick_reference();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_clear_reference(move || {
+        acc.borrow_mut().handle_clear_reference();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_update_reference(move || {
+        acc.borrow().handle_update_reference();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_update_display_filter(move || {
+        acc.borrow().handle_update_display_filter();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_rate(move |rating| {
+        let rating = rating.clamp(0, 5) as u8;
+        acc.borrow().record(Action::Rate(rating));
+        acc.borrow_mut().handle_rate(rating);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_set_flag(move |flag| {
+        if let Some(flag) = Flag::from_name(&flag) {
+            acc.borrow().record(Action::Flag(flag));
+            acc.borrow_mut().handle_set_flag(flag);
+        }
+    });
+
+    let acc = app_controller.clone();
+    fv.on_set_color_label(move |label| {
+        if let Some(label) = ColorLabel::from_name(&label) {
+            acc.borrow().record(Action::Label(label));
+            acc.borrow_mut().handle_set_color_label(label);
+        }
+    });
+
+    let acc = app_controller.clone();
+    fv.on_toggle_favorite(move || {
+        acc.borrow_mut().handle_toggle_favorite();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_update_adjustments(move || {
+        acc.borrow().handle_update_adjustments();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_export_adjusted(move || {
+        acc.borrow().handle_export_adjusted();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_save_adjustments(move || {
+        acc.borrow().handle_save_adjustments();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_export_flattened(move || {
+        acc.borrow().handle_export_flattened();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_load_caption(move || {
+        acc.borrow().handle_load_caption();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_save_caption(move |caption| {
+        acc.borrow().handle_save_caption(&caption);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_markup_begin(move |tool, x, y, color, width, text| {
+        acc.borrow()
+            .handle_markup_begin(&tool, x, y, color, width, &text);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_markup_extend(move |x, y| {
+        acc.borrow().handle_markup_extend(x, y);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_markup_end(move || {
+        acc.borrow().handle_markup_end();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_markup_undo(move || {
+        acc.borrow().handle_markup_undo();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_markup_clear(move || {
+        acc.borrow().handle_markup_clear();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_export_markup(move || {
+        acc.borrow().handle_export_markup();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_update_false_color(move || {
+        acc.borrow().handle_update_false_color();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_cycle_channel_view(move || {
+        acc.borrow().handle_cycle_channel_view();
+    });
+
+    let acc = app_controller.clone();
+    fv.on_inspect_pixel(move |x, y| {
+        acc.borrow().handle_inspect_pixel(x, y);
+    });
+
+    let window_weak = window.as_weak();
+    let acc = app_controller.clone();
+    fv.on_copy_inspector_hex(move || {
+        let Some(ui) = window_weak.upgrade() else {
+            return;
+        };
+        let hex = ui.global::<FullViewState>().get_inspector_hex();
+        if hex.is_empty() {
+            return;
+        }
+        match crate::with_clipboard(&acc.borrow().clipboard, |c| c.set_text(hex.as_str())) {
+            Ok(()) => debug!("Copied {hex} to clipboard"),
+            Err(e) => error!("Clipboard copy failed: {e}"),
+        }
+    });
+
+    let acc = app_controller.clone();
+    fv.on_find_similar(move || {
+        let c_ref = acc.borrow();
+        let Some(ui) = c_ref.window_weak.upgrade() else {
+            return;
+        };
+        let abs_index = ui.global::<FullViewState>().get_curr_image_index();
+        c_ref.handle_find_similar(abs_index as usize);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_request_upscale(move |scale| {
+        acc.borrow().record(Action::Upscale(scale as u32));
+        acc.borrow().handle_upscale(scale as u32);
+    });
+
+    let acc = app_controller.clone();
+    fv.on_export_upscale(move || {
+        let (path, weak_ui, quality, plugin_manager, watermark) = {
+            let c_ref = acc.borrow();
+            (
+                c_ref.loader.get_curr_img_path(),
+                c_ref.window_weak.clone(),
+                c_ref.jpeg_quality,
+                c_ref.loader.plugin_manager.clone(),
+                c_ref.watermark.clone(),
+            )
+        };
+        let Some(ui) = weak_ui.upgrade() else {
+            return;
+        };
+        let fv = ui.global::<FullViewState>();
+        let buffer = fv.get_upscale_preview().to_rgba8();
+        let scale = fv.get_upscale_factor();
+        fv.set_upscale_preview(Image::default());
+
+        if let (Some(path), Some(buffer)) = (path, buffer) {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_else(|| "png".into());
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let upscaled_path = path.with_file_name(format!("{stem}_x{scale}.{ext}"));
+            save_image(
+                Some(buffer),
+                Some(upscaled_path),
+                ext,
+                quality,
+                plugin_manager,
+                watermark,
+            );
+        }
+    });
+
+    let window_weak = window.as_weak();
+    fv.on_discard_upscale(move || {
+        if let Some(ui) = window_weak.upgrade() {
+            ui.global::<FullViewState>()
+                .set_upscale_preview(Image::default());
+        }
+    });
+
     let window_weak = window.as_weak();
     fv.on_clear_curr_mask_overlay(move || {
         let _ = window_weak.upgrade_in_event_loop(move |ui| {
@@ -314,6 +648,22 @@ fn save_mask(mask_buffer: SharedPixelBuffer<Rgba8Pixel>, path: &Path, file_name:
         .unwrap_or(false)
 }
 
+pub fn set_keywords(app_controller: &Rc<RefCell<AppController>>) {
+    let c_ref = app_controller.borrow();
+    let keywords = c_ref
+        .loader
+        .get_curr_img_path()
+        .map(|path| c_ref.library.keywords(&path))
+        .unwrap_or_default()
+        .iter()
+        .map(|tag| format!("{} ({:.0}%)", tag.label, tag.confidence * 100.0))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Some(ui) = c_ref.window_weak.upgrade() {
+        ui.global::<FullViewState>().set_keywords(keywords.into());
+    }
+}
+
 fn set_empty_exif() -> slint::ModelRc<slint::ModelRc<StandardListViewItem>> {
     slint::ModelRc::new(VecModel::from(vec![slint::ModelRc::new(VecModel::from(
         vec![
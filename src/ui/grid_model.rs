@@ -0,0 +1,202 @@
+//! The grid's rows, made when the grid scrolls to them rather than all at once. Only the length
+//! comes from the scan up front, so a folder of a hundred thousand files starts as fast and as
+//! small as a folder of ten. Rows that are changed, by a thumbnail, the selection or a rating,
+//! are kept until they are as made again.
+
+use crate::GridItem;
+use slint::{Image, Model, ModelNotify, ModelRc, ModelTracker};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+type MakeFn = dyn Fn(usize, usize) -> GridItem;
+
+pub struct GridModel {
+    /// Scan index of the image on each row
+    rows: Vec<usize>,
+    /// Makes the item of a row from its row and scan index
+    make: Box<MakeFn>,
+    /// Rows set since they were made
+    changed: RefCell<BTreeMap<usize, GridItem>>,
+    notify: ModelNotify,
+}
+
+impl GridModel {
+    /// A row for each scan index of `rows`, in that order.
+    pub fn new<F>(rows: Vec<usize>, make: F) -> Self
+    where
+        F: Fn(usize, usize) -> GridItem + 'static,
+    {
+        Self {
+            rows,
+            make: Box::new(make),
+            changed: RefCell::default(),
+            notify: ModelNotify::default(),
+        }
+    }
+
+    /// Row of the image at scan index `abs_index`.
+    pub fn row_of(&self, abs_index: usize) -> Option<usize> {
+        self.rows.iter().position(|&abs| abs == abs_index)
+    }
+
+    /// Rows that may differ from how they were made, in order.
+    pub fn changed_rows(&self) -> Vec<usize> {
+        self.changed.borrow().keys().copied().collect()
+    }
+
+    /// Clear the thumbnail of `row`, forgetting the row when nothing else was changed on it.
+    pub fn drop_image(&self, row: usize) {
+        let mut changed = self.changed.borrow_mut();
+        let Some(item) = changed.get_mut(&row) else {
+            return;
+        };
+        // An empty image doesn't equal another one, but it has no size
+        if item.image.size().width == 0 {
+            return;
+        }
+        item.image = Image::default();
+        // Badges are read as the row is made, they need no keeping either
+        let made = (self.make)(row, self.rows[row]);
+        let as_made = item.index == made.index
+            && item.abs_index == made.abs_index
+            && item.selected == made.selected
+            && item.name == made.name
+            && item.rating == made.rating
+            && item.flag == made.flag
+            && item.label == made.label;
+        if as_made {
+            changed.remove(&row);
+        }
+        drop(changed);
+        self.notify.row_changed(row);
+    }
+}
+
+impl Model for GridModel {
+    type Data = GridItem;
+
+    fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn row_data(&self, row: usize) -> Option<GridItem> {
+        let abs = *self.rows.get(row)?;
+        if let Some(item) = self.changed.borrow().get(&row) {
+            return Some(item.clone());
+        }
+        Some((self.make)(row, abs))
+    }
+
+    fn set_row_data(&self, row: usize, data: GridItem) {
+        if row >= self.rows.len() {
+            return;
+        }
+        self.changed.borrow_mut().insert(row, data);
+        self.notify.row_changed(row);
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        &self.notify
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Change the row of the image at `abs_index` in `model`, if it is shown.
+pub fn update(model: &ModelRc<GridItem>, abs_index: usize, f: impl FnOnce(&mut GridItem)) {
+    let row = match model.as_any().downcast_ref::<GridModel>() {
+        Some(grid) => grid.row_of(abs_index),
+        None => model
+            .iter()
+            .position(|item| item.abs_index == abs_index as i32),
+    };
+    if let Some(row) = row
+        && let Some(mut item) = model.row_data(row)
+    {
+        f(&mut item);
+        model.set_row_data(row, item);
+    }
+}
+
+/// Rows of `model` whose items are selected, in order.
+pub fn selected_rows(model: &ModelRc<GridItem>) -> Vec<usize> {
+    let rows = match model.as_any().downcast_ref::<GridModel>() {
+        // Selecting a row changes it
+        Some(grid) => grid.changed_rows(),
+        None => (0..model.row_count()).collect(),
+    };
+    rows.into_iter()
+        .filter(|&row| model.row_data(row).is_some_and(|item| item.selected))
+        .collect()
+}
+
+/// Clear the thumbnails of `model` outside of `keep`.
+pub fn drop_images(model: &ModelRc<GridItem>, keep: std::ops::RangeInclusive<usize>) {
+    if let Some(grid) = model.as_any().downcast_ref::<GridModel>() {
+        for row in grid.changed_rows() {
+            if !keep.contains(&row) {
+                grid.drop_image(row);
+            }
+        }
+        return;
+    }
+    for row in 0..model.row_count() {
+        if !keep.contains(&row)
+            && let Some(mut item) = model.row_data(row)
+            && item.image.size().width > 0
+        {
+            item.image = Image::default();
+            model.set_row_data(row, item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn item(row: usize, abs: usize) -> GridItem {
+        GridItem {
+            index: row as i32,
+            abs_index: abs as i32,
+            name: format!("{abs}.jpg").into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rows_are_made_on_demand_and_kept_while_changed() {
+        let made = Rc::new(Cell::new(0));
+        let counter = made.clone();
+        let grid = GridModel::new((0..100_000).rev().collect(), move |row, abs| {
+            counter.set(counter.get() + 1);
+            item(row, abs)
+        });
+        assert_eq!(grid.row_count(), 100_000);
+        assert_eq!(made.get(), 0);
+        assert_eq!(grid.row_data(1).unwrap().abs_index, 99_998);
+        assert_eq!(made.get(), 1);
+        assert_eq!(grid.row_of(99_998), Some(1));
+
+        let model = ModelRc::new(grid);
+        update(&model, 5, |item| item.selected = true);
+        update(&model, 7, |item| {
+            item.image = Image::from_rgba8(slint::SharedPixelBuffer::new(1, 1))
+        });
+        let grid = model.as_any().downcast_ref::<GridModel>().unwrap();
+        assert_eq!(grid.changed_rows(), [99_992, 99_994]);
+        assert_eq!(selected_rows(&model), [99_994]);
+
+        // The thumbnail was all that changed on its row
+        drop_images(&model, 99_990..=99_999);
+        assert_eq!(grid.changed_rows(), [99_992, 99_994]);
+        drop_images(&model, 0..=0);
+        assert_eq!(grid.changed_rows(), [99_994]);
+        assert!(model.row_data(99_994).unwrap().selected);
+    }
+}
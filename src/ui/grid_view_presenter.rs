@@ -1,10 +1,19 @@
 use crate::AppController;
+use crate::FolderTreeState;
 use crate::GridViewState;
 use crate::MainWindow;
+use crate::MetadataState;
+use crate::RenameState;
+use crate::ZipExportState;
+use crate::actions::Action;
 use crate::image_processing::batch_save_images;
+use crate::pipeline::run_plugin_batch;
+use crate::size_filter;
+use crate::ui::grid_model;
 use log::{info, warn};
 use slint::ComponentHandle;
 use slint::Model;
+use slint::{SharedString, VecModel};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -23,9 +32,97 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
 
     let acc = app_controller.clone();
     gv.on_search_submitted(move |query| {
+        acc.borrow().record(Action::Search(query.to_string()));
         acc.borrow_mut().handle_search(query.to_string());
     });
 
+    let acc = app_controller.clone();
+    gv.on_min_rating_changed(move |min| {
+        acc.borrow_mut().handle_min_rating(min.clamp(0, 5) as u8);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_mark_filter_changed(move |filter| {
+        acc.borrow_mut().handle_mark_filter(&filter);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_size_filter_changed(move |filter| {
+        acc.borrow_mut().handle_size_filter(&filter);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_show_favorites(move || {
+        acc.borrow_mut().handle_show_favorites();
+    });
+
+    let acc = app_controller.clone();
+    gv.on_jump_to_last_bookmark(move || {
+        acc.borrow_mut().handle_jump_to_last_bookmark();
+    });
+
+    let acc = app_controller.clone();
+    gv.on_export_zip(move |max_edge| {
+        acc.borrow().handle_export_zip(max_edge.max(0) as u32);
+    });
+
+    let acc = app_controller.clone();
+    window.global::<ZipExportState>().on_cancel(move || {
+        acc.borrow().handle_cancel_zip_export();
+    });
+
+    let acc = app_controller.clone();
+    gv.on_open_recent(move |path| {
+        acc.borrow_mut().handle_open_recent(&path);
+    });
+
+    let acc = app_controller.clone();
+    let ft = window.global::<FolderTreeState>();
+    ft.on_refresh(move || {
+        acc.borrow_mut().reveal_folder_tree();
+    });
+
+    let acc = app_controller.clone();
+    ft.on_open_folder(move |path| {
+        acc.borrow_mut().handle_open_folder(&path);
+    });
+
+    let acc = app_controller.clone();
+    ft.on_toggle_folder(move |path| {
+        acc.borrow_mut().handle_toggle_folder(&path);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_batch_rename(move || {
+        acc.borrow_mut().handle_open_rename();
+    });
+
+    let acc = app_controller.clone();
+    let rs = window.global::<RenameState>();
+    rs.on_edited(move |template| {
+        acc.borrow().handle_rename_preview(&template);
+    });
+
+    let acc = app_controller.clone();
+    rs.on_apply(move || {
+        acc.borrow_mut().handle_apply_rename();
+    });
+
+    let acc = app_controller.clone();
+    gv.on_edit_metadata(move || {
+        acc.borrow_mut().handle_open_metadata();
+    });
+
+    let acc = app_controller.clone();
+    window.global::<MetadataState>().on_apply(move || {
+        acc.borrow().handle_apply_metadata();
+    });
+
+    let acc = app_controller.clone();
+    gv.on_search_edited(move |filter| {
+        acc.borrow_mut().handle_filter_names(filter.to_string());
+    });
+
     let acc = app_controller.clone();
     gv.on_image_selected(move |index| {
         let c_ref = acc.borrow();
@@ -33,6 +130,7 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
             return;
         };
         if let Some(&abs) = c_ref.filtered_indices.get(index as usize) {
+            c_ref.record_open(abs);
             ui.set_view_mode(crate::ViewMode::Full);
             c_ref.handle_full_view_load(abs);
         }
@@ -109,6 +207,97 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
         gv.set_selected_count(total_selected);
     });
 
+    gv.set_tagger_available(
+        app_controller
+            .borrow()
+            .loader
+            .plugin_manager
+            .get_tagger_plugins()
+            .next()
+            .is_some(),
+    );
+
+    let acc = app_controller.clone();
+    gv.on_auto_tag_selected(move || {
+        let c_ref = acc.borrow();
+        let paths = c_ref.collect_selected_paths();
+        c_ref.handle_auto_tag(paths);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_show_collection(move |indices| {
+        let indices = indices.iter().map(|i| i as usize).collect();
+        acc.borrow_mut().show_collection(indices);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_find_by_color(move |color| {
+        acc.borrow().handle_find_by_color(color);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_suggest_rotations(move || {
+        let c_ref = acc.borrow();
+        let Some(ui) = c_ref.window_weak.upgrade() else {
+            return;
+        };
+        let model = ui.global::<GridViewState>().get_model();
+        let selected: Vec<usize> = grid_model::selected_rows(&model)
+            .into_iter()
+            .filter_map(|row| c_ref.filtered_indices.get(row).copied())
+            .collect();
+        let indices = if selected.is_empty() {
+            c_ref.filtered_indices.clone()
+        } else {
+            selected
+        };
+        c_ref.handle_suggest_rotations(indices);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_compare_selected(move || {
+        let c_ref = acc.borrow();
+        let Some(ui) = c_ref.window_weak.upgrade() else {
+            return;
+        };
+        let model = ui.global::<GridViewState>().get_model();
+        let selected: Vec<usize> = grid_model::selected_rows(&model)
+            .into_iter()
+            .filter_map(|row| c_ref.filtered_indices.get(row).copied())
+            .collect();
+        c_ref.handle_compare(selected);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_move_item(move |from, to| {
+        acc.borrow_mut()
+            .handle_move_item(from as usize, to as usize);
+    });
+
+    let acc = app_controller.clone();
+    gv.on_reset_custom_order(move || {
+        acc.borrow_mut().handle_reset_custom_order();
+    });
+
+    let acc = app_controller.clone();
+    gv.on_apply_rotations(move || {
+        let Some(ui) = acc.borrow().window_weak.upgrade() else {
+            return;
+        };
+        let suggestions = ui.global::<GridViewState>().get_rotation_suggestions();
+        acc.borrow()
+            .handle_apply_rotations(suggestions.iter().collect());
+    });
+
+    let acc = app_controller.clone();
+    gv.on_dismiss_rotations(move || {
+        let Some(ui) = acc.borrow().window_weak.upgrade() else {
+            return;
+        };
+        ui.global::<GridViewState>()
+            .set_rotation_suggestions(slint::ModelRc::default());
+    });
+
     let acc = app_controller.clone();
     gv.on_print_selected_paths(move || {
         let paths = acc.borrow().collect_selected_paths();
@@ -121,17 +310,17 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
 
     let acc = app_controller.clone();
     window.on_batch_save_with_format(move |format| {
-        let (paths, weak_ui) = {
+        let (paths, weak_ui, quality, watermark) = {
             let c_ref = acc.borrow();
             let paths = c_ref.collect_selected_paths();
             let weak = c_ref.window_weak.clone();
-            (paths, weak)
+            (paths, weak, c_ref.jpeg_quality, c_ref.watermark.clone())
         };
         if paths.is_empty() {
             warn!("No files selected");
             return;
         }
-        batch_save_images(paths, format);
+        batch_save_images(paths, format, quality, watermark);
         slint::invoke_from_event_loop(move || {
             if let Some(ui) = weak_ui.upgrade() {
                 ui.invoke_return_focus();
@@ -144,4 +333,61 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
     gv.on_request_sort(move |ascending| {
         acc.borrow_mut().handle_sort(ascending);
     });
+
+    let acc = app_controller.clone();
+    gv.on_request_sort_by(move |sorter| {
+        acc.borrow().handle_sort_by(sorter.to_string());
+    });
+
+    let mut sorters: Vec<SharedString> = app_controller
+        .borrow()
+        .loader
+        .plugin_manager
+        .get_sorter_plugins()
+        .flat_map(|p| p.sorters())
+        .map(SharedString::from)
+        .collect();
+    sorters.sort();
+    sorters.dedup();
+    gv.set_sorters(Rc::new(VecModel::from(sorters)).into());
+
+    let presets: Vec<SharedString> = size_filter::PRESETS
+        .iter()
+        .map(|&p| SharedString::from(p))
+        .collect();
+    gv.set_size_presets(Rc::new(VecModel::from(presets)).into());
+
+    let batch_plugins: Vec<SharedString> = app_controller
+        .borrow()
+        .loader
+        .plugin_manager
+        .get_batch_plugins()
+        .map(|p| SharedString::from(p.id.as_str()))
+        .collect();
+    gv.set_batch_plugins(Rc::new(VecModel::from(batch_plugins)).into());
+
+    let acc = app_controller.clone();
+    gv.on_process_batch(move |plugin_id| {
+        let (paths, weak_ui, plugin_manager) = {
+            let c_ref = acc.borrow();
+            (
+                c_ref.collect_selected_paths(),
+                c_ref.window_weak.clone(),
+                c_ref.loader.plugin_manager.clone(),
+            )
+        };
+        if paths.is_empty() {
+            warn!("No files selected");
+            return;
+        }
+        acc.borrow().record(Action::Batch {
+            plugin: plugin_id.to_string(),
+            names: paths
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .collect(),
+        });
+        run_plugin_batch(paths, plugin_id.to_string(), plugin_manager, weak_ui);
+    });
 }
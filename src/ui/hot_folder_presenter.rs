@@ -0,0 +1,56 @@
+use crate::AppController;
+use crate::FullViewState;
+use crate::MainWindow;
+use crate::hot_folder;
+use crate::pipeline::StepFactory;
+use log::warn;
+use slint::ComponentHandle;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+pub fn register(
+    window: &MainWindow,
+    app_controller: Rc<RefCell<AppController>>,
+    factory: Arc<StepFactory>,
+) {
+    let fv = window.global::<FullViewState>();
+
+    let acc = app_controller.clone();
+    fv.on_image_arrived(move |path, follow| {
+        acc.borrow_mut()
+            .handle_image_arrival(path.as_str().into(), follow, factory.clone());
+        super::full_view_presenter::set_keywords(&acc);
+        super::full_view_presenter::set_exif(acc.clone());
+    });
+}
+
+/// Start watching `dir`, the returned watcher must be kept alive for as long as it should run.
+pub fn watch(
+    window: &MainWindow,
+    app_controller: &Rc<RefCell<AppController>>,
+    dir: &Path,
+) -> Option<notify::RecommendedWatcher> {
+    if !dir.is_dir() {
+        warn!("Hot folder mode needs a directory, got {:?}", dir);
+        return None;
+    }
+
+    let exts = app_controller
+        .borrow()
+        .scan
+        .image_formats
+        .get_all_decoding_exts();
+    let weak = window.as_weak();
+    let watcher = hot_folder::watch(dir, exts, move |path| {
+        let path = path.to_string_lossy().to_string();
+        let _ = weak.upgrade_in_event_loop(move |ui| {
+            let fv = ui.global::<FullViewState>();
+            let follow = !fv.get_hot_folder_paused();
+            fv.invoke_image_arrived(path.into(), follow);
+        });
+    })?;
+    window.global::<FullViewState>().set_hot_folder_active(true);
+    Some(watcher)
+}
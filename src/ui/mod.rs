@@ -1,5 +1,18 @@
+pub mod background_presenter;
 pub mod bindings;
+pub mod command_palette_presenter;
+pub mod config_presenter;
+pub mod dispatcher;
 pub mod full_view_presenter;
+pub mod grid_model;
 pub mod grid_view_presenter;
+pub mod hot_folder_presenter;
+pub mod onboarding_presenter;
+pub mod perf_overlay_presenter;
 pub mod pipeline_presenter;
+pub mod remote_presenter;
+#[cfg(feature = "scripting")]
+pub mod script_presenter;
 pub mod settings_presenter;
+pub mod status_bar_presenter;
+pub mod toast_presenter;
@@ -0,0 +1,91 @@
+use crate::config::Config;
+use crate::file_association;
+use crate::{KeyHint, MainWindow, OnboardingState};
+use log::error;
+use slint::{ComponentHandle, SharedString, VecModel};
+use std::rc::Rc;
+
+/// Bindings worth knowing on day one, in the order they are shown. The rest are listed in the
+/// generated config.
+const ESSENTIAL_ACTIONS: [&str; 12] = [
+    "switch_view_mode",
+    "toggle_fullscreen",
+    "cycle_fit_mode",
+    "reset_zoom",
+    "toggle_side_panel",
+    "command_palette",
+    "show_settings",
+    "slideshow",
+    "flag_pick",
+    "flag_reject",
+    "delete",
+    "quit",
+];
+
+/// "toggle_side_panel" -> "Toggle side panel"
+fn describe(action: &str) -> String {
+    let words = action.replace('_', " ");
+    let mut chars = words.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn key_hints(config: &Config) -> Vec<KeyHint> {
+    ESSENTIAL_ACTIONS
+        .iter()
+        .filter_map(|action| {
+            config.bindings.get(*action).map(|key| KeyHint {
+                key: key.into(),
+                action: describe(action).into(),
+            })
+        })
+        .collect()
+}
+
+pub fn register(window: &MainWindow, config: &Config) {
+    if !config.first_run {
+        return;
+    }
+    let state = window.global::<OnboardingState>();
+    state.set_bindings(Rc::new(VecModel::from(key_hints(config))).into());
+    state.set_config_path(
+        config
+            .config_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default()
+            .into(),
+    );
+    state.set_can_associate(file_association::can_set_default());
+    state.set_visible(true);
+
+    let weak = window.as_weak();
+    state.on_associate(move || {
+        let Some(ui) = weak.upgrade() else {
+            return;
+        };
+        let status: SharedString = match file_association::install(true) {
+            Ok(()) => "Images now open with luminous.".into(),
+            Err(e) => {
+                error!("Failed to associate image types: {e}");
+                format!("Could not set up file associations: {e}").into()
+            }
+        };
+        ui.global::<OnboardingState>()
+            .set_association_status(status);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_read_as_sentences() {
+        assert_eq!(describe("toggle_side_panel"), "Toggle side panel");
+        assert_eq!(describe("quit"), "Quit");
+        assert_eq!(describe(""), "");
+    }
+}
@@ -0,0 +1,166 @@
+use crate::AppController;
+use crate::MainWindow;
+use crate::PerfOverlayState;
+use log::debug;
+use slint::{ComponentHandle, RenderingState, SharedString, VecModel};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Frames rendered since the last sample and the longest gap between two of them.
+struct FrameStats {
+    since: Instant,
+    last_frame: Option<Instant>,
+    frames: u32,
+    worst_gap: Duration,
+}
+
+impl FrameStats {
+    fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            last_frame: None,
+            frames: 0,
+            worst_gap: Duration::ZERO,
+        }
+    }
+
+    fn frame(&mut self, now: Instant) {
+        if let Some(last) = self.last_frame {
+            self.worst_gap = self.worst_gap.max(now - last);
+        }
+        self.last_frame = Some(now);
+        self.frames += 1;
+    }
+
+    /// Frames per second and worst frame time since the last call, then reset.
+    fn take(&mut self, now: Instant) -> (f64, Duration) {
+        let secs = (now - self.since).as_secs_f64();
+        let fps = if secs > 0.0 {
+            self.frames as f64 / secs
+        } else {
+            0.0
+        };
+        let worst = self.worst_gap;
+        self.since = now;
+        self.frames = 0;
+        self.worst_gap = Duration::ZERO;
+        (fps, worst)
+    }
+}
+
+pub(crate) fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Cache hits and misses, with the share of hits once there were lookups.
+fn hit_rate(hits: usize, misses: usize) -> String {
+    match hits + misses {
+        0 => "hit 0 miss 0".to_string(),
+        total => format!(
+            "hit {hits} miss {misses} ({:.0}%)",
+            hits as f64 * 100.0 / total as f64
+        ),
+    }
+}
+
+/// Resident memory of the process, only available on Linux.
+fn resident_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib: usize = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>) {
+    let frames = Rc::new(RefCell::new(FrameStats::new()));
+
+    let f = frames.clone();
+    if let Err(e) = window.window().set_rendering_notifier(move |state, _| {
+        if let RenderingState::AfterRendering = state {
+            f.borrow_mut().frame(Instant::now());
+        }
+    }) {
+        debug!("Frame timing unavailable for the performance overlay: {e}");
+    }
+
+    let weak = window.as_weak();
+    window.global::<PerfOverlayState>().on_sample(move || {
+        let Some(ui) = weak.upgrade() else {
+            return;
+        };
+        let (fps, worst) = frames.borrow_mut().take(Instant::now());
+        let (stats, frame_count, frame_bytes) = {
+            let c_ref = app_controller.borrow();
+            let frames = c_ref.frames.lock().unwrap();
+            (c_ref.loader.stats(), frames.len(), frames.bytes())
+        };
+        let rss = resident_bytes()
+            .map(|b| format!("{:.1} MiB", mib(b)))
+            .unwrap_or_else(|| "n/a".to_string());
+        let decode = stats
+            .last_decode
+            .map(|d| format!("{:.1} ms", d.as_secs_f64() * 1000.0))
+            .unwrap_or_else(|| "n/a".to_string());
+        let lines: Vec<SharedString> = [
+            format!(
+                "FPS    {fps:5.1} (worst {:.1} ms)",
+                worst.as_secs_f64() * 1000.0
+            ),
+            format!("Decode {decode}"),
+            format!("Queue  {}", stats.queued),
+            format!(
+                "Thumbs {} ({:.1} MiB) {}",
+                stats.thumbs,
+                mib(stats.thumb_bytes),
+                hit_rate(stats.thumb_hits, stats.thumb_misses)
+            ),
+            format!(
+                "Full   {} ({:.1} MiB) {}",
+                stats.full,
+                mib(stats.full_bytes),
+                hit_rate(stats.full_hits, stats.full_misses)
+            ),
+            format!("Frames {frame_count} ({:.1} MiB)", mib(frame_bytes)),
+            format!("RSS    {rss}"),
+        ]
+        .into_iter()
+        .map(SharedString::from)
+        .collect();
+        ui.global::<PerfOverlayState>()
+            .set_lines(Rc::new(VecModel::from(lines)).into());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats_reset_after_each_sample() {
+        let t0 = Instant::now();
+        let mut stats = FrameStats::new();
+        stats.since = t0;
+        for ms in [0, 10, 40, 50] {
+            stats.frame(t0 + Duration::from_millis(ms));
+        }
+        let (fps, worst) = stats.take(t0 + Duration::from_millis(500));
+        assert_eq!(fps, 8.0);
+        assert_eq!(worst, Duration::from_millis(30));
+
+        let (fps, worst) = stats.take(t0 + Duration::from_millis(1000));
+        assert_eq!((fps, worst), (0.0, Duration::ZERO));
+    }
+
+    #[test]
+    fn hit_rates() {
+        assert_eq!(hit_rate(0, 0), "hit 0 miss 0");
+        assert_eq!(hit_rate(3, 1), "hit 3 miss 1 (75%)");
+    }
+}
@@ -0,0 +1,66 @@
+use super::dispatcher::Dispatcher;
+use crate::MainWindow;
+use crate::actions::Action;
+use crate::remote::{self, Request};
+use log::{info, warn};
+use slint::ComponentHandle;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Requests the event loop doesn't get to within this fail, before the client gives up
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(4);
+
+pub fn register(window: &MainWindow, dispatcher: Rc<Dispatcher>) {
+    window.on_remote_request(move |line| {
+        let result = Request::parse(&line).and_then(|request| {
+            info!("Remote request: {request:?}");
+            dispatcher.dispatch(Action::from(request))
+        });
+        if let Err(e) = &result {
+            warn!("Remote request {:?} failed: {e}", line.trim());
+        }
+        remote::reply_line(&result).into()
+    });
+}
+
+/// Listen for paths from luminous started again, see [`serve`].
+pub fn serve_instance(window: &MainWindow) -> Option<remote::Server> {
+    serve(window, &remote::instance_socket()?)
+}
+
+/// Hands requests from a background thread to the event loop and waits for the answer.
+pub(super) fn forward(
+    window: &MainWindow,
+) -> impl Fn(Request) -> Result<String, String> + Send + Sync + 'static {
+    let weak = window.as_weak();
+    move |request| {
+        let line = request.to_line();
+        let (tx, rx) = mpsc::channel();
+        weak.upgrade_in_event_loop(move |ui| {
+            let _ = tx.send(ui.invoke_remote_request(line.into()));
+        })
+        .map_err(|e| e.to_string())?;
+        let reply = rx
+            .recv_timeout(ANSWER_TIMEOUT)
+            .map_err(|_| "the viewer is busy".to_string())?;
+        remote::parse_reply(&reply)
+    }
+}
+
+/// Handle requests sent to `socket` on the event loop, the returned server must be kept alive
+/// for as long as it should run.
+pub fn serve(window: &MainWindow, socket: &Path) -> Option<remote::Server> {
+    remote::serve(socket, forward(window))
+        .map_err(|e| warn!("Not listening on {:?}: {e}", socket))
+        .ok()
+}
+
+/// Handle calls to the `org.luminous.Viewer` session bus interface, see [`serve`].
+#[cfg(target_os = "linux")]
+pub fn serve_dbus(window: &MainWindow) -> Option<crate::dbus::Service> {
+    crate::dbus::serve(forward(window))
+        .map_err(|e| warn!("Not on the session bus: {e}"))
+        .ok()
+}
@@ -0,0 +1,48 @@
+use super::remote_presenter;
+use crate::MainWindow;
+use crate::config::Config;
+use crate::scripting::{self, Host};
+use log::{info, warn};
+use luminous_plugins::PluginManager;
+use slint::ComponentHandle;
+use std::sync::Arc;
+
+/// Run the scripts of `config` on their keys, again on a reload. Each runs on a thread of its
+/// own, its requests are handled on the event loop like those of the control socket.
+pub fn register(window: &MainWindow, plugin_manager: Arc<PluginManager>, config: &Config) {
+    let scripts = config.scripts.clone();
+    let quality = config.jpeg_quality;
+    let forward = Arc::new(remote_presenter::forward(window));
+    let weak = window.as_weak();
+    window.on_run_script(move |key| {
+        let Some(script) = scripts.iter().find(|s| s.key == key) else {
+            return false;
+        };
+        let path = script.path.clone();
+        let plugin_manager = plugin_manager.clone();
+        let forward = forward.clone();
+        let host = Host {
+            handle: Box::new(move |request| {
+                info!("Script request: {request:?}");
+                forward(request)
+            }),
+            plugin_manager,
+            quality,
+        };
+        let weak = weak.clone();
+        let spawned = std::thread::Builder::new()
+            .name("script".to_string())
+            .spawn(move || {
+                if let Err(e) = scripting::run(&path, host) {
+                    warn!("Script {:?} failed: {e}", path);
+                    let _ = weak.upgrade_in_event_loop(move |ui| {
+                        crate::show_osd(&ui, format!("Script failed: {e}"));
+                    });
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to spawn script thread: {e}");
+        }
+        true
+    });
+}
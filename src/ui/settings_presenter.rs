@@ -1,3 +1,5 @@
+use crate::error::{self, LuminousError};
+use crate::ui::perf_overlay_presenter::mib;
 use crate::{AppController, Config, MainWindow, SettingsState};
 use directories::ProjectDirs;
 use log::{debug, error, info};
@@ -16,29 +18,32 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
     sg.on_clear_image_cache(move || {
         let acc_bor = acc.borrow();
         acc_bor.loader.clear_disk_cache();
-        let disk_cache_count = acc_bor.loader.get_image_disk_cache_count();
-        let weak_ui = acc_bor.window_weak.clone();
-        slint::invoke_from_event_loop(move || {
-            if let Some(ui) = weak_ui.upgrade() {
-                ui.global::<SettingsState>()
-                    .set_image_cache_count(disk_cache_count as i32);
-            }
-        })
-        .unwrap();
+        refresh_cache_usage(&acc_bor);
     });
 
     let acc = app_controller.clone();
     sg.on_get_image_cache_count(move || {
-        let disk_cache_count = acc.borrow().loader.get_image_disk_cache_count();
-        let weak_ui = acc.borrow().window_weak.clone();
+        refresh_cache_usage(&acc.borrow());
+    });
 
-        slint::invoke_from_event_loop(move || {
-            if let Some(ui) = weak_ui.upgrade() {
-                ui.global::<SettingsState>()
-                    .set_image_cache_count(disk_cache_count as i32);
-            }
-        })
-        .unwrap();
+    let acc = app_controller.clone();
+    sg.on_purge_caches(move |include_disk| {
+        acc.borrow_mut().handle_purge_caches(include_disk);
+        refresh_cache_usage(&acc.borrow());
+    });
+
+    sg.set_scan_filter(
+        app_controller
+            .borrow()
+            .name_filter
+            .as_ref()
+            .map(|f| f.to_text())
+            .unwrap_or_default()
+            .into(),
+    );
+    let acc = app_controller.clone();
+    sg.on_scan_filter_changed(move |filter| {
+        acc.borrow_mut().handle_set_scan_filter(filter.to_string());
     });
 
     let plugins = app_controller
@@ -186,6 +191,30 @@ pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>)
     // });
 }
 
+/// Push the disk cache count and a memory/disk usage summary to the settings page.
+pub(crate) fn refresh_cache_usage(acc: &AppController) {
+    let stats = acc.loader.stats();
+    let disk_cache_count = acc.loader.get_image_disk_cache_count();
+    let usage = format!(
+        "Memory: {} thumbs ({:.1} MiB), {} full ({:.1} MiB) | Disk: {:.1} MiB",
+        stats.thumbs,
+        mib(stats.thumb_bytes),
+        stats.full,
+        mib(stats.full_bytes),
+        mib(acc.loader.get_image_disk_cache_bytes() as usize),
+    );
+    debug!("Cache usage: {usage}");
+    let weak_ui = acc.window_weak.clone();
+    slint::invoke_from_event_loop(move || {
+        if let Some(ui) = weak_ui.upgrade() {
+            let state = ui.global::<SettingsState>();
+            state.set_image_cache_count(disk_cache_count as i32);
+            state.set_cache_usage(usage.into());
+        }
+    })
+    .unwrap();
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct PluginSettings {
     pub id: String,
@@ -240,8 +269,9 @@ pub fn read_settings() -> Option<Settings> {
     })
 }
 
-pub fn write_settings(settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
-    let path = get_settings_path().ok_or("Could not determine settings path")?;
+pub fn write_settings(settings: &Settings) -> error::Result<()> {
+    let path = get_settings_path()
+        .ok_or_else(|| LuminousError::Config("could not determine the settings path".into()))?;
 
     let toml_string = toml::to_string_pretty(settings)?;
     std::fs::write(path, toml_string)?;
@@ -0,0 +1,28 @@
+use crate::AppController;
+use crate::MainWindow;
+use crate::StatusBarState;
+use crate::ui::perf_overlay_presenter::mib;
+use slint::ComponentHandle;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub fn register(window: &MainWindow, app_controller: Rc<RefCell<AppController>>) {
+    let weak = window.as_weak();
+    window.global::<StatusBarState>().on_sample(move || {
+        let Some(ui) = weak.upgrade() else {
+            return;
+        };
+        let stats = app_controller.borrow().loader.stats();
+        let state = ui.global::<StatusBarState>();
+        state.set_pending_jobs(stats.queued as i32);
+        state.set_cache_usage(
+            format!(
+                "Cache {:.1} MiB ({} thumbs, {} full)",
+                mib(stats.thumb_bytes + stats.full_bytes),
+                stats.thumbs,
+                stats.full
+            )
+            .into(),
+        );
+    });
+}
@@ -0,0 +1,79 @@
+use crate::MainWindow;
+use crate::ToastItem;
+use crate::ToastState;
+use crate::toast::{Toast, Toaster};
+use slint::{ComponentHandle, Model, VecModel};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+/// Toasts shown at once, the oldest make room for new ones.
+const MAX_SHOWN: usize = 4;
+
+/// A toaster whose toasts stack up in `window`, see [`crate::toast`].
+pub fn toaster(window: &MainWindow) -> Toaster {
+    let state = window.global::<ToastState>();
+    state.set_toasts(Rc::new(VecModel::<ToastItem>::default()).into());
+    let weak = window.as_weak();
+    state.on_dismiss(move |id| {
+        if let Some(ui) = weak.upgrade() {
+            dismiss(&ui, id);
+        }
+    });
+
+    // Filled in once the channel exists, before anything can be posted to it
+    let receiver: Arc<Mutex<Option<Receiver<Toast>>>> = Arc::default();
+    let next_id = Arc::new(AtomicI32::new(0));
+    let weak = window.as_weak();
+    let rx = receiver.clone();
+    let (toaster, channel) = Toaster::new(move || {
+        let rx = rx.clone();
+        let next_id = next_id.clone();
+        let _ = weak.upgrade_in_event_loop(move |ui| {
+            let Some(rx) = &*rx.lock().unwrap() else {
+                return;
+            };
+            for toast in rx.try_iter() {
+                show(&ui, toast, next_id.fetch_add(1, Ordering::Relaxed));
+            }
+        });
+    });
+    *receiver.lock().unwrap() = Some(channel);
+    toaster
+}
+
+/// Run `f` on the shown toasts.
+fn with_toasts(ui: &MainWindow, f: impl FnOnce(&VecModel<ToastItem>)) {
+    let model = ui.global::<ToastState>().get_toasts();
+    if let Some(toasts) = model.as_any().downcast_ref::<VecModel<ToastItem>>() {
+        f(toasts);
+    }
+}
+
+fn show(ui: &MainWindow, toast: Toast, id: i32) {
+    with_toasts(ui, |toasts| {
+        while toasts.row_count() >= MAX_SHOWN {
+            toasts.remove(0);
+        }
+        toasts.push(ToastItem {
+            id,
+            severity: toast.severity.name().into(),
+            message: toast.message.into(),
+        });
+    });
+    let weak = ui.as_weak();
+    slint::Timer::single_shot(toast.severity.duration(), move || {
+        if let Some(ui) = weak.upgrade() {
+            dismiss(&ui, id);
+        }
+    });
+}
+
+fn dismiss(ui: &MainWindow, id: i32) {
+    with_toasts(ui, |toasts| {
+        if let Some(row) = toasts.iter().position(|t| t.id == id) {
+            toasts.remove(row);
+        }
+    });
+}
@@ -0,0 +1,295 @@
+//! Slideshow export to video. There are no video encoder plugins, frames are piped as raw RGBA
+//! into `ffmpeg` from the PATH.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use log::{debug, warn};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Frame rate of the written video
+const FPS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    Mp4,
+    Webm,
+}
+
+impl VideoFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mp4" => Some(Self::Mp4),
+            "webm" => Some(Self::Webm),
+            _ => None,
+        }
+    }
+
+    pub fn ext(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+        }
+    }
+
+    /// H.264 or VP9 in yuv420p, what browsers and most players take
+    fn video_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &[
+                "-c:v",
+                "libx264",
+                "-crf",
+                "20",
+                "-pix_fmt",
+                "yuv420p",
+                "-movflags",
+                "+faststart",
+            ],
+            Self::Webm => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-crf",
+                "32",
+                "-b:v",
+                "0",
+                "-pix_fmt",
+                "yuv420p",
+            ],
+        }
+    }
+
+    fn audio_codec(self) -> &'static str {
+        match self {
+            Self::Mp4 => "aac",
+            Self::Webm => "libopus",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoOptions {
+    pub width: u32,
+    pub height: u32,
+    pub format: VideoFormat,
+    /// Seconds each image is on screen, its crossfade into the next included
+    pub seconds_per_image: f32,
+    /// Seconds of crossfade between images, 0 cuts
+    pub fade: f32,
+    /// Audio track, cut off where the images end
+    pub music: Option<PathBuf>,
+}
+
+impl VideoOptions {
+    fn frames_per_image(&self) -> u32 {
+        (self.seconds_per_image * FPS as f32).round().max(1.0) as u32
+    }
+
+    /// At most half of an image's time, so each is seen still for a moment
+    fn fade_frames(&self) -> u32 {
+        ((self.fade.max(0.0) * FPS as f32).round() as u32).min(self.frames_per_image() / 2)
+    }
+
+    fn ffmpeg_args(&self, dst: &Path) -> Vec<String> {
+        let size = format!("{}x{}", self.width, self.height);
+        let fps = FPS.to_string();
+        let mut args: Vec<String> = [
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+            "-s",
+            &size,
+            "-r",
+            &fps,
+            "-i",
+            "-",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        if let Some(music) = &self.music {
+            args.push("-i".into());
+            args.push(music.to_string_lossy().into());
+        }
+        args.extend(self.format.video_args().iter().map(|a| a.to_string()));
+        if self.music.is_some() {
+            args.extend(["-c:a", self.format.audio_codec(), "-shortest"].map(String::from));
+        }
+        args.push(dst.to_string_lossy().into());
+        args
+    }
+}
+
+/// Whether an `ffmpeg` that runs is on the PATH.
+pub fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// `img` scaled to fit `width` x `height`, centred on black.
+fn letterbox(img: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+    let scaled = img.resize(width, height, FilterType::Triangle).to_rgba8();
+    let mut frame = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+    let x = (width - scaled.width()) / 2;
+    let y = (height - scaled.height()) / 2;
+    imageops::overlay(&mut frame, &scaled, x as i64, y as i64);
+    frame
+}
+
+/// `t` of the way from frame `a` to `b`.
+fn blend(a: &[u8], b: &[u8], t: f32) -> Vec<u8> {
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| (a as f32 + (b as f32 - a as f32) * t).round() as u8)
+        .collect()
+}
+
+/// Render `count` images, taken from `load` in order, into a video at `dst`. Images that can't
+/// be loaded are skipped. Returns how many made it in, or `None` when `cancel` was set.
+pub fn export(
+    count: usize,
+    load: impl Fn(usize) -> Option<DynamicImage>,
+    dst: &Path,
+    options: &VideoOptions,
+    cancel: &AtomicBool,
+    on_progress: impl Fn(usize, usize),
+) -> io::Result<Option<usize>> {
+    let mut frames = (0..count).filter_map(|i| {
+        let frame = load(i).map(|img| letterbox(&img, options.width, options.height));
+        if frame.is_none() {
+            warn!("Video: skipping image {i}, it couldn't be loaded");
+        }
+        frame.map(|f| (i, f))
+    });
+    let Some(first) = frames.next() else {
+        return Err(io::Error::other("None of the images could be loaded"));
+    };
+
+    let args = options.ffmpeg_args(dst);
+    debug!("Video: ffmpeg {}", args.join(" "));
+    let mut child = Command::new("ffmpeg")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::other("ffmpeg was not found on the PATH"),
+            _ => e,
+        })?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+
+    let per_image = options.frames_per_image();
+    let fade = options.fade_frames();
+    let written = || -> io::Result<Option<usize>> {
+        let mut current = Some(first);
+        let mut added = 0;
+        while let Some((i, frame)) = current.take() {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            let next = frames.next();
+            let hold = if next.is_some() {
+                per_image - fade
+            } else {
+                per_image
+            };
+            for _ in 0..hold {
+                stdin.write_all(frame.as_raw())?;
+            }
+            if let Some((_, next)) = &next {
+                for k in 1..=fade {
+                    let t = k as f32 / (fade + 1) as f32;
+                    stdin.write_all(&blend(frame.as_raw(), next.as_raw(), t))?;
+                }
+            }
+            added += 1;
+            on_progress(i + 1, count);
+            current = next;
+        }
+        Ok(Some(added))
+    };
+    let result = written();
+    drop(stdin);
+
+    if let Ok(None) = result {
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = std::fs::remove_file(dst);
+        return Ok(None);
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rfind(|l| !l.trim().is_empty()).unwrap_or("");
+        let _ = std::fs::remove_file(dst);
+        return Err(io::Error::other(format!("ffmpeg failed: {reason}")));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> VideoOptions {
+        VideoOptions {
+            width: 1280,
+            height: 720,
+            format: VideoFormat::Mp4,
+            seconds_per_image: 2.0,
+            fade: 0.5,
+            music: None,
+        }
+    }
+
+    #[test]
+    fn wide_images_are_letterboxed() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 10, Rgba([255; 4])));
+        let frame = letterbox(&img, 20, 20);
+        assert_eq!(frame.dimensions(), (20, 20));
+        assert_eq!(frame.get_pixel(10, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(10, 10), &Rgba([255; 4]));
+    }
+
+    #[test]
+    fn crossfade_blends_and_stays_within_an_image() {
+        assert_eq!(blend(&[0, 200], &[100, 0], 0.5), vec![50, 100]);
+        let mut o = options();
+        assert_eq!((o.frames_per_image(), o.fade_frames()), (60, 15));
+        o.fade = 10.0;
+        assert_eq!(o.fade_frames(), 30);
+        o.fade = 0.0;
+        assert_eq!(o.fade_frames(), 0);
+    }
+
+    #[test]
+    fn music_is_a_second_input_cut_to_the_video() {
+        let mut o = options();
+        let args = o.ffmpeg_args(Path::new("show.mp4"));
+        assert!(!args.contains(&"-shortest".to_string()));
+        assert_eq!(args.last().unwrap(), "show.mp4");
+
+        o.music = Some("song.ogg".into());
+        o.format = VideoFormat::Webm;
+        let args = o.ffmpeg_args(Path::new("show.webm"));
+        let inputs: Vec<_> = args
+            .iter()
+            .zip(&args[1..])
+            .filter(|(flag, _)| *flag == "-i")
+            .map(|(_, input)| input.as_str())
+            .collect();
+        assert_eq!(inputs, ["-", "song.ogg"]);
+        assert!(args.contains(&"libopus".to_string()));
+        assert!(args.contains(&"-shortest".to_string()));
+    }
+}
@@ -0,0 +1,256 @@
+//! Desktop wallpaper setter, and the `luminous wallpaper` mode that keeps switching it.
+
+use log::{debug, info, warn};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+/// Formats desktops take as wallpaper, others are left out of the rotation
+const WALLPAPER_EXTS: [&str; 7] = ["jpg", "jpeg", "png", "webp", "bmp", "tif", "tiff"];
+
+pub fn usable(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| WALLPAPER_EXTS.contains(&e.as_str()))
+}
+
+/// Programs and arguments that set `path` as the wallpaper, tried in order until one works.
+#[cfg(target_os = "linux")]
+fn commands(path: &Path) -> Vec<Vec<String>> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    linux_commands(&desktop, path)
+}
+
+#[cfg(target_os = "macos")]
+fn commands(path: &Path) -> Vec<Vec<String>> {
+    let script = format!(
+        "tell application \"System Events\" to tell every desktop to set picture to \"{}\"",
+        path.display().to_string().replace('"', "\\\"")
+    );
+    vec![vec!["osascript".into(), "-e".into(), script]]
+}
+
+#[cfg(target_os = "windows")]
+fn commands(path: &Path) -> Vec<Vec<String>> {
+    // SPI_SETDESKWALLPAPER, written to the user profile and broadcast
+    let script = format!(
+        "Add-Type -Namespace Luminous -Name Desktop -MemberDefinition \
+         '[DllImport(\"user32.dll\", CharSet = CharSet.Unicode)] \
+         public static extern int SystemParametersInfo(int action, int param, string value, int flags);'; \
+         if ([Luminous.Desktop]::SystemParametersInfo(20, 0, '{}', 3) -eq 0) {{ exit 1 }}",
+        path.display().to_string().replace('\'', "''")
+    );
+    vec![vec![
+        "powershell".into(),
+        "-NoProfile".into(),
+        "-Command".into(),
+        script,
+    ]]
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn commands(_path: &Path) -> Vec<Vec<String>> {
+    Vec::new()
+}
+
+/// By `XDG_CURRENT_DESKTOP`, with `feh` for window managers that leave the root window alone.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn linux_commands(desktop: &str, path: &Path) -> Vec<Vec<String>> {
+    let file = path.display().to_string();
+    let uri = format!("file://{file}");
+    let gsettings = |schema: &str, key: &str, value: &str| {
+        ["gsettings", "set", schema, key, value]
+            .map(String::from)
+            .to_vec()
+    };
+    let desktop = desktop.to_lowercase();
+    let has = |name: &str| desktop.split(':').any(|d| d == name);
+    if has("kde") {
+        vec![vec!["plasma-apply-wallpaperimage".into(), file]]
+    } else if has("cinnamon") || has("x-cinnamon") {
+        vec![gsettings(
+            "org.cinnamon.desktop.background",
+            "picture-uri",
+            &uri,
+        )]
+    } else if has("mate") {
+        vec![gsettings("org.mate.background", "picture-filename", &file)]
+    } else if has("gnome") || has("unity") || has("budgie") || has("ubuntu") {
+        // The dark style has its own key since GNOME 42, older ones lack it
+        vec![
+            gsettings("org.gnome.desktop.background", "picture-uri", &uri),
+            gsettings("org.gnome.desktop.background", "picture-uri-dark", &uri),
+        ]
+    } else if has("sway") {
+        vec![
+            ["swaymsg", "output", "*", "bg", &file, "fill"]
+                .map(String::from)
+                .to_vec(),
+        ]
+    } else {
+        vec![vec!["feh".into(), "--bg-fill".into(), file]]
+    }
+}
+
+/// Make `path` the desktop wallpaper. Succeeds when any of the commands for this desktop did.
+pub fn set(path: &Path) -> io::Result<()> {
+    let path = path.canonicalize()?;
+    let mut last_error = io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Setting the wallpaper isn't supported here",
+    );
+    let mut set = false;
+    for argv in commands(&path) {
+        let Some((program, args)) = argv.split_first() else {
+            continue;
+        };
+        debug!("Wallpaper: {program} {args:?}");
+        match Command::new(program).args(args).output() {
+            Ok(out) if out.status.success() => set = true,
+            Ok(out) => {
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                last_error = io::Error::other(format!(
+                    "{program} exited with {}: {}",
+                    out.status,
+                    stderr.trim()
+                ));
+            }
+            Err(e) => last_error = io::Error::new(e.kind(), format!("{program}: {e}")),
+        }
+    }
+    if set { Ok(()) } else { Err(last_error) }
+}
+
+/// Order images are shown in, a new round starts when all were shown.
+struct Rotation {
+    queue: Vec<PathBuf>,
+    shuffle: bool,
+    state: u32,
+    last: Option<PathBuf>,
+}
+
+impl Rotation {
+    fn new(shuffle: bool, seed: u32) -> Self {
+        Self {
+            queue: Vec::new(),
+            shuffle,
+            state: seed | 1,
+            last: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Start a round of `paths`, shuffled so that it doesn't open with the image shown last.
+    fn refill(&mut self, mut paths: Vec<PathBuf>) {
+        if self.shuffle {
+            for i in (1..paths.len()).rev() {
+                // xorshift32
+                self.state ^= self.state << 13;
+                self.state ^= self.state >> 17;
+                self.state ^= self.state << 5;
+                paths.swap(i, self.state as usize % (i + 1));
+            }
+            if paths.len() > 1 && paths.first() == self.last.as_ref() {
+                let end = paths.len() - 1;
+                paths.swap(0, end);
+            }
+        }
+        paths.reverse();
+        self.queue = paths;
+    }
+
+    fn next(&mut self) -> Option<PathBuf> {
+        let next = self.queue.pop()?;
+        self.last = Some(next.clone());
+        Some(next)
+    }
+}
+
+/// Switch the wallpaper to the next image of `list` every `interval`, sleeping in between. The
+/// images are listed again for every round, so added and removed ones are picked up. Only
+/// returns when there are no images or the wallpaper can't be set.
+pub fn cycle(list: impl Fn() -> Vec<PathBuf>, interval: Duration, shuffle: bool) -> io::Result<()> {
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let mut rotation = Rotation::new(shuffle, seed);
+    loop {
+        if rotation.is_empty() {
+            let images: Vec<PathBuf> = list().into_iter().filter(|p| usable(p)).collect();
+            if images.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No images to use as wallpaper",
+                ));
+            }
+            debug!("Wallpaper: new round of {} images", images.len());
+            rotation.refill(images);
+        }
+        let Some(path) = rotation.next() else {
+            continue;
+        };
+        // Deleted since the round started
+        if !path.is_file() {
+            warn!("Wallpaper: {:?} is gone, skipping", path);
+            continue;
+        }
+        set(&path)?;
+        info!("Wallpaper set to {:?}", path);
+        std::thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(n: usize) -> Vec<PathBuf> {
+        (0..n).map(|i| PathBuf::from(format!("{i}.jpg"))).collect()
+    }
+
+    fn round(rotation: &mut Rotation, n: usize) -> Vec<PathBuf> {
+        rotation.refill(paths(n));
+        std::iter::from_fn(|| rotation.next()).collect()
+    }
+
+    #[test]
+    fn rounds_show_every_image_once() {
+        let mut ordered = Rotation::new(false, 7);
+        assert_eq!(round(&mut ordered, 4), paths(4));
+
+        let mut shuffled = Rotation::new(true, 7);
+        let mut last = None;
+        for _ in 0..20 {
+            let shown = round(&mut shuffled, 5);
+            let mut sorted = shown.clone();
+            sorted.sort();
+            assert_eq!(sorted, paths(5));
+            assert_ne!(
+                shown.first(),
+                last.as_ref(),
+                "a round repeated the last image"
+            );
+            last = shown.last().cloned();
+        }
+    }
+
+    #[test]
+    fn desktops_get_their_own_setter() {
+        let path = Path::new("/pics/a.jpg");
+        let gnome = linux_commands("ubuntu:GNOME", path);
+        assert_eq!(gnome.len(), 2);
+        assert_eq!(gnome[0].last().unwrap(), "file:///pics/a.jpg");
+        assert_eq!(
+            linux_commands("KDE", path),
+            [["plasma-apply-wallpaperimage", "/pics/a.jpg"]]
+        );
+        assert_eq!(linux_commands("i3", path)[0][0], "feh");
+        assert!(usable(Path::new("a.JPG")));
+        assert!(!usable(Path::new("a.exr")));
+    }
+}
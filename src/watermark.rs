@@ -0,0 +1,296 @@
+//! Watermark stamped on exported and converted images, a logo image and/or a line of text.
+
+use ab_glyph::{Font, PxScale, ScaleFont};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use log::warn;
+use std::path::PathBuf;
+
+/// Gap between the mark and the image edges, of the shorter image side
+const MARGIN: f32 = 0.02;
+/// Gap between the logo and the text under it, of the text height
+const LINE_GAP: f32 = 0.25;
+
+/// Where the mark sits, by the image edge or corner it is closest to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const NAMES: [&str; 9] = [
+        "top-left",
+        "top",
+        "top-right",
+        "left",
+        "center",
+        "right",
+        "bottom-left",
+        "bottom",
+        "bottom-right",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let i = Self::NAMES
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name.trim()))?;
+        Some(
+            [
+                Self::TopLeft,
+                Self::Top,
+                Self::TopRight,
+                Self::Left,
+                Self::Center,
+                Self::Right,
+                Self::BottomLeft,
+                Self::Bottom,
+                Self::BottomRight,
+            ][i],
+        )
+    }
+
+    /// Top left of a `mark` sized box in `image`, `margin` away from the edges it is anchored to.
+    fn place(self, image: (u32, u32), mark: (u32, u32), margin: u32) -> (i64, i64) {
+        let along = |size: u32, mark: u32, slot: u8| -> i64 {
+            match slot {
+                0 => margin as i64,
+                1 => (size as i64 - mark as i64) / 2,
+                _ => size as i64 - mark as i64 - margin as i64,
+            }
+        };
+        let i = self as u8;
+        (along(image.0, mark.0, i % 3), along(image.1, mark.1, i / 3))
+    }
+}
+
+/// `[watermark]` of the config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    /// Logo drawn at the mark's full width
+    pub image: Option<PathBuf>,
+    /// Line of text, under the logo when both are set
+    pub text: Option<String>,
+    pub anchor: Anchor,
+    /// 0 invisible, 1 opaque
+    pub opacity: f32,
+    /// Width of the mark, of the image width
+    pub scale: f32,
+    pub color: Rgba<u8>,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            image: None,
+            text: None,
+            anchor: Anchor::BottomRight,
+            opacity: 0.5,
+            scale: 0.2,
+            color: Rgba([255, 255, 255, 255]),
+        }
+    }
+}
+
+/// A watermark ready to stamp, with its logo decoded once for a whole batch.
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    watermark: Watermark,
+    logo: Option<RgbaImage>,
+}
+
+impl Stamp {
+    /// `None` when there is nothing to draw, a logo that can't be read is left out with a
+    /// warning.
+    pub fn new(watermark: &Watermark) -> Option<Self> {
+        let logo = watermark
+            .image
+            .as_ref()
+            .and_then(|path| match image::open(path) {
+                Ok(img) => Some(img.into_rgba8()),
+                Err(e) => {
+                    warn!("Watermark image {:?} can't be read: {}", path, e);
+                    None
+                }
+            });
+        let text = watermark
+            .text
+            .as_deref()
+            .is_some_and(|t| !t.trim().is_empty());
+        (logo.is_some() || text).then(|| Self {
+            watermark: watermark.clone(),
+            logo,
+        })
+    }
+
+    /// The mark for an image `width` pixels wide, logo over text.
+    fn mark(&self, width: u32) -> Option<RgbaImage> {
+        let wm = &self.watermark;
+        let mark_width = ((width as f32 * wm.scale).round() as u32).max(1);
+        let logo = self.logo.as_ref().map(|logo| {
+            let height = (logo.height() as f32 * mark_width as f32 / logo.width().max(1) as f32)
+                .round()
+                .max(1.0) as u32;
+            imageops::resize(logo, mark_width, height, FilterType::Triangle)
+        });
+        let text = wm
+            .text
+            .as_deref()
+            .filter(|t| !t.trim().is_empty())
+            .and_then(|t| text_mark(t, wm.color, mark_width));
+        match (logo, text) {
+            (Some(logo), Some(text)) => {
+                let gap = (text.height() as f32 * LINE_GAP) as u32;
+                let width = logo.width().max(text.width());
+                let mut mark = RgbaImage::new(width, logo.height() + gap + text.height());
+                imageops::overlay(&mut mark, &logo, ((width - logo.width()) / 2) as i64, 0);
+                let y = (logo.height() + gap) as i64;
+                imageops::overlay(&mut mark, &text, ((width - text.width()) / 2) as i64, y);
+                Some(mark)
+            }
+            (logo, text) => logo.or(text),
+        }
+    }
+
+    /// `img` with the mark blended over it, without an alpha channel if it had none.
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        let (w, h) = (img.width(), img.height());
+        let Some(mark) = self.mark(w) else {
+            return img;
+        };
+        let opaque = !img.color().has_alpha();
+        let mut out = img.into_rgba8();
+        let margin = (w.min(h) as f32 * MARGIN).round() as u32;
+        let (x0, y0) = self
+            .watermark
+            .anchor
+            .place((w, h), mark.dimensions(), margin);
+        let opacity = self.watermark.opacity.clamp(0.0, 1.0);
+        for (mx, my, px) in mark.enumerate_pixels() {
+            let (x, y) = (x0 + mx as i64, y0 + my as i64);
+            if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+                continue;
+            }
+            let a = px[3] as f32 / 255.0 * opacity;
+            let dst = out.get_pixel_mut(x as u32, y as u32);
+            for c in 0..3 {
+                dst[c] = (px[c] as f32 * a + dst[c] as f32 * (1.0 - a)).round() as u8;
+            }
+            dst[3] = (255.0 * a + dst[3] as f32 * (1.0 - a)).round() as u8;
+        }
+        let out = DynamicImage::ImageRgba8(out);
+        if opaque {
+            DynamicImage::ImageRgb8(out.into_rgb8())
+        } else {
+            out
+        }
+    }
+}
+
+/// `text` in `color` on transparent, sized to `width` pixels.
+fn text_mark(text: &str, color: Rgba<u8>, width: u32) -> Option<RgbaImage> {
+    let Some(font) = crate::markup::font() else {
+        warn!("No font found, skipping watermark text '{text}'");
+        return None;
+    };
+    let text = text.trim();
+    let advance = |scale: PxScale| {
+        let scaled = font.as_scaled(scale);
+        let mut prev = None;
+        text.chars()
+            .map(|ch| {
+                let id = scaled.glyph_id(ch);
+                let kern = prev.map_or(0.0, |p| scaled.kern(p, id));
+                prev = Some(id);
+                kern + scaled.h_advance(id)
+            })
+            .sum::<f32>()
+    };
+    // Laid out once at a reference size to find the one that fills the width
+    let reference = advance(PxScale::from(100.0));
+    if reference <= 0.0 {
+        return None;
+    }
+    let scale = PxScale::from(100.0 * width as f32 / reference);
+    let scaled = font.as_scaled(scale);
+    let height = (scaled.ascent() - scaled.descent()).ceil().max(1.0) as u32;
+    let mut mark = RgbaImage::new(width, height);
+    let mut caret = 0.0;
+    let mut prev = None;
+    for ch in text.chars() {
+        let id = scaled.glyph_id(ch);
+        if let Some(prev) = prev {
+            caret += scaled.kern(prev, id);
+        }
+        let glyph = id.with_scale_and_position(scale, ab_glyph::point(caret, scaled.ascent()));
+        caret += scaled.h_advance(id);
+        prev = Some(id);
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let b = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let (x, y) = (b.min.x as i64 + gx as i64, b.min.y as i64 + gy as i64);
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                let px = mark.get_pixel_mut(x as u32, y as u32);
+                let a = (coverage.clamp(0.0, 1.0) * color[3] as f32).round() as u8;
+                if a > px[3] {
+                    *px = Rgba([color[0], color[1], color[2], a]);
+                }
+            }
+        });
+    }
+    Some(mark)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchors_place_the_mark_by_their_edges() {
+        let place = |name| {
+            Anchor::from_name(name)
+                .unwrap()
+                .place((100, 50), (20, 10), 2)
+        };
+        assert_eq!(place("top-left"), (2, 2));
+        assert_eq!(place("Center"), (40, 20));
+        assert_eq!(place("bottom-right"), (78, 38));
+        assert_eq!(place("right"), (78, 20));
+        assert_eq!(Anchor::from_name("middle"), None);
+    }
+
+    #[test]
+    fn logo_is_blended_at_the_configured_opacity() {
+        let dir = tempfile::tempdir().unwrap();
+        let logo = dir.path().join("logo.png");
+        RgbaImage::from_pixel(10, 5, Rgba([255, 255, 255, 255]))
+            .save(&logo)
+            .unwrap();
+        let watermark = Watermark {
+            image: Some(logo),
+            opacity: 0.5,
+            scale: 0.5,
+            ..Default::default()
+        };
+        assert!(Stamp::new(&Watermark::default()).is_none());
+        let stamp = Stamp::new(&watermark).unwrap();
+        let black = DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 40, Rgba([0, 0, 0, 255])));
+        let out = stamp.apply(black).into_rgba8();
+        // A 20x10 logo in the bottom right corner, 1px from the edges
+        assert_eq!(out.get_pixel(30, 34), &Rgba([128, 128, 128, 255]));
+        assert_eq!(out.get_pixel(39, 39), &Rgba([0, 0, 0, 255]));
+        assert_eq!(out.get_pixel(10, 10), &Rgba([0, 0, 0, 255]));
+        // JPEG sources stay without alpha so they can be written as JPEG again
+        let rgb = DynamicImage::ImageRgb8(image::RgbImage::new(40, 40));
+        assert_eq!(stamp.apply(rgb).color(), image::ColorType::Rgb8);
+    }
+}
@@ -0,0 +1,329 @@
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use image::ImageFormat;
+use image::imageops::FilterType;
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+/// Deflate and 32-bit sizes, no zip64
+const VERSION: u16 = 20;
+/// File names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Minimal zip archive writer, entries are deflated unless that doesn't make them smaller, as
+/// with most image formats.
+pub struct ZipWriter<W: Write> {
+    out: W,
+    entries: Vec<Entry>,
+    written: u64,
+    /// DOS time and date stamped on every entry
+    modified: (u16, u16),
+}
+
+fn too_large() -> io::Error {
+    io::Error::other("Archive exceeds 4 GiB, zip64 is not supported")
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            entries: Vec::new(),
+            written: 0,
+            modified: dos_time(chrono::Local::now().naive_local()),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let deflated = encoder.finish()?;
+        let (method, payload) = if deflated.len() < data.len() {
+            (DEFLATED, deflated.as_slice())
+        } else {
+            (STORED, data)
+        };
+
+        let entry = Entry {
+            name: name.to_string(),
+            method,
+            crc: crc32fast::hash(data),
+            compressed_size: payload.len().try_into().map_err(|_| too_large())?,
+            size: data.len().try_into().map_err(|_| too_large())?,
+            offset: self.written.try_into().map_err(|_| too_large())?,
+        };
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend(LOCAL_HEADER.to_le_bytes());
+        header.extend(VERSION.to_le_bytes());
+        self.push_common_fields(&mut header, &entry);
+        header.extend(0u16.to_le_bytes()); // extra field length
+        header.extend(name.as_bytes());
+
+        self.write(&header)?;
+        self.write(payload)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let start: u32 = self.written.try_into().map_err(|_| too_large())?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend(CENTRAL_HEADER.to_le_bytes());
+            directory.extend(VERSION.to_le_bytes()); // made by
+            directory.extend(VERSION.to_le_bytes()); // needed to extract
+            self.push_common_fields(&mut directory, entry);
+            directory.extend(0u16.to_le_bytes()); // extra field length
+            directory.extend(0u16.to_le_bytes()); // comment length
+            directory.extend(0u16.to_le_bytes()); // disk number
+            directory.extend(0u16.to_le_bytes()); // internal attributes
+            directory.extend(0u32.to_le_bytes()); // external attributes
+            directory.extend(entry.offset.to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+        }
+        let count: u16 = self
+            .entries
+            .len()
+            .try_into()
+            .map_err(|_| io::Error::other("More than 65535 files, zip64 is not supported"))?;
+        let size: u32 = directory.len().try_into().map_err(|_| too_large())?;
+        directory.extend(END_OF_CENTRAL_DIR.to_le_bytes());
+        directory.extend(0u16.to_le_bytes()); // this disk
+        directory.extend(0u16.to_le_bytes()); // disk with the directory
+        directory.extend(count.to_le_bytes()); // entries on this disk
+        directory.extend(count.to_le_bytes());
+        directory.extend(size.to_le_bytes());
+        directory.extend(start.to_le_bytes());
+        directory.extend(0u16.to_le_bytes()); // comment length
+
+        self.write(&directory)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Fields shared by the local and central headers, from the flags to the name length.
+    fn push_common_fields(&self, buf: &mut Vec<u8>, entry: &Entry) {
+        buf.extend(FLAG_UTF8.to_le_bytes());
+        buf.extend(entry.method.to_le_bytes());
+        buf.extend(self.modified.0.to_le_bytes());
+        buf.extend(self.modified.1.to_le_bytes());
+        buf.extend(entry.crc.to_le_bytes());
+        buf.extend(entry.compressed_size.to_le_bytes());
+        buf.extend(entry.size.to_le_bytes());
+        buf.extend((entry.name.len() as u16).to_le_bytes());
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+}
+
+fn dos_time(t: chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let time = (t.hour() << 11 | t.minute() << 5 | (t.second() / 2)) as u16;
+    let date = ((t.year().clamp(1980, 2107) - 1980) as u32) << 9 | t.month() << 5 | t.day();
+    (time, date as u16)
+}
+
+/// `name`, or `name (2)` and so on when another file in the archive already has it, as happens
+/// with files from different folders.
+fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let path = Path::new(name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+    let ext = path.extension().and_then(|e| e.to_str());
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while taken.contains(&candidate.to_lowercase()) {
+        candidate = match ext {
+            Some(ext) => format!("{stem} ({n}).{ext}"),
+            None => format!("{stem} ({n})"),
+        };
+        n += 1;
+    }
+    taken.insert(candidate.to_lowercase());
+    candidate
+}
+
+/// Contents of `path` as stored in the archive as `name`. With `max_edge`, images larger than
+/// that are scaled down and re-encoded, formats the image crate can't write become PNG.
+fn entry_data(path: &Path, name: &str, max_edge: Option<u32>) -> io::Result<(String, Vec<u8>)> {
+    let name = name.to_string();
+    let Some(max_edge) = max_edge else {
+        return Ok((name, std::fs::read(path)?));
+    };
+    let img = image::open(path).map_err(io::Error::other)?;
+    if img.width().max(img.height()) <= max_edge {
+        return Ok((name, std::fs::read(path)?));
+    }
+    let img = img.resize(max_edge, max_edge, FilterType::Lanczos3);
+
+    let mut out = Cursor::new(Vec::new());
+    match ImageFormat::from_path(path)
+        .ok()
+        .filter(|f| f.writing_enabled())
+    {
+        Some(ImageFormat::Jpeg) => {
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 90);
+            img.to_rgb8()
+                .write_with_encoder(encoder)
+                .map_err(io::Error::other)?;
+            Ok((name, out.into_inner()))
+        }
+        Some(format) => {
+            img.write_to(&mut out, format).map_err(io::Error::other)?;
+            Ok((name, out.into_inner()))
+        }
+        None => {
+            img.write_to(&mut out, ImageFormat::Png)
+                .map_err(io::Error::other)?;
+            let name = Path::new(&name).with_extension("png");
+            Ok((name.to_string_lossy().into_owned(), out.into_inner()))
+        }
+    }
+}
+
+/// Pack `(path, name)` entries into a zip archive at `dst`, optionally scaled down to
+/// `max_edge`. Files that can't be read are skipped. Returns how many were added, or `None` when
+/// `cancel` was set.
+pub fn export(
+    entries: &[(PathBuf, String)],
+    dst: &Path,
+    max_edge: Option<u32>,
+    cancel: &AtomicBool,
+    on_progress: impl Fn(usize, usize),
+) -> io::Result<Option<usize>> {
+    let file = io::BufWriter::new(std::fs::File::create(dst)?);
+    let mut zip = ZipWriter::new(file);
+    let mut taken = HashSet::new();
+    let mut added = 0;
+    for (i, (path, name)) in entries.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            drop(zip);
+            std::fs::remove_file(dst)?;
+            return Ok(None);
+        }
+        match entry_data(path, name, max_edge) {
+            Ok((name, data)) => {
+                let name = unique_name(&name, &mut taken);
+                debug!("Zip: {:?} as {name} ({} bytes)", path, data.len());
+                zip.add(&name, &data)?;
+                added += 1;
+            }
+            Err(e) => warn!("Zip: skipping {:?}: {}", path, e),
+        }
+        on_progress(i + 1, entries.len());
+    }
+    zip.finish()?;
+    Ok(Some(added))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    fn u16_at(buf: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(buf[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(buf: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    /// Name and contents of each entry, read through the central directory.
+    fn read_entries(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(zip, end), END_OF_CENTRAL_DIR);
+        let count = u16_at(zip, end + 10) as usize;
+        let mut at = u32_at(zip, end + 16) as usize;
+        (0..count)
+            .map(|_| {
+                assert_eq!(u32_at(zip, at), CENTRAL_HEADER);
+                let method = u16_at(zip, at + 10);
+                let crc = u32_at(zip, at + 16);
+                let compressed = u32_at(zip, at + 20) as usize;
+                let name_len = u16_at(zip, at + 28) as usize;
+                let offset = u32_at(zip, at + 42) as usize;
+                let name = String::from_utf8(zip[at + 46..at + 46 + name_len].to_vec()).unwrap();
+                at += 46 + name_len;
+
+                assert_eq!(u32_at(zip, offset), LOCAL_HEADER);
+                let start = offset + 30 + u16_at(zip, offset + 26) as usize;
+                let payload = &zip[start..start + compressed];
+                let data = match method {
+                    STORED => payload.to_vec(),
+                    DEFLATED => {
+                        let mut data = Vec::new();
+                        DeflateDecoder::new(payload).read_to_end(&mut data).unwrap();
+                        data
+                    }
+                    other => panic!("Unexpected method {other}"),
+                };
+                assert_eq!(crc32fast::hash(&data), crc);
+                (name, data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn entries_roundtrip() {
+        let text = "luminous ".repeat(100).into_bytes();
+        let noise: Vec<u8> = (0..64u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("a.txt", &text).unwrap();
+        zip.add("b.bin", &noise).unwrap();
+        zip.add("empty", &[]).unwrap();
+        let zip = zip.finish().unwrap();
+
+        let entries = read_entries(&zip);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], ("a.txt".to_string(), text));
+        assert_eq!(entries[1], ("b.bin".to_string(), noise));
+        assert_eq!(entries[2], ("empty".to_string(), vec![]));
+        // Repetitive text shrinks
+        assert!(zip.len() < 600);
+    }
+
+    #[test]
+    fn duplicate_names_get_numbered() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique_name("a.jpg", &mut taken), "a.jpg");
+        assert_eq!(unique_name("A.JPG", &mut taken), "A (2).JPG");
+        assert_eq!(unique_name("a.jpg", &mut taken), "a (3).jpg");
+        assert_eq!(unique_name("b", &mut taken), "b");
+    }
+
+    #[test]
+    fn dos_timestamps() {
+        let t = chrono::NaiveDate::from_ymd_opt(2024, 3, 15)
+            .unwrap()
+            .and_hms_opt(13, 45, 31)
+            .unwrap();
+        let (time, date) = dos_time(t);
+        assert_eq!(time, 13 << 11 | 45 << 5 | 15);
+        assert_eq!(date, 44 << 9 | 3 << 5 | 15);
+    }
+}